@@ -0,0 +1,153 @@
+#ifndef HEXPXL_H
+#define HEXPXL_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The pixelisation succeeded
+ */
+#define HEXPXL_OK 0
+
+/**
+ * An argument is null, out of range or inconsistent with the others
+ */
+#define HEXPXL_INVALID_ARGUMENT 1
+
+/**
+ * The pixelisation failed, `hexpxl_last_error` tells why
+ */
+#define HEXPXL_INTERNAL_ERROR 2
+
+/**
+ * Square pixels
+ */
+#define HEXPXL_MODE_SQR 0
+
+/**
+ * Hexagonal pixels
+ */
+#define HEXPXL_MODE_HEX 1
+
+/**
+ * Triangular pixels
+ */
+#define HEXPXL_MODE_TRI 2
+
+/**
+ * Voronoi cells around random seeds
+ */
+#define HEXPXL_MODE_VORONOI 3
+
+/**
+ * Squares split where the image has details
+ */
+#define HEXPXL_MODE_QUADTREE 4
+
+/**
+ * Bricks, every other row being shifted by half a brick
+ */
+#define HEXPXL_MODE_BRICK 5
+
+/**
+ * Dots at the centers of a hexagonal grid, the darker the larger
+ */
+#define HEXPXL_MODE_DOTS 6
+
+/**
+ * Hexagons split into 3 rhombi, like isometric cubes
+ */
+#define HEXPXL_MODE_CUBE 7
+
+/**
+ * Superpixels following the edges of the image
+ */
+#define HEXPXL_MODE_SLIC 8
+
+/**
+ * Overlapping rows of round scales, like those of a fish
+ */
+#define HEXPXL_MODE_SCALES 9
+
+/**
+ * Rectangles laid in the zig-zag of a herringbone parquet
+ */
+#define HEXPXL_MODE_HERRINGBONE 10
+
+/**
+ * A Penrose tiling of thick and thin rhombi, which never repeats
+ */
+#define HEXPXL_MODE_PENROSE 11
+
+/**
+ * Hexagons surrounded by triangles, each triangle between 3 hexagons
+ */
+#define HEXPXL_MODE_TRIHEX 12
+
+/**
+ * Concentric rings around the center of the image, cut into arcs
+ */
+#define HEXPXL_MODE_RINGS 13
+
+/**
+ * A band winding along a spiral from the center of the image, cut into cells along its length
+ */
+#define HEXPXL_MODE_SPIRAL 14
+
+/**
+ * Runs of pixels along a Hilbert curve filling the image
+ */
+#define HEXPXL_MODE_HILBERT 15
+
+/**
+ * Triangles between feature points of the image, like a low poly model
+ */
+#define HEXPXL_MODE_LOWPOLY 16
+
+/**
+ * Crystals grown from random seeds until they fill the image
+ */
+#define HEXPXL_MODE_CRYSTAL 17
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ *
+ * Pixelises an RGBA image into `out`, returning `HEXPXL_OK` or an error code
+ *
+ * Both images are `width * height` pixels of 4 bytes, row by row, without padding.
+ * When an error is returned, `out` is left untouched and `hexpxl_last_error` describes it.
+ *
+ * # Safety
+ *
+ * `rgba` must be readable and `out` writable for `width * height * 4` bytes,
+ * and they must not overlap.
+ *
+ */
+int hexpxl_pixelise(const uint8_t *rgba,
+                    uint32_t width,
+                    uint32_t height,
+                    uint32_t mode,
+                    uint32_t size,
+                    uint8_t *out);
+
+/**
+ *
+ * The description of the error returned by the last call to `hexpxl_pixelise` of the current thread
+ *
+ * Returns null when that call succeeded. The text stays valid until the next call on the same thread.
+ *
+ */
+const char *hexpxl_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HEXPXL_H */
@@ -0,0 +1,302 @@
+use std::io::{self, Write};
+
+use gif;
+use gif::SetParameter;
+use image::{ImageBuffer, ImageError, ImageResult, Rgba, RgbaImage};
+use png;
+use rayon::prelude::*;
+
+use deep::PngEncoding;
+use {pixelise_with, plan, Options};
+
+
+///
+/// A frame of an animation, covering the whole canvas
+///
+pub struct AnimationFrame {
+    pub image: RgbaImage,
+    /// How long the frame is displayed, in seconds, as a numerator and a denominator,
+    /// which is 100 for the GIF frames, timed in hundredths of a second
+    pub delay: (u16, u16),
+}
+
+///
+/// An animated image
+///
+pub struct Animation {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<AnimationFrame>,
+    /// How many times the animation is repeated. `Some(0)` repeats forever, `None` plays it once.
+    pub repeat: Option<u16>,
+}
+
+///
+/// Decodes all the frames of a GIF
+///
+/// The frames are composed on the canvas according to their disposal method,
+/// so that each of the decoded frames is a complete image.
+///
+pub fn decode_gif(data: &[u8]) -> ImageResult<Animation> {
+    let mut decoder = gif::Decoder::new(data);
+    decoder.set(gif::ColorOutput::RGBA);
+    let mut reader = decoder.read_info()?;
+
+    let width = reader.width() as u32;
+    let height = reader.height() as u32;
+    let mut canvas: RgbaImage = ImageBuffer::new(width, height);
+    let mut frames = Vec::new();
+
+    while let Some(frame) = reader.read_next_frame()? {
+        let previous = if frame.dispose == gif::DisposalMethod::Previous {
+            Some(canvas.clone())
+        } else {
+            None
+        };
+
+        let (left, top) = (frame.left as u32, frame.top as u32);
+        let frame_width = frame.width as u32;
+        for (i, rgba) in frame.buffer.chunks(4).enumerate() {
+            let x = left + i as u32 % frame_width;
+            let y = top + i as u32 / frame_width;
+            if rgba[3] != 0 && x < width && y < height {
+                canvas.put_pixel(x, y, Rgba([rgba[0], rgba[1], rgba[2], rgba[3]]));
+            }
+        }
+
+        frames.push(AnimationFrame { image: canvas.clone(), delay: (frame.delay, 100) });
+
+        match frame.dispose {
+            gif::DisposalMethod::Background => {
+                for y in top..(top + frame.height as u32).min(height) {
+                    for x in left..(left + frame_width).min(width) {
+                        canvas.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+                    }
+                }
+            }
+            gif::DisposalMethod::Previous => {
+                if let Some(previous) = previous {
+                    canvas = previous;
+                }
+            }
+            gif::DisposalMethod::Any | gif::DisposalMethod::Keep => {}
+        }
+    }
+
+    Ok(Animation {
+        width,
+        height,
+        frames,
+        repeat: find_repeat(data),
+    })
+}
+
+///
+/// Reads the loop count of the NETSCAPE2.0 application extension
+///
+/// The extension is laid out as 0x21 0xFF 0x0B "NETSCAPE2.0" 0x03 0x01 <count, u16 LE> 0x00
+///
+fn find_repeat(data: &[u8]) -> Option<u16> {
+    let marker = b"\x21\xFF\x0BNETSCAPE2.0\x03\x01";
+    data.windows(marker.len() + 2)
+        .find(|w| w.starts_with(marker))
+        .map(|w| u16::from(w[marker.len()]) | u16::from(w[marker.len() + 1]) << 8)
+}
+
+///
+/// Encodes the animation as a GIF
+///
+pub fn encode_gif<W: Write>(animation: &Animation, w: W) -> io::Result<()> {
+    let (width, height) = (animation.width as u16, animation.height as u16);
+
+    let frames: Vec<gif::Frame> = animation.frames.par_iter().map(|f| {
+        let mut raw = f.image.clone().into_raw();
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut raw, 10);
+        let (numerator, denominator) = f.delay;
+        frame.delay = (numerator as u32 * 100 / denominator.max(1) as u32).min(u16::MAX as u32) as u16;
+        // each frame is complete, nothing of the previous one must show through its transparent pixels
+        frame.dispose = gif::DisposalMethod::Background;
+        frame
+    }).collect();
+
+    let mut encoder = gif::Encoder::new(w, width, height, &[])?;
+    match animation.repeat {
+        Some(0) => encoder.set(gif::Repeat::Infinite)?,
+        Some(n) => encoder.set(gif::Repeat::Finite(n))?,
+        None => {}
+    }
+    for frame in &frames {
+        encoder.write_frame(frame)?;
+    }
+    Ok(())
+}
+
+///
+/// Decodes all the frames of an APNG, None for the PNG images which are not animated
+///
+/// The frames are blended into the canvas and disposed of according to their operations,
+/// so that each of the decoded frames is a complete image. The default image is left out
+/// when it is not a frame of the animation.
+///
+pub fn decode_apng(data: &[u8]) -> ImageResult<Option<Animation>> {
+    let format_error = |e: png::DecodingError| ImageError::FormatError(e.to_string());
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(format_error)?;
+    let control = match reader.info().animation_control {
+        Some(control) => control,
+        None => return Ok(None),
+    };
+    let (width, height) = reader.info().size();
+    let default_image = reader.info().frame_control.is_none();
+
+    let mut canvas: RgbaImage = ImageBuffer::new(width, height);
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let mut frames = Vec::new();
+    for i in 0..(control.num_frames as usize + default_image as usize) {
+        let output = reader.next_frame(&mut buffer).map_err(format_error)?;
+        if i == 0 && default_image {
+            continue;
+        }
+        let control = match reader.info().frame_control {
+            Some(control) => control,
+            None => break,
+        };
+        // the previous state of the first frame is the cleared canvas
+        let dispose = match control.dispose_op {
+            png::DisposeOp::Previous if frames.is_empty() => png::DisposeOp::Background,
+            dispose => dispose,
+        };
+        let previous = if dispose == png::DisposeOp::Previous { Some(canvas.clone()) } else { None };
+
+        let channels = output.color_type.samples();
+        for y in 0..output.height {
+            let line = &buffer[y as usize * output.line_size..];
+            for x in 0..output.width {
+                let (cx, cy) = (control.x_offset + x, control.y_offset + y);
+                if cx >= width || cy >= height {
+                    continue;
+                }
+                let pixel = rgba(&line[x as usize * channels..(x as usize + 1) * channels]);
+                let blended = match control.blend_op {
+                    png::BlendOp::Source => pixel,
+                    png::BlendOp::Over => over(pixel, *canvas.get_pixel(cx, cy)),
+                };
+                canvas.put_pixel(cx, cy, blended);
+            }
+        }
+
+        // a null denominator stands for hundredths of a second
+        let delay = (control.delay_num, if control.delay_den == 0 { 100 } else { control.delay_den });
+        frames.push(AnimationFrame { image: canvas.clone(), delay });
+
+        match dispose {
+            png::DisposeOp::Background => {
+                for y in control.y_offset..(control.y_offset + control.height).min(height) {
+                    for x in control.x_offset..(control.x_offset + control.width).min(width) {
+                        canvas.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+                    }
+                }
+            }
+            png::DisposeOp::Previous => {
+                if let Some(previous) = previous {
+                    canvas = previous;
+                }
+            }
+            png::DisposeOp::None => {}
+        }
+    }
+
+    Ok(Some(Animation {
+        width,
+        height,
+        frames,
+        // the number of times the animation is played, 0 for ever
+        repeat: match control.num_plays {
+            0 => Some(0),
+            1 => None,
+            plays => Some((plays - 1).min(u16::MAX as u32) as u16),
+        },
+    }))
+}
+
+/// The RGBA pixel of the samples of a gray, gray and alpha, RGB or RGBA pixel of 8 bits
+fn rgba(samples: &[u8]) -> Rgba<u8> {
+    match *samples {
+        [v] => Rgba([v, v, v, 255]),
+        [v, a] => Rgba([v, v, v, a]),
+        [r, g, b] => Rgba([r, g, b, 255]),
+        [r, g, b, a] => Rgba([r, g, b, a]),
+        _ => unreachable!(),
+    }
+}
+
+/// The pixel composited over the one below it
+fn over(pixel: Rgba<u8>, below: Rgba<u8>) -> Rgba<u8> {
+    let alpha = pixel[3] as f32 / 255.0;
+    // the part of the pixel below seen through the pixel
+    let under = below[3] as f32 / 255.0 * (1.0 - alpha);
+    let total = alpha + under;
+    if total <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let mixed = |c: usize| ((pixel[c] as f32 * alpha + below[c] as f32 * under) / total).round() as u8;
+    Rgba([mixed(0), mixed(1), mixed(2), (total * 255.0).round() as u8])
+}
+
+///
+/// Encodes the animation as an APNG, without loss, each of its frames covering the whole canvas
+///
+pub fn encode_apng<W: Write>(animation: &Animation, w: W, encoding: &PngEncoding) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(w, animation.width, animation.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoding.apply(&mut encoder);
+    let plays = match animation.repeat {
+        Some(0) => 0,
+        Some(n) => n as u32 + 1,
+        None => 1,
+    };
+    encoder.set_animated(animation.frames.len() as u32, plays).map_err(io::Error::other)?;
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    for frame in &animation.frames {
+        // each frame replaces the whole canvas
+        writer.set_blend_op(png::BlendOp::Source).map_err(io::Error::other)?;
+        writer.set_dispose_op(png::DisposeOp::None).map_err(io::Error::other)?;
+        writer.set_frame_delay(frame.delay.0, frame.delay.1).map_err(io::Error::other)?;
+        writer.write_image_data(&frame.image).map_err(io::Error::other)?;
+    }
+    writer.finish().map_err(io::Error::other)
+}
+
+///
+/// Pixelises each frame of the animation with the same grid
+///
+pub fn pixelise_animation(animation: &Animation, options: &Options) -> Animation {
+    pixelise_animation_with_progress(animation, options, &|_, _| {})
+}
+
+///
+/// Pixelises all the frames of an animation, reporting the number of frames done and the number of frames
+///
+pub fn pixelise_animation_with_progress<P>(animation: &Animation, options: &Options, progress: &P) -> Animation
+    where P: Fn(u64, u64) {
+    let total = animation.frames.len() as u64;
+    // the frames are padded or cropped as the canvas is
+    let (width, height) = plan::fit_grid(animation.width, animation.height, options)
+        .unwrap_or((animation.width, animation.height));
+
+    Animation {
+        width: width * options.scale.max(1),
+        height: height * options.scale.max(1),
+        frames: animation.frames.iter().enumerate().map(|(i, f)| {
+            let image = pixelise_with(&f.image, options);
+            progress(i as u64 + 1, total);
+            AnimationFrame {
+                image,
+                delay: f.delay,
+            }
+        }).collect(),
+        repeat: animation.repeat,
+    }
+}
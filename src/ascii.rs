@@ -0,0 +1,36 @@
+use color::luminance;
+use font::{glyph, HEIGHT, WIDTH};
+use image::Rgba;
+
+
+///
+/// The characters of the cells, from the darkest to the brightest, each covering more of its cell than the previous,
+/// as the characters are drawn in the colors of the cells over a dark background
+///
+const RAMP: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+///
+/// The character of the ramp of that luminance, or a space when the color is more than half transparent
+///
+pub fn character(color: Rgba<u8>) -> char {
+    if color[3] < 128 {
+        return ' ';
+    }
+    let index = (luminance(color) / 256.0 * RAMP.len() as f32) as usize;
+    RAMP[index.min(RAMP.len() - 1)]
+}
+
+///
+/// Whether the character covers the point (x, y) of its cell, from its center, in widths and heights of the cell
+///
+/// The glyph of the font is stretched over the cell, leaving half a dot around it,
+/// as the characters of a text are spaced.
+///
+pub fn covers(c: char, (x, y): (f32, f32)) -> bool {
+    let column = ((x + 0.5) * (WIDTH + 1) as f32 - 0.5).floor();
+    let row = ((y + 0.5) * (HEIGHT + 1) as f32 - 0.5).floor();
+    if column < 0.0 || row < 0.0 || column >= WIDTH as f32 || row >= HEIGHT as f32 {
+        return false;
+    }
+    glyph(c)[row as usize] & (0x10 >> column as u32) != 0
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use hexpxl::{pixelise_with, Options, PixelMode};
+use image::{Rgba, RgbaImage};
+
+
+///
+/// A synthetic image of that size, the same on every machine: gradients crossed by waves and by noise,
+/// so that the samplings and the superpixels have colors to tell apart
+///
+pub fn test_image(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (u, v) = (x as f32 / width as f32, y as f32 / height as f32);
+        let wave = (u * 12.0).sin() * (v * 9.0).cos() * 0.5 + 0.5;
+        let noise = (hash(x, y) & 0x1f) as f32;
+        let channel = |value: f32| (value * 223.0 + noise).min(255.0) as u8;
+        Rgba([channel(u), channel(v), channel(wave), 255])
+    })
+}
+
+/// The bits of the pixel (x, y) mixed together
+fn hash(x: u32, y: u32) -> u32 {
+    let mut h = x.wrapping_mul(0x9e37_79b1) ^ y.wrapping_mul(0x85eb_ca77);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^ (h >> 12)
+}
+
+///
+/// The fastest of the pixelisations of an image of a mode, with a number of threads
+///
+pub struct Measure {
+    pub mode: PixelMode,
+    pub width: u32,
+    pub height: u32,
+    pub threads: usize,
+    pub time: Duration,
+}
+
+impl Measure {
+    /// The millions of pixels pixelised per second
+    pub fn throughput(&self) -> f64 {
+        self.width as f64 * self.height as f64 / 1e6 / self.time.as_secs_f64().max(1e-9)
+    }
+
+    /// The measure as a line of JSON, the duration being in milliseconds
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"mode": "{}", "width": {}, "height": {}, "threads": {}, "time_ms": {:.3}, "mpix_per_s": {:.3}}}"#,
+            self.mode,
+            self.width,
+            self.height,
+            self.threads,
+            self.time.as_secs_f64() * 1000.0,
+            self.throughput(),
+        )
+    }
+
+    /// The mode, the size and the threads of the measure, which its baseline has as well
+    pub fn key(&self) -> (String, u32, u32, usize) {
+        (self.mode.to_string(), self.width, self.height, self.threads)
+    }
+}
+
+///
+/// Times the pixelisations of the image with the options, keeping the fastest of the runs
+///
+pub fn measure(img: &RgbaImage, options: &Options, runs: u32) -> Duration {
+    (0..runs.max(1)).map(|_| {
+        let start = Instant::now();
+        black_box::<RgbaImage>(pixelise_with(img, options));
+        start.elapsed()
+    }).min().unwrap()
+}
+
+///
+/// The throughputs of the measures written by --save, by mode, width, height and threads
+///
+/// Each line holds the JSON of a measure, as written by `Measure::to_json`. The other lines are left out.
+///
+pub fn read_baseline(text: &str) -> HashMap<(String, u32, u32, usize), f64> {
+    text.lines().filter_map(|line| {
+        let key = (
+            json_field(line, "mode")?.to_string(),
+            json_field(line, "width")?.parse().ok()?,
+            json_field(line, "height")?.parse().ok()?,
+            json_field(line, "threads")?.parse().ok()?,
+        );
+        Some((key, json_field(line, "mpix_per_s")?.parse().ok()?))
+    }).collect()
+}
+
+/// The value of a field of a flat JSON object of numbers and unescaped strings
+fn json_field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let name = format!("\"{}\":", key);
+    let rest = line[line.find(&name)? + name.len()..].trim_start();
+    let end = rest.find([',', '}'])?;
+    Some(rest[..end].trim().trim_matches('"'))
+}
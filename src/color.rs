@@ -0,0 +1,111 @@
+use image::{Primitive, Rgba};
+
+
+///
+/// The type of the channels of the colors, of 8 or 16 bits
+///
+pub trait Channel: Primitive + Ord + Send + Sync + 'static {
+    /// The value of a channel at full intensity
+    const MAX: u32;
+
+    /// The value of the channel, from 0 to `MAX`
+    fn value(self) -> u32;
+
+    /// The channel of that value, from 0 to `MAX`
+    fn from_value(value: u32) -> Self;
+
+    /// The channel of the same intensity as the 8 bits one
+    fn from_8_bits(value: u8) -> Self {
+        Self::from_value(value as u32 * Self::MAX / 255)
+    }
+
+    /// The 8 bits channel closest to this one
+    fn to_8_bits(self) -> u8 {
+        ((self.value() * 255 + Self::MAX / 2) / Self::MAX) as u8
+    }
+
+    /// The channel closest to that intensity, from 0 to 1
+    fn from_unit(intensity: f32) -> Self {
+        Self::from_value((intensity.clamp(0.0, 1.0) * Self::MAX as f32).round() as u32)
+    }
+}
+
+impl Channel for u8 {
+    const MAX: u32 = 255;
+
+    fn value(self) -> u32 {
+        self as u32
+    }
+
+    fn from_value(value: u32) -> u8 {
+        value as u8
+    }
+}
+
+impl Channel for u16 {
+    const MAX: u32 = 65535;
+
+    fn value(self) -> u32 {
+        self as u32
+    }
+
+    fn from_value(value: u32) -> u16 {
+        value as u16
+    }
+}
+
+/// The color with channels of the type `C` of the same intensities as the 8 bits one
+pub fn widen<C: Channel>(color: Rgba<u8>) -> Rgba<C> {
+    Rgba(color.data.map(C::from_8_bits))
+}
+
+/// The 8 bits color closest to the color
+pub fn narrow<C: Channel>(color: Rgba<C>) -> Rgba<u8> {
+    Rgba(color.data.map(C::to_8_bits))
+}
+
+///
+/// Parses a color written as `#rrggbb` or `#rrggbbaa`, the `#` being optional
+///
+pub fn parse_color(text: &str) -> Option<Rgba<u8>> {
+    let hex = text.trim_start_matches('#');
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(3)? } else { 255 };
+    Some(Rgba([channel(0)?, channel(1)?, channel(2)?, alpha]))
+}
+
+///
+/// Writes the color channels as `#rrggbb`, leaving out the alpha channel
+///
+pub fn format_color(color: Rgba<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+///
+/// The perceived brightness of the color, from 0 for black to 255 for white, leaving out the alpha channel
+///
+pub fn luminance(color: Rgba<u8>) -> f32 {
+    0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32
+}
+
+///
+/// The intensity in linear light of an sRGB channel value, from 0 to 65535
+///
+pub fn to_linear<C: Channel>(value: C) -> u16 {
+    let v = value.value() as f32 / C::MAX as f32;
+    let linear = if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) };
+    (linear * 65535.0).round() as u16
+}
+
+///
+/// The sRGB channel value of an intensity in linear light, from 0 to 65535
+///
+pub fn from_linear<C: Channel>(intensity: f32) -> C {
+    let v = (intensity / 65535.0).clamp(0.0, 1.0);
+    let srgb = if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 };
+    C::from_unit(srgb)
+}
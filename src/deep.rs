@@ -0,0 +1,235 @@
+use std::io::{Cursor, Write};
+
+use image::{imageops, ImageError, ImageFormat, ImageResult, RgbaImage};
+use png;
+use tiff;
+use tiff::decoder::DecodingResult;
+use tiff::encoder::{colortype, TiffEncoder, TiffValue};
+use color::narrow;
+use RgbaBuffer;
+
+
+/// An RGBA image of 16 bits per channel
+pub type Rgba16Image = RgbaBuffer<u16>;
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum PngCompression {
+        fast,
+        default,
+        best,
+    }
+}
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum PngFilter {
+        none,
+        sub,
+        up,
+        avg,
+        paeth,
+        adaptive,
+    }
+}
+
+///
+/// How the rows of the PNG images are filtered and compressed
+///
+/// The adaptive filter picks the best of the others for each row, which is slower.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PngEncoding {
+    pub compression: PngCompression,
+    pub filter: PngFilter,
+}
+
+impl Default for PngEncoding {
+    fn default() -> PngEncoding {
+        PngEncoding { compression: PngCompression::default, filter: PngFilter::sub }
+    }
+}
+
+impl PngEncoding {
+    /// Sets the compression and the filter of the encoder
+    pub fn apply<W: Write>(&self, encoder: &mut png::Encoder<W>) {
+        encoder.set_compression(match self.compression {
+            PngCompression::fast => png::Compression::Fast,
+            PngCompression::default => png::Compression::Default,
+            PngCompression::best => png::Compression::Best,
+        });
+        let (filter, adaptive) = match self.filter {
+            PngFilter::none => (png::FilterType::NoFilter, false),
+            PngFilter::sub => (png::FilterType::Sub, false),
+            PngFilter::up => (png::FilterType::Up, false),
+            PngFilter::avg => (png::FilterType::Avg, false),
+            PngFilter::paeth => (png::FilterType::Paeth, false),
+            PngFilter::adaptive => (png::FilterType::Sub, true),
+        };
+        encoder.set_filter(filter);
+        encoder.set_adaptive_filter(if adaptive { png::AdaptiveFilterType::Adaptive } else { png::AdaptiveFilterType::NonAdaptive });
+    }
+}
+
+fn format_error<E: ToString>(e: E) -> ImageError {
+    ImageError::FormatError(e.to_string())
+}
+
+///
+/// Decodes the PNG or TIFF image when it has 16 bits per channel
+///
+/// None for the images of 8 bits or less per channel, and for the images of the other formats,
+/// which are decoded with 8 bits per channel.
+///
+pub fn decode(data: &[u8], format: ImageFormat) -> ImageResult<Option<Rgba16Image>> {
+    match format {
+        ImageFormat::PNG => decode_png(data),
+        ImageFormat::TIFF => decode_tiff(data),
+        _ => Ok(None),
+    }
+}
+
+///
+/// Whether the PNG or TIFF image has 16 bits per channel, read from its header without decoding its pixels
+///
+pub fn has_16_bits(data: &[u8], format: ImageFormat) -> bool {
+    match format {
+        ImageFormat::PNG => png::Decoder::new(data).read_info()
+            .map(|reader| reader.info().bit_depth == png::BitDepth::Sixteen)
+            .unwrap_or(false),
+        ImageFormat::TIFF => tiff::decoder::Decoder::new(Cursor::new(data)).and_then(|mut decoder| decoder.colortype())
+            .map(|color| matches!(color, tiff::ColorType::Gray(16) | tiff::ColorType::GrayA(16) |
+                tiff::ColorType::RGB(16) | tiff::ColorType::RGBA(16)))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+fn decode_png(data: &[u8]) -> ImageResult<Option<Rgba16Image>> {
+    let mut decoder = png::Decoder::new(data);
+    // expands the palettes and the transparent colors, but keeps the 16 bits
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(format_error)?;
+    if reader.info().bit_depth != png::BitDepth::Sixteen {
+        return Ok(None);
+    }
+    let (width, height) = reader.info().size();
+    let channels = reader.output_color_type().0.samples();
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).map_err(format_error)?;
+    let samples: Vec<u16> = buffer[..frame.buffer_size()].chunks(2).map(|s| u16::from_be_bytes([s[0], s[1]])).collect();
+    Ok(Some(to_rgba(width, height, &samples, channels)))
+}
+
+fn decode_tiff(data: &[u8]) -> ImageResult<Option<Rgba16Image>> {
+    let mut decoder = tiff::decoder::Decoder::new(Cursor::new(data)).map_err(format_error)?;
+    let channels = match decoder.colortype().map_err(format_error)? {
+        tiff::ColorType::Gray(16) => 1,
+        tiff::ColorType::GrayA(16) => 2,
+        tiff::ColorType::RGB(16) => 3,
+        tiff::ColorType::RGBA(16) => 4,
+        _ => return Ok(None),
+    };
+    let (width, height) = decoder.dimensions().map_err(format_error)?;
+    match decoder.read_image().map_err(format_error)? {
+        DecodingResult::U16(samples) => Ok(Some(to_rgba(width, height, &samples, channels))),
+        _ => Ok(None),
+    }
+}
+
+///
+/// The image made of the pixels of `samples`, of 1 to 4 channels
+///
+/// 1 channel is gray, 2 channels are gray and alpha, 3 channels are RGB.
+///
+fn to_rgba(width: u32, height: u32, samples: &[u16], channels: usize) -> Rgba16Image {
+    let pixels: Vec<u16> = samples.chunks(channels).flat_map(|pixel| match *pixel {
+        [v] => [v, v, v, u16::MAX],
+        [v, a] => [v, v, v, a],
+        [r, g, b] => [r, g, b, u16::MAX],
+        [r, g, b, a] => [r, g, b, a],
+        _ => unreachable!(),
+    }).collect();
+    RgbaBuffer::from_raw(width, height, pixels).expect("the image has as many pixels as its size")
+}
+
+///
+/// Encodes the image as a PNG or TIFF image of 16 bits per channel
+///
+/// None for the other formats, which only hold 8 bits per channel.
+///
+pub fn encode(img: &Rgba16Image, format: ImageFormat, encoding: &PngEncoding) -> Option<ImageResult<Vec<u8>>> {
+    match format {
+        ImageFormat::PNG => {
+            let bytes: Vec<u8> = img.iter().flat_map(|s| s.to_be_bytes()).collect();
+            Some(encode_png(img.width(), img.height(), &bytes, png::BitDepth::Sixteen, encoding))
+        }
+        ImageFormat::TIFF => Some(tiff_image::<colortype::RGBA16>(img.width(), img.height(), img)),
+        _ => None,
+    }
+}
+
+/// Encodes the RGBA samples of that depth, the samples of 16 bits being big endian, as a PNG image
+fn encode_png(width: u32, height: u32, bytes: &[u8], depth: png::BitDepth, encoding: &PngEncoding) -> ImageResult<Vec<u8>> {
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut encoded, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(depth);
+        encoding.apply(&mut encoder);
+        let mut writer = encoder.write_header().map_err(format_error)?;
+        writer.write_image_data(bytes).map_err(format_error)?;
+        writer.finish().map_err(format_error)?;
+    }
+    Ok(encoded)
+}
+
+///
+/// Encodes the image of 8 bits per channel as a PNG image, filtered and compressed as set
+///
+pub fn encode_png8(img: &RgbaImage, encoding: &PngEncoding) -> ImageResult<Vec<u8>> {
+    encode_png(img.width(), img.height(), img, png::BitDepth::Eight, encoding)
+}
+
+///
+/// Encodes the image of 8 bits per channel as a TIFF image, which the image crate cannot do
+///
+pub fn encode_tiff(img: &RgbaImage) -> ImageResult<Vec<u8>> {
+    tiff_image::<colortype::RGBA8>(img.width(), img.height(), img)
+}
+
+fn tiff_image<T>(width: u32, height: u32, samples: &[T::Inner]) -> ImageResult<Vec<u8>>
+    where T: colortype::ColorType, [T::Inner]: TiffValue {
+    let mut encoded = Cursor::new(Vec::new());
+    TiffEncoder::new(&mut encoded)
+        .and_then(|mut encoder| encoder.write_image::<T>(width, height, samples))
+        .map_err(format_error)?;
+    Ok(encoded.into_inner())
+}
+
+///
+/// The image with 8 bits per channel, each channel being rounded to the closest value
+///
+pub fn narrowed(img: &Rgba16Image) -> RgbaImage {
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| narrow(*img.get_pixel(x, y)))
+}
+
+///
+/// Turns the image upright according to its EXIF orientation, as does `metadata::orient`
+///
+pub fn orient(img: Rgba16Image, orientation: u32) -> Rgba16Image {
+    match orientation {
+        2 => imageops::flip_horizontal(&img),
+        3 => imageops::rotate180(&img),
+        4 => imageops::flip_vertical(&img),
+        // transposed
+        5 => imageops::flip_horizontal(&imageops::rotate90(&img)),
+        6 => imageops::rotate90(&img),
+        // transversed
+        7 => imageops::flip_horizontal(&imageops::rotate270(&img)),
+        8 => imageops::rotate270(&img),
+        _ => img,
+    }
+}
@@ -0,0 +1,110 @@
+use std::f32::consts::PI;
+
+use color::{narrow, widen, Channel};
+use image::Rgba;
+use palette::Palette;
+use tessellation::Tessellation;
+
+
+///
+/// The cells next to each cell, found from the cells of the pixels
+///
+/// Two cells are next to each other when a pixel of one is beside a pixel of the other, to the side or above.
+///
+pub struct Adjacency {
+    centers: Vec<(f32, f32)>,
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl Adjacency {
+    ///
+    /// # Arguments
+    ///
+    /// * `cells` - The cell index of each pixel of the image, row by row, as given by `cell_map`
+    /// * `width` - The width of the image
+    ///
+    pub fn new<T>(tessellation: &T, cells: &[usize], width: u32) -> Adjacency
+        where T: Tessellation + ?Sized {
+        let width = width.max(1) as usize;
+        let mut neighbours = vec![Vec::new(); tessellation.cell_count()];
+        let mut join = |a: usize, b: usize| {
+            if a != b {
+                neighbours[a].push(b);
+                neighbours[b].push(a);
+            }
+        };
+        for (i, &cell) in cells.iter().enumerate() {
+            if i % width > 0 {
+                join(cell, cells[i - 1]);
+            }
+            if i >= width {
+                join(cell, cells[i - width]);
+            }
+        }
+        for cell in neighbours.iter_mut() {
+            cell.sort_unstable();
+            cell.dedup();
+        }
+
+        Adjacency {
+            centers: (0..tessellation.cell_count()).map(|i| tessellation.center(i)).collect(),
+            neighbours,
+        }
+    }
+}
+
+///
+/// The share of the error going to a cell in that direction from the dithered one, in radians clockwise from the right
+///
+/// The shares of Floyd-Steinberg, 7/16 to the right, 1/16 below to the right, 5/16 below and 3/16 below to the left,
+/// are interpolated between these directions, for the cells which are not squares.
+///
+fn share(angle: f32) -> f32 {
+    const SHARES: [f32; 4] = [7.0, 1.0, 5.0, 3.0];
+    let step = (angle.clamp(0.0, 3.0 * PI / 4.0) / (PI / 4.0)).min(2.999);
+    let (k, t) = (step as usize, step.fract());
+    SHARES[k] + (SHARES[k + 1] - SHARES[k]) * t
+}
+
+///
+/// Snaps the colors of the cells to the palette, diffusing the error of each cell over the next ones, by Floyd-Steinberg
+///
+/// The cells are taken in the reading order of their centers, row by row, and each of them is snapped to the palette
+/// once the errors of the previous ones are added to its color. Its own error is then shared by its neighbours
+/// which come after it, mostly to its right and below it, so that the colors of an area average to those of the image
+/// rather than being banded. The transparent cells, which are not seen, neither take nor give any error.
+///
+pub fn dither<C: Channel>(colors: &mut [Rgba<C>], palette: &Palette, adjacency: &Adjacency) {
+    let centers = &adjacency.centers;
+    let mut order: Vec<usize> = (0..colors.len()).collect();
+    order.sort_by(|&a, &b| {
+        (centers[a].1, centers[a].0).partial_cmp(&(centers[b].1, centers[b].0)).unwrap()
+    });
+    let mut rank = vec![0; colors.len()];
+    for (r, &cell) in order.iter().enumerate() {
+        rank[cell] = r;
+    }
+
+    let mut errors = vec![[0f32; 3]; colors.len()];
+    for &cell in &order {
+        let color = narrow(colors[cell]);
+        let wanted = [0, 1, 2].map(|c| (color[c] as f32 + errors[cell][c]).clamp(0.0, 255.0));
+        let snapped = palette.nearest(Rgba([wanted[0].round() as u8, wanted[1].round() as u8, wanted[2].round() as u8, color[3]]));
+        colors[cell] = widen(snapped);
+        if color[3] == 0 {
+            continue;
+        }
+
+        let (x, y) = centers[cell];
+        let next: Vec<(usize, f32)> = adjacency.neighbours[cell].iter()
+            .filter(|&&n| rank[n] > rank[cell] && narrow(colors[n])[3] > 0)
+            .map(|&n| (n, share((centers[n].1 - y).atan2(centers[n].0 - x))))
+            .collect();
+        let total: f32 = next.iter().map(|&(_, s)| s).sum();
+        for &(n, s) in &next {
+            for c in 0..3 {
+                errors[n][c] += (wanted[c] - snapped[c] as f32) * s / total;
+            }
+        }
+    }
+}
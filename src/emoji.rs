@@ -0,0 +1,77 @@
+use image::Rgba;
+use palette::Palette;
+use EmojiShape;
+
+
+///
+/// The colors of the emoji of each shape, as most emoji fonts draw them: red, orange, yellow, green, blue, purple,
+/// brown, black and white
+///
+const COLORS: [[u8; 3]; 9] = [
+    [221, 46, 68], [244, 144, 12], [253, 203, 88], [120, 177, 89], [85, 172, 238],
+    [170, 142, 214], [193, 105, 79], [49, 55, 61], [230, 231, 232],
+];
+
+const SQUARES: [&str; 9] = ["🟥", "🟧", "🟨", "🟩", "🟦", "🟪", "🟫", "⬛", "⬜"];
+const CIRCLES: [&str; 9] = ["🔴", "🟠", "🟡", "🟢", "🔵", "🟣", "🟤", "⚫", "⚪"];
+const HEARTS: [&str; 9] = ["❤️", "🧡", "💛", "💚", "💙", "💜", "🤎", "🖤", "🤍"];
+
+/// The character written for the transparent cells, a space as wide as the emoji
+const BLANK: &str = "\u{3000}";
+
+///
+/// The colors of the emoji, as a palette, so that the colors of the cells can be snapped and dithered to them
+///
+pub fn palette() -> Palette {
+    Palette::new(COLORS.iter().map(|&[r, g, b]| Rgba([r, g, b, 255])).collect())
+}
+
+///
+/// The index of the emoji whose color is the closest to `color`
+///
+pub fn nearest(color: Rgba<u8>) -> usize {
+    let distance = |c: &[u8; 3]| -> u32 {
+        (0..3).map(|i| sqr!(c[i] as i32 - color[i] as i32) as u32).sum()
+    };
+    (0..COLORS.len()).min_by_key(|&i| distance(&COLORS[i])).unwrap()
+}
+
+/// The color of the emoji, keeping the transparency of `color`
+pub fn color(index: usize, color: Rgba<u8>) -> Rgba<u8> {
+    let [r, g, b] = COLORS[index];
+    Rgba([r, g, b, color[3]])
+}
+
+/// The emoji of that shape and index
+pub fn symbol(shape: EmojiShape, index: usize) -> &'static str {
+    match shape {
+        EmojiShape::square => SQUARES[index],
+        EmojiShape::circle => CIRCLES[index],
+        EmojiShape::heart => HEARTS[index],
+    }
+}
+
+///
+/// Whether the emoji of the shape covers the point (x, y) of its cell, from its center, in widths and heights of the cell
+///
+/// The shapes leave a margin inside the cell, as the emoji of a text do.
+///
+pub fn covers(shape: EmojiShape, (x, y): (f32, f32)) -> bool {
+    match shape {
+        EmojiShape::square => x.abs() < 0.42 && y.abs() < 0.42,
+        EmojiShape::circle => sqr!(x) + sqr!(y) < sqr!(0.45),
+        EmojiShape::heart => {
+            // (x² + y² - 1)³ < x² y³, the heart being 2.3 wide and 2.2 high around its center
+            let (x, y) = (x * 2.6, -y * 2.6 + 0.2);
+            let r = sqr!(x) + sqr!(y) - 1.0;
+            r * r * r < sqr!(x) * y * y * y
+        }
+    }
+}
+
+///
+/// The emoji of that shape closest to `color`, or a wide space when the color is more than half transparent
+///
+pub fn text(shape: EmojiShape, color: Rgba<u8>) -> &'static str {
+    if color[3] < 128 { BLANK } else { symbol(shape, nearest(color)) }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::fmt::Write;
+
+use color::format_color;
+use image::{GenericImageView, Rgba, RgbaImage};
+use rayon::prelude::*;
+use {cell_colors, cell_map, format_number, tessellation, Options, Orientation, PixelMode, Steps};
+
+
+///
+/// A hexagon of the grid, with its sampled color
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HexCell {
+    /// The axial coordinates q and r of the hexagon, q being along its rows of hexagons and r across them
+    pub axial: (i32, i32),
+    /// The center of the hexagon in the image, in pixels
+    pub center: (f32, f32),
+    /// The color of the hexagon, as sampled and adjusted by the settings
+    pub color: Rgba<u8>,
+}
+
+///
+/// The hexagons of the grid containing at least one pixel of the image, with their colors
+///
+/// None when the mode of the options does not pixelise with a grid of hexagons, the hex and dots modes being the ones.
+///
+pub fn hex_cells<I>(img: &I, options: &Options) -> Option<Vec<HexCell>>
+    where I: GenericImageView<Pixel=Rgba<u8>> {
+    if options.mode != PixelMode::hex && options.mode != PixelMode::dots {
+        return None;
+    }
+    let (width, height) = img.dimensions();
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    let tessellation = tessellation(img, options);
+    let cells = cell_map(tessellation.as_ref(), width, height, options.chunk_size, &Steps::new(0, &|_, _| {}));
+    let colors = cell_colors(img, tessellation.as_ref(), options, Some(&cells));
+
+    let mut used = vec![false; tessellation.cell_count()];
+    for &cell in &cells {
+        used[cell] = true;
+    }
+    Some((0..used.len()).filter(|&i| used[i]).filter_map(|i| {
+        tessellation.axial(i).map(|axial| HexCell { axial, center: tessellation.center(i), color: colors[i] })
+    }).collect())
+}
+
+///
+/// A cell of the grid, with the statistics of its pixels
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellStats {
+    /// The index of the cell in the grid
+    pub index: usize,
+    /// The center of the cell in the image, in pixels
+    pub center: (f32, f32),
+    /// The axial coordinates q and r of the hexagons, None for the other cells
+    pub axial: Option<(i32, i32)>,
+    /// The mean red, green, blue and alpha of the pixels of the cell, before the adjustments of the settings
+    pub mean: [f32; 4],
+    /// The number of pixels of the cell
+    pub pixels: u32,
+    /// The variance of the colors of the pixels, the sum of those of their red, green and blue
+    pub variance: f32,
+}
+
+///
+/// The cells of the grid containing at least one pixel of the image, with the statistics of their pixels
+///
+/// The grid is that of the whole image, without its regions, faces or mask, as for `overlay_grid`.
+///
+pub fn cell_stats<I>(img: &I, options: &Options) -> Vec<CellStats>
+    where I: GenericImageView<Pixel=Rgba<u8>> {
+    let (width, height) = img.dimensions();
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    let tessellation = tessellation(img, options);
+    let cells = cell_map(tessellation.as_ref(), width, height, options.chunk_size, &Steps::new(0, &|_, _| {}));
+
+    // the sums of the channels, and of the squares of the red, green and blue, of the pixels of each cell
+    let mut counts = vec![0u32; tessellation.cell_count()];
+    let mut sums = vec![[0f64; 4]; tessellation.cell_count()];
+    let mut squares = vec![[0f64; 3]; tessellation.cell_count()];
+    for (i, &cell) in cells.iter().enumerate() {
+        let pixel = img.get_pixel(i as u32 % width, i as u32 / width);
+        counts[cell] += 1;
+        for c in 0..4 {
+            sums[cell][c] += pixel[c] as f64;
+        }
+        for c in 0..3 {
+            squares[cell][c] += sqr!(pixel[c] as f64);
+        }
+    }
+
+    (0..counts.len()).filter(|&i| counts[i] > 0).map(|i| {
+        let count = counts[i] as f64;
+        let mean = [sums[i][0] / count, sums[i][1] / count, sums[i][2] / count, sums[i][3] / count];
+        // clamped, as the rounding may leave it a little under 0
+        let variance: f64 = (0..3).map(|c| (squares[i][c] / count - sqr!(mean[c])).max(0.0)).sum();
+        CellStats {
+            index: i,
+            center: tessellation.center(i),
+            axial: tessellation.axial(i),
+            mean: [mean[0] as f32, mean[1] as f32, mean[2] as f32, mean[3] as f32],
+            pixels: counts[i],
+            variance: variance as f32,
+        }
+    }).collect()
+}
+
+///
+/// The cells as CSV, a row for each of them under a header
+///
+/// The axial coordinates are left empty for the cells which are not hexagons.
+///
+pub fn to_csv(cells: &[CellStats]) -> String {
+    let mut csv = String::new();
+    writeln!(csv, "index,x,y,q,r,red,green,blue,alpha,pixels,variance").unwrap();
+    for cell in cells {
+        let (q, r) = match cell.axial {
+            Some((q, r)) => (q.to_string(), r.to_string()),
+            None => (String::new(), String::new()),
+        };
+        writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            cell.index,
+            format_number(cell.center.0),
+            format_number(cell.center.1),
+            q,
+            r,
+            format_number(cell.mean[0]),
+            format_number(cell.mean[1]),
+            format_number(cell.mean[2]),
+            format_number(cell.mean[3]),
+            cell.pixels,
+            format_number(cell.variance),
+        ).unwrap();
+    }
+    csv
+}
+
+/// The color of the boundaries of the cells drawn by `overlay_grid`
+const OVERLAY_LINE: Rgba<u8> = Rgba { data: [255, 0, 96, 255] };
+/// The color of the centers of the cells drawn by `overlay_grid`
+const OVERLAY_CENTER: Rgba<u8> = Rgba { data: [0, 224, 255, 255] };
+
+///
+/// The image with the grid of the options drawn over it, to see how the cells fall on the image
+///
+/// The boundaries between the cells are drawn a pixel wide, on the pixels whose right or bottom neighbour
+/// is in another cell, and the centers of the cells, where the center sampling takes their colors,
+/// as dots of 2 pixels around them. The grid is that of the whole image, without its regions, faces or mask.
+///
+pub fn overlay_grid<I>(img: &I, options: &Options) -> RgbaImage
+    where I: GenericImageView<Pixel=Rgba<u8>> + Sync {
+    let (width, height) = img.dimensions();
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    let tessellation = tessellation(img, options);
+    let cells = cell_map(tessellation.as_ref(), width, height, options.chunk_size, &Steps::new(0, &|_, _| {}));
+    let row = width.max(1) as usize;
+
+    let mut overlay = RgbaImage::new(width, height);
+    overlay.par_chunks_mut(row * 4).enumerate().for_each(|(y, pixels)| {
+        for (x, pixel) in pixels.chunks_mut(4).enumerate() {
+            let i = y * row + x;
+            let boundary = (x + 1 < row && cells[i + 1] != cells[i]) || (i + row < cells.len() && cells[i + row] != cells[i]);
+            let color = if boundary { OVERLAY_LINE } else { img.get_pixel(x as u32, y as u32) };
+            pixel.copy_from_slice(&color.data);
+        }
+    });
+
+    // only the cells with pixels, as some grids have cells which are never used
+    let mut used = vec![false; tessellation.cell_count()];
+    for &cell in &cells {
+        used[cell] = true;
+    }
+    for i in (0..used.len()).filter(|&i| used[i]) {
+        let (cx, cy) = tessellation.center(i);
+        let (left, top) = ((cx - 2.0).max(0.0).ceil() as u32, (cy - 2.0).max(0.0).ceil() as u32);
+        let (right, bottom) = ((cx + 2.0).min(width as f32 - 1.0), (cy + 2.0).min(height as f32 - 1.0));
+        if right < 0.0 || bottom < 0.0 {
+            continue;
+        }
+        for y in top..=bottom as u32 {
+            for x in left..=right as u32 {
+                if sqr!(x as f32 - cx) + sqr!(y as f32 - cy) <= 4.0 {
+                    overlay.put_pixel(x, y, OVERLAY_CENTER);
+                }
+            }
+        }
+    }
+    overlay
+}
+
+///
+/// The hexagons as a JSON document, with the settings of their grid
+///
+/// Each cell has its axial coordinates `q` and `r`, its center `x` and `y` and its `color` and `alpha`.
+///
+pub fn to_json(cells: &[HexCell], options: &Options, (width, height): (u32, u32)) -> String {
+    let mut json = String::new();
+    writeln!(json, "{{").unwrap();
+    writeln!(json, r#"  "orientation": "{}","#, options.orientation).unwrap();
+    writeln!(json, r#"  "size": {},"#, options.size).unwrap();
+    writeln!(json, r#"  "width": {},"#, width).unwrap();
+    writeln!(json, r#"  "height": {},"#, height).unwrap();
+    writeln!(json, r#"  "cells": ["#).unwrap();
+    for (i, cell) in cells.iter().enumerate() {
+        writeln!(
+            json,
+            r#"    {{"q": {}, "r": {}, "x": {}, "y": {}, "color": "{}", "alpha": {}}}{}"#,
+            cell.axial.0,
+            cell.axial.1,
+            format_number(cell.center.0),
+            format_number(cell.center.1),
+            format_color(cell.color),
+            cell.color[3],
+            if i + 1 < cells.len() { "," } else { "" },
+        ).unwrap();
+    }
+    writeln!(json, "  ]").unwrap();
+    writeln!(json, "}}").unwrap();
+    json
+}
+
+///
+/// The hexagons as a hexagonal map of Tiled, and the image of its tileset
+///
+/// Each color is a tile of the tileset, a hexagon filled with it, which has the color as its `color` property.
+/// The map uses the offset coordinates of Tiled, every other row of pointy top hexagons,
+/// or every other column of flat top ones, being staggered.
+/// The sizes of the tiles are rounded to the pixel, so that the map drifts a little from the image.
+///
+/// # Arguments
+///
+/// * `cells` - The hexagons of the map
+/// * `options` - The settings of the grid of the hexagons
+/// * `tileset` - The path of the image of the tileset, relative to the map
+///
+pub fn to_tmx(cells: &[HexCell], options: &Options, tileset: &str) -> (String, RgbaImage) {
+    let flat = options.orientation == Orientation::flat;
+    let side = options.size;
+    let across = (2.0 * side as f32 * (PI / 6.0).cos()).round() as u32;
+    let (tile_width, tile_height) = if flat { (2 * side, across) } else { (across, 2 * side) };
+
+    // the offset coordinates, the odd rows or columns being shifted by half a hexagon
+    let offsets: Vec<(i32, i32)> = cells.iter().map(|cell| {
+        let (q, r) = cell.axial;
+        if flat { (q, r + q.div_euclid(2)) } else { (q + r.div_euclid(2), r) }
+    }).collect();
+    let left = offsets.iter().map(|o| o.0).min().unwrap_or(0);
+    let top = offsets.iter().map(|o| o.1).min().unwrap_or(0);
+    let columns = offsets.iter().map(|o| o.0 - left + 1).max().unwrap_or(0) as usize;
+    let rows = offsets.iter().map(|o| o.1 - top + 1).max().unwrap_or(0) as usize;
+    // moving the map by an odd number of rows or columns changes which ones are staggered
+    let odd = if flat { left % 2 == 0 } else { top % 2 == 0 };
+
+    let mut tiles: Vec<Rgba<u8>> = Vec::new();
+    let mut ids = HashMap::new();
+    let mut gids = vec![0; columns * rows];
+    for (cell, &(column, row)) in cells.iter().zip(&offsets) {
+        let id = *ids.entry(cell.color.data).or_insert_with(|| {
+            tiles.push(cell.color);
+            tiles.len() - 1
+        });
+        // 0 is no tile, the first one is 1
+        gids[(row - top) as usize * columns + (column - left) as usize] = id + 1;
+    }
+
+    let tileset_columns = (tiles.len() as f32).sqrt().ceil().max(1.0) as u32;
+    let tileset_rows = (tiles.len() as u32).div_ceil(tileset_columns).max(1);
+    let mut image = RgbaImage::new(tileset_columns * tile_width, tileset_rows * tile_height);
+    for (id, &color) in tiles.iter().enumerate() {
+        let (tile_x, tile_y) = (id as u32 % tileset_columns * tile_width, id as u32 / tileset_columns * tile_height);
+        for y in 0..tile_height {
+            for x in 0..tile_width {
+                if in_hexagon(x, y, tile_width, tile_height, side as f32, flat) {
+                    image.put_pixel(tile_x + x, tile_y + y, color);
+                }
+            }
+        }
+    }
+
+    let mut tmx = String::new();
+    writeln!(tmx, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        tmx,
+        concat!(
+            r#"<map version="1.10" orientation="hexagonal" renderorder="right-down" width="{}" height="{}" "#,
+            r#"tilewidth="{}" tileheight="{}" hexsidelength="{}" staggeraxis="{}" staggerindex="{}" "#,
+            r#"infinite="0" nextlayerid="2" nextobjectid="1">"#,
+        ),
+        columns,
+        rows,
+        tile_width,
+        tile_height,
+        side,
+        if flat { "x" } else { "y" },
+        if odd { "odd" } else { "even" },
+    ).unwrap();
+    writeln!(
+        tmx,
+        r#" <tileset firstgid="1" name="hexpxl" tilewidth="{}" tileheight="{}" tilecount="{}" columns="{}">"#,
+        tile_width,
+        tile_height,
+        tiles.len(),
+        tileset_columns,
+    ).unwrap();
+    writeln!(tmx, r#"  <image source="{}" width="{}" height="{}"/>"#, escape(tileset), image.width(), image.height()).unwrap();
+    for (id, color) in tiles.iter().enumerate() {
+        writeln!(
+            tmx,
+            r##"  <tile id="{}"><properties><property name="color" type="color" value="#{:02x}{}"/></properties></tile>"##,
+            id,
+            color[3],
+            &format_color(*color)[1..],
+        ).unwrap();
+    }
+    writeln!(tmx, " </tileset>").unwrap();
+    writeln!(tmx, r#" <layer id="1" name="hexpxl" width="{}" height="{}">"#, columns, rows).unwrap();
+    writeln!(tmx, r#"  <data encoding="csv">"#).unwrap();
+    for (y, row) in gids.chunks(columns.max(1)).enumerate() {
+        let row: Vec<String> = row.iter().map(|gid| gid.to_string()).collect();
+        writeln!(tmx, "{}{}", row.join(","), if y + 1 < rows { "," } else { "" }).unwrap();
+    }
+    writeln!(tmx, "  </data>").unwrap();
+    writeln!(tmx, " </layer>").unwrap();
+    writeln!(tmx, "</map>").unwrap();
+    (tmx, image)
+}
+
+/// Whether the center of the pixel of the tile is inside the hexagon of outer radius `side` filling the tile
+fn in_hexagon(x: u32, y: u32, width: u32, height: u32, side: f32, flat: bool) -> bool {
+    let (dx, dy) = ((x as f32 + 0.5 - width as f32 / 2.0).abs(), (y as f32 + 0.5 - height as f32 / 2.0).abs());
+    // the distance across the hexagon and along it, from its center
+    let (across, along, half_across) = if flat { (dy, dx, height as f32 / 2.0) } else { (dx, dy, width as f32 / 2.0) };
+    across <= half_across && along <= side - across * side / 2.0 / half_across
+}
+
+/// The text with the special characters of XML replaced by their entities
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
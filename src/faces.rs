@@ -0,0 +1,90 @@
+use std::io::Cursor;
+
+use color::Channel;
+use image::{GenericImageView, ImageBuffer, Luma, Rgba};
+use rustface::{self, ImageData};
+use RgbaBuffer;
+
+
+/// The SeetaFace frontal face model, see model/LICENSE
+static MODEL: &[u8] = include_bytes!("../model/seeta_fd_frontal_v1.0.bin");
+
+///
+/// A rectangle of the image
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+
+    /// The part of the rectangle inside an image of that size, empty when it is outside of it
+    pub fn clipped(&self, width: u32, height: u32) -> Region {
+        let (x, y) = (self.x.min(width), self.y.min(height));
+        Region {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+}
+
+///
+/// Finds the faces looking at the camera
+///
+/// The regions are larger than the faces found by the detector by a quarter of their size on each side,
+/// so that they also cover the hair and the chin.
+///
+pub fn detect_faces<I>(img: &I) -> Vec<Region>
+    where I: GenericImageView<Pixel=Rgba<u8>> {
+    let (width, height) = img.dimensions();
+    let gray: ImageBuffer<Luma<u8>, Vec<u8>> = ImageBuffer::from_fn(width, height, |x, y| {
+        let p = img.get_pixel(x, y);
+        let luma = (2126 * p[0] as u32 + 7152 * p[1] as u32 + 722 * p[2] as u32) / 10000;
+        Luma([luma as u8])
+    });
+
+    let model = rustface::read_model(Cursor::new(MODEL)).expect("the embedded face model is valid");
+    let mut detector = rustface::create_detector_with_model(model);
+    detector.set_min_face_size(20);
+    detector.set_score_thresh(2.0);
+    detector.set_pyramid_scale_factor(0.8);
+    detector.set_slide_window_step(4, 4);
+
+    detector.detect(&ImageData::new(&gray, width, height)).iter().map(|face| {
+        let bbox = face.bbox();
+        let (margin_x, margin_y) = (bbox.width() as i64 / 4, bbox.height() as i64 / 4);
+        let left = (bbox.x() as i64 - margin_x).max(0);
+        let top = (bbox.y() as i64 - margin_y).max(0);
+        let right = (bbox.x() as i64 + bbox.width() as i64 + margin_x).min(width as i64);
+        let bottom = (bbox.y() as i64 + bbox.height() as i64 + margin_y).min(height as i64);
+
+        Region {
+            x: left as u32,
+            y: top as u32,
+            width: (right - left).max(0) as u32,
+            height: (bottom - top).max(0) as u32,
+        }
+    }).collect()
+}
+
+///
+/// Puts back the pixels of the original image outside of the regions
+///
+pub fn restore_outside<I, C>(img: &I, mut pixelised: RgbaBuffer<C>, regions: &[Region]) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    for (x, y, pixel) in pixelised.enumerate_pixels_mut() {
+        if !regions.iter().any(|r| r.contains(x, y)) {
+            *pixel = img.get_pixel(x, y);
+        }
+    }
+    pixelised
+}
@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use image::{ImageBuffer, Rgba};
+use PixelMode;
+
+
+// The functions and constants are those of include/hexpxl.h.
+// Their signatures and values are kept unchanged between versions, new ones being added instead.
+
+/// The pixelisation succeeded
+pub const HEXPXL_OK: c_int = 0;
+/// An argument is null, out of range or inconsistent with the others
+pub const HEXPXL_INVALID_ARGUMENT: c_int = 1;
+/// The pixelisation failed, `hexpxl_last_error` tells why
+pub const HEXPXL_INTERNAL_ERROR: c_int = 2;
+
+/// Square pixels
+pub const HEXPXL_MODE_SQR: u32 = 0;
+/// Hexagonal pixels
+pub const HEXPXL_MODE_HEX: u32 = 1;
+/// Triangular pixels
+pub const HEXPXL_MODE_TRI: u32 = 2;
+/// Voronoi cells around random seeds
+pub const HEXPXL_MODE_VORONOI: u32 = 3;
+/// Squares split where the image has details
+pub const HEXPXL_MODE_QUADTREE: u32 = 4;
+/// Bricks, every other row being shifted by half a brick
+pub const HEXPXL_MODE_BRICK: u32 = 5;
+/// Dots at the centers of a hexagonal grid, the darker the larger
+pub const HEXPXL_MODE_DOTS: u32 = 6;
+/// Hexagons split into 3 rhombi, like isometric cubes
+pub const HEXPXL_MODE_CUBE: u32 = 7;
+/// Superpixels following the edges of the image
+pub const HEXPXL_MODE_SLIC: u32 = 8;
+/// Overlapping rows of round scales, like those of a fish
+pub const HEXPXL_MODE_SCALES: u32 = 9;
+/// Rectangles laid in the zig-zag of a herringbone parquet
+pub const HEXPXL_MODE_HERRINGBONE: u32 = 10;
+/// A Penrose tiling of thick and thin rhombi, which never repeats
+pub const HEXPXL_MODE_PENROSE: u32 = 11;
+/// Hexagons surrounded by triangles, each triangle between 3 hexagons
+pub const HEXPXL_MODE_TRIHEX: u32 = 12;
+/// Concentric rings around the center of the image, cut into arcs
+pub const HEXPXL_MODE_RINGS: u32 = 13;
+/// A band winding along a spiral from the center of the image, cut into cells along its length
+pub const HEXPXL_MODE_SPIRAL: u32 = 14;
+/// Runs of pixels along a Hilbert curve filling the image
+pub const HEXPXL_MODE_HILBERT: u32 = 15;
+/// Triangles between feature points of the image, like a low poly model
+pub const HEXPXL_MODE_LOWPOLY: u32 = 16;
+/// Crystals grown from random seeds until they fill the image
+pub const HEXPXL_MODE_CRYSTAL: u32 = 17;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn fail(code: c_int, message: &str) -> c_int {
+    // the messages come from this module and the panics, which have no reason to contain a nul
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+    code
+}
+
+fn mode(mode: u32) -> Option<PixelMode> {
+    match mode {
+        HEXPXL_MODE_SQR => Some(PixelMode::sqr),
+        HEXPXL_MODE_HEX => Some(PixelMode::hex),
+        HEXPXL_MODE_TRI => Some(PixelMode::tri),
+        HEXPXL_MODE_VORONOI => Some(PixelMode::voronoi),
+        HEXPXL_MODE_QUADTREE => Some(PixelMode::quadtree),
+        HEXPXL_MODE_BRICK => Some(PixelMode::brick),
+        HEXPXL_MODE_DOTS => Some(PixelMode::dots),
+        HEXPXL_MODE_CUBE => Some(PixelMode::cube),
+        HEXPXL_MODE_SLIC => Some(PixelMode::slic),
+        HEXPXL_MODE_SCALES => Some(PixelMode::scales),
+        HEXPXL_MODE_HERRINGBONE => Some(PixelMode::herringbone),
+        HEXPXL_MODE_PENROSE => Some(PixelMode::penrose),
+        HEXPXL_MODE_TRIHEX => Some(PixelMode::trihex),
+        HEXPXL_MODE_RINGS => Some(PixelMode::rings),
+        HEXPXL_MODE_SPIRAL => Some(PixelMode::spiral),
+        HEXPXL_MODE_HILBERT => Some(PixelMode::hilbert),
+        HEXPXL_MODE_LOWPOLY => Some(PixelMode::lowpoly),
+        HEXPXL_MODE_CRYSTAL => Some(PixelMode::crystal),
+        _ => None,
+    }
+}
+
+///
+/// Pixelises an RGBA image into `out`, returning `HEXPXL_OK` or an error code
+///
+/// Both images are `width * height` pixels of 4 bytes, row by row, without padding.
+/// When an error is returned, `out` is left untouched and `hexpxl_last_error` describes it.
+///
+/// # Safety
+///
+/// `rgba` must be readable and `out` writable for `width * height * 4` bytes,
+/// and they must not overlap.
+///
+#[no_mangle]
+pub unsafe extern "C" fn hexpxl_pixelise(
+    rgba: *const u8,
+    width: u32,
+    height: u32,
+    mode: u32,
+    size: u32,
+    out: *mut u8,
+) -> c_int {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+    if rgba.is_null() || out.is_null() {
+        return fail(HEXPXL_INVALID_ARGUMENT, "the input and output images cannot be null");
+    }
+    let mode = match self::mode(mode) {
+        Some(mode) => mode,
+        None => return fail(HEXPXL_INVALID_ARGUMENT, &format!("unknown mode {}", mode)),
+    };
+    if size == 0 {
+        return fail(HEXPXL_INVALID_ARGUMENT, "the size of the pixels must be at least 1");
+    }
+    let length = match (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(4)) {
+        Some(length) if width > 0 && height > 0 => length,
+        _ => return fail(HEXPXL_INVALID_ARGUMENT, &format!("invalid dimensions {}x{}", width, height)),
+    };
+
+    let input = slice::from_raw_parts(rgba, length);
+    let img: ImageBuffer<Rgba<u8>, &[u8]> = ImageBuffer::from_raw(width, height, input).unwrap();
+    // a panic must not unwind into the C code
+    match panic::catch_unwind(AssertUnwindSafe(|| ::pixelise(&img, mode, size))) {
+        Ok(pixelised) => {
+            ptr::copy_nonoverlapping(pixelised.as_ptr(), out, length);
+            HEXPXL_OK
+        }
+        Err(cause) => {
+            let message = cause.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| cause.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "the pixelisation failed".to_string());
+            fail(HEXPXL_INTERNAL_ERROR, &message)
+        }
+    }
+}
+
+///
+/// The description of the error returned by the last call to `hexpxl_pixelise` of the current thread
+///
+/// Returns null when that call succeeded. The text stays valid until the next call on the same thread.
+///
+#[no_mangle]
+pub extern "C" fn hexpxl_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
@@ -0,0 +1,117 @@
+use image::{Rgba, RgbaImage};
+
+
+/// The width of the glyphs, in dots
+pub const WIDTH: u32 = 5;
+/// The height of the glyphs, in dots
+pub const HEIGHT: u32 = 7;
+
+///
+/// The symbols of the threads of the cross-stitch charts, from the easiest to tell apart to the least
+///
+/// The chart gives the simplest shapes to the most used threads.
+///
+pub const SYMBOLS: &str = "■□×+●○▲△▼◆◇|=/\\#*ABCDEFGHJKLMNPRSTUVWXYZ23456789";
+
+///
+/// The dots of the character, row by row, the leftmost dot being the highest bit of the 5
+///
+/// Covers the digits, the capital letters, a few punctuation marks and the shapes of the symbols.
+/// The other characters are blank.
+///
+pub fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'A' => [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '\\' => [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00],
+        '|' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        '=' => [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00],
+        '#' => [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '@' => [0x0e, 0x11, 0x17, 0x15, 0x17, 0x10, 0x0e],
+        '*' => [0x00, 0x15, 0x0e, 0x1f, 0x0e, 0x15, 0x00],
+        '×' => [0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x00],
+        '■' => [0x00, 0x1f, 0x1f, 0x1f, 0x1f, 0x1f, 0x00],
+        '□' => [0x00, 0x1f, 0x11, 0x11, 0x11, 0x1f, 0x00],
+        '●' => [0x00, 0x0e, 0x1f, 0x1f, 0x1f, 0x0e, 0x00],
+        '○' => [0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e, 0x00],
+        '▲' => [0x00, 0x04, 0x0e, 0x0e, 0x1f, 0x1f, 0x00],
+        '△' => [0x00, 0x04, 0x0a, 0x0a, 0x11, 0x1f, 0x00],
+        '▼' => [0x00, 0x1f, 0x1f, 0x0e, 0x0e, 0x04, 0x00],
+        '◆' => [0x04, 0x0e, 0x1f, 0x1f, 0x1f, 0x0e, 0x04],
+        '◇' => [0x04, 0x0a, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        _ => [0; 7],
+    }
+}
+
+///
+/// Draws the text with its top left corner at (x, y), each dot being a square of `scale` pixels
+///
+/// The characters are separated by one dot. The pixels outside of the image are left out.
+///
+pub fn draw_text(img: &mut RgbaImage, text: &str, x: u32, y: u32, scale: u32, color: Rgba<u8>) {
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i as u32 * (WIDTH + 1) * scale;
+        for (row, dots) in glyph(c).iter().enumerate() {
+            for column in 0..WIDTH {
+                if dots & (0x10 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (px, py) = (left + column * scale + dx, y + row as u32 * scale + dy);
+                        if px < img.width() && py < img.height() {
+                            img.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The width of the text drawn by `draw_text`, in pixels
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    (text.chars().count() as u32 * (WIDTH + 1)).saturating_sub(1) * scale
+}
@@ -0,0 +1,100 @@
+use color::{parse_color, Channel};
+use image::Rgba;
+
+
+///
+/// Colors along the brightness, from black to white, for the duotones and the gradient maps
+///
+/// Each stop gives the color of a brightness, from 0 for black to 1 for white,
+/// and the colors between the stops are blended from those of the closest ones.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    /// The brightnesses and the colors of the stops, by increasing brightness
+    stops: Vec<(f32, Rgba<u8>)>,
+}
+
+impl Gradient {
+    ///
+    /// The colors spread evenly from black to white, the first one for black and the last one for white
+    ///
+    /// # Panics
+    ///
+    /// When there are no colors
+    ///
+    pub fn even(colors: &[Rgba<u8>]) -> Gradient {
+        assert!(!colors.is_empty(), "a gradient needs colors");
+        let last = (colors.len() - 1).max(1) as f32;
+        Gradient { stops: colors.iter().enumerate().map(|(i, &c)| (i as f32 / last, c)).collect() }
+    }
+
+    ///
+    /// Reads a gradient map: a stop per line, either a color written as `#rrggbb` or its brightness from 0 to 1
+    /// followed by the color, e.g. `0.25 #ff8000`
+    ///
+    /// The colors without a brightness are spread evenly between their neighbours, the first one for black
+    /// and the last one for white. Empty lines and lines starting with `;` are ignored.
+    ///
+    pub fn parse(text: &str) -> Result<Gradient, String> {
+        let mut stops: Vec<(Option<f32>, Rgba<u8>)> = Vec::new();
+        for (number, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let invalid = || format!("invalid stop at line {}: {}", number + 1, line);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let stop = match fields[..] {
+                [color] => (None, parse_color(color).ok_or_else(invalid)?),
+                [brightness, color] => {
+                    let brightness: f32 = brightness.parse().map_err(|_| invalid())?;
+                    if !(0.0..=1.0).contains(&brightness) {
+                        return Err(invalid());
+                    }
+                    (Some(brightness), parse_color(color).ok_or_else(invalid)?)
+                }
+                _ => return Err(invalid()),
+            };
+            stops.push(stop);
+        }
+        if stops.is_empty() {
+            return Err("the gradient has no colors".to_string());
+        }
+
+        // the stops without a brightness are spread between those around them
+        let last = stops.len() - 1;
+        let known: Vec<(usize, f32)> = stops.iter().enumerate()
+            .filter_map(|(i, s)| match s.0 {
+                Some(b) => Some((i, b)),
+                None if i == 0 => Some((i, 0.0)),
+                None if i == last => Some((i, 1.0)),
+                None => None,
+            })
+            .collect();
+        let mut placed = Vec::with_capacity(stops.len());
+        for pair in known.windows(2) {
+            let ((from, a), (to, b)) = (pair[0], pair[1]);
+            if b < a {
+                return Err(format!("the brightness {} of a stop is below that of the stop before it, {}", b, a));
+            }
+            for (k, stop) in stops[from..to].iter().enumerate() {
+                placed.push((a + (b - a) * k as f32 / (to - from) as f32, stop.1));
+            }
+        }
+        placed.push((known[known.len() - 1].1, stops[last].1));
+        Ok(Gradient { stops: placed })
+    }
+
+    ///
+    /// The color of the gradient at the brightness of the color, keeping the transparency of the color
+    ///
+    pub fn map<C: Channel>(&self, color: Rgba<C>) -> Rgba<C> {
+        let channel = |c: usize| color[c].value() as f32 / C::MAX as f32;
+        let brightness = 0.299 * channel(0) + 0.587 * channel(1) + 0.114 * channel(2);
+        let after = self.stops.iter().position(|s| s.0 >= brightness).unwrap_or(self.stops.len() - 1);
+        let before = after.saturating_sub(1);
+        let ((a, from), (b, to)) = (self.stops[before], self.stops[after]);
+        let t = if b > a { ((brightness - a) / (b - a)).clamp(0.0, 1.0) } else { 1.0 };
+        let channel = |c: usize| C::from_unit((from[c] as f32 + (to[c] as f32 - from[c] as f32) * t) / 255.0);
+        Rgba([channel(0), channel(1), channel(2), color[3]])
+    }
+}
@@ -0,0 +1,1519 @@
+extern crate gif;
+extern crate image;
+
+#[macro_use]
+extern crate clap;
+extern crate crc32fast;
+extern crate exif;
+extern crate flate2;
+extern crate png;
+extern crate qcms;
+extern crate rayon;
+extern crate rustface;
+extern crate tiff;
+#[cfg(target_arch = "wasm32")]
+extern crate wasm_bindgen;
+
+
+use std::borrow::Cow;
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use image::{imageops, ImageBuffer, GenericImageView, RgbaImage, Rgba};
+use rayon::prelude::*;
+
+
+macro_rules! sqr {
+    ( $a:expr ) => {
+        {
+            let tmp = $a;
+            tmp * tmp
+        }
+    }
+}
+
+
+pub mod animation;
+pub mod ascii;
+pub mod color;
+pub mod deep;
+mod dither;
+pub mod emoji;
+pub mod export;
+pub mod faces;
+#[cfg(feature = "hexpxl-ffi")]
+pub mod ffi;
+pub mod font;
+pub mod gradient;
+pub mod mask;
+pub mod metadata;
+pub mod montage;
+pub mod mosaic;
+pub mod palette;
+pub mod plan;
+mod random;
+mod sampling;
+pub mod sizes;
+pub mod stitch;
+pub mod stream;
+pub mod svg;
+mod tessellation;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+use color::{luminance, narrow, widen, Channel};
+use dither::Adjacency;
+use faces::Region;
+use gradient::Gradient;
+use mask::Mask;
+use mosaic::{Mosaic, Tiles};
+use palette::Palette;
+use sizes::{Detail, SizeField};
+use tessellation::{contains, Brick, Crystal, Graded, Herringbone, Hexagon, Hilbert, Lowpoly, Penrose, Quadtree, Rhombille, Rings, Rotated, Scales, Shifted, Slic, Spiral, Square, Stretched, Tessellation, Triangle, Trihex, Voronoi};
+
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum PixelMode {
+        sqr,
+        hex,
+        tri,
+        voronoi,
+        quadtree,
+        brick,
+        dots,
+        cube,
+        slic,
+        scales,
+        herringbone,
+        penrose,
+        trihex,
+        rings,
+        spiral,
+        hilbert,
+        lowpoly,
+        crystal,
+        photomosaic,
+        emoji,
+        ascii,
+    }
+}
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Sampling {
+        center,
+        average,
+        dominant,
+        gaussian,
+    }
+}
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Orientation {
+        pointy,
+        flat,
+    }
+}
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Alpha {
+        preserve,
+        flatten,
+        binarize,
+    }
+}
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum EmojiShape {
+        square,
+        circle,
+        heart,
+    }
+}
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum FitGrid {
+        pad,
+        crop,
+    }
+}
+
+/// An RGBA image whose channels are of the type `C`, of 8 bits for an `RgbaImage`
+pub type RgbaBuffer<C> = ImageBuffer<Rgba<C>, Vec<C>>;
+
+///
+/// The pixelisation settings
+///
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The shape of the pixels
+    pub mode: PixelMode,
+    /// The size of the pixels, in pixels
+    pub size: u32,
+    /// How the size of the hexagons of the hex mode changes across the image, the size being the same everywhere
+    /// when there is none. The grid then cannot be shifted nor rotated.
+    pub size_field: Option<SizeField>,
+    /// Whether the size of the hexagons of the hex mode follows the detail of the image, from the smallest size
+    /// of the quadtree mode where it is busy to its largest size where it is flat, rather than being the size
+    pub adaptive: bool,
+    /// The seed of the random generator, for the modes using randomness
+    pub seed: u64,
+    /// How the color of each pixel is chosen
+    pub sampling: Sampling,
+    /// Whether the average and gaussian samplings average the colors in linear light, rather than their sRGB values
+    pub linear: bool,
+    /// The spread of the weights of the gaussian sampling around the center of each cell, in pixels,
+    /// half of the size by default
+    pub sigma: Option<f32>,
+    /// What becomes of the transparency of the cells: kept, composited over the background, or made all or nothing
+    pub alpha: Alpha,
+    /// Whether the hexagons have a vertex or an edge on top
+    pub orientation: Orientation,
+    /// The width of the lines drawn between the cells, in pixels
+    pub border_width: f32,
+    /// The color of the lines drawn between the cells
+    pub border_color: Rgba<u8>,
+    /// How much the edges of the cells are lightened on the side facing the light and darkened on the other side,
+    /// like raised tiles, from 0 for flat cells to 1
+    pub bevel: f32,
+    /// The direction the light of the bevels comes from, in degrees clockwise from the right, the top left by default
+    pub light_angle: f32,
+    /// Whether the pixels on the boundaries between the cells blend the colors of the cells covering them
+    pub antialias: bool,
+    /// The space left between the cells, each of them being shrunk by half of it on every side, in pixels
+    pub gap: f32,
+    /// The color seen in the gaps between the cells
+    pub background: Rgba<u8>,
+    /// The shadow the cells cast on the background seen in the gaps between them
+    pub shadow: Option<Shadow>,
+    /// The side of the smallest squares of the quadtree mode, and the size of the smallest adaptive hexagons
+    pub min_size: u32,
+    /// The side of the largest squares of the quadtree mode, and the size of the largest adaptive hexagons
+    pub max_size: u32,
+    /// The color variance above which the squares of the quadtree mode are split
+    pub variance_threshold: f32,
+    /// How regular the superpixels of the slic mode are, the lower the closer they follow the edges of the image
+    pub compactness: f32,
+    /// The number of times the superpixels of the slic mode are refined
+    pub iterations: u32,
+    /// The rotation of the square, hexagonal, triangular and brick grids, in degrees, clockwise
+    pub angle: f32,
+    /// The shift of the square, hexagonal, triangular and brick grids along their axes, in pixels,
+    /// to the right and to the bottom
+    pub offset: (f32, f32),
+    /// Whether only the faces are pixelised, the rest of the image being left untouched
+    pub faces: bool,
+    /// The rectangles which are pixelised, each with its own grid, the rest of the image being left untouched.
+    /// The whole image is pixelised when there is none.
+    pub regions: Vec<Region>,
+    /// The parts of the image which are pixelised, the rest of it being left untouched
+    pub mask: Option<Mask>,
+    /// The colors the cells are snapped to
+    pub palette: Option<Palette>,
+    /// Whether the error of snapping each cell to the palette is diffused over the next cells, by Floyd-Steinberg
+    pub dither: bool,
+    /// The colors the brightness of the cells is mapped to, for the duotones and the gradient maps
+    pub gradient: Option<Gradient>,
+    /// The number of evenly spaced levels each channel of the colors of the cells is rounded to, from 2
+    pub posterize: Option<u32>,
+    /// The width of the bricks of the brick mode, and the size of the cells of the square, hexagonal, triangular
+    /// and other regular grids along the width of the image, their grid being stretched to it. The size by default.
+    pub cell_width: Option<u32>,
+    /// The height of the bricks of the brick mode, and the size of the cells of the regular grids along the height
+    /// of the image. The size by default.
+    pub cell_height: Option<u32>,
+    /// The radius of the dots of the dots mode, in pixels.
+    /// By default, the area of each dot grows with the darkness of its cell, up to the whole cell.
+    pub dot_radius: Option<f32>,
+    /// Whether the 3 faces of each cube of the cube mode share a color, lightened on the top and darkened on the right
+    pub shade: bool,
+    /// How much of each scale of the scales mode is covered by the rows of scales above it, from 0 to 0.9
+    pub overlap: f32,
+    /// The grids of the red, green and blue channels, when they are pixelised separately
+    pub split_channels: Option<ChannelGrids>,
+    /// Whether the cells crossing the edges of the image take the pixels of the opposite edges,
+    /// as if the image was repeated, for the textures
+    pub tileable: bool,
+    /// The number of columns of cells spanning the width of the image, setting the size rather than `size`
+    pub columns: Option<u32>,
+    /// The number of rows of cells spanning the height of the image, setting the size rather than `size`
+    pub rows: Option<u32>,
+    /// The center of the rings mode and of the spiral mode, in pixels from the top left corner of the image,
+    /// shifted by the offset. The center of the image by default.
+    pub center: Option<(f32, f32)>,
+    /// How many times larger than the image the pixelised image is, each pixel of the image becoming a square
+    /// of that side, so that the edges of the cells are drawn sharply at that resolution
+    pub scale: u32,
+    /// Whether the image is padded with the background color or cropped to a whole number of columns and rows
+    /// of the cells of the regular grids, so that no sliver of cells is left along its right and bottom edges
+    pub fit_grid: Option<FitGrid>,
+    /// The images which the squares of the photomosaic mode are replaced with, the one closest to the color
+    /// of each square. The squares are flat, as in the sqr mode, when there are none.
+    pub tiles: Option<Tiles>,
+    /// The shape of the emoji of the emoji mode, which all have the same
+    pub emoji_shape: EmojiShape,
+    /// The fewest rows of the image which each task of the threads paints. Larger chunks spare the scheduling
+    /// of the tasks, smaller ones share the rows more evenly between the threads.
+    pub chunk_size: usize,
+}
+
+///
+/// The grids of the red, green and blue channels, pixelised separately for a chromatic aberration effect
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelGrids {
+    /// The offsets of the grids of the red, green and blue channels, added to the offset of the options, in pixels
+    pub offsets: [(f32, f32); 3],
+    /// The sizes of the pixels of the grids of the red, green and blue channels
+    pub sizes: [u32; 3],
+}
+
+///
+/// A shadow cast by the cells on the background, seen in the gaps between them, like tiles on a board
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shadow {
+    /// The shift of the shadow from the cells, in pixels, to the right and to the bottom
+    pub offset: (f32, f32),
+    /// The width of the soft edge of the shadow, in pixels, around the edge of the shifted cells
+    pub blur: f32,
+    /// The color of the shadow, whose alpha is that of its darkest part
+    pub color: Rgba<u8>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            mode: PixelMode::hex,
+            size: 20,
+            size_field: None,
+            adaptive: false,
+            seed: 0,
+            sampling: Sampling::center,
+            linear: false,
+            sigma: None,
+            alpha: Alpha::preserve,
+            orientation: Orientation::pointy,
+            border_width: 0.0,
+            border_color: Rgba([0, 0, 0, 255]),
+            bevel: 0.0,
+            light_angle: 225.0,
+            antialias: false,
+            gap: 0.0,
+            background: Rgba([0, 0, 0, 0]),
+            shadow: None,
+            min_size: 4,
+            max_size: 64,
+            variance_threshold: 100.0,
+            compactness: 10.0,
+            iterations: 10,
+            angle: 0.0,
+            offset: (0.0, 0.0),
+            faces: false,
+            regions: Vec::new(),
+            mask: None,
+            palette: None,
+            dither: false,
+            gradient: None,
+            posterize: None,
+            cell_width: None,
+            cell_height: None,
+            dot_radius: None,
+            shade: false,
+            overlap: 0.0,
+            split_channels: None,
+            tileable: false,
+            columns: None,
+            rows: None,
+            center: None,
+            scale: 1,
+            fit_grid: None,
+            tiles: None,
+            emoji_shape: EmojiShape::square,
+            chunk_size: 1,
+        }
+    }
+}
+
+impl Options {
+    ///
+    /// The options with the size giving their columns and rows of cells on an image of that size, as does `plan::fit_size`
+    ///
+    /// The options are kept as they are when they set neither.
+    ///
+    pub fn fitted(&self, width: u32, height: u32) -> Cow<'_, Options> {
+        match plan::fit_size(width, height, self) {
+            Some(size) => Cow::Owned(Options { size, columns: None, rows: None, ..self.clone() }),
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    ///
+    /// The options for the image enlarged by their scale, with all their lengths multiplied by it and a scale of 1
+    ///
+    /// The sizes of the hilbert mode, which are numbers of pixels, are multiplied by the square of the scale.
+    ///
+    pub fn scaled(&self) -> Options {
+        let n = self.scale.max(1);
+        let f = n as f32;
+        let point = |p: (f32, f32)| (p.0 * f, p.1 * f);
+        Options {
+            size: if self.mode == PixelMode::hilbert { self.size * n * n } else { self.size * n },
+            size_field: self.size_field.as_ref().map(|s| s.scaled(f)),
+            sigma: self.sigma.map(|s| s * f),
+            border_width: self.border_width * f,
+            gap: self.gap * f,
+            shadow: self.shadow.map(|s| Shadow { offset: point(s.offset), blur: s.blur * f, ..s }),
+            min_size: self.min_size * n,
+            max_size: self.max_size * n,
+            offset: point(self.offset),
+            regions: self.regions.iter()
+                .map(|r| Region { x: r.x * n, y: r.y * n, width: r.width * n, height: r.height * n })
+                .collect(),
+            mask: self.mask.as_ref().map(|m| Mask { feather: m.feather * f, ..m.clone() }),
+            cell_width: self.cell_width.map(|w| w * n),
+            cell_height: self.cell_height.map(|h| h * n),
+            dot_radius: self.dot_radius.map(|r| r * f),
+            split_channels: self.split_channels.map(|g| ChannelGrids {
+                offsets: g.offsets.map(point),
+                sizes: g.sizes.map(|s| if self.mode == PixelMode::hilbert { s * n * n } else { s * n }),
+            }),
+            center: self.center.map(point),
+            scale: 1,
+            ..self.clone()
+        }
+    }
+
+    /// The spread of the weights of the gaussian sampling, in pixels
+    pub fn gaussian_sigma(&self) -> f32 {
+        self.sigma.unwrap_or(self.size as f32 / 2.0)
+    }
+
+    /// Whether the colors of the cells need the cells of all the pixels, as given by `cell_map`, to be found
+    pub fn needs_cell_map(&self) -> bool {
+        self.sampling != Sampling::center || self.dither && self.palette.is_some()
+    }
+}
+
+///
+/// Pixelises an image with the given mode
+///
+/// # Arguments
+///
+/// * `img` - The input image to pixelise
+/// * `mode` - The shape of the pixels
+/// * `size` - The size of the pixels, in pixels
+///
+pub fn pixelise<I, C>(img: &I, mode: PixelMode, size: u32) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel {
+    pixelise_with(img, &Options { mode, size, ..Options::default() })
+}
+
+///
+/// Pixelises an image with the given settings
+///
+/// # Arguments
+///
+/// * `img` - The input image to pixelise
+/// * `options` - The pixelisation settings
+///
+pub fn pixelise_with<I, C>(img: &I, options: &Options) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel {
+    pixelise_with_progress(img, options, &|_, _| {})
+}
+
+///
+/// Pixelises an image with the given settings, reporting the advancement of the work
+///
+/// The images of 16 bits per channel are pixelised with their full precision,
+/// but for the shapes of the superpixels, the quadtree, the low poly triangles and the faces, which are found on 8 bits.
+///
+/// # Arguments
+///
+/// * `img` - The input image to pixelise
+/// * `options` - The pixelisation settings
+/// * `progress` - Receives the number of steps done and the total number of steps, after each row of pixels.
+///   It is called from several threads.
+///
+pub fn pixelise_with_progress<I, C, P>(img: &I, options: &Options, progress: &P) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel, P: Fn(u64, u64) + Sync {
+    let (width, height) = img.dimensions();
+    let fitted = options.fitted(width, height);
+    match plan::fit_grid(width, height, options) {
+        Some((width, height)) => {
+            let canvas = Canvas { img, width, height, background: widen(options.background) };
+            pixelise_scaled(&canvas, &fitted, progress)
+        }
+        None => pixelise_scaled(img, &fitted, progress),
+    }
+}
+
+/// Pixelises the image with the options whose columns and rows are already turned into a size, at their scale
+fn pixelise_scaled<I, C, P>(img: &I, options: &Options, progress: &P) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel, P: Fn(u64, u64) + Sync {
+    if options.scale > 1 {
+        // the grid is laid on the image, and drawn on the enlarged one
+        pixelise_fitted(&Enlarged { img, scale: options.scale }, &options.scaled(), progress)
+    } else {
+        pixelise_fitted(img, options, progress)
+    }
+}
+
+/// Pixelises the image with the options whose columns and rows are already turned into a size, at their scale of 1
+fn pixelise_fitted<I, C, P>(img: &I, options: &Options, progress: &P) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel, P: Fn(u64, u64) + Sync {
+    let (width, height) = img.dimensions();
+    let regions: Vec<Region> = options.regions.iter().map(|r| r.clipped(width, height))
+        .filter(|r| r.width > 0 && r.height > 0)
+        .collect();
+    // the tileable parts are pixelised with their margins
+    let margins = if options.tileable { 2 * wrap_margin(options) } else { 0 };
+    let rows = if options.regions.is_empty() {
+        height + margins
+    } else {
+        regions.iter().map(|r| r.height + margins).sum()
+    };
+    let passes = if options.needs_cell_map() { 2 } else { 1 };
+    let channels = if options.split_channels.is_some() { 3 } else { 1 };
+    let steps = Steps::new(rows as u64 * passes * channels, progress);
+
+    let pixelised = if options.regions.is_empty() {
+        pixelise_part(img, options, &steps)
+    } else {
+        // each region is pixelised as an image of its own, so that its grid starts at its corner
+        let mut pixelised = ImageBuffer::from_fn(width, height, |x, y| img.get_pixel(x, y));
+        for r in &regions {
+            let part: RgbaBuffer<C> = ImageBuffer::from_fn(r.width, r.height, |x, y| img.get_pixel(r.x + x, r.y + y));
+            imageops::replace(&mut pixelised, &pixelise_part(&part, options, &steps), r.x, r.y);
+        }
+        pixelised
+    };
+    match options.mask {
+        Some(ref mask) => mask::blend(img, pixelised, mask),
+        None => pixelised,
+    }
+}
+
+///
+/// The number of cells of the pixelisation of the image holding at least one of its pixels
+///
+/// The cells of each region are counted when the options have some.
+/// The channels split by the options are counted with the grid of the options, and the faces and the mask are left out.
+///
+pub fn cell_count<I, C>(img: &I, options: &Options) -> usize
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    let (width, height) = img.dimensions();
+    let fitted = options.fitted(width, height);
+    match plan::fit_grid(width, height, options) {
+        Some((width, height)) => region_cells(&Canvas { img, width, height, background: widen(options.background) }, &fitted),
+        None => region_cells(img, &fitted),
+    }
+}
+
+/// The number of cells of the regions of the image, or of the whole image, for the options already fitted to it
+fn region_cells<I, C>(img: &I, options: &Options) -> usize
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    let (width, height) = img.dimensions();
+    if options.regions.is_empty() {
+        return used_cells(img, options);
+    }
+    options.regions.iter().map(|r| r.clipped(width, height))
+        .filter(|r| r.width > 0 && r.height > 0)
+        .map(|r| {
+            let part: RgbaBuffer<C> = ImageBuffer::from_fn(r.width, r.height, |x, y| img.get_pixel(r.x + x, r.y + y));
+            used_cells(&part, options)
+        })
+        .sum()
+}
+
+/// The number of cells of the tessellation of the whole image holding at least one of its pixels
+fn used_cells<I, C>(img: &I, options: &Options) -> usize
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    let (width, height) = img.dimensions();
+    let tessellation = tessellation(img, options);
+    let mut used = vec![false; tessellation.cell_count()];
+    for cell in cell_map(tessellation.as_ref(), width, height, options.chunk_size, &Steps::new(0, &|_, _| {})) {
+        used[cell] = true;
+    }
+    used.iter().filter(|&&u| u).count()
+}
+
+/// Pixelises the whole image, or only its faces
+fn pixelise_part<I, C>(img: &I, options: &Options, steps: &Steps) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel {
+    let pixelised = if options.tileable {
+        render_wrapped(img, options, steps)
+    } else {
+        render_grids(img, options, steps)
+    };
+    if options.faces {
+        faces::restore_outside(img, pixelised, &faces::detect_faces(&Narrowed(img)))
+    } else {
+        pixelised
+    }
+}
+
+/// Pixelises the image with the grid of the options, or the grids of its channels
+fn render_grids<I, C>(img: &I, options: &Options, steps: &Steps) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel {
+    match options.split_channels {
+        Some(ref grids) => render_channels(img, options, grids, steps),
+        None => render(img, tessellation(img, options).as_ref(), options, steps),
+    }
+}
+
+///
+/// Pixelises the image surrounded by its opposite edges, as if it was repeated, and crops it back
+///
+/// The grid is shifted by the margin, so that it stays where it is on the image, to the pixel.
+/// The cells crossing an edge then take the pixels past it, which are those of the opposite edge.
+///
+fn render_wrapped<I, C>(img: &I, options: &Options, steps: &Steps) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel {
+    let (width, height) = img.dimensions();
+    let margin = wrap_margin(options);
+    let options = Options {
+        offset: (options.offset.0 + margin as f32, options.offset.1 + margin as f32),
+        // the center of the larger image
+        center: Some(options.center.unwrap_or((width as f32 / 2.0, height as f32 / 2.0))),
+        tileable: false,
+        ..options.clone()
+    };
+    let mut pixelised = render_grids(&Wrapped { img, margin }, &options, steps);
+    imageops::crop(&mut pixelised, margin, margin, width, height).to_image()
+}
+
+/// The width of the margin around the tileable images, in which lie the largest cells crossing their edges
+fn wrap_margin(options: &Options) -> u32 {
+    let size = match options.mode {
+        PixelMode::quadtree => options.max_size,
+        PixelMode::brick => options.cell_width.unwrap_or(options.size).max(options.cell_height.unwrap_or(options.size)),
+        _ => stretched_side(options).0,
+    };
+    // the hexagons are twice as wide as their size
+    2 * size
+}
+
+///
+/// The image padded with the background color to the width and the height, or cropped to them, from its top left corner
+///
+struct Canvas<'a, I: 'a, C: Channel> {
+    img: &'a I,
+    width: u32,
+    height: u32,
+    background: Rgba<C>,
+}
+
+impl<'a, I, C> GenericImageView for Canvas<'a, I, C>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    type Pixel = Rgba<C>;
+    type InnerImageView = Self;
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        (0, 0, self.width, self.height)
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Rgba<C> {
+        let (width, height) = self.img.dimensions();
+        if x < width && y < height { self.img.get_pixel(x, y) } else { self.background }
+    }
+
+    fn inner(&self) -> &Self {
+        self
+    }
+}
+
+///
+/// The image enlarged by the scale of the options, each of its pixels becoming a square of that side
+///
+struct Enlarged<'a, I: 'a> {
+    img: &'a I,
+    scale: u32,
+}
+
+impl<'a, I, C> GenericImageView for Enlarged<'a, I>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    type Pixel = Rgba<C>;
+    type InnerImageView = Self;
+
+    fn dimensions(&self) -> (u32, u32) {
+        let (width, height) = self.img.dimensions();
+        (width * self.scale, height * self.scale)
+    }
+
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        let (width, height) = self.dimensions();
+        (0, 0, width, height)
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Rgba<C> {
+        self.img.get_pixel(x / self.scale, y / self.scale)
+    }
+
+    fn inner(&self) -> &Self {
+        self
+    }
+}
+
+///
+/// The image surrounded by a margin of its opposite edges, as if it was repeated, the pixels wrapping around it
+///
+struct Wrapped<'a, I: 'a> {
+    img: &'a I,
+    margin: u32,
+}
+
+impl<'a, I, C> GenericImageView for Wrapped<'a, I>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    type Pixel = Rgba<C>;
+    type InnerImageView = Self;
+
+    fn dimensions(&self) -> (u32, u32) {
+        let (width, height) = self.img.dimensions();
+        (width + 2 * self.margin, height + 2 * self.margin)
+    }
+
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        let (width, height) = self.dimensions();
+        (0, 0, width, height)
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Rgba<C> {
+        let (width, height) = self.img.dimensions();
+        let wrap = |v: u32, length: u32| (v + length - self.margin % length) % length;
+        self.img.get_pixel(wrap(x, width), wrap(y, height))
+    }
+
+    fn inner(&self) -> &Self {
+        self
+    }
+}
+
+/// The tessellation of the image for the mode of the options
+fn tessellation<I, C>(img: &I, options: &Options) -> Box<dyn Tessellation>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    let (width, height) = img.dimensions();
+    let img = Narrowed(img);
+    grid(width, height, options).unwrap_or_else(|| match options.mode {
+        PixelMode::slic => Box::new(Slic::new(&img, options.size, options.compactness, options.iterations)),
+        PixelMode::hex => {
+            let detail = Detail::new(&img, options.min_size, options.max_size);
+            Box::new(Graded::new(width, height, |x, y| detail.size((x, y)), options.orientation == Orientation::flat))
+        }
+        PixelMode::lowpoly => Box::new(Lowpoly::new(&img, options.size)),
+        _ => Box::new(Quadtree::new(&img, options.min_size, options.max_size, options.variance_threshold)),
+    })
+}
+
+///
+/// The image seen with 8 bits per channel, for the parts of the pixelisation which do not need more
+///
+/// The pixels are narrowed as they are read.
+///
+struct Narrowed<'a, I: 'a>(&'a I);
+
+impl<'a, I, C> GenericImageView for Narrowed<'a, I>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    type Pixel = Rgba<u8>;
+    type InnerImageView = Self;
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.0.dimensions()
+    }
+
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        self.0.bounds()
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Rgba<u8> {
+        narrow(self.0.get_pixel(x, y))
+    }
+
+    fn inner(&self) -> &Self {
+        self
+    }
+}
+
+///
+/// The tessellation of an image of that size for the mode of the options
+///
+/// None for the quadtree, slic and lowpoly modes, whose cells depend on the colors of the image.
+/// The cells larger than the image are a single square covering it, whatever the mode.
+///
+fn grid(width: u32, height: u32, options: &Options) -> Option<Box<dyn Tessellation>> {
+    if plan::single_cell(width, height, options) {
+        return Some(Box::new(Square::new(width, height, width.max(height))));
+    }
+    let (size, _) = stretched_side(options);
+    let side = size as f32;
+    // the distance between the centers of 2 adjacent hexagons of a row, and between every other row
+    let hexagons = if options.orientation == Orientation::flat {
+        (3.0 * side, 2.0 * side * (PI / 6.0).cos())
+    } else {
+        (2.0 * side * (PI / 6.0).cos(), 3.0 * side)
+    };
+
+    Some(match options.mode {
+        PixelMode::sqr | PixelMode::photomosaic | PixelMode::emoji | PixelMode::ascii => lattice(width, height, options, (side, side), |w, h| Square::new(w, h, size)),
+        // the sizes follow the detail of the image
+        PixelMode::hex if options.adaptive => return None,
+        PixelMode::hex if options.size_field.is_some() => {
+            let field = options.size_field.as_ref().unwrap();
+            let flat = options.orientation == Orientation::flat;
+            Box::new(Graded::new(width, height, |x, y| field.size(width, height, (x, y)), flat))
+        }
+        PixelMode::hex | PixelMode::dots =>
+            lattice(width, height, options, hexagons, |w, h| Hexagon::new(w, h, size, options.orientation)),
+        PixelMode::cube =>
+            lattice(width, height, options, hexagons, |w, h| Rhombille::new(w, h, size, options.orientation)),
+        PixelMode::tri => {
+            // the triangles alternate pointing up and down along the rows and the columns
+            let period = (side, 2.0 * side * (PI / 3.0).sin());
+            lattice(width, height, options, period, |w, h| Triangle::new(w, h, size))
+        }
+        PixelMode::voronoi => Box::new(Voronoi::new(width, height, options.size, options.seed)),
+        PixelMode::crystal => Box::new(Crystal::new(width, height, options.size, options.seed)),
+        PixelMode::brick => {
+            let (cell_width, cell_height) = (options.cell_width.unwrap_or(size).max(1), options.cell_height.unwrap_or(size).max(1));
+            let period = (cell_width as f32, 2.0 * cell_height as f32);
+            lattice(width, height, options, period, |w, h| Brick::new(w, h, cell_width, cell_height))
+        }
+        PixelMode::scales => {
+            // every other row is shifted by the radius of the scales
+            let period = (2.0 * side, 2.0 * Scales::row_spacing(side, options.overlap));
+            lattice(width, height, options, period, |w, h| Scales::new(w, h, size, options.overlap))
+        }
+        PixelMode::herringbone => {
+            // the pattern repeats along the diagonals (s, s) and (2s, -2s), and so along (4s, 0) and (0, 4s)
+            lattice(width, height, options, (4.0 * side, 4.0 * side), |w, h| Herringbone::new(w, h, size))
+        }
+        // the tiling never repeats, and cannot be shifted by the offset
+        PixelMode::penrose => rotated(width, height, options.angle, |w, h| Penrose::new(w, h, options.size)),
+        PixelMode::trihex => {
+            // the hexagons are laid in columns, every other column being shifted by half a hexagon
+            let period = if options.orientation == Orientation::flat {
+                (2.0 * side, 2.0 * side * 3f32.sqrt())
+            } else {
+                (2.0 * side * 3f32.sqrt(), 2.0 * side)
+            };
+            lattice(width, height, options, period, |w, h| Trihex::new(w, h, size, options.orientation))
+        }
+        PixelMode::rings | PixelMode::spiral => {
+            let center = options.center.unwrap_or((width as f32 / 2.0, height as f32 / 2.0));
+            let center = (center.0 + options.offset.0, center.1 + options.offset.1);
+            if options.mode == PixelMode::rings {
+                Box::new(Rings::new(width, height, options.size, center))
+            } else {
+                Box::new(Spiral::new(width, height, options.size, center))
+            }
+        }
+        // the curve fills the image, and cannot be shifted by the offset
+        PixelMode::hilbert => rotated(width, height, options.angle, |w, h| Hilbert::new(w, h, options.size)),
+        PixelMode::quadtree | PixelMode::slic | PixelMode::lowpoly => return None,
+    })
+}
+
+///
+/// The side of the cells of the grids, and how many times larger than the image the grids are built along each axis,
+/// so that they are squeezed to the width and the height of the cells of the options when they differ
+///
+/// The grids are built with the larger of the width and the height, and squeezed along the other axis.
+/// The bricks of the brick mode have their own width and height, and the other modes are not stretched.
+///
+fn stretched_side(options: &Options) -> (u32, (f32, f32)) {
+    match options.mode {
+        PixelMode::sqr | PixelMode::photomosaic | PixelMode::emoji | PixelMode::ascii | PixelMode::hex | PixelMode::dots | PixelMode::cube
+        | PixelMode::tri | PixelMode::scales | PixelMode::herringbone | PixelMode::trihex => {
+            let width = options.cell_width.unwrap_or(options.size).max(1);
+            let height = options.cell_height.unwrap_or(options.size).max(1);
+            let side = width.max(height);
+            (side, (side as f32 / width as f32, side as f32 / height as f32))
+        }
+        _ => (options.size, (1.0, 1.0)),
+    }
+}
+
+///
+/// The grid built by `grid`, stretched to the cells of the options, shifted by their offset and rotated by their angle
+///
+/// The `period` of the grid is the distance along its axes by which it can be shifted without changing,
+/// before it is stretched. The grid is stretched and shifted along its own axes, before being rotated.
+///
+fn lattice<T, F>(width: u32, height: u32, options: &Options, period: (f32, f32), grid: F) -> Box<dyn Tessellation>
+    where T: Tessellation + 'static, F: FnOnce(u32, u32) -> T {
+    let (_, factors) = stretched_side(options);
+    if factors == (1.0, 1.0) {
+        shifted(width, height, options, period, grid)
+    } else {
+        let period = (period.0 / factors.0, period.1 / factors.1);
+        shifted(width, height, options, period, |w, h| Stretched::new(w, h, factors, grid))
+    }
+}
+
+/// The grid built by `grid`, shifted by the offset of the options and rotated by their angle
+fn shifted<T, F>(width: u32, height: u32, options: &Options, period: (f32, f32), grid: F) -> Box<dyn Tessellation>
+    where T: Tessellation + 'static, F: FnOnce(u32, u32) -> T {
+    if options.offset == (0.0, 0.0) {
+        rotated(width, height, options.angle, grid)
+    } else {
+        rotated(width, height, options.angle, |w, h| Shifted::new(w, h, options.offset, period, grid))
+    }
+}
+
+/// The grid built by `grid`, rotated by `angle` degrees unless it is a multiple of a full turn
+fn rotated<T, F>(width: u32, height: u32, angle: f32, grid: F) -> Box<dyn Tessellation>
+    where T: Tessellation + 'static, F: FnOnce(u32, u32) -> T {
+    if angle % 360.0 == 0.0 {
+        Box::new(grid(width, height))
+    } else {
+        Box::new(Rotated::new(width, height, angle, grid))
+    }
+}
+
+///
+/// Pixelises an image using a square pattern
+///
+/// # Arguments
+///
+/// * `img` - The input image to pixelise
+/// * `radius` - The side of the squares
+///
+pub fn square_pixelisation<I>(img: &I, radius: u32) -> RgbaImage
+    where I: GenericImageView<Pixel=Rgba<u8>> + Sync {
+    pixelise(img, PixelMode::sqr, radius)
+}
+
+///
+/// Pixelises an image using a hexagonal pattern
+///
+/// # Arguments
+///
+/// * `img` - The input image to pixelise
+/// * `outer_radius` - The radius of the hexagon's outer circle
+///
+pub fn hexagon_pixelisation<I>(img: &I, outer_radius: u32) -> RgbaImage
+    where I: GenericImageView<Pixel=Rgba<u8>> + Sync {
+    pixelise(img, PixelMode::hex, outer_radius)
+}
+
+///
+/// Pixelises an image using a pattern of equilateral triangles
+///
+/// # Arguments
+///
+/// * `img` - The input image to pixelise
+/// * `side` - The length of the triangles' sides
+///
+pub fn triangle_pixelisation<I>(img: &I, side: u32) -> RgbaImage
+    where I: GenericImageView<Pixel=Rgba<u8>> + Sync {
+    pixelise(img, PixelMode::tri, side)
+}
+
+///
+/// Pixelises an image using a Voronoi diagram
+///
+/// # Arguments
+///
+/// * `img` - The input image to pixelise
+/// * `size` - The average distance between the seeds
+/// * `seed` - The seed of the random generator placing the cells
+///
+pub fn voronoi_pixelisation<I>(img: &I, size: u32, seed: u64) -> RgbaImage
+    where I: GenericImageView<Pixel=Rgba<u8>> + Sync {
+    pixelise_with(img, &Options { mode: PixelMode::voronoi, size, seed, ..Options::default() })
+}
+
+/// Counts the steps done by several threads
+struct Steps<'a> {
+    done: AtomicU64,
+    total: u64,
+    progress: &'a (dyn Fn(u64, u64) + Sync),
+}
+
+impl<'a> Steps<'a> {
+    fn new(total: u64, progress: &'a (dyn Fn(u64, u64) + Sync)) -> Steps<'a> {
+        progress(0, total);
+        Steps { done: AtomicU64::new(0), total, progress }
+    }
+
+    fn step(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        (self.progress)(done, self.total)
+    }
+}
+
+/// The number rounded to a hundredth, without trailing zeros, as written in the SVG and grid files
+fn format_number(value: f32) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    // avoids writing -0
+    format!("{}", rounded + 0.0)
+}
+
+///
+/// The cell of each pixel of the image, row by row, the threads finding those of `chunk_size` rows at least at once
+///
+fn cell_map<T>(tessellation: &T, width: u32, height: u32, chunk_size: usize, steps: &Steps) -> Vec<usize>
+    where T: Tessellation + ?Sized {
+    let mut cells = vec![0; width as usize * height as usize];
+    cells.par_chunks_mut(width.max(1) as usize).with_min_len(chunk_size.max(1)).enumerate().for_each(|(y, row)| {
+        tessellation.row_cells(0, y as u32, row);
+        steps.step();
+    });
+    cells
+}
+
+///
+/// The image as lines of characters, one for each cell of the square grid of the emoji and ascii modes,
+/// for pasting into a chat or a text file
+///
+/// Each line is a row of cells, and each character the one of the color of the cell at the center
+/// of its block of the row and of the column. The transparent cells are spaces.
+/// The regions, the faces and the mask of the options are left out, the whole image being written.
+///
+pub fn to_text<I>(img: &I, options: &Options) -> String
+    where I: GenericImageView<Pixel=Rgba<u8>> {
+    let (width, height) = img.dimensions();
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    let tessellation = tessellation(img, options);
+    let cells = if options.needs_cell_map() {
+        Some(cell_map(tessellation.as_ref(), width, height, options.chunk_size, &Steps::new(0, &|_, _| {})))
+    } else {
+        None
+    };
+    let colors: Vec<Rgba<u8>> = cell_colors(img, tessellation.as_ref(), options, cells.as_deref());
+
+    let cell_width = options.cell_width.unwrap_or(options.size).max(1);
+    let cell_height = options.cell_height.unwrap_or(options.size).max(1);
+    let mut text = String::new();
+    for row in 0..height.div_ceil(cell_height) {
+        let y = (row * cell_height + cell_height / 2).min(height - 1);
+        for column in 0..width.div_ceil(cell_width) {
+            let x = (column * cell_width + cell_width / 2).min(width - 1);
+            let color = colors[tessellation.cell_index(x, y)];
+            if options.mode == PixelMode::ascii {
+                text.push(ascii::character(color));
+            } else {
+                text.push_str(emoji::text(options.emoji_shape, color));
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+///
+/// Samples the colors of the cells and adjusts them, as done by `adjust_colors`
+///
+/// The average, dominant and gaussian samplings and the dithering need the `cells` of the pixels, as given by `cell_map`.
+///
+fn cell_colors<I, C, T>(img: &I, tessellation: &T, options: &Options, cells: Option<&[usize]>) -> Vec<Rgba<C>>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel, T: Tessellation + ?Sized {
+    let mut colors = match options.sampling {
+        Sampling::center => sampling::center_colors(img, tessellation),
+        Sampling::average => {
+            let cells = cells.expect("the average sampling needs the cells of the pixels");
+            sampling::average_colors(img, cells, tessellation.cell_count(), options.linear)
+        }
+        Sampling::dominant => {
+            let cells = cells.expect("the dominant sampling needs the cells of the pixels");
+            sampling::dominant_colors(img, cells, tessellation.cell_count())
+        }
+        Sampling::gaussian => {
+            let cells = cells.expect("the gaussian sampling needs the cells of the pixels");
+            sampling::gaussian_colors(img, cells, tessellation, options.gaussian_sigma(), options.linear)
+        }
+    };
+    let adjacency = match cells {
+        Some(cells) if options.dither => Some(Adjacency::new(tessellation, cells, img.width())),
+        _ => None,
+    };
+    adjust_colors(&mut colors, options, adjacency.as_ref());
+    colors
+}
+
+///
+/// Shades the cubes, applies the alpha mode, maps the brightness to the gradient, posterizes
+/// and snaps the sampled colors of the cells to the palette
+///
+/// The colors are dithered when the options say so, given the `adjacency` of the cells.
+///
+fn adjust_colors<C: Channel>(colors: &mut [Rgba<C>], options: &Options, adjacency: Option<&Adjacency>) {
+    shade_cubes(colors, options);
+    apply_alpha(colors, options);
+    if let Some(ref gradient) = options.gradient {
+        for color in colors.iter_mut() {
+            *color = gradient.map(*color);
+        }
+    }
+    posterize(colors, options);
+    match (&options.palette, adjacency) {
+        (Some(palette), Some(adjacency)) if options.dither => dither::dither(colors, palette, adjacency),
+        _ => snap_to_palette(colors, options),
+    }
+}
+
+///
+/// Gives the 3 faces of each cube the mean of their colors, lightened on the top face and darkened on the right one,
+/// when the cube mode is shaded
+///
+/// The faces without pixels, which are transparent, are left out of the mean.
+///
+fn shade_cubes<C: Channel>(colors: &mut [Rgba<C>], options: &Options) {
+    if options.mode != PixelMode::cube || !options.shade {
+        return;
+    }
+    let towards = |color: Rgba<C>, target: f32, amount: f32| {
+        let channel = |c: usize| {
+            let value = color[c].value() as f32;
+            C::from_value((value + (target - value) * amount).round() as u32)
+        };
+        Rgba([channel(0), channel(1), channel(2), color[3]])
+    };
+    for faces in colors.chunks_mut(3) {
+        let mut sum = [0u64; 4];
+        let mut count = 0;
+        for face in faces.iter().filter(|f| f[3].value() > 0) {
+            for c in 0..4 {
+                sum[c] += face[c].value() as u64;
+            }
+            count += 1;
+        }
+        let cube = sampling::mean(&sum, count);
+        faces.copy_from_slice(&[towards(cube, C::MAX as f32, 0.3), cube, towards(cube, 0.0, 0.35)]);
+    }
+}
+
+///
+/// Flattens the colors over the background or binarizes their alpha, as set by the alpha mode of the options
+///
+/// Flattening composites each color over the background, giving an opaque color when the background is opaque.
+/// Binarizing makes the colors with an alpha of at least half opaque and the others fully transparent.
+///
+fn apply_alpha<C: Channel>(colors: &mut [Rgba<C>], options: &Options) {
+    let max = C::MAX as f32;
+    match options.alpha {
+        Alpha::preserve => {}
+        Alpha::flatten => {
+            let background: Rgba<C> = widen(options.background);
+            for color in colors.iter_mut() {
+                let alpha = color[3].value() as f32 / max;
+                // the part of the background seen through the color
+                let under = background[3].value() as f32 / max * (1.0 - alpha);
+                let total = alpha + under;
+                *color = if total > 0.0 {
+                    let channel = |c: usize| {
+                        let mixed = (color[c].value() as f32 * alpha + background[c].value() as f32 * under) / total;
+                        C::from_value(mixed.round() as u32)
+                    };
+                    Rgba([channel(0), channel(1), channel(2), C::from_unit(total)])
+                } else {
+                    sampling::transparent()
+                };
+            }
+        }
+        Alpha::binarize => {
+            for color in colors.iter_mut() {
+                color[3] = C::from_value(if color[3].value() > C::MAX / 2 { C::MAX } else { 0 });
+            }
+        }
+    }
+}
+
+///
+/// Rounds each channel of the colors to the closest of the evenly spaced levels of the options, if any,
+/// black and the full channel being the first and the last ones
+///
+/// The alpha channel is left as it is.
+///
+fn posterize<C: Channel>(colors: &mut [Rgba<C>], options: &Options) {
+    if let Some(levels) = options.posterize {
+        let step = C::MAX as f32 / (levels.max(2) - 1) as f32;
+        for color in colors.iter_mut() {
+            for c in 0..3 {
+                color[c] = C::from_value(((color[c].value() as f32 / step).round() * step).round() as u32);
+            }
+        }
+    }
+}
+
+/// Replaces the colors by the closest ones of the palette of the options, if any
+fn snap_to_palette<C: Channel>(colors: &mut [Rgba<C>], options: &Options) {
+    if let Some(ref palette) = options.palette {
+        for color in colors.iter_mut() {
+            *color = widen(palette.nearest(narrow(*color)));
+        }
+    }
+}
+
+///
+/// Colors each pixel of the image with the color of its cell
+///
+/// The rows of the output are painted in parallel, directly in the output buffer.
+/// Only the center sampling does not need to find the cells of all the pixels beforehand.
+///
+/// The pixels closer than half of the gap to the edge of their cell are painted with the background color.
+/// The pixels closer than half of the border width to the edge of the remaining shrunk cell
+/// are painted with the border color, so that the line between 2 adjacent cells is as wide as the border.
+///
+/// In the dots mode, only the pixels of the dot at the center of each cell take its color,
+/// the outermost border width of the dot being painted with the border color, and the others the background color.
+///
+fn render<I, C, T>(img: &I, tessellation: &T, options: &Options, steps: &Steps) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel, T: Tessellation + ?Sized {
+    let (width, height) = img.dimensions();
+    let cells = if options.needs_cell_map() {
+        Some(cell_map(tessellation, width, height, options.chunk_size, steps))
+    } else {
+        None
+    };
+    let colors = cell_colors(img, tessellation, options, cells.as_deref());
+    let mosaic = match options.tiles {
+        Some(ref tiles) if options.mode == PixelMode::photomosaic => {
+            let cell = (options.cell_width.unwrap_or(options.size), options.cell_height.unwrap_or(options.size));
+            Some(Mosaic::new(tiles, &colors, cell))
+        }
+        _ => None,
+    };
+
+    let mut pixelised: RgbaBuffer<C> = ImageBuffer::new(width, height);
+    let rows = pixelised.par_chunks_mut(width.max(1) as usize * 4).with_min_len(options.chunk_size.max(1));
+    rows.enumerate().for_each(|(y, row)| {
+        let row_cells = cells.as_ref().map(|cells| &cells[y * width as usize..(y + 1) * width as usize]);
+        render_row(tessellation, &colors, options, (y as u32, height), row, row_cells, mosaic.as_ref());
+        steps.step();
+    });
+    pixelised
+}
+
+///
+/// Pixelises the red, green and blue channels of the image with their own grids
+///
+/// Each channel is taken from the image pixelised with its grid, and the alpha channel from the most opaque of them.
+///
+fn render_channels<I, C>(img: &I, options: &Options, grids: &ChannelGrids, steps: &Steps) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel {
+    let channels: Vec<RgbaBuffer<C>> = (0..3).map(|c| {
+        let options = Options {
+            size: grids.sizes[c],
+            offset: (options.offset.0 + grids.offsets[c].0, options.offset.1 + grids.offsets[c].1),
+            split_channels: None,
+            ..options.clone()
+        };
+        render(img, tessellation(img, &options).as_ref(), &options, steps)
+    }).collect();
+
+    let mut pixelised = channels[0].clone();
+    pixelised.par_chunks_mut(4).zip(channels[1].par_chunks(4)).zip(channels[2].par_chunks(4))
+        .for_each(|((red, green), blue)| {
+            red[1] = green[1];
+            red[2] = blue[2];
+            red[3] = red[3].max(green[3]).max(blue[3]);
+        });
+    pixelised
+}
+
+///
+/// The radius of the dot of a cell of that color, in the dots mode
+///
+/// Without a fixed radius, the area of the dot is proportional to the darkness of the color,
+/// the dot of a black cell being as large as the circle around its hexagon.
+///
+fn dot_radius<C: Channel>(color: Rgba<C>, options: &Options) -> f32 {
+    options.dot_radius.unwrap_or_else(|| options.size as f32 * (1.0 - luminance(narrow(color)) / 255.0).sqrt())
+}
+
+///
+/// Colors the pixels of the row `y` with the colors of their cells, or with the background or border colors
+///
+/// The `cells` of the pixels of the row are found when they are not given.
+/// The row is given along with the `height` of the image, as the shadows of the cells are cast from the other rows.
+/// The cells are filled with their tiles rather than their colors when there is a `mosaic`.
+///
+fn render_row<C, T>(tessellation: &T, colors: &[Rgba<C>], options: &Options, (y, height): (u32, u32), row: &mut [C],
+                    cells: Option<&[usize]>, mosaic: Option<&Mosaic>)
+    where C: Channel, T: Tessellation + ?Sized {
+    let width = (row.len() / 4) as u32;
+    let half_gap = options.gap.max(0.0) / 2.0;
+    let half_border = options.border_width / 2.0;
+    let (background, border_color): (Rgba<C>, Rgba<C>) = (widen(options.background), widen(options.border_color));
+    let bevel_width = bevel_width(options);
+    let (cell_width, cell_height) = (options.cell_width.unwrap_or(options.size) as f32, options.cell_height.unwrap_or(options.size) as f32);
+    let mut outlines = Outlines::default();
+    let mut row_cells = Vec::new();
+    let cells = match cells {
+        Some(cells) => cells,
+        None => {
+            row_cells.resize(width as usize, 0);
+            tessellation.row_cells(0, y, &mut row_cells);
+            &row_cells
+        }
+    };
+    for (x, pixel) in row.chunks_mut(4).enumerate() {
+        let cell = cells[x];
+        if options.mode == PixelMode::dots {
+            let (cx, cy) = tessellation.center(cell);
+            let distance = (sqr!(x as f32 - cx) + sqr!(y as f32 - cy)).sqrt();
+            let radius = dot_radius(colors[cell], options);
+            let color = if distance >= radius {
+                shadowed(background, tessellation, colors, options, (x as u32, y), (width, height))
+            } else if distance >= radius - options.border_width {
+                border_color
+            } else {
+                colors[cell]
+            };
+            pixel.copy_from_slice(&color.data);
+            continue;
+        }
+        if options.mode == PixelMode::emoji || options.mode == PixelMode::ascii {
+            // the centers of the squares are their corners
+            let (cx, cy) = middle(outlines.get(tessellation, cell));
+            let point = ((x as f32 + 0.5 - cx) / cell_width, (y as f32 + 0.5 - cy) / cell_height);
+            let color = narrow(colors[cell]);
+            let color = if options.mode == PixelMode::ascii {
+                Some(colors[cell]).filter(|_| ascii::covers(ascii::character(color), point))
+            } else {
+                Some(widen(emoji::color(emoji::nearest(color), color))).filter(|_| emoji::covers(options.emoji_shape, point))
+            };
+            let color = color.unwrap_or_else(|| {
+                shadowed(background, tessellation, colors, options, (x as u32, y), (width, height))
+            });
+            pixel.copy_from_slice(&color.data);
+            continue;
+        }
+        let edge = if half_gap > 0.0 || half_border > 0.0 || options.bevel > 0.0 || options.antialias {
+            tessellation.edge_distance(x as u32, y, cell)
+        } else {
+            f32::MAX
+        };
+        let color = if edge < half_gap {
+            shadowed(background, tessellation, colors, options, (x as u32, y), (width, height))
+        } else if edge < half_gap + half_border {
+            border_color
+        } else {
+            // the boundaries between the cells, where there is neither gap nor border
+            let color = if options.antialias && edge < ANTIALIASED_EDGE {
+                antialiased(tessellation, colors, cell, (x as u32, y), (width, height), &mut outlines)
+            } else if let Some(mosaic) = mosaic {
+                mosaic.color(cell, middle(outlines.get(tessellation, cell)), (x as u32, y), colors[cell])
+            } else {
+                colors[cell]
+            };
+            if edge - half_gap - half_border < bevel_width {
+                let point = (x as f32 + 0.5, y as f32 + 0.5);
+                let polygon = outlines.get(tessellation, cell);
+                bevel(color, polygon, tessellation.center(cell), point, edge - half_gap - half_border, options)
+            } else {
+                color
+            }
+        };
+        pixel.copy_from_slice(&color.data);
+    }
+}
+
+///
+/// The outlines of the last cells of a row, as found by `Tessellation::polygon`
+///
+/// The pixels of a cell following each other along the rows, the outlines of the few last cells are kept
+/// rather than found again for each pixel.
+///
+#[derive(Default)]
+struct Outlines {
+    cells: Vec<(usize, Vec<(f32, f32)>)>,
+}
+
+/// The number of outlines kept by `Outlines`
+const OUTLINES: usize = 8;
+
+impl Outlines {
+    fn get<T>(&mut self, tessellation: &T, cell: usize) -> &[(f32, f32)]
+        where T: Tessellation + ?Sized {
+        let i = match self.cells.iter().position(|c| c.0 == cell) {
+            Some(i) => i,
+            None => {
+                if self.cells.len() == OUTLINES {
+                    self.cells.remove(0);
+                }
+                self.cells.push((cell, tessellation.polygon(cell)));
+                self.cells.len() - 1
+            }
+        };
+        &self.cells[i].1
+    }
+}
+
+/// The middle of the bounding box of the outline of a cell
+fn middle(polygon: &[(f32, f32)]) -> (f32, f32) {
+    let (mut left, mut top, mut right, mut bottom) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for &(x, y) in polygon {
+        left = left.min(x);
+        top = top.min(y);
+        right = right.max(x);
+        bottom = bottom.max(y);
+    }
+    ((left + right) / 2.0, (top + bottom) / 2.0)
+}
+
+/// The distance to the edge of their cell under which the pixels may be partly covered by the next cells
+const ANTIALIASED_EDGE: f32 = 0.5;
+
+///
+/// The color of the pixel `(x, y)` of the `cell`, in an image of the `size`, blended from the colors
+/// of the cells covering it
+///
+/// The pixel is sampled at 4 points around it, each taking the color of the cell whose outline contains it,
+/// among the cell of the pixel and those of its 8 neighbours. The colors are averaged weighted by their alpha.
+///
+fn antialiased<C, T>(tessellation: &T, colors: &[Rgba<C>], cell: usize, (x, y): (u32, u32), (width, height): (u32, u32),
+                     outlines: &mut Outlines) -> Rgba<C>
+    where C: Channel, T: Tessellation + ?Sized {
+    let mut neighbours = Vec::with_capacity(8);
+    for dy in -1..=1i64 {
+        for dx in -1..=1i64 {
+            let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+            if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                continue;
+            }
+            let neighbour = tessellation.cell_index(nx as u32, ny as u32);
+            if neighbour != cell && !neighbours.contains(&neighbour) {
+                neighbours.push(neighbour);
+            }
+        }
+    }
+    if neighbours.is_empty() {
+        return colors[cell];
+    }
+
+    let mut sum = [0f32; 4];
+    for &(dx, dy) in &[(-0.25, -0.25), (0.25, -0.25), (-0.25, 0.25), (0.25, 0.25)] {
+        let point = (x as f32 + dx, y as f32 + dy);
+        let sample = if contains(outlines.get(tessellation, cell), point) {
+            cell
+        } else {
+            neighbours.iter().cloned().find(|&n| contains(outlines.get(tessellation, n), point)).unwrap_or(cell)
+        };
+        let color = colors[sample];
+        let alpha = color[3].value() as f32;
+        for c in 0..3 {
+            sum[c] += color[c].value() as f32 * alpha;
+        }
+        sum[3] += alpha;
+    }
+    if sum[3] == 0.0 {
+        return sampling::transparent();
+    }
+    let channel = |c: usize| C::from_value((sum[c] / sum[3]).round() as u32);
+    Rgba([channel(0), channel(1), channel(2), C::from_value((sum[3] / 4.0).round() as u32)])
+}
+
+///
+/// The background of the pixel `(x, y)`, darkened by the shadow of the cells of the options, if any,
+/// in an image of the `size`
+///
+/// The pixel is in the shadow of the cell found at the pixel shifted back by the offset of the shadow,
+/// when that pixel is inside of the cell once shrunk by the gap, or of its dot in the dots mode.
+/// The shadow fades out over the blur width across the edge of the cell, and the transparent cells cast none.
+///
+fn shadowed<C, T>(background: Rgba<C>, tessellation: &T, colors: &[Rgba<C>], options: &Options, (x, y): (u32, u32),
+                  (width, height): (u32, u32)) -> Rgba<C>
+    where C: Channel, T: Tessellation + ?Sized {
+    let shadow = match options.shadow {
+        Some(shadow) => shadow,
+        None => return background,
+    };
+    let (sx, sy) = ((x as f32 - shadow.offset.0).round(), (y as f32 - shadow.offset.1).round());
+    if sx < 0.0 || sy < 0.0 || sx >= width as f32 || sy >= height as f32 {
+        return background;
+    }
+    let (sx, sy) = (sx as u32, sy as u32);
+    let cell = tessellation.cell_index(sx, sy);
+
+    // how deep the shifted pixel is inside of the visible part of its cell, negative outside of it
+    let depth = if options.mode == PixelMode::dots {
+        let (cx, cy) = tessellation.center(cell);
+        dot_radius(colors[cell], options) - (sqr!(sx as f32 - cx) + sqr!(sy as f32 - cy)).sqrt()
+    } else {
+        tessellation.edge_distance(sx, sy, cell) - options.gap.max(0.0) / 2.0
+    };
+    let coverage = if shadow.blur > 0.0 {
+        ((depth + shadow.blur / 2.0) / shadow.blur).clamp(0.0, 1.0)
+    } else if depth > 0.0 {
+        1.0
+    } else {
+        0.0
+    };
+    let max = C::MAX as f32;
+    let alpha = coverage * shadow.color[3] as f32 / 255.0 * colors[cell][3].value() as f32 / max;
+    if alpha <= 0.0 {
+        return background;
+    }
+
+    // the shadow over the background
+    let color: Rgba<C> = widen(shadow.color);
+    let under = background[3].value() as f32 / max * (1.0 - alpha);
+    let total = alpha + under;
+    let channel = |c: usize| {
+        let mixed = (color[c].value() as f32 * alpha + background[c].value() as f32 * under) / total;
+        C::from_value(mixed.round() as u32)
+    };
+    Rgba([channel(0), channel(1), channel(2), C::from_unit(total)])
+}
+
+///
+/// The color of the pixel at `point` of a beveled cell, `depth` pixels inside of the edge of its shrunk cell
+///
+/// The bevel is a quarter of the size wide. The edge closest to the pixel is lightened when it faces the light,
+/// darkened when it faces away from it, the more so the closer the pixel is to it.
+///
+fn bevel<C: Channel>(color: Rgba<C>, polygon: &[(f32, f32)], center: (f32, f32), point: (f32, f32), depth: f32,
+                     options: &Options) -> Rgba<C> {
+    // the direction from the closest point of the outline to the pixel, inwards
+    let mut closest = (f32::MAX, (0.0, 0.0));
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let length = sqr!(dx) + sqr!(dy);
+        let t = if length > 0.0 { (((point.0 - a.0) * dx + (point.1 - a.1) * dy) / length).clamp(0.0, 1.0) } else { 0.0 };
+        let inwards = (point.0 - a.0 - t * dx, point.1 - a.1 - t * dy);
+        let distance = sqr!(inwards.0) + sqr!(inwards.1);
+        if distance < closest.0 {
+            closest = (distance, inwards);
+        }
+    }
+    // the pixels on the outline face away from the center
+    let (nx, ny) = if closest.0 > 1e-6 { closest.1 } else { (center.0 - point.0, center.1 - point.1) };
+    let length = (sqr!(nx) + sqr!(ny)).sqrt();
+    if length == 0.0 {
+        return color;
+    }
+
+    let light = options.light_angle.to_radians();
+    let facing = -(nx * light.cos() + ny * light.sin()) / length;
+    let amount = options.bevel.clamp(0.0, 1.0) * facing * (1.0 - depth.max(0.0) / bevel_width(options));
+    let channel = |c: usize| {
+        let value = color[c].value() as f32;
+        let target = if amount > 0.0 { C::MAX as f32 } else { 0.0 };
+        C::from_value((value + (target - value) * amount.abs()).round() as u32)
+    };
+    Rgba([channel(0), channel(1), channel(2), color[3]])
+}
+
+/// The width of the bevels of the cells, none when the cells are flat
+fn bevel_width(options: &Options) -> f32 {
+    if options.bevel > 0.0 { (options.size as f32 / 4.0).max(1.0) } else { 0.0 }
+}
@@ -2,208 +2,3984 @@ extern crate image;
 
 #[macro_use]
 extern crate clap;
+extern crate form_urlencoded;
+extern crate glob;
+extern crate hexpxl;
+extern crate indicatif;
+#[cfg(feature = "preview")]
+extern crate minifb;
+#[cfg(feature = "parquet")]
+extern crate parquet;
+extern crate ravif;
 extern crate rayon;
+extern crate serde;
+extern crate tempfile;
+extern crate tiny_http;
+extern crate toml;
+extern crate twox_hash;
+extern crate ureq;
+extern crate webp;
 
 
-use image::{ImageBuffer, DynamicImage, GenericImageView, RgbaImage, Rgba};
-use std::f32::consts::PI;
-use std::time::{Duration, Instant};
-use clap::{App, Arg};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{self, Path, PathBuf};
+use std::str::FromStr;
+use std::process::{self, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use image::{DynamicImage, ImageBuffer, ImageError, ImageFormat, ImageOutputFormat, Rgba, RgbaImage};
+use hexpxl::{Alpha, ChannelGrids, EmojiShape, FitGrid, Options, Orientation, PixelMode, Sampling, Shadow};
+use hexpxl::emoji;
+use hexpxl::deep::{self, PngCompression, PngEncoding, PngFilter, Rgba16Image};
+use hexpxl::mask::Mask;
+use hexpxl::mosaic::Tiles;
+use hexpxl::metadata::{self, Metadata};
+use hexpxl::animation;
+use hexpxl::export;
+use hexpxl::faces::Region;
+use hexpxl::gradient::Gradient;
+use hexpxl::color::parse_color;
+use hexpxl::palette::Palette;
+use hexpxl::sizes::SizeField;
+use hexpxl::plan;
+use hexpxl::stream::{self, PngReader, PngWriter, PnmReader, PnmWriter, RowReader, RowWriter, StreamError, TiffReader};
+use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
+use serde::{de, Deserialize, Deserializer};
+use server::{serve_http, Server};
+use terminal::Graphics;
+use toml::value::{Table, Value};
 
-
-macro_rules! sqr {
-    ( $a:expr ) => {
-        {
-            let tmp = $a;
-            tmp * tmp
-        }
-    }
-}
+mod bench;
+#[cfg(feature = "preview")]
+mod preview;
+mod server;
+mod terminal;
+mod verify;
 
 
-arg_enum! {
-    #[derive(Debug)]
-    enum PixelMode {
-        sqr,
-        hex,
-    }
-}
-
 fn main() {
+    // the capture of --capture takes the place of the source, which then comes after the other positional arguments
+    let capture = env::args_os().skip(1).take_while(|a| a != "--")
+        .any(|a| a == "--capture" || a.to_string_lossy().starts_with("--capture="));
     let matches = App::new("hexpxl, a non-square pixelisation tool")
         .version("0.1.1") // TODO: find how to sync this with cargo.toml
         .author("Christophe '116' Loiseau <116@lab0.net>")
         .about("Pixelises an image using a hexagonal pattern")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::ArgsNegateSubcommands)
         .arg(
-            Arg::from_usage("<source> 'Input image path'")
-                .required(true)
+            Arg::from_usage("<source> 'Input image path or http(s) url, - for stdin'")
+                .required_unless_one(&["show-params", "capture"])
+                .conflicts_with("capture")
+                .index(if capture { 3 } else { 1 })
         )
         .arg(
-            Arg::from_usage("<destination> 'Output image path'")
-                .required(true)
+            Arg::from_usage("<destination> 'Output image path, - for stdout'")
+                .required_unless("show-params")
+                .index(if capture { 1 } else { 2 })
         )
         .arg(
-            Arg::from_usage("<size> 'The size of the pixels, in pixel :P'")
+            Arg::from_usage("<size> 'The size of the pixels, in pixel :P, or their width and height, e.g. 20x10, or a length such as 5mm or 0.2in'")
                 .default_value("20")
+                .index(if capture { 2 } else { 3 })
+        )
+        .arg(
+            Arg::from_usage("--show-params [image] 'Prints the parameters a PNG or JPEG image was pixelised with, \
+            as a config file pixelising the same way'")
+        )
+        .arg(
+            Arg::from_usage("--capture [capture] 'Pixelises a capture of the screen rather than a source: \
+            screen for all the screens, screen:N for the Nth one, from 1, or region for a region selected with the mouse. \
+            Taken by screencapture on macOS, grim and slurp on Wayland, or maim, slop and xrandr on X11.'")
+        )
+        .args(&pixelisation_args())
+        .arg(
+            Arg::from_usage("--input-format [format] 'The format of the input image. Guessed from its content by default.'")
+        )
+        .arg(
+            Arg::from_usage("--format [format] 'The format of the output image, e.g. png, jpeg, webp, bmp or tiff, \
+            rather than the one of the extension of the destination. Needed when writing to stdout. \
+            svg writes the cells as polygons.'")
+        )
+        .args(&encoding_args())
+        .args(&stats_args())
+        .args(&overwrite_args())
+        .arg(
+            Arg::from_usage("--max-memory [size] 'Streams the image in bands of rows, keeping the memory used \
+            under this size, e.g. 512M. Reads PNG, TIFF and PNM images, writes PNG and PNM ones.'")
+        )
+        .arg(
+            Arg::from_usage("--watch 'Pixelises the source again whenever it changes, or with --capture \
+            captures the screen again every second and pixelises it whenever it changes, until interrupted'")
+        )
+        .arg(dry_run_arg())
+        .arg(
+            Arg::from_usage("--export-grid [path] 'Also writes the hexagons of the hex and dots modes, \
+            with their axial coordinates, centers and colors, as a .json file or a .tmx map of Tiled. \
+            The tileset of the map is written next to it, as <name>-tiles.png.'")
+        )
+        .arg(
+            Arg::from_usage("--overlay-grid [path] 'Also writes the source image with the boundaries \
+            and the centers of the cells drawn over it, to see how the grid falls on the image'")
+        )
+        .arg(
+            Arg::from_usage("--export-cells [path] 'Also writes a row for each cell, with its center, \
+            its axial coordinates, the mean color, the number and the variance of the colors of its pixels, \
+            as a .csv file, or a .parquet file when hexpxl is built with --features parquet'")
+        )
+        .arg(
+            Arg::from_usage("--terminal 'Also prints the pixelised image in the terminal, \
+            with colored half blocks, shrunk to the width of the terminal'")
+        )
+        .arg(
+            Arg::from_usage("--graphics [graphics] 'How --terminal draws the image: with colored half blocks, \
+            braille characters, or the kitty or sixel graphics of the terminals which have them. \
+            Guessed from the terminal by default.'")
+                .possible_values(&Graphics::variants())
+                .requires("terminal")
         )
         .arg(
-            Arg::from_usage("<mode> 'The PixelMode to use'")
-                .help("The pixelisation mode")
-                .short("m")
-                .long("mode")
-                .default_value("hex")
+            Arg::from_usage("--braille 'Also prints the pixelised image in the terminal as colored braille characters, \
+            each drawing 2 x 4 pixels, as --terminal --graphics braille does'")
+                .conflicts_with("graphics")
+        )
+        .subcommand(
+            SubCommand::with_name("batch")
+                .about("Pixelises many images in parallel")
+                .arg(
+                    Arg::from_usage("[source]... 'Input image paths, directories, glob patterns or http(s) urls'")
+                        .required_unless("sequence")
+                        .conflicts_with("sequence")
+                )
+                .arg(
+                    Arg::from_usage("--sequence [pattern] 'Pixelises the numbered frames of a sequence, \
+                    their path having the number as in printf, e.g. frame_%04d.png. \
+                    The frames share the grid of the first one.'")
+                )
+                .arg(
+                    Arg::from_usage("--frames [range] 'The first and last numbers of the frames of --sequence, \
+                    e.g. 1-240. All the frames found by default.'")
+                        .requires("sequence")
+                )
+                .arg(
+                    Arg::from_usage("-o, --output <template> 'Output path template. \
+                    Replaces {dir}, {stem}, {ext}, {mode} and {size} with the values of each input.'")
+                        .default_value("{dir}/{stem}_{mode}{size}.{ext}")
+                )
+                .arg(
+                    Arg::from_usage("--format [format] 'The format of the output images, rather than the one \
+                    of the extension of their paths'")
+                )
+                .args(&encoding_args())
+                .args(&stats_args())
+                .args(&overwrite_args())
+                .arg(dry_run_arg())
+                .arg(
+                    Arg::from_usage("--watch 'Pixelises the images of the sources again whenever they change \
+                    or new ones appear, until interrupted'")
+                )
+                .arg(
+                    Arg::from_usage("-s, --size <size> 'The size of the pixels, in pixel :P, or their width and height, e.g. 20x10, or a length such as 5mm or 0.2in'")
+                        .default_value("20")
+                )
+                .args(&pixelisation_args())
+        )
+        .subcommand(
+            SubCommand::with_name("video")
+                .about("Pixelises a video, decoded and encoded by ffmpeg")
+                .arg(
+                    Arg::from_usage("<source> 'Input video path'")
+                )
+                .arg(
+                    Arg::from_usage("<destination> 'Output video path'")
+                )
+                .arg(
+                    Arg::from_usage("-s, --size <size> 'The size of the pixels, in pixel :P, or their width and height, e.g. 20x10, or a length such as 5mm or 0.2in'")
+                        .default_value("20")
+                )
+                .arg(
+                    Arg::from_usage("--ffmpeg [path] 'The ffmpeg executable'")
+                        .default_value("ffmpeg")
+                )
+                .arg(
+                    Arg::from_usage("--ffprobe [path] 'The ffprobe executable'")
+                        .default_value("ffprobe")
+                )
+                .args(&pixelisation_args())
+                .args(&overwrite_args())
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Serves the pixelisation over HTTP, of the images POSTed to / \
+                or at the url of the query string, with the options of the query string")
+                .arg(
+                    Arg::from_usage("-a, --address [address] 'The address and the port to listen on'")
+                        .default_value("127.0.0.1:8080")
+                )
+                .arg(
+                    Arg::from_usage("--workers [count] 'The number of requests pixelised at once, \
+                    the others waiting for their turn'")
+                        .default_value("4")
+                )
+                .arg(
+                    Arg::from_usage("--max-body [size] 'The largest image accepted, posted or downloaded, e.g. 16M'")
+                        .default_value("16M")
+                )
+                .arg(
+                    Arg::from_usage("--max-memory [size] 'The most memory the pixelisation of a request may take, \
+                    roughly, as estimated from the size of its image before it is decoded, e.g. 1G'")
+                        .default_value("1G")
+                )
+                .arg(
+                    Arg::from_usage("--max-cells [count] 'The most cells the pixelisation of a request may make, \
+                    roughly, as estimated from the size of its image and of the smallest cells of its options'")
+                        .default_value("1000000")
+                )
+                .arg(
+                    Arg::from_usage("--max-iterations [count] 'The most times the superpixels of the slic mode \
+                    may be refined for a request'")
+                        .default_value("20")
+                )
+                .arg(
+                    Arg::from_usage("--allow-urls 'Downloads and pixelises the images at the url of the query strings'")
+                )
+                .arg(
+                    Arg::from_usage("--format [format] 'The format of the pixelised images when the query string \
+                    gives none, rather than the one of the posted image'")
+                )
+                .arg(
+                    Arg::from_usage("-s, --size <size> 'The size of the pixels, in pixel :P, or their width and height, e.g. 20x10'")
+                        .default_value("20")
+                )
+                .args(&encoding_args())
+                .args(&pixelisation_args())
+        )
+        .subcommand(
+            SubCommand::with_name("preview")
+                .about("Shows the pixelised image in a window, with controls for its size, its mode and its border")
+                .arg(
+                    Arg::from_usage("<source> 'Input image path or http(s) url'")
+                )
+                .arg(
+                    Arg::from_usage("-o, --output <template> 'Output path template of the save button. \
+                    Replaces {dir}, {stem}, {ext}, {mode} and {size} with the values of the input and the settings.'")
+                        .default_value("{dir}/{stem}_{mode}{size}.{ext}")
+                )
+                .arg(
+                    Arg::from_usage("-s, --size <size> 'The initial size of the pixels, in pixel :P'")
+                        .default_value("20")
+                )
+                .args(&pixelisation_args())
+        )
+        .subcommand(
+            SubCommand::with_name("montage")
+                .about("Pixelises an image with several modes and sizes, side by side on a labeled contact sheet")
+                .arg(
+                    Arg::from_usage("<source> 'Input image path or http(s) url, - for stdin'")
+                )
+                .arg(
+                    Arg::from_usage("<destination> 'Output image path of the contact sheet'")
+                )
+                .arg(
+                    Arg::from_usage("--sizes <sizes> 'The sizes of the pixels of the columns of the sheet, \
+                    separated by commas'")
+                        .default_value("10,20,40")
+                )
+                .arg(
+                    Arg::from_usage("--modes [modes] 'The modes of the rows of the sheet, separated by commas. \
+                    The mode of --mode by default.'")
+                )
+                .arg(
+                    Arg::from_usage("--thumbnail-width [pixels] 'The largest width of the images on the sheet'")
+                        .default_value("400")
+                )
+                .args(&pixelisation_args())
+                .args(&overwrite_args())
+        )
+        .subcommand(
+            SubCommand::with_name("preset")
+                .about("Saves sets of options as presets, reused with --preset")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("save")
+                        .about("Saves the options given on the command line under a name, \
+                        along with those of the preset and the config file it is given")
+                        .arg(
+                            Arg::from_usage("<name> 'The name of the preset'")
+                        )
+                        .arg(
+                            Arg::from_usage("-s, --size [size] 'The size of the pixels, in pixel :P'")
+                        )
+                        .args(&pixelisation_args())
+                )
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("Lists the presets and their options")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("stitch")
+                .about("Makes a cross-stitch pattern of DMC threads, with its chart and its legend")
+                .arg(
+                    Arg::from_usage("<source> 'Input image path or http(s) url, - for stdin'")
+                )
+                .arg(
+                    Arg::from_usage("<chart> 'Output image path of the chart, showing the symbol of the thread of each stitch'")
+                )
+                .arg(
+                    Arg::from_usage("<legend> 'Output path of the legend, listing the threads and their numbers of stitches. \
+                    Written as a PDF document for the .pdf paths, as an image otherwise.'")
+                )
+                .arg(
+                    Arg::from_usage("-s, --size <size> 'The side of the squares of the input image making a stitch, in pixels'")
+                        .default_value("10")
+                )
+                .arg(
+                    Arg::from_usage("--cell-size [pixels] 'The side of the stitches on the chart'")
+                        .default_value("24")
+                )
+                .arg(
+                    Arg::from_usage("--sampling [sampling] 'How the color of each stitch is chosen'")
+                        .possible_values(&Sampling::variants())
+                        .default_value("average")
+                )
+                .arg(
+                    Arg::from_usage("--palette [palette] 'Snaps the colors of the stitches to a palette before choosing their threads'")
+                )
+                .arg(
+                    Arg::from_usage("-q, --quiet 'Does not show the size of the pattern'")
+                )
+                .arg(
+                    Arg::from_usage("--threads [count] 'The number of threads making the pattern, \
+                    RAYON_NUM_THREADS or one per core by default'")
+                )
+                .args(&overwrite_args())
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Measures how fast the modes pixelise synthetic images, in millions of pixels per second")
+                .arg(
+                    Arg::from_usage("--sizes [sizes] 'The widths and heights of the square images, separated by commas'")
+                        .default_value("512,2048")
+                )
+                .arg(
+                    Arg::from_usage("--modes [modes] 'The modes measured, separated by commas, \
+                    all of them but photomosaic by default'")
+                )
+                .arg(
+                    Arg::from_usage("-s, --size <size> 'The size of the pixels, in pixel :P'")
+                        .default_value("20")
+                )
+                .arg(
+                    Arg::from_usage("--threads [counts] 'The numbers of threads measured, separated by commas, \
+                    RAYON_NUM_THREADS or one per core by default'")
+                )
+                .arg(
+                    Arg::from_usage("--runs [count] 'The pixelisations of each image, the fastest one being kept'")
+                        .default_value("3")
+                )
+                .arg(
+                    Arg::from_usage("--save [path] 'Also writes the measures as lines of JSON, to compare with later'")
+                )
+                .arg(
+                    Arg::from_usage("--baseline [path] 'Compares the measures with those written by --save'")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("verify-golden")
+                .about("Pixelises small reference images with each mode and with the main options, \
+                and compares them with their golden images")
+                .setting(AppSettings::Hidden)
+                .arg(
+                    Arg::from_usage("--golden [directory] 'The directory of the golden images'")
+                        .default_value("tests/golden")
+                )
+                .arg(
+                    Arg::from_usage("--tolerance [difference] 'The largest difference of each channel of the pixels \
+                    which is still a match, from 0 to 255'")
+                        .default_value("2")
+                )
+                .arg(
+                    Arg::from_usage("--update 'Writes the pixelisations as the golden images, \
+                    after a change of the output which is meant'")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Checks that the pixels of an image have the hash printed by --print-hash")
+                .arg(
+                    Arg::from_usage("<image> 'The image whose pixels are hashed'")
+                )
+                .arg(
+                    Arg::from_usage("<hash> 'The hash which the pixels of the image must have'")
+                )
         )
         .get_matches();
 
-
-    let src = matches.value_of("source").unwrap();
-    let dst = matches.value_of("destination").unwrap();
-    let size = value_t!(matches, "size", u32).unwrap_or_else(|e| e.exit());
-    let mode = value_t!(matches.value_of("mode"), PixelMode).unwrap_or_else(|e| e.exit());
-
-    pixelise(mode, src, dst, size)
+    let result = thread_pool(&matches).and_then(|pool| pool.install(|| run(&matches)));
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(e.exit_code());
+    }
 }
 
-fn pixelise(mode: PixelMode, src: &str, dst: &str, size: u32) {
-    let load_start = Instant::now();
-    let img = image::open(src).unwrap();
-    println!("Image loading time: {}", load_start.elapsed().as_millis());
+///
+/// Runs the command of the arguments
+///
+fn run(matches: &ArgMatches) -> Result<(), Error> {
+    if let Some(batch) = matches.subcommand_matches("batch") {
+        let template = batch.value_of("output").unwrap();
+        let watch = batch.is_present("watch");
+        options(batch).and_then(|options| {
+            let formats = format_args(batch)?;
+            let frames = match batch.value_of("sequence") {
+                Some(pattern) => Some(sequence_frames(pattern, batch.value_of("frames"))?),
+                None => None,
+            };
+            let sources: Vec<&str> = match frames {
+                Some(ref frames) => frames.iter().map(String::as_str).collect(),
+                None => batch.values_of("source").unwrap().collect(),
+            };
+            // the size of the cells is fitted once to the first frame, for the columns and rows
+            let options = match frames.as_ref().and_then(|frames| frames.first()) {
+                Some(first) => {
+                    let data = read_input(first)?;
+                    let (width, height) = image_dimensions(first, &data, input_format(first, &data, &formats)?)?;
+                    options.fitted(width, height).into_owned()
+                }
+                None => options,
+            };
+            if batch.is_present("dry-run") {
+                return dry_run_batch(&options, &sources, template, &formats);
+            }
+            let reports = Reports {
+                quiet: batch.is_present("quiet"),
+                stats: StatsOutput::new(batch)?,
+                hash: batch.is_present("print-hash"),
+            };
+            pixelise_batch(&options, &sources, template, &formats, &reports, watch)
+        })
+    } else if let Some(video) = matches.subcommand_matches("video") {
+        let src = video.value_of("source").unwrap();
+        let dst = video.value_of("destination").unwrap();
+        let tools = Ffmpeg {
+            ffmpeg: video.value_of("ffmpeg").unwrap(),
+            ffprobe: video.value_of("ffprobe").unwrap(),
+        };
+        let quiet = video.is_present("quiet");
+        let no_clobber = video.is_present("no-clobber");
+        options(video).and_then(|options| pixelise_video(&options, src, dst, &tools, quiet, no_clobber))
+    } else if let Some(serve) = matches.subcommand_matches("serve") {
+        let address = serve.value_of("address").unwrap();
+        let workers = value_t!(serve, "workers", usize).unwrap_or_else(|e| e.exit());
+        let max_body = serve.value_of("max-body").unwrap();
+        let max_body = parse_memory(max_body).ok_or_else(|| {
+            Error::Usage(format!("invalid size '{}' for --max-body, e.g. 16M", max_body))
+        })?;
+        let max_memory = serve.value_of("max-memory").unwrap();
+        let max_memory = parse_memory(max_memory).ok_or_else(|| {
+            Error::Usage(format!("invalid size '{}' for --max-memory, e.g. 1G", max_memory))
+        })?;
+        let max_cells = value_t!(serve, "max-cells", u64).unwrap_or_else(|e| e.exit());
+        let max_iterations = value_t!(serve, "max-iterations", u32).unwrap_or_else(|e| e.exit());
+        // the options of the command line are checked before serving them
+        options(serve).and_then(|_| {
+            let server = Server {
+                settings: config_table(serve)?,
+                formats: format_args(serve)?,
+                max_body,
+                max_memory,
+                max_cells,
+                max_iterations,
+                allow_urls: serve.is_present("allow-urls"),
+                pool: thread_pool(matches)?,
+            };
+            serve_http(serve, &server, address, workers.max(1))
+        })
+    } else if let Some(preview) = matches.subcommand_matches("preview") {
+        let src = preview.value_of("source").unwrap();
+        let template = preview.value_of("output").unwrap();
+        options(preview).and_then(|options| show_preview(options, src, template))
+    } else if let Some(montage) = matches.subcommand_matches("montage") {
+        let src = montage.value_of("source").unwrap();
+        let dst = montage.value_of("destination").unwrap();
+        let thumbnail_width = value_t!(montage, "thumbnail-width", u32).unwrap_or_else(|e| e.exit());
+        let quiet = montage.is_present("quiet");
+        let no_clobber = montage.is_present("no-clobber");
+        options(montage).and_then(|options| {
+            let sizes = montage.value_of("sizes").unwrap();
+            let sizes = parse_sizes(sizes).ok_or_else(|| {
+                Error::Usage(format!("invalid sizes '{}' for --sizes, e.g. 10,20,40", sizes))
+            })?;
+            let modes = match montage.value_of("modes") {
+                Some(modes) => parse_modes(modes).ok_or_else(|| Error::Usage(format!(
+                    "invalid modes '{}' for --modes, the modes are {}", modes, PixelMode::variants().join(", ")
+                )))?,
+                None => vec![options.mode],
+            };
+            make_montage(&options, src, dst, &modes, &sizes, thumbnail_width, quiet, no_clobber)
+        })
+    } else if let Some(preset) = matches.subcommand_matches("preset") {
+        match preset.subcommand() {
+            ("save", Some(save)) => save_preset(save, save.value_of("name").unwrap()),
+            _ => list_presets(),
+        }
+    } else if let Some(bench) = matches.subcommand_matches("bench") {
+        let numbers = |name: &str, example: &str| {
+            let text = bench.value_of(name).unwrap();
+            parse_sizes(text).ok_or_else(|| Error::Usage(format!("invalid {} '{}' for --{}, e.g. {}", name, text, name, example)))
+        };
+        let sizes = numbers("sizes", "512,2048")?;
+        let modes = match bench.value_of("modes") {
+            Some(modes) => parse_modes(modes).ok_or_else(|| Error::Usage(format!(
+                "invalid modes '{}' for --modes, the modes are {}", modes, PixelMode::variants().join(", ")
+            )))?,
+            // the photomosaic mode needs images
+            None => PixelMode::variants().iter().filter_map(|m| m.parse().ok()).filter(|&m| m != PixelMode::photomosaic).collect(),
+        };
+        let threads = match bench.value_of("threads") {
+            Some(_) => numbers("threads", "1,4")?.into_iter().map(|n| n as usize).collect(),
+            None => vec![rayon::current_num_threads()],
+        };
+        let size = value_t!(bench, "size", u32).unwrap_or_else(|e| e.exit());
+        let runs = value_t!(bench, "runs", u32).unwrap_or_else(|e| e.exit());
+        run_bench(&sizes, &modes, &threads, size, runs, bench.value_of("save"), bench.value_of("baseline"))
+    } else if let Some(verify) = matches.subcommand_matches("verify-golden") {
+        let tolerance = value_t!(verify, "tolerance", u8).unwrap_or_else(|e| e.exit());
+        run_verify(Path::new(verify.value_of("golden").unwrap()), tolerance, verify.is_present("update"))
+    } else if let Some(verify) = matches.subcommand_matches("verify") {
+        verify_hash(verify.value_of("image").unwrap(), verify.value_of("hash").unwrap())
+    } else if let Some(stitch) = matches.subcommand_matches("stitch") {
+        let src = stitch.value_of("source").unwrap();
+        let chart = stitch.value_of("chart").unwrap();
+        let legend = stitch.value_of("legend").unwrap();
+        let cell_size = value_t!(stitch, "cell-size", u32).unwrap_or_else(|e| e.exit());
+        let quiet = stitch.is_present("quiet");
+        let no_clobber = stitch.is_present("no-clobber");
+        palette_arg(stitch.value_of("palette")).and_then(|palette| {
+            let options = Options {
+                size: value_t!(stitch, "size", u32).unwrap_or_else(|e| e.exit()),
+                sampling: value_t!(stitch, "sampling", Sampling).unwrap_or_else(|e| e.exit()),
+                palette,
+                ..Options::default()
+            };
+            stitch_pattern(&options, src, chart, legend, cell_size, quiet, no_clobber)
+        })
+    } else if let Some(image) = matches.value_of("show-params") {
+        show_params(image)
+    } else {
+        let dst = matches.value_of("destination").unwrap();
+        let capture = match matches.value_of("capture") {
+            Some(text) => Some(parse_capture(text).ok_or_else(|| {
+                Error::Usage(format!("invalid capture '{}' for --capture, e.g. screen, screen:2 or region", text))
+            })?),
+            None => None,
+        };
+        options(matches).and_then(|options| {
+            let max_memory = match matches.value_of("max-memory") {
+                Some(size) => Some(parse_memory(size).ok_or_else(|| {
+                    Error::Usage(format!("invalid memory size '{}' for --max-memory, e.g. 512M", size))
+                })?),
+                None => None,
+            };
+            let formats = format_args(matches)?;
+            let grids = GridFiles {
+                export: matches.value_of("export-grid"),
+                overlay: matches.value_of("overlay-grid"),
+                cells: matches.value_of("export-cells"),
+                terminal: match (matches.is_present("terminal"), matches.is_present("graphics")) {
+                    _ if matches.is_present("braille") => Some(Graphics::braille),
+                    (true, true) => Some(value_t!(matches, "graphics", Graphics).unwrap_or_else(|e| e.exit())),
+                    (true, false) => Some(Graphics::auto),
+                    _ => None,
+                },
+            };
+            if grids.export.is_some() && max_memory.is_some() {
+                return Err(Error::Usage("--export-grid cannot be used with --max-memory".to_string()));
+            }
+            if grids.export.is_some() && options.tileable {
+                return Err(Error::Usage("--export-grid cannot be used with --tileable".to_string()));
+            }
+            if grids.cells.is_some() && max_memory.is_some() {
+                return Err(Error::Usage("--export-cells cannot be used with --max-memory".to_string()));
+            }
+            if grids.cells.is_some() && options.tileable {
+                return Err(Error::Usage("--export-cells cannot be used with --tileable".to_string()));
+            }
+            if grids.overlay.is_some() && max_memory.is_some() {
+                return Err(Error::Usage("--overlay-grid cannot be used with --max-memory".to_string()));
+            }
+            if grids.terminal.is_some() && max_memory.is_some() {
+                return Err(Error::Usage("--terminal and --braille cannot be used with --max-memory".to_string()));
+            }
+            if grids.terminal.is_some() && dst == "-" {
+                return Err(Error::Usage("--terminal and --braille cannot be used when writing to stdout".to_string()));
+            }
+            if matches.is_present("stats") && max_memory.is_some() {
+                return Err(Error::Usage("--stats cannot be used with --max-memory".to_string()));
+            }
+            if matches.is_present("print-hash") && max_memory.is_some() {
+                return Err(Error::Usage("--print-hash cannot be used with --max-memory".to_string()));
+            }
+            let quiet = matches.is_present("quiet");
+            if matches.is_present("dry-run") {
+                let print = |src: &str| -> Result<(), Error> {
+                    println!("{}", dry_run(&options, src, dst, &formats, max_memory)?);
+                    Ok(())
+                };
+                return match capture {
+                    Some(capture) => with_captures(capture, false, quiet, print),
+                    None => print(matches.value_of("source").unwrap()),
+                };
+            }
+            let reports = Reports {
+                quiet,
+                stats: StatsOutput::new(matches)?,
+                hash: matches.is_present("print-hash"),
+            };
+            if let Some(capture) = capture {
+                return with_captures(capture, matches.is_present("watch"), quiet, |src| {
+                    pixelise(&options, src, dst, &formats, max_memory, &grids, &reports)
+                });
+            }
+            let src = matches.value_of("source").unwrap();
+            if !matches.is_present("watch") {
+                return pixelise(&options, src, dst, &formats, max_memory, &grids, &reports);
+            }
+            if !Path::new(src).is_file() {
+                return Err(Error::Usage(format!("--watch needs a source file, {} is not one", src)));
+            }
+            watch(&[src], reports.quiet, |_| {
+                if let Err(e) = pixelise(&options, src, dst, &formats, max_memory, &grids, &reports) {
+                    eprintln!("{}", e);
+                }
+                vec![PathBuf::from(dst)]
+            })
+        })
+    }
+}
 
-    let pixelisation_start = Instant::now();
-    let pixelised = match mode {
-        PixelMode::sqr => square_pixelisation(&img, size),
-        PixelMode::hex => hexagon_pixelisation(&img, size),
+///
+/// The pool of the threads running the command, as many as --threads, else RAYON_NUM_THREADS, else the cores
+///
+fn thread_pool(matches: &ArgMatches) -> Result<rayon::ThreadPool, Error> {
+    let command = matches.subcommand().1.unwrap_or(matches);
+    let threads = match command.value_of("threads") {
+        // the bench command makes a pool for each of its numbers of threads
+        Some(_) if matches.subcommand_name() == Some("bench") => 0,
+        Some(threads) => threads.parse().ok().filter(|&n| n > 0).ok_or_else(|| {
+            Error::Usage(format!("invalid number of threads '{}' for --threads", threads))
+        })?,
+        // rayon then reads RAYON_NUM_THREADS
+        None => 0,
     };
-    println!("Pixelisation time: {}", pixelisation_start.elapsed().as_millis());
+    rayon::ThreadPoolBuilder::new().num_threads(threads).build()
+        .map_err(|e| Error::Usage(format!("cannot start the threads: {}", e)))
+}
 
-    let save_start = Instant::now();
-    pixelised.save(dst).unwrap();
-    println!("Image save time: {}", save_start.elapsed().as_millis());
+///
+/// The reasons why hexpxl can fail, each with its own exit code
+///
+#[derive(Debug)]
+enum Error {
+    /// The arguments are invalid
+    Usage(String),
+    /// The input cannot be read or decoded
+    Input(String),
+    /// The image format is not supported
+    UnsupportedFormat(String),
+    /// The output cannot be written
+    Output(String),
+    /// Some images of a batch failed, with the exit code of the first failure
+    Batch(usize, i32),
+    /// Some pixelisations differ from their golden images, or the pixels of an image from their hash
+    Mismatch(String),
 }
 
-fn square_pixelisation(img: &DynamicImage, radius: u32) -> RgbaImage {
-    let (w, h) = img.dimensions();
-    let mut pixelised: RgbaImage = ImageBuffer::new(w, h);
+impl Error {
+    fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Usage(_) => 1,
+            Error::Input(_) => 2,
+            Error::UnsupportedFormat(_) => 3,
+            Error::Output(_) => 4,
+            Error::Batch(_, code) => code,
+            Error::Mismatch(_) => 5,
+        }
+    }
+
+    /// The HTTP status of the responses of the server failing with the error
+    fn status(&self) -> u16 {
+        match *self {
+            Error::Usage(_) => 400,
+            Error::Input(_) => 422,
+            Error::UnsupportedFormat(_) => 415,
+            Error::Output(_) | Error::Batch(..) | Error::Mismatch(_) => 500,
+        }
+    }
+
+    fn read(path: &str, e: io::Error) -> Error {
+        Error::Input(format!("cannot read {}: {}", path, e))
+    }
+
+    fn decode(path: &str, e: ImageError) -> Error {
+        match e {
+            ImageError::IoError(e) => Error::read(path, e),
+            ImageError::UnsupportedError(_) | ImageError::UnsupportedColor(_) =>
+                Error::UnsupportedFormat(format!("cannot decode {}: {}", path, e)),
+            e => Error::Input(format!("cannot decode {}: {}", path, e)),
+        }
+    }
+
+    fn write<E: fmt::Display>(path: &str, e: E) -> Error {
+        Error::Output(format!("cannot write {}: {}", path, e))
+    }
+}
 
-    for (x, y, pixel) in pixelised.enumerate_pixels_mut() {
-        *pixel = img.get_pixel(x / radius * radius, y / radius * radius);
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Usage(ref m) | Error::Input(ref m) | Error::UnsupportedFormat(ref m) | Error::Output(ref m)
+            | Error::Mismatch(ref m) => write!(f, "{}", m),
+            Error::Batch(count, _) => write!(f, "{} images failed", count),
+        }
     }
-    return pixelised;
 }
 
 ///
-/// Pixelises an image using a hexagonal pattern
+/// The arguments of the encoding of the images, shared by the commands which write them
 ///
-/// Illustration in doc/schema.xcf (gimp file)
+fn encoding_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::from_usage("--quality [quality] 'The quality of the JPEG, WebP and AVIF images, from 0 to 100. \
+        75 for JPEG and WebP and 80 for AVIF by default.'"),
+        Arg::from_usage("--png-compression [level] 'How hard the PNG images are compressed, the best being the smallest \
+        and the slowest'")
+            .possible_values(&PngCompression::variants())
+            .default_value("default"),
+        Arg::from_usage("--png-filter [filter] 'How the rows of the PNG images are filtered before they are compressed, \
+        adaptive picking the best filter for each row'")
+            .possible_values(&PngFilter::variants())
+            .default_value("sub"),
+        Arg::from_usage("--lossless 'Encodes the WebP images without loss'"),
+        Arg::from_usage("--strip-metadata 'Leaves the EXIF and XMP metadata of the input image, \
+        and the parameters of the pixelisation, out of the PNG and JPEG images, which keep them by default. \
+        Its ICC profile is still kept.'"),
+        Arg::from_usage("--convert-srgb 'Converts the colors of the input image from its ICC profile to sRGB, \
+        rather than keeping the profile in the PNG and JPEG images'"),
+    ]
+}
+
 ///
-/// On an regular hexagonal grid,
-/// with an hexagon centered on the origin,
-/// with 2 of its edges parallel to the Y axis,
-/// with an outer circle radius R,
-/// with an inner circle radius r = R cos(PI/6),
+/// The arguments of the statistics and of the hashes of the images, shared by the commands which write them
 ///
-/// the hexagons to the left and to the right (on the X axis) of the centered hexagon
-/// have their centers at x = 0, x = 2r, x = 4r etc.
-/// These positions are referred to as x_0, x_2, x_4 etc.
+fn stats_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::from_usage("--stats [format] 'Writes the timings, the size and the numbers of cells and colors \
+        of each image on stderr, as a line of JSON, rather than the timings as text'")
+            .possible_values(&["json"]),
+        Arg::from_usage("--stats-file [path] 'Writes the statistics into this file rather than on stderr'")
+            .requires("stats"),
+        Arg::from_usage("--print-hash 'Prints a hash of the pixels of each image, as they are read back from it, \
+        followed by its path, which hexpxl verify checks'"),
+    ]
+}
+
 ///
-/// The y coordinate is y_0 = 0
+/// The arguments of the overwriting of the existing images, shared by the commands which write them
 ///
-/// Above and below the line of the hexagons on the X axis, hexagons are shifted by 1r.
-/// Their centers are at 1r, 3r, 5r etc.
-/// Those positions are referred to as x_1, x_3, x_5 etc.
+/// The last one given wins, so that an alias with --no-clobber can still be forced.
 ///
-/// Considering the line above the row on the X axis, the y coordinate is y_1 = 3R/2
-/// Let the gap g = 3R/2
+fn overwrite_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::from_usage("-n, --no-clobber 'Leaves the existing output images as they are, \
+        skipping the images they would be pixelised from'")
+            .overrides_with("force"),
+        Arg::from_usage("-f, --force 'Overwrites the existing output images, which is the default, \
+        cancelling a --no-clobber given before'")
+            .overrides_with("no-clobber"),
+    ]
+}
+
+/// The argument printing what would be done rather than doing it, shared by the commands pixelising files
+fn dry_run_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::from_usage("--dry-run 'Prints the size of the image, the inner radius and the grid of the cells, \
+    the estimated memory and the output path, without pixelising anything. Only reads the start of the image holding its header, \
+    the whole TIFF and WebP images being read.'")
+        .conflicts_with("watch")
+}
+
 ///
-/// Given a point P on the plane. That point's closest hex center will be located on (Hx,Hy)
+/// The arguments shared by all the commands which pixelise images
 ///
+fn pixelisation_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::from_usage("-q, --quiet 'Shows neither the progress nor the timings'"),
+        Arg::from_usage("--threads [count] 'The number of threads pixelising the images, \
+        RAYON_NUM_THREADS or one per core by default'"),
+        Arg::from_usage("--chunk-size [rows] 'The fewest rows of the image which each task of the threads paints. \
+        Larger chunks spare the scheduling of the tasks, smaller ones share the rows more evenly between the threads.'"),
+        Arg::from_usage("--config [file] 'A TOML file with the values of these options, \
+        e.g. border-width = 2. The options given on the command line take precedence.'"),
+        Arg::from_usage("--preset [name] 'Uses the options saved by preset save under this name. \
+        The config file and the options given on the command line take precedence.'"),
+        Arg::from_usage("<mode> 'The PixelMode to use'")
+            .help("The pixelisation mode")
+            .short("m")
+            .long("mode")
+            .default_value("hex"),
+        Arg::from_usage("--sampling [sampling] 'How the color of each pixel is chosen: the color at its center, \
+        the average of its colors, the dominant one, which keeps the contrasted pixels from being blended, \
+        or the average of its colors weighted by their closeness to its center'")
+            .possible_values(&Sampling::variants())
+            .default_value("center"),
+        Arg::from_usage("--linear 'Averages the colors in linear light, which keeps the contrasted cells \
+        of the average sampling from getting darker'"),
+        Arg::from_usage("--sigma [pixels] 'How far from the center of each cell the colors weigh in the gaussian sampling, \
+        half of the size by default'"),
+        Arg::from_usage("--alpha [alpha] 'What becomes of the transparency of the cells: kept as it is, \
+        flattened over the background color, or binarized, the cells being either opaque or fully transparent'")
+            .possible_values(&Alpha::variants())
+            .default_value("preserve"),
+        Arg::from_usage("--orientation [orientation] 'Whether the hexagons have a vertex or an edge on top'")
+            .possible_values(&Orientation::variants())
+            .default_value("pointy"),
+        Arg::from_usage("--angle [degrees] 'The clockwise rotation of the square, hexagonal, triangular and brick grids'")
+            .allow_hyphen_values(true)
+            .default_value("0"),
+        Arg::from_usage("--border-width [width] 'The width of the lines drawn between the cells, in pixels'")
+            .default_value("0"),
+        Arg::from_usage("--border-color [color] 'The color of the lines drawn between the cells, as #rrggbb or #rrggbbaa'")
+            .default_value("#000000"),
+        Arg::from_usage("--gap [width] 'The space left between the cells, in pixels'")
+            .default_value("0"),
+        Arg::from_usage("--antialias 'Smooths the boundaries between the cells, the pixels on them blending \
+        the colors of the cells covering them'"),
+        Arg::from_usage("--shadow 'Casts a soft shadow of the cells on the background seen in the gaps between them'"),
+        Arg::from_usage("--shadow-offset [offset] 'The shift of the shadow from the cells, x,y in pixels, to the right and to the bottom'")
+            .allow_hyphen_values(true)
+            .default_value("4,4"),
+        Arg::from_usage("--shadow-blur [width] 'The width of the soft edge of the shadow, in pixels'")
+            .default_value("4"),
+        Arg::from_usage("--shadow-color [color] 'The color of the shadow, as #rrggbb or #rrggbbaa'")
+            .default_value("#00000080"),
+        Arg::from_usage("--bevel [strength] 'Lightens the edges of the cells facing the light and darkens the others, \
+        like raised tiles, from 0 for flat cells to 1'")
+            .default_value("0"),
+        Arg::from_usage("--light-angle [degrees] 'The direction the light of the bevels comes from, clockwise from the right'")
+            .allow_hyphen_values(true)
+            .default_value("225"),
+        Arg::from_usage("--background [color] 'The color seen in the gaps between the cells, and under them with --alpha flatten. Transparent by default.'")
+            .default_value("#00000000"),
+        Arg::from_usage("--seed [seed] 'The seed of the random generator, for reproducible outputs. Random by default.'"),
+        Arg::from_usage("--palette [palette] 'Snaps the colors of the cells to a palette: \
+        pico8, nes, gameboy, or a GIMP palette (.gpl), a list of #rrggbb colors (.hex) \
+        or an Adobe swatch exchange file (.ase)'"),
+        Arg::from_usage("--dither 'Diffuses the error of snapping each cell to the palette over the next cells, \
+        so that the small palettes give shades rather than bands'"),
+        Arg::from_usage("--duotone [colors] 'Maps the brightness of the cells from the first color, for black, \
+        to the second one, for white, e.g. #1d2b53,#ffec27'"),
+        Arg::from_usage("--gradient-map [file] 'Maps the brightness of the cells to the gradient of a file, \
+        with a #rrggbb color per line from black to white, each optionally after its brightness from 0 to 1'")
+            .conflicts_with("duotone"),
+        Arg::from_usage("--posterize [levels] 'Rounds each channel of the colors of the cells to this many evenly spaced levels, \
+        for a screen print look'"),
+        Arg::from_usage("--faces 'Pixelises only the faces, leaving the rest of the image untouched'"),
+        Arg::from_usage("--region [rectangle] 'Pixelises only the rectangle x,y,width,height, in pixels, \
+        leaving the rest of the image untouched. Can be repeated, each rectangle having its own grid.'")
+            .multiple(true)
+            .number_of_values(1),
+        Arg::from_usage("--mask [image] 'A grayscale image, stretched to the size of the input, \
+        whose white parts are pixelised and black parts left untouched, the grays blending them'"),
+        Arg::from_usage("--feather [radius] 'Blurs the edges of the mask over about this many pixels, \
+        for a smooth transition between the pixelised and the untouched parts'")
+            .default_value("0"),
+        Arg::from_usage("--cell-width [width] 'The width of the cells of the square, hexagonal and brick grids, the size by default'"),
+        Arg::from_usage("--cell-height [height] 'The height of the cells of the square, hexagonal and brick grids, the size by default'"),
+        Arg::from_usage("--tiles [directory] 'The images of the directory which the squares of the photomosaic mode \
+        are replaced with, the one whose mean color is the closest to that of each square'"),
+        Arg::from_usage("--emoji-shape [shape] 'The shape of the emoji of the emoji mode, which replace the squares \
+        with the closest of their 9 colors'")
+            .possible_values(&EmojiShape::variants())
+            .default_value("square"),
+        Arg::from_usage("--dot-radius [radius] 'The radius of the dots of the dots mode, in pixels. \
+        By default, the darker the cell, the larger its dot.'"),
+        Arg::from_usage("--shade 'Gives the faces of each cube of the cube mode the same color, \
+        lightened on the top face and darkened on the right one'"),
+        Arg::from_usage("--overlap [fraction] 'How much of each scale of the scales mode is covered by the rows above it, \
+        from 0, where the scales look like semicircles, to 0.9'")
+            .default_value("0"),
+        Arg::from_usage("--split-channels [offset] 'Pixelises the red, green and blue channels separately, \
+        the grid of the red one being shifted to the right by this many pixels and the grid of the blue one to the left'")
+            .allow_hyphen_values(true),
+        Arg::from_usage("--channel-sizes [sizes] 'The sizes of the pixels of the red, green and blue channels, \
+        e.g. 18,20,22, which are then pixelised separately'"),
+        Arg::from_usage("--columns [count] 'The number of columns of cells spanning the width of the image, \
+        setting the size of the cells rather than the size'"),
+        Arg::from_usage("--rows [count] 'The number of rows of cells spanning the height of the image, \
+        setting the size of the cells rather than the size, along with the columns'"),
+        Arg::from_usage("--offset-x [shift] 'Shifts the grid to the right by this many pixels, \
+        or by this fraction of the size of the cells when followed by cells, e.g. 0.5cells'")
+            .allow_hyphen_values(true),
+        Arg::from_usage("--offset-y [shift] 'Shifts the grid to the bottom by this many pixels, \
+        or by this fraction of the size of the cells when followed by cells, e.g. 0.5cells'")
+            .allow_hyphen_values(true),
+        Arg::from_usage("--size-gradient [sizes] 'Makes the size of the hexagons of the hex mode go from a size to another \
+        across the image, along a direction in degrees clockwise from the right, e.g. 4,40,90 from the top to the bottom'"),
+        Arg::from_usage("--size-radial [focus] 'Makes the size of the hexagons of the hex mode go from a size at a point \
+        to another at the furthest corner of the image, e.g. 400,300,4,40 for hexagons growing away from 400,300'")
+            .allow_hyphen_values(true)
+            .conflicts_with("size-gradient"),
+        Arg::from_usage("--depth-map [path] 'Makes the hexagons of the hex mode small where the grayscale depth map \
+        of the image is white, near, and large where it is black, far, from the min size to the max size'")
+            .conflicts_with_all(&["size-gradient", "size-radial"]),
+        Arg::from_usage("--adaptive 'Makes the hexagons of the hex mode small where the image is busy and large where it is flat, \
+        from the min size to the max size, to keep the faces and the text readable'")
+            .conflicts_with_all(&["size-gradient", "size-radial", "depth-map"]),
+        Arg::from_usage("--center [point] 'The center of the rings mode and of the spiral mode, x,y in pixels \
+        from the top left corner of the image. The center of the image by default.'")
+            .allow_hyphen_values(true),
+        Arg::from_usage("--tileable 'Makes the cells crossing the edges of the image take the pixels of the opposite edges, \
+        as if the image was repeated, for the textures'"),
+        Arg::from_usage("--scale [factor] 'Makes the pixelised image this many times larger than the source, up to 16, \
+        the grid being laid on the source and its cells drawn with sharp edges at the larger size'")
+            .default_value("1"),
+        Arg::from_usage("--fit-grid [fit] 'Pads the image with the background color, or crops it, to a whole number \
+        of columns and rows of cells of the regular grids, leaving no sliver of cells along its right and bottom edges'")
+            .possible_values(&FitGrid::variants()),
+        Arg::from_usage("--dpi [resolution] 'The resolution of the image in dots per inch, which turns a size in mm, cm or in \
+        into pixels, read from the image by default'"),
+        Arg::from_usage("--min-size [size] 'The side of the smallest squares of the quadtree mode, and the size of the smallest adaptive hexagons'")
+            .default_value("4"),
+        Arg::from_usage("--max-size [size] 'The side of the largest squares of the quadtree mode, and the size of the largest adaptive hexagons'")
+            .default_value("64"),
+        Arg::from_usage("--variance-threshold [variance] 'The color variance above which the squares of the quadtree mode are split'")
+            .default_value("100"),
+        Arg::from_usage("--compactness [compactness] 'How regular the superpixels of the slic mode are, \
+        the lower the closer they follow the edges of the image'")
+            .default_value("10"),
+        Arg::from_usage("--iterations [count] 'The number of times the superpixels of the slic mode are refined'")
+            .default_value("10"),
+    ]
+}
+
+fn format_args(matches: &ArgMatches) -> Result<Formats, Error> {
+    let unknown = |name: &str| Error::UnsupportedFormat(format!("unknown image format {}", name));
+    let input = match matches.value_of("input-format") {
+        Some(name) => Some(parse_format(name).ok_or_else(|| unknown(name))?),
+        None => None,
+    };
+    let output = match matches.value_of("format") {
+        Some(name) => Some(parse_output_format(name).ok_or_else(|| unknown(name))?),
+        None => None,
+    };
+
+    let quality = match matches.value_of("quality") {
+        Some(quality) => Some(quality.parse().ok().filter(|q| (0.0..=100.0).contains(q)).ok_or_else(|| {
+            Error::Usage(format!("invalid quality '{}' for --quality, from 0 to 100", quality))
+        })?),
+        None => None,
+    };
+
+    Ok(Formats {
+        input,
+        output,
+        quality,
+        lossless: matches.is_present("lossless"),
+        strip_metadata: matches.is_present("strip-metadata"),
+        convert_srgb: matches.is_present("convert-srgb"),
+        png: PngEncoding {
+            compression: value_t!(matches, "png-compression", PngCompression).unwrap_or_else(|e| e.exit()),
+            filter: value_t!(matches, "png-filter", PngFilter).unwrap_or_else(|e| e.exit()),
+        },
+        no_clobber: matches.is_present("no-clobber"),
+        parameters: Some(settings(matches)?),
+    })
+}
+
+fn color_arg(matches: &ArgMatches, name: &str) -> Rgba<u8> {
+    let value = matches.value_of(name).unwrap();
+    parse_color(value).unwrap_or_else(|| {
+        clap::Error::value_validation_auto(format!("invalid color '{}' for --{}", value, name)).exit()
+    })
+}
+
 ///
-/// How to find Hx and Hy?
+/// A built-in palette, or else the palette in the file of that name
 ///
-/// On the X axis, the point will be located between 2 x coordinates, x_low and x_high, with abs(high-low) = 1
-/// On the Y axis, the point will be located between 2 y coordinates, y_low and y_high, with abs(high-low) = 1
+fn palette_arg(name: Option<&str>) -> Result<Option<Palette>, Error> {
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    if let Some(palette) = Palette::named(name) {
+        return Ok(Some(palette));
+    }
+
+    let bytes = fs::read(name).map_err(|e| {
+        Error::Input(format!("cannot read the palette {}: {}, the built-in palettes are {}",
+                             name, e, Palette::names().join(", ")))
+    })?;
+    Palette::read(&bytes)
+        .map(Some)
+        .map_err(|e| Error::Input(format!("invalid palette {}: {}", name, e)))
+}
+
 ///
-/// The closest hex center will be at either of (x_low,y_low), (x_low,y_high), (x_high,y_low) or (x_high,y_high)
+/// The tiles of the photomosaic mode, made of the images of the directory
 ///
-/// We can notice that given the coordinate system we use, there will never be any hex center on indices with different parities.
-/// The closest center is therefore either on coordinates which indices have the same parity.
+fn tiles_arg(directory: &str) -> Result<Tiles, Error> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|e| Error::read(directory, e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && format_from_path(p).is_some())
+        .collect();
+    paths.sort();
+    let images = paths.par_iter().map(|path| {
+        image::open(path).map(|img| img.to_rgba()).map_err(|e| Error::decode(&path.to_string_lossy(), e))
+    }).collect::<Result<Vec<_>, _>>()?;
+    Tiles::new(images).ok_or_else(|| Error::Input(format!("cannot make the tiles: {} has no images", directory)))
+}
+
 ///
-/// This reduces the number of points to check to only 2.
+/// The gradient of `--duotone` or of `--gradient-map`, the command line taking precedence over the config
 ///
-/// # Arguments
+fn gradient_arg(matches: &ArgMatches, config: &Config) -> Result<Option<Gradient>, Error> {
+    let duotone = matches.value_of("duotone");
+    let file = matches.value_of("gradient-map");
+    let (duotone, file) = match (duotone, file) {
+        (None, None) => (config.duotone.as_deref(), config.gradient_map.as_deref()),
+        given => given,
+    };
+    if let Some(text) = duotone {
+        let colors: Option<Vec<Rgba<u8>>> = text.split(',').map(parse_color).collect();
+        return match colors {
+            Some(ref colors) if colors.len() == 2 => Ok(Some(Gradient::even(colors))),
+            _ => Err(Error::Usage(format!("invalid colors '{}' for --duotone, e.g. #1d2b53,#ffec27", text))),
+        };
+    }
+    match file {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| Error::read(path, e))?;
+            Gradient::parse(&text)
+                .map(Some)
+                .map_err(|e| Error::Input(format!("invalid gradient map {}: {}", path, e)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// How the size of the hexagons changes across the image, given on the command line, else in the config file
 ///
-/// * `img` - The input image to pixelise
-/// * `outer_radius` - The radius of the hexagon's outer circle
+/// The sizes of the depth map go from the smallest size, for the nearest pixels, to the largest one.
 ///
-#[derive(Debug)]
-struct Point {
-    x: u32,
-    y: u32,
+fn size_field_arg(matches: &ArgMatches, config: &Config, mode: PixelMode, (min_size, max_size): (u32, u32))
+                  -> Result<Option<SizeField>, Error> {
+    let given = (matches.value_of("size-gradient"), matches.value_of("size-radial"), matches.value_of("depth-map"));
+    let (gradient, radial, depth) = match given {
+        (None, None, None) =>
+            (config.size_gradient.map(Ok), config.size_radial.map(Ok), config.depth_map.as_deref()),
+        (gradient, radial, depth) => (gradient.map(Err), radial.map(Err), depth),
+    };
+    let field = match (gradient, radial, depth) {
+        (Some(gradient), _, _) => {
+            let [start, end, angle] = gradient.or_else(|text| parse_numbers(text).ok_or_else(|| {
+                Error::Usage(format!("invalid sizes '{}' for --size-gradient, e.g. 4,40,90", text))
+            }))?;
+            SizeField::Gradient { start, end, angle }
+        }
+        (None, Some(radial), _) => {
+            let [x, y, near, far] = radial.or_else(|text| parse_numbers(text).ok_or_else(|| {
+                Error::Usage(format!("invalid focus and sizes '{}' for --size-radial, e.g. 400,300,4,40", text))
+            }))?;
+            SizeField::Radial { center: (x, y), near, far }
+        }
+        (None, None, Some(path)) => SizeField::Depth {
+            map: image::open(path).map_err(|e| Error::decode(path, e))?.to_luma(),
+            near: min_size as f32,
+            far: max_size as f32,
+        },
+        (None, None, None) => return Ok(None),
+    };
+    if mode != PixelMode::hex {
+        return Err(Error::Usage("the size of the cells only varies in the hex mode".to_string()));
+    }
+    match field {
+        SizeField::Gradient { start: a, end: b, .. } | SizeField::Radial { near: a, far: b, .. }
+            | SizeField::Depth { near: a, far: b, .. } if a <= 0.0 || b <= 0.0 =>
+            Err(Error::Usage(format!("invalid sizes {} and {} of the cells, above 0", a, b))),
+        field => Ok(Some(field)),
+    }
 }
 
-#[derive(Debug)]
-struct ColoredPoint {
-    x: u32,
-    y: u32,
-    color: Rgba<u8>,
+///
+/// The settings of a config file, all of them optional
+///
+/// The keys are the names of the command line options, e.g. `border-width = 2`.
+///
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Config {
+    #[serde(default, deserialize_with = "parsed")]
+    mode: Option<PixelMode>,
+    #[serde(default, deserialize_with = "cell_size")]
+    size: Option<CellSize>,
+    #[serde(default, deserialize_with = "seed")]
+    seed: Option<u64>,
+    #[serde(default, deserialize_with = "parsed")]
+    sampling: Option<Sampling>,
+    linear: Option<bool>,
+    sigma: Option<f32>,
+    #[serde(default, deserialize_with = "parsed")]
+    alpha: Option<Alpha>,
+    #[serde(default, deserialize_with = "parsed")]
+    orientation: Option<Orientation>,
+    angle: Option<f32>,
+    border_width: Option<f32>,
+    #[serde(default, deserialize_with = "color")]
+    border_color: Option<Rgba<u8>>,
+    gap: Option<f32>,
+    antialias: Option<bool>,
+    shadow: Option<bool>,
+    shadow_offset: Option<[f32; 2]>,
+    shadow_blur: Option<f32>,
+    #[serde(default, deserialize_with = "color")]
+    shadow_color: Option<Rgba<u8>>,
+    bevel: Option<f32>,
+    light_angle: Option<f32>,
+    #[serde(default, deserialize_with = "color")]
+    background: Option<Rgba<u8>>,
+    min_size: Option<u32>,
+    max_size: Option<u32>,
+    variance_threshold: Option<f32>,
+    compactness: Option<f32>,
+    iterations: Option<u32>,
+    faces: Option<bool>,
+    region: Option<Vec<[u32; 4]>>,
+    mask: Option<String>,
+    feather: Option<f32>,
+    palette: Option<String>,
+    dither: Option<bool>,
+    duotone: Option<String>,
+    gradient_map: Option<String>,
+    posterize: Option<u32>,
+    cell_width: Option<u32>,
+    cell_height: Option<u32>,
+    dot_radius: Option<f32>,
+    tiles: Option<String>,
+    #[serde(default, deserialize_with = "parsed")]
+    emoji_shape: Option<EmojiShape>,
+    shade: Option<bool>,
+    overlap: Option<f32>,
+    split_channels: Option<f32>,
+    channel_sizes: Option<[u32; 3]>,
+    tileable: Option<bool>,
+    columns: Option<u32>,
+    rows: Option<u32>,
+    #[serde(default, deserialize_with = "shift")]
+    offset_x: Option<Shift>,
+    #[serde(default, deserialize_with = "shift")]
+    offset_y: Option<Shift>,
+    size_gradient: Option<[f32; 3]>,
+    size_radial: Option<[f32; 4]>,
+    depth_map: Option<String>,
+    adaptive: Option<bool>,
+    center: Option<[f32; 2]>,
+    scale: Option<u32>,
+    #[serde(default, deserialize_with = "parsed")]
+    fit_grid: Option<FitGrid>,
+    dpi: Option<f32>,
 }
 
-fn hexagon_pixelisation(img: &DynamicImage, outer_radius: u32) -> RgbaImage {
-    let (width, height) = img.dimensions();
-    let mut pixelised: RgbaImage = ImageBuffer::new(width, height);
+/// Deserializes a string with the parser of the command line argument
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where D: Deserializer<'de>, T: FromStr, T::Err: fmt::Display {
+    let text: Option<String> = Option::deserialize(deserializer)?;
+    text.map(|t| t.parse().map_err(de::Error::custom)).transpose()
+}
+
+/// Deserializes a seed, whose bits are written as those of a signed integer, as the integers of TOML are
+fn seed<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+    where D: Deserializer<'de> {
+    let seed: Option<i64> = Option::deserialize(deserializer)?;
+    Ok(seed.map(|seed| seed as u64))
+}
 
-    let inner_radius = (outer_radius as f32 * (PI / 6.0).cos()) as u32;
-    let gap = (3.0 * outer_radius as f32 / 2.0) as u32;
+/// Deserializes a size, or a string with a width and a height
+fn cell_size<'de, D>(deserializer: D) -> Result<Option<CellSize>, D::Error>
+    where D: Deserializer<'de> {
+    let value: Option<Value> = Option::deserialize(deserializer)?;
+    value.map(|v| match v {
+        Value::Integer(size) if size > 0 && size <= u32::MAX as i64 =>
+            Ok(CellSize { width: Length::Pixels(size as u32), height: None }),
+        Value::String(ref text) => text.parse().map_err(de::Error::custom),
+        other => Err(de::Error::custom(format!("invalid size {}", other))),
+    }).transpose()
+}
 
-    let coordinates: Vec<Point> = (0..width).flat_map(|x| {
-        return (0..height).map(move |y| {
-            Point { x, y }
-        }).into_iter();
-    }).collect();
+/// Deserializes a number of pixels, or a string with a number of cells
+fn shift<'de, D>(deserializer: D) -> Result<Option<Shift>, D::Error>
+    where D: Deserializer<'de> {
+    let value: Option<Value> = Option::deserialize(deserializer)?;
+    value.map(|v| match v {
+        Value::Integer(pixels) => Ok(Shift::Pixels(pixels as f32)),
+        Value::Float(pixels) => Ok(Shift::Pixels(pixels as f32)),
+        Value::String(ref text) => parse_shift(text).ok_or_else(|| de::Error::custom(format!("invalid shift '{}'", text))),
+        other => Err(de::Error::custom(format!("invalid shift {}", other))),
+    }).transpose()
+}
 
-    let pixels: Vec<ColoredPoint> = coordinates.par_iter().map(|p| {
-        let x = p.x;
-        let y = p.y;
-        let x_low_idx = x / inner_radius;
-        let x_high_idx = x / inner_radius + 1;
+fn color<'de, D>(deserializer: D) -> Result<Option<Rgba<u8>>, D::Error>
+    where D: Deserializer<'de> {
+    let text: Option<String> = Option::deserialize(deserializer)?;
+    text.map(|t| parse_color(&t).ok_or_else(|| de::Error::custom(format!("invalid color '{}'", t)))).transpose()
+}
 
-        let y_low_idx = y / gap;
-        let y_high_idx = y / gap + 1;
+///
+/// The settings of the preset and of the config file, those of the config file taking precedence
+///
+fn config(matches: &ArgMatches) -> Result<Config, Error> {
+    Value::Table(config_table(matches)?).try_into()
+        .map_err(|e| Error::Usage(format!("invalid settings: {}", e)))
+}
 
-        let (corner_a_idx, corner_b_idx) =
-            // do they have the same parity?
-            if (x_low_idx % 2 == 0) == (y_low_idx % 2 == 0) {
-                ((x_low_idx, y_low_idx), (x_high_idx, y_high_idx))
-            } else {
-                ((x_low_idx, y_high_idx), (x_high_idx, y_low_idx))
-            };
+/// The settings of the preset and of the config file, as the table of a config file
+fn config_table(matches: &ArgMatches) -> Result<Table, Error> {
+    let mut table = match matches.value_of("preset") {
+        Some(name) => preset(name)?,
+        None => Table::new(),
+    };
+    if let Some(path) = matches.value_of("config") {
+        let text = fs::read_to_string(path).map_err(|e| Error::read(path, e))?;
+        let invalid = |e: toml::de::Error| Error::Usage(format!("invalid config {}: {}", path, e));
+        // checked on its own, so that the errors tell which file they are in
+        toml::from_str::<Config>(&text).map_err(invalid)?;
+        table.extend(toml::from_str::<Table>(&text).map_err(invalid)?);
+    }
+    Ok(table)
+}
+
+/// The keys of the settings of the config files, which are also the names of their command line options
+const SETTINGS: &[&str] = &[
+    "mode", "size", "seed", "sampling", "linear", "sigma", "alpha", "orientation", "angle", "border-width", "border-color",
+    "gap", "bevel", "light-angle", "background", "antialias", "shadow", "shadow-offset", "shadow-blur",
+    "shadow-color", "min-size", "max-size", "variance-threshold", "compactness",
+    "iterations", "faces", "region", "mask", "feather", "palette", "dither", "duotone",
+    "gradient-map", "posterize", "cell-width", "cell-height", "dot-radius", "tiles", "emoji-shape", "shade", "overlap", "split-channels", "channel-sizes", "tileable",
+    "columns", "rows", "offset-x", "offset-y", "size-gradient", "size-radial", "depth-map", "adaptive", "center", "scale", "fit-grid",
+    "dpi",
+];
+
+/// The file of the presets, in the config directory of the user
+fn presets_path() -> Result<PathBuf, Error> {
+    let directory = env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()).map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .ok_or_else(|| Error::Usage("cannot find the config directory: neither XDG_CONFIG_HOME nor HOME is set".to_string()))?;
+    Ok(directory.join("hexpxl").join("presets.toml"))
+}
+
+/// The table of each preset of the file, by name, none when the file does not exist yet
+fn presets(path: &Path) -> Result<Table, Error> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Table::new()),
+        Err(e) => return Err(Error::read(&path.to_string_lossy(), e)),
+    };
+    toml::from_str(&text).map_err(|e| Error::Input(format!("invalid presets {}: {}", path.display(), e)))
+}
+
+/// The settings of the preset of that name
+fn preset(name: &str) -> Result<Table, Error> {
+    let path = presets_path()?;
+    let mut presets = presets(&path)?;
+    let names: Vec<String> = presets.keys().cloned().collect();
+    match presets.remove(name) {
+        Some(Value::Table(settings)) => {
+            Value::Table(settings.clone()).try_into::<Config>().map_err(|e| {
+                Error::Input(format!("invalid preset {} in {}: {}", name, path.display(), e))
+            })?;
+            Ok(settings)
+        }
+        Some(_) => Err(Error::Input(format!("invalid preset {} in {}: it is not a table", name, path.display()))),
+        None if names.is_empty() => Err(Error::Usage(format!("unknown preset {}, none is saved yet", name))),
+        None => Err(Error::Usage(format!("unknown preset {}, the presets are {}", name, names.join(", ")))),
+    }
+}
 
-        // first Hx / Hy
-        let (corner_a_x, corner_a_y) = (corner_a_idx.0 * inner_radius, corner_a_idx.1 * gap);
-        // second Hx / Hy
-        let (corner_b_x, corner_b_y) = (corner_b_idx.0 * inner_radius, corner_b_idx.1 * gap);
+///
+/// Saves the options given on the command line, over those of its preset and config file, as a preset
+///
+/// The other presets of the file are kept, but its comments are lost.
+///
+fn save_preset(matches: &ArgMatches, name: &str) -> Result<(), Error> {
+    let settings = settings(matches)?;
+    Value::Table(settings.clone()).try_into::<Config>()
+        .map_err(|e| Error::Usage(format!("invalid preset {}: {}", name, e)))?;
 
-        let d1 = sqr!(corner_a_x - x) + sqr!(corner_a_y - y);
-        let d2 = sqr!(corner_b_x - x) + sqr!(corner_b_y - y);
+    let path = presets_path()?;
+    let mut presets = presets(&path)?;
+    presets.insert(name.to_string(), Value::Table(settings));
+    let text = toml::to_string(&presets).map_err(|e| Error::write(&path.to_string_lossy(), e))?;
+    path.parent().map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, text))
+        .map_err(|e| Error::write(&path.to_string_lossy(), e))?;
+    if !matches.is_present("quiet") {
+        println!("Saved the preset {} in {}", name, path.display());
+    }
+    Ok(())
+}
 
-        let (x_index, y_index) = if d1 < d2 {
-            (corner_a_x, corner_a_y)
+///
+/// The settings of the preset and of the config file, and those given on the command line over them
+///
+fn settings(matches: &ArgMatches) -> Result<Table, Error> {
+    let mut settings = config_table(matches)?;
+    for &key in SETTINGS.iter().filter(|&&key| matches.occurrences_of(key) > 0) {
+        let value = if key == "region" {
+            Value::Array(matches.values_of(key).unwrap().map(|text| setting_value(key, Some(text))).collect())
         } else {
-            (corner_b_x, corner_b_y)
+            setting_value(key, matches.value_of(key))
         };
+        settings.insert(key.to_string(), value);
+    }
+    Ok(settings)
+}
+
+///
+/// The parameters of a pixelisation written into its image, as a config file pixelising the same way
+///
+/// The settings are completed with the mode, the seed and the size used, which they may leave to their defaults,
+/// the size being left out when it is given by the columns or the rows.
+///
+fn stamp(settings: &Table, options: &Options) -> String {
+    let mut settings = settings.clone();
+    settings.insert("mode".to_string(), Value::String(options.mode.to_string()));
+    // the integers of TOML are signed, and the seeds above theirs are written with the same bits
+    settings.insert("seed".to_string(), Value::Integer(options.seed as i64));
+    if !["size", "columns", "rows"].iter().any(|&key| settings.contains_key(key)) {
+        settings.insert("size".to_string(), Value::Integer(options.size as i64));
+    }
+    // the settings are all strings, numbers and arrays of them
+    let text = toml::to_string(&settings).unwrap_or_default();
+    format!("# hexpxl {}\n{}", env!("CARGO_PKG_VERSION"), text)
+}
+
+///
+/// Prints the parameters an image was pixelised with, as a config file
+///
+fn show_params(path: &str) -> Result<(), Error> {
+    let data = read_input(path)?;
+    let parameters = metadata::parameters(&data)
+        .ok_or_else(|| Error::Input(format!("{} holds no parameters of hexpxl", path)))?;
+    print!("{}", parameters);
+    Ok(())
+}
+
+///
+/// The value of an option of the command line, typed as in a config file
+///
+/// The masks, palette files and gradient maps are saved with their absolute paths, so that the preset can be used from anywhere.
+///
+fn setting_value(key: &str, text: Option<&str>) -> Value {
+    let text = match text {
+        Some(text) => text,
+        // the flags
+        None => return Value::Boolean(true),
+    };
+    let absolute = || match fs::canonicalize(text) {
+        Ok(path) => Value::String(path.to_string_lossy().into_owned()),
+        Err(_) => Value::String(text.to_string()),
+    };
+    match key {
+        "channel-sizes" => match parse_channel_sizes(text) {
+            Some(sizes) => Value::Array(sizes.iter().map(|&s| Value::Integer(s as i64)).collect()),
+            // left as it is for the check to report it
+            None => Value::String(text.to_string()),
+        },
+        "center" | "shadow-offset" => match parse_point(text) {
+            Some((x, y)) => Value::Array(vec![Value::Float(x as f64), Value::Float(y as f64)]),
+            None => Value::String(text.to_string()),
+        },
+        "size-gradient" => match parse_numbers::<3>(text) {
+            Some(values) => Value::Array(values.iter().map(|&v| Value::Float(v as f64)).collect()),
+            None => Value::String(text.to_string()),
+        },
+        "size-radial" => match parse_numbers::<4>(text) {
+            Some(values) => Value::Array(values.iter().map(|&v| Value::Float(v as f64)).collect()),
+            None => Value::String(text.to_string()),
+        },
+        "region" => match parse_region(text) {
+            Some(r) => Value::Array([r.x, r.y, r.width, r.height].iter().map(|&v| Value::Integer(v as i64)).collect()),
+            None => Value::String(text.to_string()),
+        },
+        "seed" => match text.parse::<u64>() {
+            Ok(seed) => Value::Integer(seed as i64),
+            Err(_) => Value::String(text.to_string()),
+        },
+        "mask" | "gradient-map" | "depth-map" | "tiles" => absolute(),
+        "palette" if Palette::named(text).is_none() => absolute(),
+        "mode" | "sampling" | "alpha" | "orientation" | "border-color" | "background" | "shadow-color" | "palette"
+        | "duotone" | "fit-grid" | "emoji-shape" =>
+            Value::String(text.to_string()),
+        _ => text.parse().map(Value::Integer)
+            .or_else(|_| text.parse().map(Value::Float))
+            .unwrap_or_else(|_| Value::String(text.to_string())),
+    }
+}
 
-        let color = img.get_pixel(x_index.min(width - 1), y_index.min(height - 1));
-        ColoredPoint {
-            x: x,
-            y: y,
-            color: color,
+/// Prints the presets with their settings
+fn list_presets() -> Result<(), Error> {
+    let path = presets_path()?;
+    let presets = presets(&path)?;
+    if presets.is_empty() {
+        println!("No preset is saved in {}", path.display());
+    }
+    for (name, settings) in &presets {
+        println!("{}", name);
+        if let Value::Table(ref settings) = *settings {
+            for (key, value) in settings {
+                println!("  {} = {}", key, value);
+            }
         }
-    }).collect();
+    }
+    Ok(())
+}
+
+///
+/// The value of the argument given on the command line, else in the config file, else its default value
+///
+fn setting<T>(matches: &ArgMatches, name: &str, config: Option<T>) -> Result<T, Error>
+    where T: FromStr, T::Err: fmt::Display {
+    match config {
+        Some(value) if matches.occurrences_of(name) == 0 => Ok(value),
+        _ => matches.value_of(name).unwrap().parse().map_err(|e: T::Err| Error::Usage(e.to_string())),
+    }
+}
+
+fn options(matches: &ArgMatches) -> Result<Options, Error> {
+    options_with(matches, config(matches)?)
+}
+
+/// The largest factor of --scale, which enlarges the memory taken by its square
+const MAX_SCALE: u32 = 16;
+
+/// The options of the command line, over the settings of the config
+fn options_with(matches: &ArgMatches, config: Config) -> Result<Options, Error> {
+    // the montage has sizes rather than a size
+    let CellSize { width, height } = if matches.value_of("size").is_some() {
+        setting(matches, "size", config.size)?
+    } else {
+        CellSize { width: Length::Pixels(Options::default().size), height: None }
+    };
+    // the lengths are turned into pixels at the resolution of the image, which is only read for them
+    let physical = [Some(width), height].iter().any(|l| matches!(l, Some(Length::Inches(_))));
+    let dpi = if physical { dpi_arg(matches, &config)? } else { None };
+    let pixels = |length: Length| length.pixels(dpi).ok_or_else(|| Error::Usage(
+        "the image gives no resolution to turn the size into pixels, set it with --dpi".to_string()
+    ));
+    let size = pixels(width)?;
+    let cell_height = match height {
+        Some(height) => Some(pixels(height)?),
+        None => None,
+    };
+    let mode = setting(matches, "mode", config.mode)?;
+    let sampling = setting(matches, "sampling", config.sampling)?;
+    let alpha = setting(matches, "alpha", config.alpha)?;
+    let orientation = setting(matches, "orientation", config.orientation)?;
+    let angle = setting(matches, "angle", config.angle)?;
+    let border_width = setting(matches, "border-width", config.border_width)?;
+    let color = |name: &str, config: Option<Rgba<u8>>| match config {
+        Some(color) if matches.occurrences_of(name) == 0 => color,
+        _ => color_arg(matches, name),
+    };
+    let border_color = color("border-color", config.border_color);
+    let gap = setting(matches, "gap", config.gap)?;
+    let bevel: f32 = setting(matches, "bevel", config.bevel)?;
+    if !(0.0..=1.0).contains(&bevel) {
+        return Err(Error::Usage(format!("invalid strength {} for --bevel, from 0 to 1", bevel)));
+    }
+    let light_angle = setting(matches, "light-angle", config.light_angle)?;
+    let background = color("background", config.background);
+    let shadow = if matches.is_present("shadow") || config.shadow == Some(true) {
+        let offset = match (matches.occurrences_of("shadow-offset"), config.shadow_offset) {
+            (0, Some([x, y])) => (x, y),
+            _ => {
+                let text = matches.value_of("shadow-offset").unwrap();
+                parse_point(text).ok_or_else(|| {
+                    Error::Usage(format!("invalid offset '{}' for --shadow-offset, e.g. 4,4", text))
+                })?
+            }
+        };
+        Some(Shadow {
+            offset,
+            blur: setting(matches, "shadow-blur", config.shadow_blur)?,
+            color: color("shadow-color", config.shadow_color),
+        })
+    } else {
+        None
+    };
+    let min_size = setting(matches, "min-size", config.min_size)?;
+    let max_size = setting(matches, "max-size", config.max_size)?;
+    let variance_threshold = setting(matches, "variance-threshold", config.variance_threshold)?;
+    let compactness = setting(matches, "compactness", config.compactness)?;
+    let iterations = setting(matches, "iterations", config.iterations)?;
+    let overlap: f32 = setting(matches, "overlap", config.overlap)?;
+    if !(0.0..=0.9).contains(&overlap) {
+        return Err(Error::Usage(format!("invalid overlap {} for --overlap, from 0 to 0.9", overlap)));
+    }
+    let seed = if matches.is_present("seed") {
+        setting(matches, "seed", None)?
+    } else if let Some(seed) = config.seed {
+        seed
+    } else {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+    };
+    let optional = |name: &str, config: Option<u32>| -> Result<Option<u32>, Error> {
+        if matches.is_present(name) {
+            Ok(Some(setting(matches, name, None)?))
+        } else {
+            Ok(config)
+        }
+    };
+    let sigma = if matches.is_present("sigma") {
+        Some(setting(matches, "sigma", None)?)
+    } else {
+        config.sigma
+    };
+    if let Some(sigma) = sigma.filter(|&s| s.is_nan() || s <= 0.0) {
+        return Err(Error::Usage(format!("invalid sigma {} for --sigma, above 0", sigma)));
+    }
+    let dot_radius = if matches.is_present("dot-radius") {
+        Some(setting(matches, "dot-radius", None)?)
+    } else {
+        config.dot_radius
+    };
+    let split_offset = if matches.is_present("split-channels") {
+        Some(setting(matches, "split-channels", None)?)
+    } else {
+        config.split_channels
+    };
+    let channel_sizes = match matches.value_of("channel-sizes") {
+        Some(sizes) => Some(parse_channel_sizes(sizes).ok_or_else(|| {
+            Error::Usage(format!("invalid sizes '{}' for --channel-sizes, e.g. 18,20,22", sizes))
+        })?),
+        None => config.channel_sizes,
+    };
+    let split_channels = if split_offset.is_some() || channel_sizes.is_some() {
+        let offset = split_offset.unwrap_or(0.0);
+        Some(ChannelGrids {
+            offsets: [(offset, 0.0), (0.0, 0.0), (-offset, 0.0)],
+            sizes: channel_sizes.unwrap_or([size; 3]),
+        })
+    } else {
+        None
+    };
+    let center = match matches.value_of("center") {
+        Some(text) => Some(parse_point(text).ok_or_else(|| {
+            Error::Usage(format!("invalid point '{}' for --center, e.g. 400,300", text))
+        })?),
+        None => config.center.map(|[x, y]| (x, y)),
+    };
+    let shift = |name: &str, config: Option<Shift>| -> Result<f32, Error> {
+        let shift = match matches.value_of(name) {
+            Some(text) => parse_shift(text).ok_or_else(|| {
+                Error::Usage(format!("invalid shift '{}' for --{}, e.g. 6 or 0.5cells", text, name))
+            })?,
+            None => config.unwrap_or(Shift::Pixels(0.0)),
+        };
+        Ok(shift.pixels(size))
+    };
+    let offset = (shift("offset-x", config.offset_x)?, shift("offset-y", config.offset_y)?);
+    let chunk_size = match matches.value_of("chunk-size") {
+        Some(rows) => rows.parse().ok().filter(|&n| n > 0).ok_or_else(|| {
+            Error::Usage(format!("invalid number of rows '{}' for --chunk-size, from 1", rows))
+        })?,
+        None => Options::default().chunk_size,
+    };
+    let size_field = size_field_arg(matches, &config, mode, (min_size, max_size))?;
+    let adaptive = matches.is_present("adaptive") || config.adaptive == Some(true);
+    if adaptive && mode != PixelMode::hex {
+        return Err(Error::Usage("the size of the cells only varies in the hex mode".to_string()));
+    }
+    if adaptive && size_field.is_some() {
+        return Err(Error::Usage("--adaptive cannot be used with --size-gradient, --size-radial nor --depth-map".to_string()));
+    }
+    let scale = setting(matches, "scale", config.scale)?;
+    if !(1..=MAX_SCALE).contains(&scale) {
+        return Err(Error::Usage(format!("invalid factor {} for --scale, from 1 to {}", scale, MAX_SCALE)));
+    }
+    let fit_grid = if matches.is_present("fit-grid") {
+        Some(setting(matches, "fit-grid", None)?)
+    } else {
+        config.fit_grid
+    };
+    let palette = matches.value_of("palette").or(config.palette.as_deref());
+    let dither = matches.is_present("dither") || config.dither == Some(true);
+    if dither && palette.is_none() && mode != PixelMode::emoji {
+        return Err(Error::Usage("--dither needs a palette to snap the colors to, e.g. --palette pico8".to_string()));
+    }
+    let gradient = gradient_arg(matches, &config)?;
+    let posterize = optional("posterize", config.posterize)?;
+    if let Some(levels) = posterize.filter(|&l| l < 2) {
+        return Err(Error::Usage(format!("invalid levels {} for --posterize, from 2", levels)));
+    }
+    let regions = match matches.values_of("region") {
+        Some(regions) => regions.map(|text| parse_region(text).ok_or_else(|| {
+            Error::Usage(format!("invalid rectangle '{}' for --region, e.g. 10,20,200,100", text))
+        })).collect::<Result<_, _>>()?,
+        None => config.region.unwrap_or_default().iter()
+            .map(|&[x, y, width, height]| Region { x, y, width, height })
+            .collect(),
+    };
+    let feather = setting(matches, "feather", config.feather)?;
+    let mask = match matches.value_of("mask").or(config.mask.as_deref()) {
+        Some(path) => Some(Mask { image: image::open(path).map_err(|e| Error::decode(path, e))?.to_luma(), feather }),
+        None => None,
+    };
+    let tiles = match matches.value_of("tiles").or(config.tiles.as_deref()) {
+        Some(_) if mode != PixelMode::photomosaic =>
+            return Err(Error::Usage("--tiles is only used by the photomosaic mode".to_string())),
+        Some(directory) => Some(tiles_arg(directory)?),
+        None if mode == PixelMode::photomosaic =>
+            return Err(Error::Usage("the photomosaic mode needs a directory of images, e.g. --tiles photos/".to_string())),
+        None => None,
+    };
+
+    let options = Options {
+        mode,
+        size,
+        size_field,
+        adaptive,
+        seed,
+        sampling,
+        linear: matches.is_present("linear") || config.linear == Some(true),
+        sigma,
+        alpha,
+        orientation,
+        border_width,
+        border_color,
+        bevel,
+        light_angle,
+        antialias: matches.is_present("antialias") || config.antialias == Some(true),
+        gap,
+        background,
+        shadow,
+        min_size,
+        max_size,
+        variance_threshold,
+        compactness,
+        iterations,
+        angle,
+        faces: matches.is_present("faces") || config.faces == Some(true),
+        regions,
+        mask,
+        // the emoji are snapped to, and dithered between, the colors of the emoji by default
+        palette: match palette_arg(palette)? {
+            None if mode == PixelMode::emoji => Some(emoji::palette()),
+            palette => palette,
+        },
+        dither,
+        gradient,
+        posterize,
+        cell_width: optional("cell-width", config.cell_width)?,
+        cell_height: optional("cell-height", config.cell_height)?.or(cell_height),
+        dot_radius,
+        shade: matches.is_present("shade") || config.shade == Some(true),
+        overlap,
+        offset,
+        split_channels,
+        tileable: matches.is_present("tileable") || config.tileable == Some(true),
+        columns: optional("columns", config.columns)?,
+        rows: optional("rows", config.rows)?,
+        center,
+        scale,
+        fit_grid,
+        tiles,
+        emoji_shape: setting(matches, "emoji-shape", config.emoji_shape)?,
+        chunk_size,
+    };
+    if options.fit_grid.is_some() && plan::grid_steps(&options).is_none() {
+        return Err(Error::Usage("--fit-grid needs the grid of the sqr, photomosaic, emoji, ascii, hex, tri, brick, dots, cube, scales or trihex mode, \
+        neither rotated nor of varying sizes".to_string()));
+    }
+    Ok(options)
+}
+
+///
+/// How the images are read and written
+///
+#[derive(Default)]
+struct Formats {
+    input: Option<ImageFormat>,
+    output: Option<OutputFormat>,
+    /// The quality of the JPEG and of the lossy WebP and AVIF encodings, from 0 to 100
+    quality: Option<f32>,
+    /// Whether the WebP images are encoded without loss
+    lossless: bool,
+    /// Whether the metadata of the input images are left out of the pixelised ones
+    strip_metadata: bool,
+    /// Whether the colors of the input images are converted from their ICC profiles to sRGB
+    convert_srgb: bool,
+    /// How the PNG images are filtered and compressed
+    png: PngEncoding,
+    /// Whether the existing output images are left as they are rather than overwritten
+    no_clobber: bool,
+    /// The settings written into the PNG and JPEG images, with the mode and the seed, to pixelise them again
+    parameters: Option<Table>,
+}
+
+///
+/// How the pixelised image is written
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    /// Encoded by the image crate, but for TIFF
+    Raster(ImageFormat),
+    /// Encoded by libwebp
+    Webp,
+    /// Encoded by ravif
+    Avif,
+    /// Each cell is a polygon of an SVG document
+    Svg,
+    /// Each cell is an emoji or a character of a text
+    Text,
+}
+
+/// The quality of the JPEG images, by default, that of the image crate
+const JPEG_QUALITY: u8 = 75;
+/// The quality of the WebP images, by default
+const WEBP_QUALITY: f32 = 75.0;
+/// The quality of the AVIF images, by default
+const AVIF_QUALITY: f32 = 80.0;
+
+fn pixelise(
+    options: &Options,
+    src: &str,
+    dst: &str,
+    formats: &Formats,
+    max_memory: Option<u64>,
+    grids: &GridFiles,
+    reports: &Reports,
+) -> Result<(), Error> {
+    // the timings must not be mixed with the image when it is written to stdout
+    let to_stdout = dst == "-";
+    let report = |line: String| {
+        if reports.quiet || reports.stats.is_some() {
+        } else if to_stdout {
+            eprintln!("{}", line)
+        } else {
+            println!("{}", line)
+        }
+    };
+
+    let warn = |line: String| eprintln!("warning: {}", line);
+    let hash = |line: String| if to_stdout { eprintln!("{}", line) } else { println!("{}", line) };
+    let feedback = Feedback {
+        report: &report,
+        warn: &warn,
+        progress: !reports.quiet,
+        stats: reports.stats.is_some(),
+        hash: if reports.hash { Some(&hash) } else { None },
+    };
+    if clobbered(dst, formats.no_clobber) {
+        feedback.warn(format!("{} exists, left as it is", dst));
+        return Ok(());
+    }
+    let stats = match max_memory {
+        Some(max_memory) => return stream(options, src, dst, formats, max_memory, &feedback),
+        None => process(options, src, dst, formats, grids, &feedback)?,
+    };
+    match (stats, &reports.stats) {
+        (Some(stats), Some(output)) => output.write(&stats),
+        _ => Ok(()),
+    }
+}
+
+///
+/// The files describing the grid of the image, and the other views of it, written along with the pixelised image
+///
+#[derive(Default)]
+struct GridFiles<'a> {
+    /// Where the hexagons are exported, as by --export-grid
+    export: Option<&'a str>,
+    /// Where the image is written with the grid drawn over it, as by --overlay-grid
+    overlay: Option<&'a str>,
+    /// Where the statistics of the cells are exported, as by --export-cells
+    cells: Option<&'a str>,
+    /// How the image is printed in the terminal, if it is, as by --terminal
+    terminal: Option<Graphics>,
+}
+
+///
+/// What is shown of the work on the images
+///
+struct Reports {
+    /// Whether the progress and the timings are hidden
+    quiet: bool,
+    /// Where the statistics of the images are written, if anywhere
+    stats: Option<StatsOutput>,
+    /// Whether the hashes of the pixels of the images are printed
+    hash: bool,
+}
 
-    for (x, y, pixel) in pixelised.enumerate_pixels_mut() {
-        let i = y + x * height;
-        *pixel = pixels[i as usize].color
+/// The sizes of the red, green and blue channels, separated by commas
+fn parse_channel_sizes(text: &str) -> Option<[u32; 3]> {
+    match parse_sizes(text)?[..] {
+        [red, green, blue] => Some([red, green, blue]),
+        _ => None,
+    }
+}
+
+/// Sizes of pixels separated by commas
+fn parse_sizes(text: &str) -> Option<Vec<u32>> {
+    text.split(',').map(|s| s.trim().parse().ok().filter(|&s| s > 0)).collect()
+}
+
+/// A rectangle x,y,width,height, in pixels
+fn parse_region(text: &str) -> Option<Region> {
+    let values: Vec<u32> = text.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>()?;
+    match values[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Some(Region { x, y, width, height }),
+        _ => None,
+    }
+}
+
+/// A point x,y, in pixels
+fn parse_point(text: &str) -> Option<(f32, f32)> {
+    let values: Vec<f32> = text.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>()?;
+    match values[..] {
+        [x, y] if x.is_finite() && y.is_finite() => Some((x, y)),
+        _ => None,
+    }
+}
+
+/// As many finite numbers as asked for, separated by commas
+fn parse_numbers<const N: usize>(text: &str) -> Option<[f32; N]> {
+    let values: Vec<f32> = text.split(',').map(|v| v.trim().parse().ok().filter(|v: &f32| v.is_finite())).collect::<Option<_>>()?;
+    values.try_into().ok()
+}
+
+/// Modes separated by commas
+fn parse_modes(text: &str) -> Option<Vec<PixelMode>> {
+    text.split(',').map(|m| m.trim().parse().ok()).collect()
+}
+
+///
+/// The size of the cells, and their height when it differs from their width
+///
+#[derive(Debug, Clone, Copy)]
+struct CellSize {
+    width: Length,
+    height: Option<Length>,
+}
+
+impl FromStr for CellSize {
+    type Err = String;
+
+    /// A size, or a width and a height separated by x, e.g. 20x10 or 5mmx3mm
+    fn from_str(text: &str) -> Result<CellSize, String> {
+        if let Some(width) = parse_length(text) {
+            return Ok(CellSize { width, height: None });
+        }
+        // the x of px is not a separator
+        text.match_indices(['x', 'X'])
+            .find_map(|(i, _)| Some(CellSize { width: parse_length(&text[..i])?, height: Some(parse_length(&text[i + 1..])?) }))
+            .ok_or_else(|| format!("invalid size '{}', e.g. 20, 20x10 or 5mm", text))
+    }
+}
+
+///
+/// A length in pixels, or in inches for the printed images, turned into pixels at their resolution
+///
+#[derive(Debug, Clone, Copy)]
+enum Length {
+    Pixels(u32),
+    Inches(f32),
+}
+
+impl Length {
+    /// The length in pixels at that resolution in dots per inch, None for the inches without one
+    fn pixels(self, dpi: Option<f32>) -> Option<u32> {
+        match self {
+            Length::Pixels(pixels) => Some(pixels),
+            Length::Inches(inches) => dpi.map(|dpi| ((inches * dpi).round() as u32).max(1)),
+        }
+    }
+}
+
+/// A number of pixels, optionally followed by px, or a length followed by mm, cm or in, e.g. 5mm
+fn parse_length(text: &str) -> Option<Length> {
+    let text = text.trim();
+    let inches = |digits: &str, per_inch: f32| digits.trim().parse().ok()
+        .filter(|&v: &f32| v.is_finite() && v > 0.0)
+        .map(|v| Length::Inches(v / per_inch));
+    if let Some(digits) = text.strip_suffix("mm") {
+        inches(digits, 25.4)
+    } else if let Some(digits) = text.strip_suffix("cm") {
+        inches(digits, 2.54)
+    } else if let Some(digits) = text.strip_suffix("in") {
+        inches(digits, 1.0)
+    } else {
+        text.strip_suffix("px").unwrap_or(text).trim().parse().ok().filter(|&p: &u32| p > 0).map(Length::Pixels)
+    }
+}
+
+///
+/// The resolution the lengths are turned into pixels at, in dots per inch
+///
+/// --dpi, else the resolution of the source when there is a single one, read from its file.
+/// None when the source gives none, or is read from stdin.
+///
+fn dpi_arg(matches: &ArgMatches, config: &Config) -> Result<Option<f32>, Error> {
+    if matches.is_present("dpi") {
+        let dpi = value_t!(matches, "dpi", f32).unwrap_or_else(|e| e.exit());
+        if !(dpi > 0.0 && dpi.is_finite()) {
+            return Err(Error::Usage(format!("invalid resolution {} for --dpi, above 0", dpi)));
+        }
+        return Ok(Some(dpi));
+    }
+    if config.dpi.is_some() {
+        return Ok(config.dpi);
+    }
+    match matches.values_of("source").map(|sources| sources.collect::<Vec<_>>()) {
+        Some(ref sources) if sources.len() == 1 && sources[0] != "-" && Path::new(sources[0]).is_file() =>
+            Ok(metadata::resolution(&read_input(sources[0])?)),
+        _ => Ok(None),
+    }
+}
+
+///
+/// A shift of the grid along one of its axes
+///
+#[derive(Debug, Clone, Copy)]
+enum Shift {
+    Pixels(f32),
+    /// A fraction of the size of the cells
+    Cells(f32),
+}
+
+impl Shift {
+    fn pixels(self, size: u32) -> f32 {
+        match self {
+            Shift::Pixels(pixels) => pixels,
+            Shift::Cells(cells) => cells * size as f32,
+        }
+    }
+}
+
+/// A number of pixels, optionally followed by px, or a number of cells followed by cells, e.g. 0.5cells
+fn parse_shift(text: &str) -> Option<Shift> {
+    let text = text.trim();
+    let number = |digits: &str| digits.trim().parse().ok().filter(|v: &f32| v.is_finite());
+    match text.strip_suffix("cells") {
+        Some(cells) => number(cells).map(Shift::Cells),
+        None => number(text.strip_suffix("px").unwrap_or(text)).map(Shift::Pixels),
+    }
+}
+
+///
+/// A number of bytes, optionally followed by K, M or G for the powers of 1024, e.g. 512M
+///
+fn parse_memory(text: &str) -> Option<u64> {
+    let text = text.trim().trim_end_matches(['B', 'b']);
+    let (digits, unit) = match text.char_indices().last()? {
+        (i, 'k') | (i, 'K') => (&text[..i], 1 << 10),
+        (i, 'm') | (i, 'M') => (&text[..i], 1 << 20),
+        (i, 'g') | (i, 'G') => (&text[..i], 1 << 30),
+        _ => (text, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+///
+/// Where the statistics of the images are written, a line of JSON for each of them
+///
+enum StatsOutput {
+    Stderr,
+    File(String, Mutex<File>),
+}
+
+impl StatsOutput {
+    /// The output of --stats and --stats-file, None without --stats
+    fn new(matches: &ArgMatches) -> Result<Option<StatsOutput>, Error> {
+        if !matches.is_present("stats") {
+            return Ok(None);
+        }
+        Ok(Some(match matches.value_of("stats-file") {
+            Some(path) => StatsOutput::File(path.to_string(), Mutex::new(File::create(path).map_err(|e| Error::write(path, e))?)),
+            None => StatsOutput::Stderr,
+        }))
+    }
+
+    fn write(&self, stats: &Stats) -> Result<(), Error> {
+        match *self {
+            StatsOutput::Stderr => {
+                eprintln!("{}", stats.to_json());
+                Ok(())
+            }
+            StatsOutput::File(ref path, ref file) =>
+                writeln!(file.lock().unwrap(), "{}", stats.to_json()).map_err(|e| Error::write(path, e)),
+        }
+    }
+}
+
+///
+/// The timings and the figures of the pixelisation of an image
+///
+struct Stats {
+    source: String,
+    destination: String,
+    mode: PixelMode,
+    size: u32,
+    seed: u64,
+    width: u32,
+    height: u32,
+    /// The number of cells holding at least one pixel of the image, fewer than those of the grid of the dry run
+    /// when some of its cells along the edges miss the pixels
+    used_cells: usize,
+    /// The number of distinct colors of the pixelised image, None for the SVG documents
+    colors: Option<usize>,
+    load: Duration,
+    /// None when the grid is not exported
+    export: Option<Duration>,
+    pixelisation: Duration,
+    save: Duration,
+}
+
+impl Stats {
+    /// The statistics as a line of JSON, the durations being in milliseconds
+    fn to_json(&self) -> String {
+        let milliseconds = |d: Duration| format!("{:.3}", d.as_secs_f64() * 1000.0);
+        let null = || "null".to_string();
+        format!(
+            concat!(
+                r#"{{"source": {}, "destination": {}, "mode": "{}", "size": {}, "seed": {}, "width": {}, "height": {}, "#,
+                r#""used_cells": {}, "colors": {}, "load_ms": {}, "export_ms": {}, "pixelisation_ms": {}, "save_ms": {}}}"#,
+            ),
+            json_string(&self.source),
+            json_string(&self.destination),
+            self.mode,
+            self.size,
+            self.seed,
+            self.width,
+            self.height,
+            self.used_cells,
+            self.colors.map(|c| c.to_string()).unwrap_or_else(null),
+            milliseconds(self.load),
+            self.export.map(milliseconds).unwrap_or_else(null),
+            milliseconds(self.pixelisation),
+            milliseconds(self.save),
+        )
+    }
+}
+
+/// The text as a JSON string, quoted and escaped
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+///
+/// How the advancement of the work on an image is shown
+///
+struct Feedback<'a> {
+    /// Receives the timings of each step
+    report: &'a (dyn Fn(String) + Sync),
+    /// Receives the warnings about the images, which are shown even when the timings are not
+    warn: &'a (dyn Fn(String) + Sync),
+    /// Whether the progress bars are shown
+    progress: bool,
+    /// Whether the statistics of the images are gathered, which takes counting their cells and colors
+    stats: bool,
+    /// Receives the hashes of the pixels of the images followed by their paths, when they are printed
+    hash: Option<&'a (dyn Fn(String) + Sync)>,
+}
+
+impl<'a> Feedback<'a> {
+    fn report(&self, line: String) {
+        (self.report)(line)
+    }
+
+    fn warn(&self, line: String) {
+        (self.warn)(line)
+    }
+
+    /// A progress bar for a step of the work, or a hidden one
+    fn bar(&self, message: &'static str) -> ProgressBar {
+        if self.progress { progress_bar(message) } else { ProgressBar::hidden() }
+    }
+
+    /// A spinner for a step of the work which cannot tell its advancement, or a hidden one
+    fn spinner(&self, message: &'static str) -> ProgressBar {
+        if self.progress { spinner(message) } else { ProgressBar::hidden() }
+    }
+}
+
+///
+/// The seed of the modes placing their cells at random, the voronoi and the crystal ones, which is printed
+/// to pixelise the image again with the same cells
+///
+fn seed_line(options: &Options) -> Option<String> {
+    match options.mode {
+        PixelMode::voronoi | PixelMode::crystal => Some(format!("Seed: {}", options.seed)),
+        _ => None,
+    }
+}
+
+///
+/// Checks that the cells of the options fit in an image of that size, warning when they are larger than the image
+///
+fn check_size(width: u32, height: u32, options: &Options, src: &str, feedback: &Feedback) -> Result<(), Error> {
+    match plan::check(width, height, options) {
+        Ok(Some(warning)) => feedback.warn(format!("{}: {}", src, warning)),
+        Ok(None) => {}
+        Err(e) => return Err(Error::Usage(format!("cannot pixelise {}: {}", src, e))),
+    }
+    Ok(())
+}
+
+///
+/// A progress bar on stderr, which is only drawn when stderr is a terminal
+///
+fn progress_bar(message: &'static str) -> ProgressBar {
+    let bar = ProgressBar::no_length();
+    bar.set_style(
+        ProgressStyle::with_template("{msg} [{bar:40}] {percent}% (eta {eta})").unwrap().progress_chars("=> ")
+    );
+    bar.set_message(message);
+    bar
+}
+
+fn spinner(message: &'static str) -> ProgressBar {
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(ProgressStyle::with_template("{spinner} {msg} {elapsed}").unwrap());
+    spinner.set_message(message);
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+/// The progress callback of the library, updating the bar
+fn advance(bar: &ProgressBar) -> impl Fn(u64, u64) + Sync + '_ {
+    move |done, total| {
+        bar.set_length(total);
+        bar.set_position(done);
+    }
+}
+
+///
+/// Loads, pixelises and saves an image, and writes the files of its grid, if any
+///
+fn process(options: &Options, src: &str, dst: &str, formats: &Formats, grids: &GridFiles, feedback: &Feedback)
+           -> Result<Option<Stats>, Error> {
+    // checked before the image is read and pixelised, which may take minutes
+    let output_format = output_format(dst, formats)?;
+    if feedback.hash.is_some() {
+        hashable(output_format)?;
+    }
+    check_destination(dst)?;
+    let load_start = Instant::now();
+    let data = read_input(src)?;
+    let input_format = input_format(src, &data, formats)?;
+
+    // the animations are kept in the animated formats, the GIF and APNG images
+    let animated = [OutputFormat::Raster(ImageFormat::GIF), OutputFormat::Raster(ImageFormat::PNG)].contains(&output_format);
+    let animation = match input_format {
+        // the still GIF images stay still PNG images
+        ImageFormat::GIF if animated => Some(animation::decode_gif(&data).map_err(|e| Error::decode(src, e))?)
+            .filter(|a| a.frames.len() > 1 || output_format == OutputFormat::Raster(ImageFormat::GIF)),
+        ImageFormat::PNG if animated => animation::decode_apng(&data).map_err(|e| Error::decode(src, e))?,
+        _ => None,
+    };
+    if let Some(animation) = animation {
+        if grids.export.is_some() {
+            return Err(Error::Usage("--export-grid cannot be used with animations".to_string()));
+        }
+        if grids.overlay.is_some() {
+            return Err(Error::Usage("--overlay-grid cannot be used with animations".to_string()));
+        }
+        if grids.cells.is_some() {
+            return Err(Error::Usage("--export-cells cannot be used with animations".to_string()));
+        }
+        if grids.terminal.is_some() {
+            return Err(Error::Usage("--terminal and --braille cannot be used with animations".to_string()));
+        }
+        if feedback.stats {
+            return Err(Error::Usage("--stats cannot be used with animations".to_string()));
+        }
+        if feedback.hash.is_some() {
+            return Err(Error::Usage("--print-hash cannot be used with animations".to_string()));
+        }
+        check_size(animation.width, animation.height, options, src, feedback)?;
+        feedback.report(format!("Image loading time: {}", load_start.elapsed().as_millis()));
+
+        if let Some(line) = seed_line(options) {
+            feedback.report(line);
+        }
+        let pixelisation_start = Instant::now();
+        let bar = feedback.bar("Pixelising");
+        let pixelised = animation::pixelise_animation_with_progress(&animation, options, &advance(&bar));
+        bar.finish_and_clear();
+        feedback.report(format!("Pixelisation time: {}", pixelisation_start.elapsed().as_millis()));
+
+        let save_start = Instant::now();
+        let spinner = feedback.spinner("Saving");
+        let mut encoded = Vec::new();
+        if output_format == OutputFormat::Raster(ImageFormat::GIF) {
+            animation::encode_gif(&pixelised, &mut encoded)
+        } else {
+            animation::encode_apng(&pixelised, &mut encoded, &formats.png)
+        }.map_err(|e| Error::write(dst, e))?;
+        write_new_output(dst, &encoded, formats.no_clobber)?;
+        spinner.finish_and_clear();
+        feedback.report(format!("Image save time: {}", save_start.elapsed().as_millis()));
+        return Ok(None);
+    }
+
+    let (img, mut metadata) = decode_picture(src, &data, input_format, formats)?;
+    metadata.parameters = formats.parameters.as_ref().map(|settings| stamp(settings, options));
+    let load = load_start.elapsed();
+    // the size of the cells spanning the image with the columns and the rows of the options
+    let (width, height) = img.dimensions();
+    check_size(width, height, options, src, feedback)?;
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    feedback.report(format!("Image loading time: {}", load.as_millis()));
+
+    if let Some(line) = seed_line(options) {
+        feedback.report(line);
+    }
+    let mut export_time = None;
+    if grids.export.is_some() || grids.overlay.is_some() || grids.cells.is_some() {
+        let export_start = Instant::now();
+        let rgba = img.to_rgba();
+        if let Some(path) = grids.export {
+            export(&rgba, options, path)?;
+        }
+        if let Some(path) = grids.overlay {
+            save_image(export::overlay_grid(&rgba, options), path)?;
+        }
+        if let Some(path) = grids.cells {
+            export_cells(&rgba, options, path)?;
+        }
+        let export = export_start.elapsed();
+        feedback.report(format!("Grid export time: {}", export.as_millis()));
+        export_time = Some(export);
+    }
+    // the cells are counted once the work is timed
+    let stats = |colors: Option<usize>, pixelisation: Duration, save: Duration| {
+        let (width, height) = img.dimensions();
+        Ok(Some(Stats {
+            source: src.to_string(),
+            destination: dst.to_string(),
+            mode: options.mode,
+            size: options.size,
+            seed: options.seed,
+            width,
+            height,
+            used_cells: img.cell_count(options),
+            colors,
+            load,
+            export: export_time,
+            pixelisation,
+            save,
+        }))
+    };
+    let pixelisation_start = Instant::now();
+    match output_format {
+        OutputFormat::Raster(_) | OutputFormat::Webp | OutputFormat::Avif => {}
+        OutputFormat::Text => {
+            text_options(options)?;
+            let spinner = feedback.spinner("Pixelising");
+            let text = hexpxl::to_text(&img.to_rgba(), options);
+            spinner.finish_and_clear();
+            let pixelisation = pixelisation_start.elapsed();
+            feedback.report(format!("Pixelisation time: {}", pixelisation.as_millis()));
+
+            let save_start = Instant::now();
+            write_new_output(dst, text.as_bytes(), formats.no_clobber)?;
+            let save = save_start.elapsed();
+            feedback.report(format!("Image save time: {}", save.as_millis()));
+            if grids.terminal.is_some() {
+                // the emoji and the characters are text already
+                print!("{}", text);
+            }
+            return if feedback.stats { stats(None, pixelisation, save) } else { Ok(None) };
+        }
+        OutputFormat::Svg => {
+            svg_options(options)?;
+            if grids.terminal.is_some() {
+                return Err(Error::Usage("--terminal and --braille cannot be used with the SVG output".to_string()));
+            }
+            let spinner = feedback.spinner("Pixelising");
+            let svg = hexpxl::svg::pixelise_svg(&img.to_rgba(), options);
+            spinner.finish_and_clear();
+            let pixelisation = pixelisation_start.elapsed();
+            feedback.report(format!("Pixelisation time: {}", pixelisation.as_millis()));
+
+            let save_start = Instant::now();
+            write_new_output(dst, svg.as_bytes(), formats.no_clobber)?;
+            let save = save_start.elapsed();
+            feedback.report(format!("Image save time: {}", save.as_millis()));
+            return if feedback.stats { stats(None, pixelisation, save) } else { Ok(None) };
+        }
+    }
+    let bar = feedback.bar("Pixelising");
+    let pixelised = match img {
+        Picture::Narrow(ref img) => Picture::Narrow(hexpxl::pixelise_with_progress(img, options, &advance(&bar))),
+        Picture::Deep(ref img) => Picture::Deep(hexpxl::pixelise_with_progress(img, options, &advance(&bar))),
+    };
+    bar.finish_and_clear();
+    let pixelisation = pixelisation_start.elapsed();
+    feedback.report(format!("Pixelisation time: {}", pixelisation.as_millis()));
+    let colors = if feedback.stats { Some(pixelised.color_count()) } else { None };
+    if let Some(graphics) = grids.terminal {
+        print!("{}", terminal::draw(&pixelised.to_rgba(), graphics));
+    }
+
+    let save_start = Instant::now();
+    let spinner = feedback.spinner("Saving");
+    let encoded = match pixelised {
+        Picture::Narrow(pixelised) => encode(pixelised, dst, output_format, formats, &metadata)?,
+        Picture::Deep(pixelised) => encode_deep(pixelised, dst, output_format, formats, &metadata)?,
+    };
+    write_new_output(dst, &encoded, formats.no_clobber)?;
+    spinner.finish_and_clear();
+    let save = save_start.elapsed();
+    feedback.report(format!("Image save time: {}", save.as_millis()));
+    if let Some(hash) = feedback.hash {
+        hash(format!("{}  {}", pixels_hash(dst, &encoded)?, dst));
+    }
+    if feedback.stats { stats(colors, pixelisation, save) } else { Ok(None) }
+}
+
+///
+/// Fails with the output formats whose pixels cannot be hashed: the SVG documents and the texts, which have none,
+/// and the WebP, AVIF and PNM images, which the decoders cannot read back
+///
+fn hashable(format: OutputFormat) -> Result<(), Error> {
+    let name = match format {
+        OutputFormat::Raster(ImageFormat::PNM) => "PNM",
+        OutputFormat::Raster(_) => return Ok(()),
+        OutputFormat::Webp => "WebP",
+        OutputFormat::Avif => "AVIF",
+        OutputFormat::Svg => "SVG",
+        OutputFormat::Text => "text",
+    };
+    Err(Error::Usage(format!("--print-hash cannot be used with the {} output", name)))
+}
+
+///
+/// The hash of the pixels of an encoded image, as they are decoded, the name only naming it in the errors
+///
+/// The pixels are those read back from the image, so that the hash of a JPEG or a GIF image is that of
+/// the colors it kept, and that the same image gives the same hash whether it is pixelised or verified.
+///
+fn pixels_hash(name: &str, data: &[u8]) -> Result<String, Error> {
+    let formats = Formats::default();
+    let format = input_format(name, data, &formats)?;
+    let (picture, _) = decode_picture(name, data, format, &formats)?;
+    Ok(picture.hash())
+}
+
+/// Fails with the options which the SVG documents cannot have
+fn svg_options(options: &Options) -> Result<(), Error> {
+    if options.faces {
+        Err(Error::Usage("--faces cannot be used with the SVG output".to_string()))
+    } else if options.split_channels.is_some() {
+        Err(Error::Usage("the channels cannot be split in the SVG output".to_string()))
+    } else if !options.regions.is_empty() {
+        Err(Error::Usage("--region cannot be used with the SVG output".to_string()))
+    } else if options.mask.is_some() {
+        Err(Error::Usage("--mask cannot be used with the SVG output".to_string()))
+    } else if options.tileable {
+        Err(Error::Usage("--tileable cannot be used with the SVG output".to_string()))
+    } else if options.tiles.is_some() {
+        Err(Error::Usage("the photomosaic mode cannot be used with the SVG output".to_string()))
+    } else if options.mode == PixelMode::emoji || options.mode == PixelMode::ascii {
+        Err(Error::Usage(format!("the {} mode cannot be used with the SVG output", options.mode)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Fails with the options which the texts cannot have
+fn text_options(options: &Options) -> Result<(), Error> {
+    if options.mode != PixelMode::emoji && options.mode != PixelMode::ascii {
+        Err(Error::Usage(format!("the {} mode cannot be written as text, only the emoji and ascii modes", options.mode)))
+    } else if options.angle % 360.0 != 0.0 {
+        Err(Error::Usage("--angle cannot be used with the text output".to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+///
+/// An image of 8 or 16 bits per channel
+///
+enum Picture {
+    Narrow(RgbaImage),
+    Deep(Rgba16Image),
+}
+
+impl Picture {
+    fn dimensions(&self) -> (u32, u32) {
+        match *self {
+            Picture::Narrow(ref img) => img.dimensions(),
+            Picture::Deep(ref img) => img.dimensions(),
+        }
+    }
+
+    /// The image with 8 bits per channel
+    fn to_rgba(&self) -> RgbaImage {
+        match *self {
+            Picture::Narrow(ref img) => img.clone(),
+            Picture::Deep(ref img) => deep::narrowed(img),
+        }
+    }
+
+    /// The number of cells of the pixelisation of the image holding at least one of its pixels
+    fn cell_count(&self, options: &Options) -> usize {
+        match *self {
+            Picture::Narrow(ref img) => hexpxl::cell_count(img, options),
+            Picture::Deep(ref img) => hexpxl::cell_count(img, options),
+        }
+    }
+
+    /// The hash of the pixels of the image, which tells its 8 and 16 bits channels apart
+    fn hash(&self) -> String {
+        match *self {
+            Picture::Narrow(ref img) => verify::hash(img.width(), img.height(), 8, img),
+            Picture::Deep(ref img) => {
+                let channels: Vec<u8> = img.iter().flat_map(|c| c.to_be_bytes()).collect();
+                verify::hash(img.width(), img.height(), 16, &channels)
+            }
+        }
+    }
+
+    /// The number of distinct colors of the image
+    fn color_count(&self) -> usize {
+        match *self {
+            Picture::Narrow(ref img) => img.pixels().map(|p| p.data).collect::<HashSet<_>>().len(),
+            Picture::Deep(ref img) => img.pixels().map(|p| p.data).collect::<HashSet<_>>().len(),
+        }
+    }
+}
+
+///
+/// Decodes the image as `decode` does, keeping the 16 bits per channel of the PNG and TIFF images which have them,
+/// and converts its colors to sRGB when they are
+///
+/// The images whose colors are converted are decoded with 8 bits per channel, as the conversion only takes them.
+///
+fn decode_picture(src: &str, data: &[u8], format: ImageFormat, formats: &Formats) -> Result<(Picture, Metadata), Error> {
+    let metadata = Metadata::read(data);
+    if !(formats.convert_srgb && metadata.icc.is_some()) {
+        if let Some(img) = deep::decode(data, format).map_err(|e| Error::decode(src, e))? {
+            return Ok((Picture::Deep(deep::orient(img, metadata.orientation)), metadata));
+        }
+    }
+
+    let (img, mut metadata) = decode(src, data, Some(format))?;
+    let mut img = match img {
+        DynamicImage::ImageRgba8(img) => img,
+        img => img.to_rgba(),
+    };
+    if formats.convert_srgb {
+        if let Some(icc) = metadata.icc.take() {
+            img = metadata::to_srgb(img, &icc).ok_or_else(|| {
+                Error::Input(format!("cannot convert {} to sRGB: its ICC profile is not supported", src))
+            })?;
+        }
+    }
+    Ok((Picture::Narrow(img), metadata))
+}
+
+///
+/// Decodes the image, turned upright according to its EXIF orientation, and reads its metadata
+///
+/// The format is guessed from the content of the image when it is not given.
+///
+fn decode(src: &str, data: &[u8], format: Option<ImageFormat>) -> Result<(DynamicImage, Metadata), Error> {
+    let img = match format {
+        Some(format) => image::load_from_memory_with_format(data, format),
+        None => image::load_from_memory(data),
+    }.map_err(|e| Error::decode(src, e))?;
+    let metadata = Metadata::read(data);
+    Ok((metadata::orient(img, metadata.orientation), metadata))
+}
+
+///
+/// Encodes the image in the format, the destination only naming it in the errors
+///
+/// The metadata are written in the PNG and JPEG images, all but the ICC profile being left out when they are stripped.
+///
+fn encode(img: RgbaImage, dst: &str, format: OutputFormat, formats: &Formats, metadata: &Metadata)
+          -> Result<Vec<u8>, Error> {
+    let stripped = metadata.stripped();
+    let metadata = if formats.strip_metadata { &stripped } else { metadata };
+    let encoded = match format {
+        OutputFormat::Raster(ImageFormat::TIFF) => deep::encode_tiff(&img).map_err(|e| Error::write(dst, e))?,
+        OutputFormat::Raster(ImageFormat::PNG) => {
+            let encoded = deep::encode_png8(&img, &formats.png).map_err(|e| Error::write(dst, e))?;
+            metadata::embed(encoded, ImageFormat::PNG, metadata)
+        }
+        OutputFormat::Raster(ImageFormat::JPEG) => {
+            let quality = formats.quality.map_or(JPEG_QUALITY, |q| q.round().max(1.0) as u8);
+            let mut encoded = Vec::new();
+            DynamicImage::ImageRgba8(img).write_to(&mut encoded, ImageOutputFormat::JPEG(quality))
+                .map_err(|e| Error::write(dst, e))?;
+            metadata::embed(encoded, ImageFormat::JPEG, metadata)
+        }
+        OutputFormat::Raster(format) => {
+            let mut encoded = Vec::new();
+            DynamicImage::ImageRgba8(img).write_to(&mut encoded, format).map_err(|e| Error::write(dst, e))?;
+            metadata::embed(encoded, format, metadata)
+        }
+        OutputFormat::Webp => {
+            let encoder = webp::Encoder::from_rgba(&img, img.width(), img.height());
+            if formats.lossless {
+                encoder.encode_lossless().to_vec()
+            } else {
+                encoder.encode(formats.quality.unwrap_or(WEBP_QUALITY)).to_vec()
+            }
+        }
+        OutputFormat::Avif => {
+            let pixels: Vec<ravif::RGBA8> = img.pixels().map(|p| ravif::RGBA8::new(p[0], p[1], p[2], p[3])).collect();
+            ravif::Encoder::new()
+                .with_quality(formats.quality.unwrap_or(AVIF_QUALITY))
+                .encode_rgba(ravif::Img::new(&pixels[..], img.width() as usize, img.height() as usize))
+                .map_err(|e| Error::write(dst, e))?
+                .avif_file
+        }
+        OutputFormat::Svg | OutputFormat::Text => unreachable!("the SVG documents and the texts are written as they are"),
+    };
+    Ok(encoded)
+}
+
+///
+/// Encodes the image of 16 bits per channel in the format, the destination only naming it in the errors
+///
+/// The formats other than PNG and TIFF are written with 8 bits per channel.
+///
+fn encode_deep(img: Rgba16Image, dst: &str, format: OutputFormat, formats: &Formats, metadata: &Metadata)
+               -> Result<Vec<u8>, Error> {
+    if let OutputFormat::Raster(raster) = format {
+        if let Some(encoded) = deep::encode(&img, raster, &formats.png) {
+            let encoded = encoded.map_err(|e| Error::write(dst, e))?;
+            let stripped = metadata.stripped();
+            let metadata = if formats.strip_metadata { &stripped } else { metadata };
+            return Ok(metadata::embed(encoded, raster, metadata));
+        }
+    }
+    encode(deep::narrowed(&img), dst, format, formats, metadata)
+}
+
+/// Writes the encoded output into the destination, - for stdout
+fn write_output(dst: &str, data: &[u8]) -> Result<(), Error> {
+    write_new_output(dst, data, false)
+}
+
+/// Writes the encoded output into the destination, - for stdout, failing when it exists and `no_clobber` is set
+fn write_new_output(dst: &str, data: &[u8], no_clobber: bool) -> Result<(), Error> {
+    if dst == "-" {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        return out.write_all(data).and_then(|_| out.flush()).map_err(|e| Error::write(dst, e));
+    }
+    let temporary = temporary_path(dst);
+    let written = fs::write(&temporary, data).map_err(|e| Error::write(dst, e));
+    commit_output(&temporary, dst, written, no_clobber)
+}
+
+///
+/// Writes an image along with the output, such as the grid drawn over it, in the format of its extension,
+/// through a temporary file as the output is
+///
+fn save_image(img: RgbaImage, path: &str) -> Result<(), Error> {
+    save_new_image(img, path, false)
+}
+
+/// Saves the image as `save_image` does, failing when it exists and `no_clobber` is set
+fn save_new_image(img: RgbaImage, path: &str, no_clobber: bool) -> Result<(), Error> {
+    let formats = Formats::default();
+    match output_format(path, &formats)? {
+        OutputFormat::Svg | OutputFormat::Text =>
+            Err(Error::UnsupportedFormat(format!("cannot write {}: SVG and text are not supported here", path))),
+        format => write_new_output(path, &encode(img, path, format, &formats, &Metadata::default())?, no_clobber),
+    }
+}
+
+///
+/// The file an output is written to before it is renamed to its destination, next to it,
+/// so that an interrupted run never leaves a truncated image at the destination
+///
+fn temporary_path(dst: &str) -> PathBuf {
+    let path = Path::new(dst);
+    let name = path.file_name().map_or_else(|| "output".into(), |name| name.to_string_lossy());
+    path.with_file_name(format!(".{}.{}.tmp", name, process::id()))
+}
+
+///
+/// Renames the temporary file to the destination once it is written, else removes it
+///
+/// With `no_clobber`, the destination is linked to the temporary file instead, which fails when it exists:
+/// it may have been written since it was checked, e.g. by another image of a batch having the same output.
+///
+fn commit_output(temporary: &Path, dst: &str, written: Result<(), Error>, no_clobber: bool) -> Result<(), Error> {
+    let result = written.and_then(|_| if no_clobber {
+        fs::hard_link(temporary, dst).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => Error::Output(format!("cannot write {}: it exists, and is left as it is", dst)),
+            _ => Error::write(dst, e),
+        })
+    } else {
+        fs::rename(temporary, dst).map_err(|e| Error::write(dst, e))
+    });
+    if result.is_err() || no_clobber {
+        // it may not have been created
+        let _ = fs::remove_file(temporary);
+    }
+    result
+}
+
+///
+/// Checks that the destination can be written before the image is pixelised: that its directory exists
+/// and that the temporary file it is written to can be created there
+///
+fn check_destination(dst: &str) -> Result<(), Error> {
+    if dst == "-" {
+        return Ok(());
+    }
+    let path = Path::new(dst);
+    let directory = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+    if !directory.is_dir() {
+        return Err(Error::Output(format!("cannot write {}: the directory {} does not exist", dst, directory.display())));
+    }
+    if path.is_dir() {
+        return Err(Error::Output(format!("cannot write {}: it is a directory", dst)));
+    }
+    let temporary = temporary_path(dst);
+    File::options().write(true).create_new(true).open(&temporary)
+        .and_then(|_| fs::remove_file(&temporary))
+        .map_err(|e| Error::write(dst, e))
+}
+
+/// Whether the destination exists already and is to be left as it is, as with --no-clobber
+fn clobbered(dst: &str, no_clobber: bool) -> bool {
+    no_clobber && dst != "-" && Path::new(dst).exists()
+}
+
+///
+/// Writes the hexagons of the grid of the image as JSON or as a map of Tiled, whose tileset is written next to it
+///
+fn export(img: &RgbaImage, options: &Options, path: &str) -> Result<(), Error> {
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+    let tmx = match extension.as_deref() {
+        Some("json") => false,
+        Some("tmx") => true,
+        _ => return Err(Error::Usage(format!("cannot export the grid to {}: the extension must be .json or .tmx", path))),
+    };
+    let cells = export::hex_cells(img, options).ok_or_else(|| {
+        Error::Usage(format!("cannot export the grid of the {} mode, only of the hex and dots modes", options.mode))
+    })?;
+    if !tmx {
+        return write_output(path, export::to_json(&cells, options, img.dimensions()).as_bytes());
+    }
+
+    let stem = Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or("grid");
+    let tileset = format!("{}-tiles.png", stem);
+    let tileset_path = Path::new(path).with_file_name(&tileset);
+    let (map, image) = export::to_tmx(&cells, options, &tileset);
+    save_image(image, &tileset_path.to_string_lossy())?;
+    write_output(path, map.as_bytes())
+}
+
+///
+/// Writes the statistics of the cells of the grid of the image as CSV or as Parquet
+///
+fn export_cells(img: &RgbaImage, options: &Options, path: &str) -> Result<(), Error> {
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+    match extension.as_deref() {
+        Some("csv") => write_output(path, export::to_csv(&export::cell_stats(img, options)).as_bytes()),
+        Some("parquet") => write_parquet(&export::cell_stats(img, options), path),
+        _ => Err(Error::Usage(format!("cannot export the cells to {}: the extension must be .csv or .parquet", path))),
+    }
+}
+
+///
+/// Writes the cells as a Parquet file of a single row group, with the columns of `export::to_csv`
+///
+#[cfg(feature = "parquet")]
+fn write_parquet(cells: &[export::CellStats], path: &str) -> Result<(), Error> {
+    use parquet::schema::parser::parse_message_type;
+
+    let schema = parse_message_type("
+        message cells {
+            required int64 index;
+            required float x;
+            required float y;
+            optional int32 q;
+            optional int32 r;
+            required float red;
+            required float green;
+            required float blue;
+            required float alpha;
+            required int64 pixels;
+            required float variance;
+        }
+    ").expect("a valid schema");
+    let failed = |e: parquet::errors::ParquetError| Error::write(path, e);
+    let temporary = temporary_path(path);
+    let file = File::create(&temporary).map_err(|e| Error::write(path, e))?;
+    let written = write_cells(cells, schema, file).map_err(failed);
+    commit_output(&temporary, path, written, false)
+}
+
+/// Writes the cells into the Parquet file, as `write_parquet` does
+#[cfg(feature = "parquet")]
+fn write_cells(cells: &[export::CellStats], schema: parquet::schema::types::Type, file: File) -> parquet::errors::Result<()> {
+    use parquet::data_type::{DataType, FloatType, Int32Type, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+    use std::sync::Arc;
+
+    fn column<T: DataType>(group: &mut SerializedRowGroupWriter<File>, values: &[T::T], definitions: Option<&[i16]>)
+                           -> parquet::errors::Result<()> {
+        let mut column = group.next_column()?.expect("the columns of the schema");
+        column.typed::<T>().write_batch(values, definitions, None)?;
+        column.close()
+    }
+
+    let mut writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(WriterProperties::builder().build()))?;
+    let mut group = writer.next_row_group()?;
+
+    let floats = |value: &dyn Fn(&export::CellStats) -> f32| cells.iter().map(value).collect::<Vec<_>>();
+    // the axial coordinates are only defined for the hexagons
+    let axial: Vec<(i32, i32)> = cells.iter().filter_map(|cell| cell.axial).collect();
+    let defined: Vec<i16> = cells.iter().map(|cell| cell.axial.is_some() as i16).collect();
+    column::<Int64Type>(&mut group, &cells.iter().map(|cell| cell.index as i64).collect::<Vec<_>>(), None)
+        .and_then(|_| column::<FloatType>(&mut group, &floats(&|cell| cell.center.0), None))
+        .and_then(|_| column::<FloatType>(&mut group, &floats(&|cell| cell.center.1), None))
+        .and_then(|_| column::<Int32Type>(&mut group, &axial.iter().map(|a| a.0).collect::<Vec<_>>(), Some(&defined)))
+        .and_then(|_| column::<Int32Type>(&mut group, &axial.iter().map(|a| a.1).collect::<Vec<_>>(), Some(&defined)))
+        .and_then(|_| (0..4).try_for_each(|c| column::<FloatType>(&mut group, &floats(&|cell| cell.mean[c]), None)))
+        .and_then(|_| column::<Int64Type>(&mut group, &cells.iter().map(|cell| cell.pixels as i64).collect::<Vec<_>>(), None))
+        .and_then(|_| column::<FloatType>(&mut group, &floats(&|cell| cell.variance), None))?;
+    group.close()?;
+    writer.close().map(|_| ())
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_parquet(_cells: &[export::CellStats], path: &str) -> Result<(), Error> {
+    Err(Error::Usage(format!("cannot export the cells to {}: Parquet needs hexpxl to be built with --features parquet", path)))
+}
+
+///
+/// Pixelises an image in bands of rows, so that the memory used stays under `max_memory` bytes
+///
+fn stream(options: &Options, src: &str, dst: &str, formats: &Formats, max_memory: u64, feedback: &Feedback)
+          -> Result<(), Error> {
+    let read_error = |e: io::Error| match e.kind() {
+        io::ErrorKind::Unsupported => Error::UnsupportedFormat(format!("cannot stream {}: {}", src, e)),
+        _ => Error::read(src, e),
+    };
+    let mut input = BufReader::new(open_input(src)?);
+    let input_format = match formats.input {
+        Some(format) => format,
+        None => image::guess_format(input.fill_buf().map_err(read_error)?).ok()
+            .or_else(|| format_from_path(src))
+            .ok_or_else(|| Error::UnsupportedFormat(format!("cannot guess the format of {}, use --input-format", src)))?,
+    };
+    let mut reader: Box<dyn RowReader> = match input_format {
+        ImageFormat::PNG => Box::new(PngReader::new(input).map_err(read_error)?),
+        ImageFormat::PNM => Box::new(PnmReader::new(input).map_err(read_error)?),
+        // the strips are found by seeking in the file
+        ImageFormat::TIFF if src != "-" && !is_url(src) => {
+            Box::new(TiffReader::new(BufReader::new(File::open(src).map_err(read_error)?)).map_err(read_error)?)
+        }
+        format => return Err(Error::UnsupportedFormat(format!(
+            "cannot stream {}: {:?} images cannot be streamed from {}", src, format, match src {
+                "-" => "stdin",
+                src if is_url(src) => "urls",
+                _ => "files",
+            }
+        ))),
+    };
+
+    // checked before creating the destination, so that no empty file is left behind
+    let (width, height) = reader.dimensions();
+    check_size(width, height, options, src, feedback)?;
+    let band = stream::band_height(width, height, options, max_memory).map_err(|e| Error::Usage(e.to_string()))?;
+    let output_format = output_format(dst, formats)?;
+    if output_format != OutputFormat::Raster(ImageFormat::PNG) && output_format != OutputFormat::Raster(ImageFormat::PNM) {
+        return Err(Error::UnsupportedFormat(format!("cannot stream to {}: only PNG and PNM images are streamed", dst)));
+    }
+    check_destination(dst)?;
+    // the image is written next to the destination, which it replaces once it is whole
+    let temporary = if dst == "-" { None } else { Some(temporary_path(dst)) };
+    let output: Box<dyn Write> = match temporary {
+        None => Box::new(io::stdout()),
+        Some(ref temporary) => Box::new(File::create(temporary).map_err(|e| Error::write(dst, e))?),
+    };
+    let output = BufWriter::new(output);
+    let mut writer: Box<dyn RowWriter> = match output_format {
+        OutputFormat::Raster(ImageFormat::PNG) =>
+            Box::new(PngWriter::new(output, width, height, &formats.png).map_err(|e| Error::write(dst, e))?),
+        // the PPM images have no transparency
+        OutputFormat::Raster(ImageFormat::PNM) => {
+            let ppm = format_from_path(dst).is_some() && !dst.to_ascii_lowercase().ends_with(".pam");
+            Box::new(PnmWriter::new(output, width, height, !ppm).map_err(|e| Error::write(dst, e))?)
+        }
+        _ => unreachable!(),
+    };
+
+    if let Some(line) = seed_line(options) {
+        feedback.report(line);
+    }
+    feedback.report(format!("Bands of {} rows", band));
+    let start = Instant::now();
+    let bar = feedback.bar("Pixelising");
+    let streamed = stream::pixelise_stream(reader.as_mut(), writer.as_mut(), options, max_memory, &advance(&bar))
+        .map_err(|e| match e {
+            StreamError::Options(m) => Error::Usage(m),
+            StreamError::Read(e) => read_error(e),
+            StreamError::Write(e) => Error::write(dst, e),
+        });
+    // closes the file before it is renamed
+    drop(writer);
+    match temporary {
+        Some(temporary) => commit_output(&temporary, dst, streamed, formats.no_clobber)?,
+        None => streamed?,
+    }
+    bar.finish_and_clear();
+    feedback.report(format!("Pixelisation time: {}", start.elapsed().as_millis()));
+    Ok(())
+}
+
+///
+/// What pixelising the image would do, from the size read in its header, without pixelising it nor reading all of it
+///
+/// The size of the cells, the grid, the memory, or the height of the bands of rows when it is streamed,
+/// and the destination, which is not written.
+///
+fn dry_run(options: &Options, src: &str, dst: &str, formats: &Formats, max_memory: Option<u64>) -> Result<String, Error> {
+    let mut input = open_input(src)?;
+    let mut data = Vec::new();
+    (&mut input).take(HEADER_SIZE).read_to_end(&mut data).map_err(|e| Error::read(src, e))?;
+    let input_format = input_format(src, &data, formats)?;
+    output_format(dst, formats)?;
+    // the TIFF images may keep their header at their end, and the WebP decoder decodes the whole image
+    let dimensions = match input_format {
+        ImageFormat::TIFF | ImageFormat::WEBP => None,
+        format => image_dimensions(src, &data, format).ok(),
+    };
+    let (width, height) = match dimensions {
+        Some(dimensions) => dimensions,
+        None => {
+            input.read_to_end(&mut data).map_err(|e| Error::read(src, e))?;
+            image_dimensions(src, &data, input_format)?
+        }
+    };
+    let metadata = Metadata::read(&data);
+    // the streamed images are neither turned nor pixelised with 16 bits per channel
+    let (width, height) = if max_memory.is_none() && metadata.orientation >= 5 { (height, width) } else { (width, height) };
+    let deep = max_memory.is_none() && !(formats.convert_srgb && metadata.icc.is_some())
+        && deep::has_16_bits(&data, input_format);
+    let warning = plan::check(width, height, options).map_err(|e| Error::Usage(format!("cannot pixelise {}: {}", src, e)))?;
+    let fitted = options.fitted(width, height);
+    let mut lines = vec![
+        format!("{}: {}x{} pixels, {} bits per channel", src, width, height, if deep { 16 } else { 8 }),
+        format!("Destination: {}", dst),
+        format!("Mode: {}, size {}", fitted.mode, fitted.size),
+    ];
+    let (width, height) = match (options.fit_grid, plan::fit_grid(width, height, options)) {
+        (Some(fit), Some((fit_width, fit_height))) => {
+            lines.push(format!("Fit: {}, {}x{} pixels", fit, fit_width, fit_height));
+            (fit_width, fit_height)
+        }
+        _ => (width, height),
+    };
+    // the cells are drawn on the image enlarged by the scale
+    let scaled = fitted.scaled();
+    let (width, height, options) = if fitted.scale > 1 {
+        lines.push(format!("Scale: {}, {}x{} pixels", fitted.scale, width * fitted.scale, height * fitted.scale));
+        (width * fitted.scale, height * fitted.scale, &scaled)
+    } else {
+        (width, height, &*fitted)
+    };
+    let plan = plan::plan(width, height, options, if deep { 2 } else { 1 });
+
+    if let Some(inner_radius) = plan.inner_radius {
+        lines.push(format!("Inner radius: {:.2} pixels", inner_radius));
+    }
+    if let Some(row_spacing) = plan.row_spacing {
+        lines.push(format!("Row spacing: {:.2} pixels", row_spacing));
+    }
+    lines.push(format!("Gap: {} pixels, border: {} pixels", options.gap, options.border_width));
+    if let Some((columns, rows)) = plan.grid {
+        lines.push(format!("Grid: {} columns x {} rows", columns, rows));
+    }
+    lines.push(match plan.cells {
+        Some(cells) => format!("Grid cells: {}", cells),
+        None => "Grid cells: found from the colors of the image".to_string(),
+    });
+    lines.push(match max_memory {
+        Some(max_memory) => {
+            let band = stream::band_height(width, height, &fitted, max_memory).map_err(|e| Error::Usage(e.to_string()))?;
+            format!("Memory: streamed in bands of {} rows, under {}", band, format_memory(max_memory))
+        }
+        None => format!("Memory: about {}", format_memory(plan.memory)),
+    });
+    if let Some(warning) = warning {
+        lines.push(format!("Warning: {}", warning));
+    }
+    Ok(lines.join("\n"))
+}
+
+///
+/// Prints what pixelising each image matching the sources would do, without pixelising them
+///
+fn dry_run_batch(options: &Options, sources: &[&str], template: &str, formats: &Formats) -> Result<(), Error> {
+    let inputs = expand_sources(sources)?;
+    let failures: Vec<Error> = inputs.iter().filter_map(|src| {
+        let dst = output_path(template, src, options);
+        match dry_run(options, &src.to_string_lossy(), &dst.to_string_lossy(), formats, None) {
+            Ok(report) => {
+                println!("{}\n", report);
+                None
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                Some(e)
+            }
+        }
+    }).collect();
+    println!("Would pixelise {} images", inputs.len() - failures.len());
+
+    match failures.first() {
+        Some(e) => Err(Error::Batch(failures.len(), e.exit_code())),
+        None => Ok(()),
+    }
+}
+
+///
+/// The size of the image, read from its header without decoding its pixels
+///
+fn image_dimensions(src: &str, data: &[u8], format: ImageFormat) -> Result<(u32, u32), Error> {
+    use image::ImageDecoder;
+
+    let data = io::Cursor::new(data);
+    let (width, height) = match format {
+        ImageFormat::PNG => image::png::PNGDecoder::new(data).map(|d| d.dimensions()),
+        ImageFormat::JPEG => image::jpeg::JPEGDecoder::new(data).map(|d| d.dimensions()),
+        ImageFormat::GIF => image::gif::Decoder::new(data).map(|d| d.dimensions()),
+        ImageFormat::WEBP => image::webp::WebpDecoder::new(data).map(|d| d.dimensions()),
+        ImageFormat::PNM => image::pnm::PNMDecoder::new(data).map(|d| d.dimensions()),
+        ImageFormat::TIFF => image::tiff::TIFFDecoder::new(data).map(|d| d.dimensions()),
+        ImageFormat::TGA => image::tga::TGADecoder::new(data).map(|d| d.dimensions()),
+        ImageFormat::BMP => image::bmp::BMPDecoder::new(data).map(|d| d.dimensions()),
+        ImageFormat::ICO => image::ico::ICODecoder::new(data).map(|d| d.dimensions()),
+        ImageFormat::HDR => image::hdr::HDRAdapter::new(data).map(|d| d.dimensions()),
+    }.map_err(|e| Error::decode(src, e))?;
+    Ok((width as u32, height as u32))
+}
+
+///
+/// The memory taken by decoding and pixelising an image of that size, in bytes, roughly, once it is fitted
+/// to the grid and enlarged by the scale, without building the cells
+///
+fn pixelisation_memory(width: u32, height: u32, options: &Options, channel_bytes: u64) -> u64 {
+    let decoded = width as u64 * height as u64 * 4 * channel_bytes;
+    let fitted = options.fitted(width, height);
+    let (width, height) = plan::fit_grid(width, height, options).unwrap_or((width, height));
+    let scale = fitted.scale.max(1);
+    match (width.checked_mul(scale), height.checked_mul(scale)) {
+        (Some(width), Some(height)) =>
+            decoded.saturating_add(plan::memory(width, height, &fitted.scaled(), channel_bytes)),
+        _ => u64::MAX,
+    }
+}
+
+///
+/// A number of bytes in the largest of the powers of 1024 it reaches, as parsed by `parse_memory`, e.g. 15.1M
+///
+fn format_memory(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1}G", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1}M", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1}K", b as f64 / (1u64 << 10) as f64),
+        b => format!("{}B", b),
+    }
+}
+
+///
+/// The format of the source, as given by --input-format, else guessed from its content, else from its extension
+///
+fn input_format(src: &str, data: &[u8], formats: &Formats) -> Result<ImageFormat, Error> {
+    formats.input
+        .or_else(|| image::guess_format(data).ok())
+        .or_else(|| format_from_path(src))
+        .ok_or_else(|| Error::UnsupportedFormat(format!("cannot guess the format of {}, use --input-format", src)))
+}
+
+/// The bytes read from the start of the images by the dry runs, which hold the headers of most of them
+const HEADER_SIZE: u64 = 1 << 20;
+
+///
+/// The source as it is read, `-` being stdin, and the http(s) urls being downloaded
+///
+fn open_input(src: &str) -> Result<Box<dyn Read>, Error> {
+    Ok(if src == "-" {
+        Box::new(io::stdin())
+    } else if is_url(src) {
+        Box::new(open_url(src, MAX_DOWNLOAD)?)
+    } else {
+        Box::new(File::open(src).map_err(|e| Error::read(src, e))?)
+    })
+}
+
+///
+/// Reads the whole source, `-` being stdin, and the http(s) urls being downloaded
+///
+fn read_input(src: &str) -> Result<Vec<u8>, Error> {
+    if src == "-" {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data).map_err(|e| Error::read("stdin", e))?;
+        Ok(data)
+    } else if is_url(src) {
+        download(src, MAX_DOWNLOAD)
+    } else {
+        fs::read(src).map_err(|e| Error::read(src, e))
+    }
+}
+
+///
+/// The format to encode the destination with, which must be supported by the image crate's encoders or be TIFF
+///
+/// `--format` overrides the extension of the destination, which stdout does not have.
+///
+fn output_format(dst: &str, formats: &Formats) -> Result<OutputFormat, Error> {
+    let format = match formats.output {
+        Some(format) => format,
+        None if dst == "-" => return Err(Error::Usage("writing to stdout requires --format".to_string())),
+        None => Path::new(dst).extension()
+            .and_then(|e| e.to_str())
+            .and_then(parse_output_format)
+            .ok_or_else(|| Error::UnsupportedFormat(format!("cannot write {}: unknown image format", dst)))?,
+    };
+    writable(format, dst, formats)
+}
+
+/// The format if it can be encoded with the formats, the destination only naming it in the errors
+fn writable(format: OutputFormat, dst: &str, formats: &Formats) -> Result<OutputFormat, Error> {
+    match format {
+        OutputFormat::Avif if formats.lossless =>
+            Err(Error::Usage(format!("cannot write {}: AVIF images cannot be lossless, use --quality 100", dst))),
+        OutputFormat::Raster(ImageFormat::PNG) | OutputFormat::Raster(ImageFormat::JPEG) |
+        OutputFormat::Raster(ImageFormat::GIF) | OutputFormat::Raster(ImageFormat::BMP) |
+        OutputFormat::Raster(ImageFormat::ICO) | OutputFormat::Raster(ImageFormat::PNM) |
+        OutputFormat::Raster(ImageFormat::TIFF) | OutputFormat::Webp | OutputFormat::Avif | OutputFormat::Svg
+        | OutputFormat::Text => Ok(format),
+        OutputFormat::Raster(f) =>
+            Err(Error::UnsupportedFormat(format!("cannot write {}: {:?} encoding is not supported", dst, f))),
+    }
+}
+
+///
+/// The output format matching a name or a file extension
+///
+fn parse_output_format(name: &str) -> Option<OutputFormat> {
+    match &name.to_ascii_lowercase()[..] {
+        "svg" => Some(OutputFormat::Svg),
+        "txt" => Some(OutputFormat::Text),
+        "webp" => Some(OutputFormat::Webp),
+        "avif" => Some(OutputFormat::Avif),
+        _ => parse_format(name).map(OutputFormat::Raster),
+    }
+}
+
+///
+/// The format matching a name or a file extension
+///
+fn parse_format(name: &str) -> Option<ImageFormat> {
+    match &name.to_ascii_lowercase()[..] {
+        "jpg" | "jpeg" => Some(ImageFormat::JPEG),
+        "png" => Some(ImageFormat::PNG),
+        "gif" => Some(ImageFormat::GIF),
+        "webp" => Some(ImageFormat::WEBP),
+        "tif" | "tiff" => Some(ImageFormat::TIFF),
+        "tga" => Some(ImageFormat::TGA),
+        "bmp" => Some(ImageFormat::BMP),
+        "ico" => Some(ImageFormat::ICO),
+        "hdr" => Some(ImageFormat::HDR),
+        "pnm" | "pbm" | "pam" | "ppm" | "pgm" => Some(ImageFormat::PNM),
+        _ => None,
+    }
+}
+
+fn format_from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+    path.as_ref().extension()
+        .and_then(|e| e.to_str())
+        .and_then(parse_format)
+}
+
+///
+/// Pixelises all the images matching the sources, in parallel
+///
+fn pixelise_batch(options: &Options, sources: &[&str], template: &str, formats: &Formats, reports: &Reports, watch: bool)
+                  -> Result<(), Error> {
+    if let Some(line) = seed_line(options).filter(|_| !reports.quiet && reports.stats.is_none()) {
+        println!("{}", line);
+    }
+    if watch {
+        return self::watch(sources, reports.quiet, |inputs| {
+            // the failures are already reported, the next changes may fix them
+            let _ = pixelise_files(options, inputs, template, formats, reports);
+            inputs.iter().map(|src| output_path(template, src, options)).collect()
+        });
+    }
+    pixelise_files(options, &expand_sources(sources)?, template, formats, reports)
+}
+
+///
+/// Pixelises the images in parallel, reporting each of them
+///
+fn pixelise_files(options: &Options, inputs: &[PathBuf], template: &str, formats: &Formats, reports: &Reports)
+                  -> Result<(), Error> {
+    let start = Instant::now();
+    // the statistics replace the timings
+    let quiet = reports.quiet || reports.stats.is_some();
+    let bar = if reports.quiet { ProgressBar::hidden() } else { progress_bar("Pixelising") };
+    let warn = |line: String| bar.suspend(|| eprintln!("warning: {}", line));
+    let hash = |line: String| bar.suspend(|| println!("{}", line));
+    let feedback = Feedback {
+        report: &|_| {},
+        warn: &warn,
+        progress: false,
+        stats: reports.stats.is_some(),
+        hash: if reports.hash { Some(&hash) } else { None },
+    };
+    // the images whose outputs exist, with --no-clobber
+    let skipped = AtomicUsize::new(0);
+    bar.set_length(inputs.len() as u64);
+    let failures: Vec<Error> = inputs.par_iter().filter_map(|src| {
+        let dst = output_path(template, src, options);
+        let (src, dst) = (src.to_string_lossy(), dst.to_string_lossy());
+        if clobbered(&dst, formats.no_clobber) {
+            skipped.fetch_add(1, Ordering::Relaxed);
+            bar.inc(1);
+            if !quiet {
+                bar.suspend(|| println!("{} -> {} exists, skipped", src, dst));
+            }
+            return None;
+        }
+        let file_start = Instant::now();
+        let result = process(options, &src, &dst, formats, &GridFiles::default(), &feedback).and_then(|stats| {
+            match (stats, &reports.stats) {
+                (Some(stats), Some(output)) => bar.suspend(|| output.write(&stats)),
+                _ => Ok(()),
+            }
+        });
+        bar.inc(1);
+        match result {
+            Ok(_) => {
+                if !quiet {
+                    bar.suspend(|| println!("{} -> {} ({}ms)", src, dst, file_start.elapsed().as_millis()));
+                }
+                None
+            }
+            Err(e) => {
+                bar.suspend(|| eprintln!("{}", e));
+                Some(e)
+            }
+        }
+    }).collect();
+    bar.finish_and_clear();
+    let skipped = skipped.into_inner();
+    if !quiet {
+        println!(
+            "Pixelised {} images in {}ms",
+            inputs.len() - failures.len() - skipped,
+            start.elapsed().as_millis()
+        );
+        if skipped > 0 {
+            println!("Skipped {} images whose outputs exist", skipped);
+        }
+    }
+
+    match failures.first() {
+        Some(e) => Err(Error::Batch(failures.len(), e.exit_code())),
+        None => Ok(()),
+    }
+}
+
+/// How often the watched files are checked
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+///
+/// Calls `run` with the files of the sources, then with those which changed, every time some of them change
+///
+/// A file has changed when its modification time or its size are different.
+/// It is only given to `run` once they stayed the same for an interval, so that it is not read while being written.
+/// `run` returns the files it wrote, which are not watched, so that its outputs do not trigger it again.
+/// Only returns when the sources cannot be listed.
+///
+fn watch<F>(sources: &[&str], quiet: bool, mut run: F) -> Result<(), Error>
+    where F: FnMut(&[PathBuf]) -> Vec<PathBuf> {
+    let stamp = |path: &Path| fs::metadata(path).and_then(|m| Ok((m.modified()?, m.len()))).ok();
+
+    let inputs = expand_sources(sources)?;
+    let mut ignored: HashSet<PathBuf> = run(&inputs).into_iter().collect();
+    let mut seen: HashMap<PathBuf, (SystemTime, u64)> = inputs.iter()
+        .filter(|p| !ignored.contains(*p))
+        .filter_map(|p| Some((p.clone(), stamp(p)?)))
+        .collect();
+    let mut pending: HashMap<PathBuf, (SystemTime, u64)> = HashMap::new();
+    if !quiet {
+        println!("Watching {}, interrupt to stop", sources.join(", "));
+    }
+
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        let mut changed = Vec::new();
+        for path in expand_sources(sources)? {
+            let current = match stamp(&path) {
+                Some(current) if !ignored.contains(&path) && seen.get(&path) != Some(&current) => current,
+                _ => continue,
+            };
+            if pending.get(&path) == Some(&current) {
+                pending.remove(&path);
+                seen.insert(path.clone(), current);
+                changed.push(path);
+            } else {
+                pending.insert(path, current);
+            }
+        }
+        if !changed.is_empty() {
+            ignored.extend(run(&changed));
+        }
+    }
+}
+
+///
+/// Lists the files matching the sources, which may be files, directories, glob patterns or urls
+///
+fn expand_sources(sources: &[&str]) -> Result<Vec<PathBuf>, Error> {
+    let mut inputs = Vec::new();
+    for source in sources {
+        let path = Path::new(source);
+        if is_url(source) {
+            inputs.push(path.to_path_buf());
+        } else if path.is_dir() {
+            let mut files: Vec<PathBuf> = fs::read_dir(path)
+                .map_err(|e| Error::read(source, e))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && format_from_path(p).is_some())
+                .collect();
+            files.sort();
+            inputs.extend(files);
+        } else if path.is_file() {
+            inputs.push(path.to_path_buf());
+        } else {
+            let pattern = glob::glob(source)
+                .map_err(|e| Error::Usage(format!("invalid pattern {}: {}", source, e)))?;
+            inputs.extend(pattern.filter_map(Result::ok).filter(|p| p.is_file()));
+        }
+    }
+    Ok(inputs)
+}
+
+///
+/// The paths of the frames of a sequence, in the order of their numbers
+///
+/// With a range, all its frames must exist. Without one, the frames are the files of the directory of the pattern
+/// matching it, with at least as many digits as the pattern pads the numbers to.
+///
+fn sequence_frames(pattern: &str, range: Option<&str>) -> Result<Vec<String>, Error> {
+    let sequence = parse_sequence(pattern).ok_or_else(|| Error::Usage(format!(
+        "invalid pattern '{}' for --sequence, it needs a single number such as %d or %04d, e.g. frame_%04d.png", pattern
+    )))?;
+
+    if let Some(range) = range {
+        let (first, last) = parse_range(range).ok_or_else(|| {
+            Error::Usage(format!("invalid range '{}' for --frames, e.g. 1-240", range))
+        })?;
+        return (first..=last).map(|number| {
+            let path = sequence.path(number);
+            if Path::new(&path).is_file() {
+                Ok(path)
+            } else {
+                Err(Error::Input(format!("cannot read {}: frame {} of the sequence is missing", path, number)))
+            }
+        }).collect();
+    }
+
+    let path = Path::new(&sequence.prefix);
+    // the prefix ending with a separator has no file name, all of it is the directory
+    let (dir, name) = if sequence.prefix.ends_with(path::is_separator) {
+        (path, "")
+    } else {
+        (path.parent().unwrap_or(path), path.file_name().and_then(|n| n.to_str()).unwrap_or(""))
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let mut frames: Vec<(u64, String)> = fs::read_dir(dir)
+        .map_err(|e| Error::read(&dir.to_string_lossy(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .filter_map(|p| {
+            let number = p.file_name()?.to_str()?.strip_prefix(name)?.strip_suffix(sequence.suffix.as_str())?;
+            if number.len() < sequence.width.max(1) || !number.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            Some((number.parse().ok()?, p.to_string_lossy().into_owned()))
+        })
+        .collect();
+    if frames.is_empty() {
+        return Err(Error::Input(format!("no frames of the sequence {} found", pattern)));
+    }
+    frames.sort();
+    Ok(frames.into_iter().map(|(_, path)| path).collect())
+}
+
+///
+/// The path of the frames of a sequence, around their number padded with zeros to a width
+///
+struct Sequence {
+    prefix: String,
+    width: usize,
+    suffix: String,
+}
+
+impl Sequence {
+    fn path(&self, number: u64) -> String {
+        format!("{}{:0width$}{}", self.prefix, number, self.suffix, width = self.width)
+    }
+}
+
+/// A path with a single %d or %0Nd
+fn parse_sequence(text: &str) -> Option<Sequence> {
+    let start = text.find('%')?;
+    let rest = &text[start + 1..];
+    let end = rest.find('d')?;
+    let width = &rest[..end];
+    let width = match width {
+        "" => 0,
+        _ if width.starts_with('0') && width.len() > 1 => width[1..].parse().ok()?,
+        _ => return None,
+    };
+    let suffix = &rest[end + 1..];
+    if suffix.contains('%') {
+        return None;
+    }
+    Some(Sequence { prefix: text[..start].to_string(), width, suffix: suffix.to_string() })
+}
+
+/// The first and last numbers first-last, or a single one
+fn parse_range(text: &str) -> Option<(u64, u64)> {
+    let (first, last) = match text.find('-') {
+        Some(dash) => (text[..dash].trim().parse().ok()?, text[dash + 1..].trim().parse().ok()?),
+        None => {
+            let number = text.trim().parse().ok()?;
+            (number, number)
+        }
+    };
+    Some((first, last)).filter(|&(first, last)| first <= last)
+}
+
+///
+/// Fills the output template with the values of the source
+///
+/// The images downloaded from urls are in the current directory, named after the path of their url.
+///
+fn output_path(template: &str, src: &Path, options: &Options) -> PathBuf {
+    let url = src.to_str().filter(|src| is_url(src));
+    let path = url.map(|url| url.split(['?', '#']).next().unwrap_or(url));
+    let src = path.map_or(src, Path::new);
+    let dir = src.parent()
+        .filter(|_| url.is_none())
+        .map(|d| d.to_string_lossy().into_owned())
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| ".".to_string());
+    let stem = src.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let ext = src.extension().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+
+    PathBuf::from(
+        template
+            .replace("{dir}", &dir)
+            .replace("{stem}", &stem)
+            .replace("{ext}", &ext)
+            .replace("{mode}", &options.mode.to_string())
+            .replace("{size}", &options.size.to_string())
+    )
+}
+
+///
+/// Opens the preview window of an image
+///
+#[cfg(feature = "preview")]
+fn show_preview(options: Options, src: &str, template: &str) -> Result<(), Error> {
+    let data = read_input(src)?;
+    let img = decode(src, &data, None)?.0.to_rgba();
+    let path = Path::new(src);
+    preview::preview(&img, options, path, |options| output_path(template, path, options)).map_err(Error::Output)
+}
+
+#[cfg(not(feature = "preview"))]
+fn show_preview(_options: Options, _src: &str, _template: &str) -> Result<(), Error> {
+    Err(Error::Usage("the preview command needs hexpxl to be built with --features preview".to_string()))
+}
+
+/// The paths of the ffmpeg executables
+struct Ffmpeg<'a> {
+    ffmpeg: &'a str,
+    ffprobe: &'a str,
+}
+
+///
+/// Pixelises all the frames of a video
+///
+/// ffmpeg decodes the source into raw RGBA frames, which are pixelised in parallel, some frames at a time,
+/// and sent in order to another ffmpeg encoding them into the destination with the audio of the source.
+/// All the frames share the same options, so that the grid stays still.
+///
+fn pixelise_video(options: &Options, src: &str, dst: &str, tools: &Ffmpeg, quiet: bool, no_clobber: bool) -> Result<(), Error> {
+    if clobbered(dst, no_clobber) {
+        eprintln!("warning: {} exists, left as it is", dst);
+        return Ok(());
+    }
+    // before the video is probed and decoded, which may take long
+    check_destination(dst)?;
+    let video = probe_video(src, tools)?;
+    if let Some(line) = seed_line(options).filter(|_| !quiet) {
+        println!("{}", line);
+    }
+
+    // ffmpeg tells the container from the extension, which the temporary file keeps
+    let temporary = match Path::new(dst).extension() {
+        Some(extension) => temporary_path(dst).with_extension(extension),
+        None => temporary_path(dst),
+    };
+    let start = Instant::now();
+    let counter = if quiet { ProgressBar::hidden() } else { spinner("Pixelising") };
+    let pixelised = pixelise_frames(options, src, dst, &temporary, video, tools, &counter);
+    counter.finish_and_clear();
+    let count = pixelised.as_ref().map_or(0, |&count| count);
+    commit_output(&temporary, dst, pixelised.map(|_| ()), no_clobber)?;
+
+    if !quiet {
+        println!("Pixelised {} frames in {}ms", count, start.elapsed().as_millis());
+    }
+    Ok(())
+}
+
+///
+/// Pixelises the frames of the video of the given width, height and frame rate, encoded into `output`,
+/// and returns their number
+///
+fn pixelise_frames(options: &Options, src: &str, dst: &str, output: &Path, (width, height, rate): (u32, u32, String),
+                   tools: &Ffmpeg, counter: &ProgressBar) -> Result<usize, Error> {
+    let mut decoder = Command::new(tools.ffmpeg)
+        .args(["-v", "error", "-i", src, "-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Input(format!("cannot run {}: {}", tools.ffmpeg, e)))?;
+    // the frames are padded or cropped as the canvas is
+    let (fit_width, fit_height) = plan::fit_grid(width, height, options).unwrap_or((width, height));
+    let mut encoder = Command::new(tools.ffmpeg)
+        .args(["-v", "error", "-y", "-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(["-s", &format!("{}x{}", fit_width * options.scale, fit_height * options.scale), "-r", &rate, "-i", "-"])
+        .args(["-i", src, "-map", "0:v", "-map", "1:a?", "-c:a", "copy", "-pix_fmt", "yuv420p"])
+        .arg(output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Output(format!("cannot run {}: {}", tools.ffmpeg, e)))?;
+
+    let frame_size = width as usize * height as usize * 4;
+    let mut count = 0;
+    {
+        let mut frames = decoder.stdout.take().unwrap();
+        let mut encoded = BufWriter::new(encoder.stdin.take().unwrap());
+        loop {
+            let mut batch = Vec::new();
+            while batch.len() < rayon::current_num_threads() {
+                match read_frame(&mut frames, frame_size).map_err(|e| Error::read(src, e))? {
+                    Some(frame) => batch.push(frame),
+                    None => break,
+                }
+            }
+            if batch.is_empty() {
+                break;
+            }
+
+            let pixelised: Vec<RgbaImage> = batch.into_par_iter().map(|frame| {
+                let frame: RgbaImage = ImageBuffer::from_raw(width, height, frame).unwrap();
+                hexpxl::pixelise_with(&frame, options)
+            }).collect();
+            for frame in &pixelised {
+                encoded.write_all(frame).map_err(|e| Error::write(dst, e))?;
+            }
+            count += pixelised.len();
+            counter.set_message(format!("Pixelised {} frames", count));
+        }
+        encoded.flush().map_err(|e| Error::write(dst, e))?;
+    }
+
+    let decoded = decoder.wait().map_err(|e| Error::read(src, e))?;
+    if !decoded.success() {
+        return Err(Error::Input(format!("cannot decode {}: {} failed with {}", src, tools.ffmpeg, decoded)));
+    }
+    let encoded = encoder.wait().map_err(|e| Error::write(dst, e))?;
+    if !encoded.success() {
+        return Err(Error::Output(format!("cannot write {}: {} failed with {}", dst, tools.ffmpeg, encoded)));
+    }
+    Ok(count)
+}
+
+///
+/// The width, height and frame rate of the first video stream of the source
+///
+fn probe_video(src: &str, tools: &Ffmpeg) -> Result<(u32, u32, String), Error> {
+    let output = Command::new(tools.ffprobe)
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height,r_frame_rate", "-of", "csv=p=0", src])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| Error::Input(format!("cannot run {}: {}", tools.ffprobe, e)))?;
+    if !output.status.success() {
+        return Err(Error::Input(format!("cannot probe {}: {} failed with {}", src, tools.ffprobe, output.status)));
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = text.trim().split(',').collect();
+    match fields[..] {
+        [width, height, rate] => match (width.parse(), height.parse()) {
+            (Ok(width), Ok(height)) => Ok((width, height, rate.to_string())),
+            _ => Err(Error::Input(format!("cannot probe {}: unexpected size {}x{}", src, width, height))),
+        },
+        _ => Err(Error::UnsupportedFormat(format!("cannot probe {}: no video stream", src))),
+    }
+}
+
+///
+/// Reads the next frame, or nothing at the end of the stream
+///
+fn read_frame<R: Read>(reader: &mut R, frame_size: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut frame = vec![0; frame_size];
+    let mut filled = 0;
+    while filled < frame_size {
+        match reader.read(&mut frame[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated frame")),
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(Some(frame))
+}
+
+///
+/// What is captured of the screen
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Capture {
+    /// All the screens, or the one of the number, from 1
+    Screen(Option<u32>),
+    /// A region selected with the mouse
+    Region,
+}
+
+/// The capture matching screen, screen:N or region
+fn parse_capture(text: &str) -> Option<Capture> {
+    match text {
+        "screen" => Some(Capture::Screen(None)),
+        "region" => Some(Capture::Region),
+        _ => text.strip_prefix("screen:")
+            .and_then(|n| n.parse().ok())
+            .filter(|&n| n > 0)
+            .map(|n| Capture::Screen(Some(n))),
+    }
+}
+
+/// How often the screen is captured again by --watch
+const CAPTURE_INTERVAL: Duration = Duration::from_secs(1);
+
+///
+/// Captures the screen into a private temporary directory and runs `run` on the path of the capture,
+/// or with `watch` captures it again every `CAPTURE_INTERVAL` and runs `run` whenever it changed, until interrupted
+///
+/// The errors of `run` are printed when watching. The directory and the captures are deleted on every path,
+/// but for the interruption, which leaves the directory and at most the capture being pixelised behind.
+///
+fn with_captures<F>(capture: Capture, watch: bool, quiet: bool, mut run: F) -> Result<(), Error>
+    where F: FnMut(&str) -> Result<(), Error> {
+    let screenshot = Screenshot::new(capture, watch)?;
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("hexpxl-capture-");
+    // the captures show whatever is on the screen
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o700));
+    }
+    let directory = builder.tempdir()
+        .map_err(|e| Error::Output(format!("cannot create the directory of the capture: {}", e)))?;
+    let path = directory.path().join("capture.png");
+    let src = path.to_string_lossy();
+    if !watch {
+        screenshot.take(&path)?;
+        return run(&src);
+    }
+
+    let mut last = None;
+    loop {
+        screenshot.take(&path)?;
+        let data = fs::read(&path).map_err(|e| Error::read(&src, e))?;
+        if last.as_ref() != Some(&data) {
+            if let Err(e) = run(&src) {
+                eprintln!("{}", e);
+            }
+            if last.is_none() && !quiet {
+                println!("Watching the screen, interrupt to stop");
+            }
+            last = Some(data);
+        }
+        let _ = fs::remove_file(&path);
+        thread::sleep(CAPTURE_INTERVAL);
+    }
+}
+
+///
+/// How the screen is captured, the region being selected once so that the same one is captured again by --watch
+///
+struct Screenshot {
+    tool: &'static str,
+    args: Vec<String>,
+}
+
+impl Screenshot {
+    ///
+    /// The capture taken by screencapture on macOS, by grim, with slurp selecting the regions, on Wayland,
+    /// and by maim, with slop selecting the regions and xrandr finding the screens, on X11
+    ///
+    /// The regions of screencapture are selected as they are captured, so that they cannot be captured again.
+    ///
+    fn new(capture: Capture, watch: bool) -> Result<Screenshot, Error> {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        if cfg!(windows) {
+            Err(Error::Usage("the screen cannot be captured on Windows".to_string()))
+        } else if cfg!(target_os = "macos") {
+            // without the sound of the camera
+            let args = match capture {
+                Capture::Screen(None) => args(&["-x"]),
+                Capture::Screen(Some(n)) => args(&["-x", "-D", &n.to_string()]),
+                Capture::Region if watch => return Err(Error::Usage(
+                    "--watch cannot be used with --capture region on macOS, whose regions are selected for each capture".to_string()
+                )),
+                Capture::Region => args(&["-x", "-i"]),
+            };
+            Ok(Screenshot { tool: "screencapture", args })
+        } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+            let args = match capture {
+                Capture::Screen(None) => Vec::new(),
+                Capture::Screen(Some(_)) =>
+                    return Err(Error::Usage("the screens cannot be chosen on Wayland, use --capture region".to_string())),
+                Capture::Region => args(&["-g", &capture_tool("slurp", &[])?]),
+            };
+            Ok(Screenshot { tool: "grim", args })
+        } else {
+            let args = match capture {
+                Capture::Screen(None) => Vec::new(),
+                Capture::Screen(Some(n)) => args(&["-g", &monitor_geometry(n)?]),
+                Capture::Region => args(&["-g", &capture_tool("slop", &["-f", "%g"])?]),
+            };
+            Ok(Screenshot { tool: "maim", args })
+        }
+    }
+
+    /// Captures the screen into a PNG image at the path
+    fn take(&self, path: &Path) -> Result<(), Error> {
+        let status = Command::new(self.tool).args(&self.args).arg(path).status()
+            .map_err(|e| Error::Input(format!("cannot capture the screen: cannot run {}: {}", self.tool, e)))?;
+        if !status.success() {
+            return Err(Error::Input(format!("cannot capture the screen: {} failed with {}", self.tool, status)));
+        }
+        // screencapture succeeds without a file when the selection is cancelled
+        if !path.is_file() {
+            return Err(Error::Input("cannot capture the screen: the selection was cancelled".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// The output of a tool of the capture, trimmed
+fn capture_tool(tool: &str, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new(tool)
+        .args(args)
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| Error::Input(format!("cannot capture the screen: cannot run {}: {}", tool, e)))?;
+    if !output.status.success() {
+        return Err(Error::Input(format!("cannot capture the screen: {} failed with {}", tool, output.status)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+///
+/// The geometry of the Nth screen, from 1, as WxH+X+Y
+///
+/// xrandr lists the screens as ` 0: +*eDP-1 1920/344x1080/194+0+0  eDP-1`, with their sizes in pixels and in mm.
+///
+fn monitor_geometry(n: u32) -> Result<String, Error> {
+    let monitors = capture_tool("xrandr", &["--listmonitors"])?;
+    let count = monitors.lines().skip(1).count();
+    monitors.lines().skip(1).nth(n as usize - 1)
+        .and_then(|line| line.split_whitespace().nth(2))
+        .and_then(parse_monitor)
+        .ok_or_else(|| Error::Usage(format!("cannot capture screen {}: there are {} screens", n, count)))
+}
+
+/// The geometry WxH+X+Y of a screen listed as W/mmxH/mm+X+Y
+fn parse_monitor(text: &str) -> Option<String> {
+    let (width, rest) = text.split_once('x')?;
+    let (height, offset) = rest.split_once('+')?;
+    let width = width.split('/').next()?;
+    let height = height.split('/').next()?;
+    Some(format!("{}x{}+{}", width.parse::<u32>().ok()?, height.parse::<u32>().ok()?, offset))
+}
+
+/// The longest time a download may take
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+/// The largest image downloaded from the url of a source, in bytes
+const MAX_DOWNLOAD: u64 = 256 << 20;
+
+/// Whether the source is an http(s) url rather than a path
+fn is_url(src: &str) -> bool {
+    src.starts_with("http://") || src.starts_with("https://")
+}
+
+/// The error of a download of the url, no larger than `max_size` bytes
+fn download_error(url: &str, max_size: u64, e: ureq::Error) -> Error {
+    match e {
+        ureq::Error::BodyExceedsLimit(_) =>
+            Error::Input(format!("cannot download {}: it is larger than {}", url, format_memory(max_size))),
+        e => Error::Input(format!("cannot download {}: {}", url, e)),
+    }
+}
+
+/// The response of the url, once it answered with a success
+fn request_url(url: &str, max_size: u64) -> Result<ureq::http::Response<ureq::Body>, Error> {
+    let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(DOWNLOAD_TIMEOUT)).build().into();
+    agent.get(url).call().map_err(|e| download_error(url, max_size, e))
+}
+
+///
+/// Downloads the file at the url, failing when it is larger than `max_size` bytes
+///
+fn download(url: &str, max_size: u64) -> Result<Vec<u8>, Error> {
+    request_url(url, max_size)?
+        .body_mut().with_config().limit(max_size).read_to_vec().map_err(|e| download_error(url, max_size, e))
+}
+
+///
+/// Reads the file at the url as it is downloaded, failing when it is larger than `max_size` bytes
+///
+fn open_url(url: &str, max_size: u64) -> Result<impl Read, Error> {
+    Ok(request_url(url, max_size)?.into_body().into_with_config().limit(max_size).reader())
+}
+
+///
+/// Makes the contact sheet of an image pixelised with each mode and size
+///
+#[allow(clippy::too_many_arguments)]
+fn make_montage(
+    options: &Options,
+    src: &str,
+    dst: &str,
+    modes: &[PixelMode],
+    sizes: &[u32],
+    thumbnail_width: u32,
+    quiet: bool,
+    no_clobber: bool,
+) -> Result<(), Error> {
+    if clobbered(dst, no_clobber) {
+        eprintln!("warning: {} exists, left as it is", dst);
+        return Ok(());
+    }
+    let data = read_input(src)?;
+    let (img, _) = decode(src, &data, None)?;
+    let formats = Formats::default();
+    let format = output_format(dst, &formats)?;
+    if format == OutputFormat::Svg || format == OutputFormat::Text {
+        return Err(Error::UnsupportedFormat(format!("cannot write {}: SVG and text are not supported here", dst)));
+    }
+    check_destination(dst)?;
+
+    let start = Instant::now();
+    let sheet = hexpxl::montage::contact_sheet(&img.to_rgba(), options, modes, sizes, thumbnail_width);
+    write_new_output(dst, &encode(sheet, dst, format, &formats, &Metadata::default())?, no_clobber)?;
+    if !quiet {
+        println!("Montage: {} modes x {} sizes in {} ms", modes.len(), sizes.len(), start.elapsed().as_millis());
+    }
+    Ok(())
+}
+
+///
+/// Makes the cross-stitch pattern of an image, and saves its chart and its legend
+///
+fn stitch_pattern(options: &Options, src: &str, chart: &str, legend: &str, cell_size: u32, quiet: bool,
+                  no_clobber: bool) -> Result<(), Error> {
+    // the chart and the legend go together, neither is written when one of them exists
+    if let Some(dst) = [chart, legend].iter().find(|&&dst| clobbered(dst, no_clobber)) {
+        eprintln!("warning: {} exists, left as it is", dst);
+        return Ok(());
+    }
+    let data = read_input(src)?;
+    let (img, _) = decode(src, &data, None)?;
+
+    let raster = |dst: &str| match output_format(dst, &Formats::default())? {
+        OutputFormat::Raster(_) => Ok(()),
+        format => Err(Error::UnsupportedFormat(format!("cannot write {}: {:?} is not supported here", dst, format))),
+    };
+    raster(chart)?;
+    let pdf = Path::new(legend).extension().is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+    if !pdf {
+        raster(legend)?;
+    }
+    check_destination(chart)?;
+    check_destination(legend)?;
+    let pattern = hexpxl::stitch::stitch_pattern(&img.to_rgba(), options);
+
+    save_new_image(pattern.chart(cell_size), chart, no_clobber)?;
+    if pdf {
+        write_new_output(legend, &pattern.legend_pdf(), no_clobber)?;
+    } else {
+        save_new_image(pattern.legend(), legend, no_clobber)?;
+    }
+
+    if !quiet {
+        let (columns, rows) = pattern.dimensions();
+        println!("Pattern: {} x {} stitches, {} threads", columns, rows, pattern.threads().len());
+    }
+    Ok(())
+}
+
+///
+/// Measures the modes on the synthetic images of the sizes with each number of threads, printing each measure
+/// as it is done, and writes them as lines of JSON to `save`
+///
+/// The measures of the `baseline`, written by an earlier --save, are compared with the new ones.
+///
+fn run_bench(sizes: &[u32], modes: &[PixelMode], threads: &[usize], size: u32, runs: u32,
+             save: Option<&str>, baseline: Option<&str>) -> Result<(), Error> {
+    let baseline = match baseline {
+        Some(path) => bench::read_baseline(&fs::read_to_string(path).map_err(|e| Error::read(path, e))?),
+        None => HashMap::new(),
+    };
+    let mut measures = Vec::new();
+    for &side in sizes {
+        let img = bench::test_image(side, side);
+        for &count in threads {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(count).build()
+                .map_err(|e| Error::Usage(format!("cannot start the threads: {}", e)))?;
+            for &mode in modes {
+                let options = Options { mode, size, ..Options::default() };
+                let measure = bench::Measure {
+                    mode,
+                    width: side,
+                    height: side,
+                    threads: count,
+                    time: pool.install(|| bench::measure(&img, &options, runs)),
+                };
+                let comparison = match baseline.get(&measure.key()) {
+                    Some(&before) => format!("{:+7.1}%", (measure.throughput() / before - 1.0) * 100.0),
+                    None => String::new(),
+                };
+                let line = format!(
+                    "{:<12} {:>5} x {:<5} {:>3} threads {:>10.2} MPix/s {}",
+                    mode.to_string(), side, side, count, measure.throughput(), comparison
+                );
+                println!("{}", line.trim_end());
+                measures.push(measure);
+            }
+        }
+    }
+    if let Some(path) = save {
+        let lines: Vec<String> = measures.iter().map(|m| m.to_json() + "\n").collect();
+        fs::write(path, lines.concat()).map_err(|e| Error::write(path, e))?;
+    }
+    Ok(())
+}
+
+///
+/// Pixelises the reference images of the verify-golden command and compares them with their golden images,
+/// or writes them as the golden images with --update
+///
+fn run_verify(directory: &Path, tolerance: u8, update: bool) -> Result<(), Error> {
+    let cases = verify::cases();
+    if update {
+        fs::create_dir_all(directory).map_err(|e| Error::write(&directory.display().to_string(), e))?;
+    }
+    let mut mismatches = 0;
+    for case in &cases {
+        let path = verify::golden_path(directory, case);
+        let name = path.display().to_string();
+        let pixelised = case.render();
+        if update {
+            save_image(pixelised, &name)?;
+            continue;
+        }
+        let golden = image::open(&path).map_err(|e| Error::decode(&name, e))?.to_rgba();
+        match verify::differences(&pixelised, &golden, tolerance) {
+            Some(0) => println!("{:<24} ok", case.name),
+            Some(count) => {
+                println!("{:<24} {} pixels differ", case.name, count);
+                mismatches += 1;
+            }
+            None => {
+                println!("{:<24} {}x{} rather than {}x{}", case.name,
+                         pixelised.width(), pixelised.height(), golden.width(), golden.height());
+                mismatches += 1;
+            }
+        }
+    }
+    for name in verify::stale(directory, &cases) {
+        eprintln!("{} has no case", directory.join(name).display());
+    }
+    if mismatches > 0 {
+        return Err(Error::Mismatch(format!("{} images differ from their golden images", mismatches)));
+    }
+    Ok(())
+}
+
+///
+/// Checks that the pixels of the image have the hash, as printed by --print-hash
+///
+fn verify_hash(src: &str, expected: &str) -> Result<(), Error> {
+    if !verify::is_hash(expected) {
+        return Err(Error::Usage(format!("invalid hash '{}', which has 32 hexadecimal digits", expected)));
+    }
+    let hash = pixels_hash(src, &read_input(src)?)?;
+    if !hash.eq_ignore_ascii_case(expected) {
+        return Err(Error::Mismatch(format!("{}: the pixels have the hash {}, rather than {}", src, hash, expected)));
     }
-    return pixelised;
+    println!("{} ok", src);
+    Ok(())
 }
@@ -0,0 +1,61 @@
+use color::Channel;
+use image::{imageops, FilterType, GenericImageView, GrayImage, Rgba};
+use rayon::prelude::*;
+use RgbaBuffer;
+
+
+///
+/// The parts of the image to pixelise, white being pixelised and black left untouched
+///
+/// The grays blend the pixelised image with the original one.
+///
+#[derive(Debug, Clone)]
+pub struct Mask {
+    pub image: GrayImage,
+    /// The radius over which the edges of the mask are blurred, in pixels of the image
+    pub feather: f32,
+}
+
+impl Mask {
+    ///
+    /// The weight of the pixelised image at each pixel of an image of that size, from 0 to 255
+    ///
+    /// The mask is stretched to the size of the image when they differ.
+    ///
+    pub fn weights(&self, width: u32, height: u32) -> GrayImage {
+        let weights = if self.image.dimensions() == (width, height) {
+            self.image.clone()
+        } else {
+            imageops::resize(&self.image, width, height, FilterType::Triangle)
+        };
+        if self.feather > 0.0 {
+            // the blur fades out over about 2 standard deviations
+            imageops::blur(&weights, self.feather / 2.0)
+        } else {
+            weights
+        }
+    }
+}
+
+///
+/// Blends the pixelised image with the original one, weighted by the mask
+///
+pub fn blend<I, C>(img: &I, mut pixelised: RgbaBuffer<C>, mask: &Mask) -> RgbaBuffer<C>
+    where I: GenericImageView<Pixel=Rgba<C>> + Sync, C: Channel {
+    let (width, height) = img.dimensions();
+    let weights = mask.weights(width, height);
+    pixelised.par_chunks_mut(4 * width.max(1) as usize).zip(weights.par_chunks(width.max(1) as usize)).enumerate()
+        .for_each(|(y, (row, weights))| {
+            for (x, (pixel, &weight)) in row.chunks_mut(4).zip(weights).enumerate() {
+                if weight == 255 {
+                    continue;
+                }
+                let original = img.get_pixel(x as u32, y as u32);
+                let weight = weight as u32;
+                for c in 0..4 {
+                    pixel[c] = C::from_value((pixel[c].value() * weight + original[c].value() * (255 - weight) + 127) / 255);
+                }
+            }
+        });
+    pixelised
+}
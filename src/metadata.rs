@@ -0,0 +1,392 @@
+use std::io::{Cursor, Read, Write};
+
+use exif::experimental::Writer;
+use exif::{In, Reader, Tag, Value};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use qcms::{DataType, Intent, Profile, Transform};
+
+
+/// The identifier starting the APP1 segments of JPEG images holding EXIF fields
+const JPEG_EXIF: &[u8] = b"Exif\0\0";
+/// The identifier starting the APP1 segments of JPEG images holding an XMP packet
+const JPEG_XMP: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// The keyword of the iTXt chunks of PNG images holding an XMP packet
+const PNG_XMP: &[u8] = b"XML:com.adobe.xmp";
+/// The keyword of the iTXt chunks of PNG images holding the parameters of their pixelisation
+const PNG_PARAMETERS: &[u8] = b"hexpxl";
+/// The identifier starting the COM segments of JPEG images holding the parameters of their pixelisation
+const JPEG_PARAMETERS: &[u8] = b"hexpxl\0";
+/// The identifier starting the APP2 segments of JPEG images holding a part of an ICC profile
+const JPEG_ICC: &[u8] = b"ICC_PROFILE\0";
+/// The largest part of an ICC profile in an APP2 segment, after its length, identifier and numbers
+const JPEG_ICC_PART: usize = u16::MAX as usize - 2 - 14;
+
+///
+/// The metadata of an image kept in its pixelised image
+///
+/// The orientation is not kept, since the pixelised image is turned upright.
+/// The ICC profile is kept along with the colors it applies to, unless they are converted to sRGB.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// The EXIF orientation of the image, from 1 to 8, 1 being upright
+    pub orientation: u32,
+    /// The EXIF fields of the image but its orientation, as a TIFF structure.
+    /// The thumbnail is left out, since it would show the image before its pixelisation.
+    pub exif: Option<Vec<u8>>,
+    /// The XMP packet of the image, with its orientation set to upright
+    pub xmp: Option<Vec<u8>>,
+    /// The ICC profile of the colors of the image
+    pub icc: Option<Vec<u8>>,
+    /// The parameters the image was pixelised with, as a config file, which are not read from the source image
+    pub parameters: Option<String>,
+}
+
+impl Metadata {
+    ///
+    /// The metadata of the encoded image, read from its EXIF fields, its XMP packet and its ICC profile
+    ///
+    /// The metadata which cannot be read are left out, rather than failing.
+    /// The EXIF fields are read from the JPEG, PNG, TIFF, WebP and HEIF images,
+    /// and the XMP packets and ICC profiles from the JPEG, PNG and WebP ones.
+    ///
+    pub fn read(data: &[u8]) -> Metadata {
+        let mut metadata = Metadata {
+            orientation: 1,
+            exif: None,
+            xmp: xmp(data).map(upright_xmp),
+            icc: icc(data),
+            parameters: None,
+        };
+        if let Ok(exif) = Reader::new().read_from_container(&mut Cursor::new(data)) {
+            if let Some(orientation) = exif.get_field(Tag::Orientation, In::PRIMARY).and_then(|f| f.value.get_uint(0)) {
+                metadata.orientation = orientation;
+            }
+            let mut writer = Writer::new();
+            for field in exif.fields().filter(|f| f.ifd_num == In::PRIMARY && f.tag != Tag::Orientation) {
+                writer.push_field(field);
+            }
+            let mut tiff = Cursor::new(Vec::new());
+            // fails when there are no other fields
+            if writer.write(&mut tiff, exif.little_endian()).is_ok() {
+                metadata.exif = Some(tiff.into_inner());
+            }
+        }
+        metadata
+    }
+
+    /// Whether there are metadata to write in the pixelised image
+    pub fn is_empty(&self) -> bool {
+        self.exif.is_none() && self.xmp.is_none() && self.icc.is_none() && self.parameters.is_none()
+    }
+
+    ///
+    /// The metadata without the EXIF fields, the XMP packet and the parameters, which may tell the paths of the files
+    /// they read, keeping the ICC profile the colors depend on
+    ///
+    pub fn stripped(&self) -> Metadata {
+        Metadata { icc: self.icc.clone(), ..Metadata::default() }
+    }
+}
+
+///
+/// The horizontal resolution of the encoded image, in dots per inch
+///
+/// It is read from the pHYs chunk of the PNG images and from the JFIF segment of the JPEG images,
+/// else from the EXIF fields. None when the image gives none, or only the ratio of its pixels.
+///
+pub fn resolution(data: &[u8]) -> Option<f32> {
+    let dpi = if data.starts_with(&[0xff, 0xd8]) {
+        // the version, the unit and the horizontal and vertical densities
+        jpeg_segments(data).find(|&(marker, segment)| marker == 0xe0 && segment.len() >= 12 && segment.starts_with(b"JFIF\0"))
+            .and_then(|(_, segment)| {
+                let density = u16::from_be_bytes([segment[8], segment[9]]) as f32;
+                match segment[7] {
+                    1 => Some(density),
+                    2 => Some(density * 2.54),
+                    _ => None,
+                }
+            })
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        // the horizontal and vertical pixels per unit, and the unit, the meter or none
+        png_chunks(data).find(|&(kind, chunk)| kind == b"pHYs" && chunk.len() == 9 && chunk[8] == 1)
+            .map(|(_, chunk)| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f32 * 0.0254)
+    } else {
+        None
+    };
+    dpi.or_else(|| {
+        let exif = Reader::new().read_from_container(&mut Cursor::new(data)).ok()?;
+        let resolution = match exif.get_field(Tag::XResolution, In::PRIMARY)?.value {
+            Value::Rational(ref values) if !values.is_empty() => values[0].to_f64() as f32,
+            _ => return None,
+        };
+        // in inches by default
+        match exif.get_field(Tag::ResolutionUnit, In::PRIMARY).and_then(|f| f.value.get_uint(0)).unwrap_or(2) {
+            2 => Some(resolution),
+            3 => Some(resolution * 2.54),
+            _ => None,
+        }
+    }).filter(|&dpi| dpi.is_finite() && dpi > 0.0)
+}
+
+///
+/// The image with its colors converted from the ICC profile to sRGB
+///
+/// None when the profile is not an RGB profile or cannot be read.
+///
+pub fn to_srgb(mut img: RgbaImage, icc: &[u8]) -> Option<RgbaImage> {
+    let profile = Profile::new_from_slice(icc, false)?;
+    let mut srgb = Profile::new_sRGB();
+    srgb.precache_output_transform();
+    let transform = Transform::new(&profile, &srgb, DataType::RGBA8, Intent::Perceptual)?;
+    transform.apply(&mut img);
+    Some(img)
+}
+
+///
+/// The image turned upright according to its EXIF orientation
+///
+pub fn orient(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        // transposed
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        // transversed
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+///
+/// Writes the metadata into the encoded image
+///
+/// Only the PNG and JPEG images can hold them, the other ones being returned as they are.
+///
+pub fn embed(encoded: Vec<u8>, format: ImageFormat, metadata: &Metadata) -> Vec<u8> {
+    match format {
+        ImageFormat::PNG => embed_png(encoded, metadata),
+        ImageFormat::JPEG => embed_jpeg(encoded, metadata),
+        _ => encoded,
+    }
+}
+
+///
+/// The parameters a JPEG or PNG image was pixelised with, as written by `embed`
+///
+pub fn parameters(data: &[u8]) -> Option<String> {
+    let parameters = if data.starts_with(&[0xff, 0xd8]) {
+        jpeg_segments(data).find(|&(marker, segment)| marker == 0xfe && segment.starts_with(JPEG_PARAMETERS))
+            .map(|(_, segment)| segment[JPEG_PARAMETERS.len()..].to_vec())
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_text(data, PNG_PARAMETERS)
+    } else {
+        None
+    };
+    parameters.and_then(|parameters| String::from_utf8(parameters).ok())
+}
+
+/// The XMP packet of a JPEG, PNG or WebP image
+fn xmp(data: &[u8]) -> Option<Vec<u8>> {
+    if data.starts_with(&[0xff, 0xd8]) {
+        jpeg_segments(data).find(|&(marker, segment)| marker == 0xe1 && segment.starts_with(JPEG_XMP))
+            .map(|(_, segment)| segment[JPEG_XMP.len()..].to_vec())
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_text(data, PNG_XMP)
+    } else {
+        webp_chunk(data, b"XMP ").map(|chunk| chunk.to_vec())
+    }
+}
+
+/// The text of the first uncompressed iTXt chunk of a PNG image with that keyword
+fn png_text(data: &[u8], keyword: &[u8]) -> Option<Vec<u8>> {
+    png_chunks(data).filter(|&(kind, _)| kind == b"iTXt").find_map(|(_, chunk)| {
+        // the keyword, the compression flag and method, the language and the translated keyword
+        let rest = chunk.strip_prefix(keyword)?.strip_prefix(b"\0\0\0")?;
+        let language = rest.iter().position(|&b| b == 0)?;
+        let rest = &rest[language + 1..];
+        let translated = rest.iter().position(|&b| b == 0)?;
+        Some(rest[translated + 1..].to_vec())
+    })
+}
+
+/// The ICC profile of a JPEG, PNG or WebP image
+fn icc(data: &[u8]) -> Option<Vec<u8>> {
+    if data.starts_with(&[0xff, 0xd8]) {
+        // the profile is split into numbered parts, from 1
+        let mut parts: Vec<(u8, &[u8])> = jpeg_segments(data)
+            .filter(|&(marker, segment)| marker == 0xe2 && segment.len() >= 14 && segment.starts_with(JPEG_ICC))
+            .map(|(_, segment)| (segment[12], &segment[14..]))
+            .collect();
+        if parts.is_empty() {
+            return None;
+        }
+        parts.sort_by_key(|&(number, _)| number);
+        Some(parts.iter().flat_map(|&(_, part)| part.iter().cloned()).collect())
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_chunks(data).find(|&(kind, _)| kind == b"iCCP").and_then(|(_, chunk)| {
+            // the name of the profile and the compression method, before the compressed profile
+            let name = chunk.iter().position(|&b| b == 0)?;
+            let mut profile = Vec::new();
+            ZlibDecoder::new(chunk.get(name + 2..)?).read_to_end(&mut profile).ok()?;
+            Some(profile)
+        })
+    } else {
+        webp_chunk(data, b"ICCP").map(|chunk| chunk.to_vec())
+    }
+}
+
+/// The content of the first chunk of that type of a WebP image
+fn webp_chunk<'a>(data: &'a [u8], kind: &[u8]) -> Option<&'a [u8]> {
+    if data.len() < 12 || !data.starts_with(b"RIFF") || &data[8..12] != b"WEBP" {
+        return None;
+    }
+    let mut position = 12;
+    while position + 8 <= data.len() {
+        let size = u32::from_le_bytes([data[position + 4], data[position + 5], data[position + 6], data[position + 7]]) as usize;
+        let chunk = data.get(position + 8..position + 8 + size)?;
+        if &data[position..position + 4] == kind {
+            return Some(chunk);
+        }
+        // the chunks are padded to an even size
+        position += 8 + size + size % 2;
+    }
+    None
+}
+
+/// The XMP packet with its TIFF orientation, if any, set to upright
+fn upright_xmp(xmp: Vec<u8>) -> Vec<u8> {
+    let mut xmp = match String::from_utf8(xmp) {
+        Ok(xmp) => xmp,
+        Err(e) => return e.into_bytes(),
+    };
+    // as an attribute or as an element
+    for &(open, close) in &[("tiff:Orientation=\"", "\""), ("tiff:Orientation='", "'"), ("<tiff:Orientation>", "<")] {
+        if let Some(start) = xmp.find(open).map(|i| i + open.len()) {
+            if let Some(end) = xmp[start..].find(close).map(|i| start + i) {
+                xmp.replace_range(start..end, "1");
+            }
+        }
+    }
+    xmp.into_bytes()
+}
+
+/// The markers and the contents of the segments of the header of a JPEG image, until its scan
+fn jpeg_segments(data: &[u8]) -> impl Iterator<Item=(u8, &[u8])> {
+    let mut position = 2;
+    std::iter::from_fn(move || {
+        // the markers may be padded with 0xff
+        while data.get(position) == Some(&0xff) && data.get(position + 1) == Some(&0xff) {
+            position += 1;
+        }
+        let marker = *data.get(position + 1)?;
+        // the scan and the end of the image have no length
+        if data[position] != 0xff || marker == 0xda || marker == 0xd9 {
+            return None;
+        }
+        let length = u16::from_be_bytes([*data.get(position + 2)?, *data.get(position + 3)?]) as usize;
+        let segment = data.get(position + 4..position + 2 + length.max(2))?;
+        position += 2 + length;
+        Some((marker, segment))
+    })
+}
+
+/// The types and the contents of the chunks of a PNG image
+fn png_chunks(data: &[u8]) -> impl Iterator<Item=(&[u8], &[u8])> {
+    let mut position = 8;
+    std::iter::from_fn(move || {
+        let length = u32::from_be_bytes([
+            *data.get(position)?, *data.get(position + 1)?, *data.get(position + 2)?, *data.get(position + 3)?,
+        ]) as usize;
+        let kind = data.get(position + 4..position + 8)?;
+        let chunk = data.get(position + 8..position + 8 + length)?;
+        // the length, the type, the content and the CRC
+        position += 12 + length;
+        Some((kind, chunk))
+    })
+}
+
+/// The PNG image with iCCP, eXIf and iTXt chunks of the metadata, after its header
+fn embed_png(encoded: Vec<u8>, metadata: &Metadata) -> Vec<u8> {
+    let mut chunks = Vec::new();
+    let mut write_chunk = |kind: &[u8], content: &[u8]| {
+        let mut typed = kind.to_vec();
+        typed.extend_from_slice(content);
+        chunks.extend_from_slice(&(content.len() as u32).to_be_bytes());
+        chunks.extend_from_slice(&typed);
+        chunks.extend_from_slice(&crc32fast::hash(&typed).to_be_bytes());
+    };
+    if let Some(ref icc) = metadata.icc {
+        let mut content = b"ICC profile\0\0".to_vec();
+        let mut encoder = ZlibEncoder::new(content, Compression::default());
+        // writing into memory does not fail
+        encoder.write_all(icc).unwrap();
+        content = encoder.finish().unwrap();
+        write_chunk(b"iCCP", &content);
+    }
+    if let Some(ref exif) = metadata.exif {
+        write_chunk(b"eXIf", exif);
+    }
+    let xmp = metadata.xmp.as_deref().map(|xmp| (PNG_XMP, xmp));
+    let parameters = metadata.parameters.as_ref().map(|parameters| (PNG_PARAMETERS, parameters.as_bytes()));
+    for (keyword, text) in xmp.into_iter().chain(parameters) {
+        // uncompressed, without language nor translated keyword
+        let mut content = keyword.to_vec();
+        content.extend_from_slice(b"\0\0\0\0\0");
+        content.extend_from_slice(text);
+        write_chunk(b"iTXt", &content);
+    }
+
+    // the signature and the IHDR chunk
+    let header = 8 + 12 + 13;
+    let mut png = encoded[..header].to_vec();
+    png.extend_from_slice(&chunks);
+    png.extend_from_slice(&encoded[header..]);
+    png
+}
+
+/// The JPEG image with APP1, APP2 and COM segments of the metadata, after its JFIF segment
+fn embed_jpeg(encoded: Vec<u8>, metadata: &Metadata) -> Vec<u8> {
+    let mut segments = Vec::new();
+    let exif = metadata.exif.as_deref().map(|exif| (0xe1, JPEG_EXIF, exif));
+    let xmp = metadata.xmp.as_deref().map(|xmp| (0xe1, JPEG_XMP, xmp));
+    let parameters = metadata.parameters.as_ref().map(|parameters| (0xfe, JPEG_PARAMETERS, parameters.as_bytes()));
+    for (marker, identifier, content) in exif.into_iter().chain(xmp).chain(parameters) {
+        // the length counts itself, and cannot exceed 16 bits
+        let length = 2 + identifier.len() + content.len();
+        if length <= u16::MAX as usize {
+            segments.extend_from_slice(&[0xff, marker]);
+            segments.extend_from_slice(&(length as u16).to_be_bytes());
+            segments.extend_from_slice(identifier);
+            segments.extend_from_slice(content);
+        }
+    }
+    if let Some(ref icc) = metadata.icc {
+        let count = icc.len().div_ceil(JPEG_ICC_PART);
+        // there can be at most 255 parts
+        if count <= u8::MAX as usize {
+            for (i, part) in icc.chunks(JPEG_ICC_PART).enumerate() {
+                segments.extend_from_slice(&[0xff, 0xe2]);
+                segments.extend_from_slice(&((2 + JPEG_ICC.len() + 2 + part.len()) as u16).to_be_bytes());
+                segments.extend_from_slice(JPEG_ICC);
+                segments.extend_from_slice(&[i as u8 + 1, count as u8]);
+                segments.extend_from_slice(part);
+            }
+        }
+    }
+
+    let header = match jpeg_segments(&encoded).next() {
+        Some((0xe0, jfif)) => 2 + 4 + jfif.len(),
+        _ => 2,
+    };
+    let mut jpeg = encoded[..header].to_vec();
+    jpeg.extend_from_slice(&segments);
+    jpeg.extend_from_slice(&encoded[header..]);
+    jpeg
+}
@@ -0,0 +1,66 @@
+use font::{draw_text, text_width, HEIGHT};
+use image::{imageops, FilterType, GenericImageView, Rgba, RgbaImage};
+use rayon::prelude::*;
+use {pixelise_with, Options, PixelMode};
+
+
+/// The scale of the text of the labels
+const TEXT_SCALE: u32 = 2;
+/// The space around and between the thumbnails, in pixels
+const MARGIN: u32 = 16;
+/// The space between a thumbnail and its label, in pixels
+const LABEL_GAP: u32 = 6;
+
+const BACKGROUND: Rgba<u8> = Rgba { data: [32, 32, 32, 255] };
+const TEXT: Rgba<u8> = Rgba { data: [224, 224, 224, 255] };
+
+///
+/// Pixelises the image with each mode and size, and lays the results out on a contact sheet
+///
+/// Each mode is a row of the sheet and each size a column, the images being labeled with their mode and size.
+/// The images are pixelised at the full resolution, then scaled down to thumbnails of `thumbnail_width` pixels,
+/// or left as they are when they are narrower.
+///
+/// # Arguments
+///
+/// * `img` - The input image
+/// * `options` - The settings of all the images, but their modes and sizes
+/// * `modes` - The modes of the rows
+/// * `sizes` - The sizes of the pixels of the columns
+/// * `thumbnail_width` - The largest width of the images on the sheet
+///
+pub fn contact_sheet<I>(img: &I, options: &Options, modes: &[PixelMode], sizes: &[u32], thumbnail_width: u32) -> RgbaImage
+    where I: GenericImageView<Pixel=Rgba<u8>> + Sync {
+    let (width, height) = img.dimensions();
+    let thumbnail_width = thumbnail_width.min(width).max(1);
+    let thumbnail_height = ((height as u64 * thumbnail_width as u64) / width.max(1) as u64).max(1) as u32;
+
+    let settings: Vec<(PixelMode, u32)> = modes.iter().flat_map(|&mode| sizes.iter().map(move |&size| (mode, size))).collect();
+    // each pixelisation is already parallel, this only keeps the threads busy between them
+    let thumbnails: Vec<RgbaImage> = settings.par_iter().map(|&(mode, size)| {
+        let pixelised = pixelise_with(img, &Options { mode, size, columns: None, rows: None, ..options.clone() });
+        if thumbnail_width == width {
+            pixelised
+        } else {
+            imageops::resize(&pixelised, thumbnail_width, thumbnail_height, FilterType::Triangle)
+        }
+    }).collect();
+
+    let label_height = HEIGHT * TEXT_SCALE;
+    let (cell_width, cell_height) = (thumbnail_width + MARGIN, thumbnail_height + LABEL_GAP + label_height + MARGIN);
+    let mut sheet = RgbaImage::from_pixel(
+        MARGIN + sizes.len() as u32 * cell_width,
+        MARGIN + modes.len() as u32 * cell_height,
+        BACKGROUND,
+    );
+    for (i, (thumbnail, &(mode, size))) in thumbnails.iter().zip(&settings).enumerate() {
+        let (column, row) = (i as u32 % sizes.len() as u32, i as u32 / sizes.len() as u32);
+        let (left, top) = (MARGIN + column * cell_width, MARGIN + row * cell_height);
+        imageops::overlay(&mut sheet, thumbnail, left, top);
+
+        let label = format!("{} {}", mode, size);
+        let label_left = left + thumbnail_width.saturating_sub(text_width(&label, TEXT_SCALE)) / 2;
+        draw_text(&mut sheet, &label, label_left, top + thumbnail_height + LABEL_GAP, TEXT_SCALE, TEXT);
+    }
+    sheet
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use color::{narrow, widen, Channel};
+use image::{imageops, FilterType, GenericImageView, Rgba, RgbaImage};
+use rayon::prelude::*;
+
+
+/// The side of the thumbnails of the tiles, in pixels, which are shrunk again to the size of the cells
+const THUMBNAIL_SIDE: u32 = 128;
+
+///
+/// The images which the cells of the photomosaic mode are replaced with, matched by their mean colors
+///
+/// The images are kept as square thumbnails, cropped around their centers.
+///
+#[derive(Debug, Clone)]
+pub struct Tiles {
+    /// Shared, as the options holding them are cloned for the regions and the channels
+    tiles: Arc<Vec<Tile>>,
+}
+
+#[derive(Debug)]
+struct Tile {
+    thumbnail: RgbaImage,
+    /// The mean red, green and blue of the thumbnail
+    mean: [u8; 3],
+}
+
+impl Tiles {
+    ///
+    /// The tiles made of the images, None when there are none
+    ///
+    pub fn new(images: Vec<RgbaImage>) -> Option<Tiles> {
+        if images.is_empty() {
+            return None;
+        }
+        let tiles = images.into_par_iter().map(|image| {
+            let thumbnail = thumbnail(&image);
+            let count = (thumbnail.width() * thumbnail.height()).max(1) as u64;
+            let mut sums = [0u64; 3];
+            for pixel in thumbnail.pixels() {
+                for c in 0..3 {
+                    sums[c] += pixel[c] as u64;
+                }
+            }
+            let mean = [(sums[0] / count) as u8, (sums[1] / count) as u8, (sums[2] / count) as u8];
+            Tile { thumbnail, mean }
+        }).collect();
+        Some(Tiles { tiles: Arc::new(tiles) })
+    }
+
+    ///
+    /// The index of the tile whose mean color is the closest to `color`
+    ///
+    pub fn nearest(&self, color: Rgba<u8>) -> usize {
+        let distance = |tile: &Tile| -> u32 {
+            (0..3).map(|i| sqr!(tile.mean[i] as i32 - color[i] as i32) as u32).sum()
+        };
+        (0..self.tiles.len()).min_by_key(|&i| distance(&self.tiles[i])).unwrap()
+    }
+}
+
+/// The image cropped to a square around its center, and shrunk to the side of the thumbnails when it is larger
+fn thumbnail(image: &RgbaImage) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let side = width.min(height);
+    if side == 0 {
+        return RgbaImage::new(1, 1);
+    }
+    let square = image.view((width - side) / 2, (height - side) / 2, side, side).to_image();
+    if side > THUMBNAIL_SIDE {
+        imageops::resize(&square, THUMBNAIL_SIDE, THUMBNAIL_SIDE, FilterType::Triangle)
+    } else {
+        square
+    }
+}
+
+///
+/// The tiles chosen for the cells of a pixelisation, shrunk to the size of the cells
+///
+pub(crate) struct Mosaic {
+    /// The tile of each cell
+    choices: Vec<usize>,
+    /// The tiles of the cells, by their index
+    tiles: HashMap<usize, RgbaImage>,
+    /// The width and the height of the cells, in pixels
+    cell: (f32, f32),
+}
+
+impl Mosaic {
+    ///
+    /// The tiles closest to the colors of the cells, shrunk to cells of that width and height
+    ///
+    pub(crate) fn new<C: Channel>(tiles: &Tiles, colors: &[Rgba<C>], (width, height): (u32, u32)) -> Mosaic {
+        let choices: Vec<usize> = colors.par_iter().map(|&color| tiles.nearest(narrow(color))).collect();
+        let mut used: Vec<usize> = choices.clone();
+        used.sort_unstable();
+        used.dedup();
+        let (width, height) = (width.max(1), height.max(1));
+        let resized = used.into_par_iter()
+            .map(|i| (i, imageops::resize(&tiles.tiles[i].thumbnail, width, height, FilterType::Triangle)))
+            .collect();
+        Mosaic { choices, tiles: resized, cell: (width as f32, height as f32) }
+    }
+
+    ///
+    /// The color of the pixel at (x, y) of the cell, whose bounding box is centered on `center`, taken from its tile
+    ///
+    /// The tile is laid upright over the cell, and the transparency of the cell is kept.
+    ///
+    pub(crate) fn color<C: Channel>(&self, cell: usize, center: (f32, f32), (x, y): (u32, u32), color: Rgba<C>) -> Rgba<C> {
+        let tile = &self.tiles[&self.choices[cell]];
+        let (width, height) = tile.dimensions();
+        let u = (x as f32 + 0.5 - center.0) / self.cell.0 + 0.5;
+        let v = (y as f32 + 0.5 - center.1) / self.cell.1 + 0.5;
+        let pixel = tile.get_pixel(
+            (u * width as f32).max(0.0).min(width as f32 - 1.0) as u32,
+            (v * height as f32).max(0.0).min(height as f32 - 1.0) as u32,
+        );
+        let alpha = (pixel[3] as u32 * narrow(color)[3] as u32 + 127) / 255;
+        widen(Rgba([pixel[0], pixel[1], pixel[2], alpha as u8]))
+    }
+}
@@ -0,0 +1,191 @@
+use color::parse_color;
+use image::Rgba;
+
+
+const PICO8: &[&str] = &[
+    "000000", "1d2b53", "7e2553", "008751", "ab5236", "5f574f", "c2c3c7", "fff1e8",
+    "ff004d", "ffa300", "ffec27", "00e436", "29adff", "83769c", "ff77a8", "ffccaa",
+];
+
+const NES: &[&str] = &[
+    "7c7c7c", "0000fc", "0000bc", "4428bc", "940084", "a80020", "a81000", "881400",
+    "503000", "007800", "006800", "005800", "004058", "000000", "bcbcbc", "0078f8",
+    "0058f8", "6844fc", "d800cc", "e40058", "f83800", "e45c10", "ac7c00", "00b800",
+    "00a800", "00a844", "008888", "f8f8f8", "3cbcfc", "6888fc", "9878f8", "f878f8",
+    "f85898", "f87858", "fca044", "f8b800", "b8f818", "58d854", "58f898", "00e8d8",
+    "787878", "fcfcfc", "a4e4fc", "b8b8f8", "d8b8f8", "f8b8f8", "f8a4c0", "f0d0b0",
+    "fce0a8", "f8d878", "d8f878", "b8f8b8", "b8f8d8", "00fcfc", "f8d8f8",
+];
+
+const GAMEBOY: &[&str] = &["0f380f", "306230", "8bac0f", "9bbc0f"];
+
+///
+/// A limited set of colors
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    colors: Vec<Rgba<u8>>,
+}
+
+impl Palette {
+    /// The palette of the colors
+    pub fn new(colors: Vec<Rgba<u8>>) -> Palette {
+        Palette { colors }
+    }
+
+    /// The names of the built-in palettes
+    pub fn names() -> [&'static str; 3] {
+        ["pico8", "nes", "gameboy"]
+    }
+
+    ///
+    /// One of the built-in palettes
+    ///
+    pub fn named(name: &str) -> Option<Palette> {
+        let colors = match &name.to_ascii_lowercase()[..] {
+            "pico8" => PICO8,
+            "nes" => NES,
+            "gameboy" => GAMEBOY,
+            _ => return None,
+        };
+        Some(Palette { colors: colors.iter().map(|c| parse_color(c).unwrap()).collect() })
+    }
+
+    ///
+    /// Reads an Adobe swatch exchange file, or the text of a palette as read by `parse`
+    ///
+    pub fn read(bytes: &[u8]) -> Result<Palette, String> {
+        if bytes.starts_with(b"ASEF") {
+            return parse_ase(bytes);
+        }
+        let text = std::str::from_utf8(bytes).map_err(|_| "the palette is neither text nor a swatch exchange file".to_string())?;
+        Palette::parse(text)
+    }
+
+    ///
+    /// Reads a GIMP palette, or a list of colors written as `#rrggbb` or `rrggbb`, one per line, as in the `.hex` files
+    ///
+    /// Empty lines and lines starting with `;` are ignored in the lists of colors.
+    ///
+    pub fn parse(text: &str) -> Result<Palette, String> {
+        let mut lines = text.lines().map(str::trim).enumerate();
+        let gimp = text.trim_start().starts_with("GIMP Palette");
+        if gimp {
+            lines.next();
+        }
+
+        let mut colors = Vec::new();
+        for (number, line) in lines {
+            let invalid = || format!("invalid color at line {}: {}", number + 1, line);
+            if gimp {
+                // the header's fields and the comments, then "red green blue name"
+                if line.is_empty() || line.starts_with('#') || line.contains(':') {
+                    continue;
+                }
+                let channels: Vec<u8> = line.split_whitespace().take(3)
+                    .map(|c| c.parse().map_err(|_| invalid()))
+                    .collect::<Result<_, _>>()?;
+                if channels.len() < 3 {
+                    return Err(invalid());
+                }
+                colors.push(Rgba([channels[0], channels[1], channels[2], 255]));
+            } else {
+                if line.is_empty() || line.starts_with(';') {
+                    continue;
+                }
+                colors.push(parse_color(line).ok_or_else(invalid)?);
+            }
+        }
+
+        if colors.is_empty() {
+            return Err("the palette has no colors".to_string());
+        }
+        Ok(Palette { colors })
+    }
+
+    pub fn colors(&self) -> &[Rgba<u8>] {
+        &self.colors
+    }
+
+    ///
+    /// The color of the palette closest to `color`, keeping the transparency of `color`
+    ///
+    pub fn nearest(&self, color: Rgba<u8>) -> Rgba<u8> {
+        let distance = |c: &Rgba<u8>| -> u32 {
+            (0..3).map(|i| sqr!(c[i] as i32 - color[i] as i32) as u32).sum()
+        };
+        let closest = self.colors.iter().min_by_key(|c| distance(c)).unwrap();
+        Rgba([closest[0], closest[1], closest[2], color[3]])
+    }
+}
+
+/// The block of a color of a swatch exchange file, the others opening and closing the groups of colors
+const ASE_COLOR: u16 = 0x0001;
+
+///
+/// Reads the colors of an Adobe swatch exchange file, in all its groups
+///
+/// The file is big endian: the signature `ASEF`, its version on 2 x 2 bytes and its number of blocks on 4 bytes,
+/// then the blocks, each with its type on 2 bytes and its length on 4 bytes. A color block holds the name of the color,
+/// its length on 2 bytes then its UTF-16 characters, the model of the color on 4 bytes, its channels as floats
+/// and its type on 2 bytes.
+///
+fn parse_ase(bytes: &[u8]) -> Result<Palette, String> {
+    let truncated = || "the swatch exchange file is truncated".to_string();
+    let u16_at = |i: usize| bytes.get(i..i + 2).map(|b| u16::from_be_bytes([b[0], b[1]])).ok_or_else(truncated);
+    let u32_at = |i: usize| bytes.get(i..i + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]])).ok_or_else(truncated);
+    let f32_at = |i: usize| u32_at(i).map(f32::from_bits);
+
+    let blocks = u32_at(8)?;
+    let mut colors = Vec::new();
+    let mut position = 12;
+    for _ in 0..blocks {
+        let kind = u16_at(position)?;
+        let length = u32_at(position + 2)? as usize;
+        let start = position + 6;
+        position = start.checked_add(length).filter(|&end| end <= bytes.len()).ok_or_else(truncated)?;
+        if kind != ASE_COLOR {
+            continue;
+        }
+
+        let model_at = start + 2 + 2 * u16_at(start)? as usize;
+        let model = bytes.get(model_at..model_at + 4).ok_or_else(truncated)?;
+        let channels_at = model_at + 4;
+        let channel = |c: usize| f32_at(channels_at + 4 * c);
+        let rgb = match model {
+            b"RGB " => [channel(0)?, channel(1)?, channel(2)?],
+            b"Gray" => [channel(0)?; 3],
+            b"CMYK" => {
+                let [c, m, y, k] = [channel(0)?, channel(1)?, channel(2)?, channel(3)?];
+                [c, m, y].map(|v| (1.0 - v) * (1.0 - k))
+            }
+            b"LAB " => lab_to_rgb(channel(0)? * 100.0, channel(1)?, channel(2)?),
+            _ => return Err(format!("unknown color model '{}' in the swatch exchange file", String::from_utf8_lossy(model))),
+        };
+        let value = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        colors.push(Rgba([value(rgb[0]), value(rgb[1]), value(rgb[2]), 255]));
+    }
+
+    if colors.is_empty() {
+        return Err("the palette has no colors".to_string());
+    }
+    Ok(Palette { colors })
+}
+
+///
+/// The sRGB channels, from 0 to 1, of the CIE L*a*b* color under the D50 white of the swatch exchange files
+///
+fn lab_to_rgb(l: f32, a: f32, b: f32) -> [f32; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let (fx, fz) = (fy + a / 500.0, fy - b / 200.0);
+    let inverse = |f: f32| if f > 6.0 / 29.0 { f * f * f } else { 3.0 * sqr!(6.0 / 29.0) * (f - 4.0 / 29.0) };
+    let (x, y, z) = (0.9642 * inverse(fx), inverse(fy), 0.8249 * inverse(fz));
+
+    // the Bradford adapted matrix from XYZ under D50 to linear sRGB
+    let linear = [
+        3.1339 * x - 1.6169 * y - 0.4906 * z,
+        -0.9788 * x + 1.9161 * y + 0.0335 * z,
+        0.0719 * x - 0.2290 * y + 1.4052 * z,
+    ];
+    linear.map(|v| if v <= 0.0031308 { 12.92 * v } else { 1.055 * v.max(0.0).powf(1.0 / 2.4) - 0.055 })
+}
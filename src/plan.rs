@@ -0,0 +1,395 @@
+use std::f32::consts::PI;
+use std::mem::size_of;
+
+use tessellation::Scales;
+use {grid, stretched_side, wrap_margin, FitGrid, Options, Orientation, PixelMode, Sampling};
+
+
+///
+/// What the pixelisation of an image would be made of, found from its size without pixelising it
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plan {
+    /// The radius of the circle inscribed in the cells, in pixels, None when their shapes vary
+    pub inner_radius: Option<f32>,
+    /// The distance between 2 adjacent rows of cells, in pixels, None when the cells are not in rows
+    pub row_spacing: Option<f32>,
+    /// The numbers of columns and rows of cells overlapping the image, before the grid is rotated
+    pub grid: Option<(u32, u32)>,
+    /// The number of cells overlapping the image, None for the quadtree and lowpoly modes, whose cells depend on its colors
+    pub cells: Option<u64>,
+    /// The memory the pixelisation takes, in bytes, roughly
+    pub memory: u64,
+}
+
+///
+/// What pixelising an image of that size with the options would be made of
+///
+/// # Arguments
+///
+/// * `width`, `height` - The size of the image, in pixels
+/// * `options` - The pixelisation settings
+/// * `channel_bytes` - The size of the channels of the image, 1 for 8 bits and 2 for 16 bits
+///
+pub fn plan(width: u32, height: u32, options: &Options, channel_bytes: u64) -> Plan {
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    // the regular grids are counted before they are squeezed to the width and the height of their cells
+    let (side, factors) = stretched_side(options);
+    let side = side.max(1) as f32;
+    let (grid_width, grid_height) = ((width as f32 * factors.0).ceil() as u32, (height as f32 * factors.1).ceil() as u32);
+    // the number of cells of `step` pixels, centered `center` pixels after their start, overlapping `length` pixels
+    let count = |length: u32, step: f32, center: f32| ((length as f32 + center) / step).ceil() as u32;
+    let flat = options.orientation == Orientation::flat;
+    // the hexagons have a row every 3/2 of their outer radius
+    let hexagons = || {
+        let inner_radius = side * (PI / 6.0).cos();
+        let (along, across) = if flat { (grid_height, grid_width) } else { (grid_width, grid_height) };
+        let (columns, rows) = (count(along, 2.0 * inner_radius, inner_radius), count(across, 1.5 * side, side));
+        (inner_radius, if flat { (rows, columns) } else { (columns, rows) })
+    };
+
+    let (inner_radius, row_spacing, grid_size) = match options.mode {
+        // the hexagons of varying size are not in a grid
+        PixelMode::hex if options.size_field.is_some() || options.adaptive => (None, None, None),
+        PixelMode::sqr | PixelMode::photomosaic | PixelMode::emoji | PixelMode::ascii => (Some(side / 2.0), Some(side), Some((count(grid_width, side, 0.0), count(grid_height, side, 0.0)))),
+        PixelMode::hex | PixelMode::dots => {
+            let (inner_radius, grid) = hexagons();
+            (Some(inner_radius), Some(1.5 * side), Some(grid))
+        }
+        // each hexagon is split in 3 rhombi, the faces of a cube
+        PixelMode::cube => {
+            let (_, grid) = hexagons();
+            (Some(side * 3f32.sqrt() / 4.0), Some(1.5 * side), Some(grid))
+        }
+        // the triangles alternate pointing up and down along the rows
+        PixelMode::tri => {
+            let row_height = side * (PI / 3.0).sin();
+            let grid = (count(grid_width, side / 2.0, side / 2.0), count(grid_height, row_height, 0.0));
+            (Some(side / (2.0 * 3f32.sqrt())), Some(row_height), Some(grid))
+        }
+        // every other row of bricks is shifted by half a brick
+        PixelMode::brick => {
+            let cell_width = options.cell_width.unwrap_or(options.size).max(1) as f32;
+            let cell_height = options.cell_height.unwrap_or(options.size).max(1) as f32;
+            let grid = (count(grid_width, cell_width, cell_width / 2.0), count(grid_height, cell_height, 0.0));
+            (Some(cell_width.min(cell_height) / 2.0), Some(cell_height), Some(grid))
+        }
+        // the scales are discs, every other row being shifted by their radius, the rows below covering their bottoms
+        PixelMode::scales => {
+            let row_spacing = Scales::row_spacing(side, options.overlap);
+            let grid = (count(grid_width, 2.0 * side, side), count(grid_height, row_spacing, side));
+            (Some(2.0 * row_spacing / 3.0), Some(row_spacing), Some(grid))
+        }
+        // the rectangles are laid along the diagonals, not in rows
+        PixelMode::herringbone => (Some(side / 2.0), None, None),
+        // the hexagons are laid side by side along the columns, every other column being shifted by half a hexagon,
+        // and the triangles fill the space between them
+        PixelMode::trihex => {
+            let width_across = side * 3f32.sqrt();
+            let (along, across) = if flat { (grid_height, grid_width) } else { (grid_width, grid_height) };
+            let (columns, rows) = (count(along, width_across, width_across / 2.0), count(across, 2.0 * side, side));
+            (Some(side / (2.0 * 3f32.sqrt())), None, Some(if flat { (rows, columns) } else { (columns, rows) }))
+        }
+        // the arcs and the cells of the spiral are as wide as they are long
+        PixelMode::rings | PixelMode::spiral => (Some(side / 2.0), None, None),
+        PixelMode::voronoi | PixelMode::crystal | PixelMode::quadtree | PixelMode::slic | PixelMode::penrose | PixelMode::hilbert
+            | PixelMode::lowpoly => (None, None, None),
+    };
+    let inner_radius = inner_radius.map(|r| r / factors.0.max(factors.1));
+    // the rows of the flat hexagons are columns
+    let hexagonal = [PixelMode::hex, PixelMode::dots, PixelMode::cube, PixelMode::trihex].contains(&options.mode);
+    let across = if flat && hexagonal { factors.0 } else { factors.1 };
+    let row_spacing = row_spacing.map(|r| r / across);
+    let cells = match options.mode {
+        PixelMode::hex if options.size_field.is_some() => grid(width, height, options).map(|g| g.cell_count() as u64),
+        PixelMode::cube => grid_size.map(|(columns, rows)| 3 * columns as u64 * rows as u64),
+        PixelMode::voronoi | PixelMode::crystal | PixelMode::penrose | PixelMode::rings | PixelMode::spiral | PixelMode::hilbert =>
+            grid(width, height, options).map(|g| g.cell_count() as u64),
+        // the superpixels start on a grid of squares of the size
+        PixelMode::slic => Some(count(grid_width, side, 0.0) as u64 * count(grid_height, side, 0.0) as u64),
+        PixelMode::quadtree | PixelMode::lowpoly => None,
+        // each rectangle covers 2 squares of the size, and those along the edges of the image are cut
+        // each hexagon comes with 2 triangles
+        PixelMode::trihex => grid_size.map(|(columns, rows)| 3 * columns as u64 * rows as u64),
+        PixelMode::herringbone => {
+            let squares = (count(grid_width, side, 0.0) as u64 + 1) * (count(grid_height, side, 0.0) as u64 + 1);
+            Some(squares.div_ceil(2))
+        }
+        _ => grid_size.map(|(columns, rows)| columns as u64 * rows as u64),
+    };
+
+    let (grid_size, cells) = if single_cell(width, height, options) {
+        (grid_size.map(|_| (1, 1)), Some(1))
+    } else {
+        (grid_size, cells)
+    };
+
+    Plan { inner_radius, row_spacing, grid: grid_size, cells, memory: memory(width, height, options, channel_bytes) }
+}
+
+///
+/// The size of the cells whose columns span the width of an image of that size and whose rows span its height,
+/// for the numbers of columns and rows of the options
+///
+/// None when the options set neither. The size is the mean of those given by the columns and the rows when both are set,
+/// and is rounded to the pixel, so that the cells may span a few pixels more or less than the image.
+/// The cells of the voronoi, crystal and slic modes are as far from each other as squares of their size,
+/// and the sizes of the quadtree mode do not depend on it.
+/// The size of the hilbert mode is the number of pixels of the cells, the square of their side.
+///
+pub fn fit_size(width: u32, height: u32, options: &Options) -> Option<u32> {
+    let (column_step, row_step) = steps(options);
+    let span = |length: u32, count: Option<u32>, step: f32| count.map(|n| length as f32 / (n.max(1) as f32 * step));
+    let sizes: Vec<f32> = span(width, options.columns, column_step).into_iter()
+        .chain(span(height, options.rows, row_step))
+        .collect();
+    if sizes.is_empty() {
+        return None;
+    }
+    let side = sizes.iter().sum::<f32>() / sizes.len() as f32;
+    let size = if options.mode == PixelMode::hilbert { sqr!(side) } else { side };
+    Some(size.round().max(1.0) as u32)
+}
+
+///
+/// The size of an image of that size padded or cropped to a whole number of columns and rows of cells,
+/// as set by the fit of the options
+///
+/// None when the options have no fit, or when their cells are not in a regular grid along the axes of the image.
+/// The width and the height are rounded to the pixel when the cells are not, and the image is not cropped
+/// along the axes where it holds less than a cell.
+///
+pub fn fit_grid(width: u32, height: u32, options: &Options) -> Option<(u32, u32)> {
+    let fit = options.fit_grid?;
+    let fitted = options.fitted(width, height);
+    let (column_step, row_step) = grid_steps(&fitted)?;
+    let fit = |length: u32, step: f32| {
+        let cells = length as f32 / step;
+        match fit {
+            FitGrid::pad => (cells.ceil() * step).round() as u32,
+            // the image is left as it is when it holds less than a cell
+            FitGrid::crop if cells < 1.0 => length,
+            FitGrid::crop => (cells.floor() * step).round() as u32,
+        }
+    };
+    Some((fit(width, column_step), fit(height, row_step)))
+}
+
+///
+/// Checks that the options can pixelise an image of that size, Err telling why they cannot
+///
+/// The size of the cells, their width and their height must be 1 pixel at least, the numbers of columns
+/// and rows spanning the image 1 at least, the distances between the cells following from them,
+/// and the smallest size no larger than the largest one.
+/// Ok holds a warning when the cells are larger than the shorter side of the image, which is then covered
+/// by a few cells at most, or by a single one when they are larger than the image.
+///
+pub fn check(width: u32, height: u32, options: &Options) -> Result<Option<String>, String> {
+    for &(name, count) in &[("columns", options.columns), ("rows", options.rows)] {
+        if count == Some(0) {
+            return Err(format!("the image is spanned by 0 {} of cells, it must be 1 at least", name));
+        }
+    }
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    // the sizes of the quadtree mode are its smallest and largest sizes
+    if options.size == 0 && options.mode != PixelMode::quadtree {
+        return Err("the size of the cells is 0 pixels, it must be 1 at least".to_string());
+    }
+    for &(name, length) in &[("width", options.cell_width), ("height", options.cell_height)] {
+        if length == Some(0) {
+            return Err(format!("the {} of the cells is 0 pixels, it must be 1 at least", name));
+        }
+    }
+    if options.min_size > options.max_size {
+        return Err(format!("the smallest size of the cells, {} pixels, is larger than the largest one, {} pixels",
+                           options.min_size, options.max_size));
+    }
+
+    let side = match cell_side(options) {
+        Some(side) => side,
+        None => return Ok(None),
+    };
+    if single_cell(width, height, options) {
+        return Ok(Some(format!(
+            "the cells, {} pixels across, are larger than the {}x{} image, which is pixelised as a single cell",
+            side, width, height
+        )));
+    }
+    if side > width.min(height) {
+        return Ok(Some(format!(
+            "the cells, {} pixels across, are larger than the {}x{} image, which they cover with a few cells at most",
+            side, width, height
+        )));
+    }
+    Ok(None)
+}
+
+///
+/// The most cells the pixelisation of an image of that size may have, roughly, found without laying them out
+///
+/// It is the number of squares of the side of the smallest cells covering the image, with a column and a row more
+/// for the cells cut by its edges, three times as many
+/// for the triangles, less than half as large as the squares, and twice as many for the lowpoly mode.
+///
+pub fn most_cells(width: u32, height: u32, options: &Options) -> u64 {
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    let side = match options.mode {
+        PixelMode::quadtree => options.min_size,
+        PixelMode::hex if options.adaptive => options.min_size,
+        PixelMode::hex if options.size_field.is_some() => options.size_field.as_ref().unwrap().smallest() as u32,
+        PixelMode::hilbert => (options.size as f32).sqrt() as u32,
+        _ => options.size.min(options.cell_width.unwrap_or(options.size)).min(options.cell_height.unwrap_or(options.size)),
+    };
+    let side = side.max(1) as u64;
+    let squares = ((width as u64).div_ceil(side) + 1) * ((height as u64).div_ceil(side) + 1);
+    match options.mode {
+        PixelMode::tri | PixelMode::trihex => 3 * squares,
+        PixelMode::lowpoly => 2 * squares,
+        _ => squares,
+    }
+}
+
+///
+/// Whether the cells of the options are larger than an image of that size, which is then pixelised as a single cell
+/// rather than cut in the few pieces of the cells where the grid happens to fall
+///
+pub fn single_cell(width: u32, height: u32, options: &Options) -> bool {
+    cell_side(options).is_some_and(|side| side >= width.max(height))
+}
+
+/// The distance across the cells, in pixels, None for the cells of varying sizes
+fn cell_side(options: &Options) -> Option<u32> {
+    match options.mode {
+        PixelMode::quadtree => None,
+        PixelMode::hex if options.size_field.is_some() || options.adaptive => None,
+        PixelMode::hilbert => Some((options.size as f32).sqrt().round() as u32),
+        _ => Some(stretched_side(options).0),
+    }
+}
+
+///
+/// The distances between the columns and between the rows of cells of the regular grids, in pixels,
+/// None for the other modes, the grids of varying sizes and the rotated grids
+///
+pub fn grid_steps(options: &Options) -> Option<(f32, f32)> {
+    let (side, factors) = stretched_side(options);
+    let side = side.max(1) as f32;
+    match options.mode {
+        _ if options.angle % 360.0 != 0.0 => None,
+        PixelMode::hex if options.size_field.is_some() || options.adaptive => None,
+        PixelMode::brick => Some((
+            options.cell_width.unwrap_or(options.size).max(1) as f32,
+            options.cell_height.unwrap_or(options.size).max(1) as f32,
+        )),
+        PixelMode::sqr | PixelMode::photomosaic | PixelMode::emoji | PixelMode::ascii | PixelMode::hex | PixelMode::dots | PixelMode::cube
+        | PixelMode::tri | PixelMode::scales | PixelMode::trihex => {
+            let (column_step, row_step) = steps(options);
+            Some((column_step * side / factors.0, row_step * side / factors.1))
+        }
+        _ => None,
+    }
+}
+
+/// The distances between the columns and between the rows of cells of size 1, roughly for the irregular cells
+fn steps(options: &Options) -> (f32, f32) {
+    let hexagons = (3f32.sqrt(), 1.5);
+    match options.mode {
+        PixelMode::hex | PixelMode::dots | PixelMode::cube if options.orientation == Orientation::flat =>
+            (hexagons.1, hexagons.0),
+        PixelMode::hex | PixelMode::dots | PixelMode::cube => hexagons,
+        // the triangles alternate pointing up and down along the rows
+        PixelMode::tri => (0.5, (PI / 3.0).sin()),
+        PixelMode::scales => (2.0, Scales::row_spacing(1.0, options.overlap)),
+        // a line across the parquet crosses as many horizontal rectangles, 2 long, as vertical ones, 1 wide
+        PixelMode::herringbone => (4.0 / 3.0, 4.0 / 3.0),
+        // the rhombi of side 1 take about 0.8 of a square of side 1 each
+        PixelMode::penrose => (0.9, 0.9),
+        PixelMode::trihex if options.orientation == Orientation::flat => (2.0, 3f32.sqrt()),
+        PixelMode::trihex => (3f32.sqrt(), 2.0),
+        _ => (1.0, 1.0),
+    }
+}
+
+/// The memory taken by pixelising an image of that size, in bytes, roughly
+pub fn memory(width: u32, height: u32, options: &Options, channel_bytes: u64) -> u64 {
+    let pixel = 4 * channel_bytes;
+    // the image
+    let mut memory = width as u64 * height as u64 * pixel;
+    // the tileable images are pixelised with a margin around them, and cropped
+    let (width, height) = if options.tileable {
+        let margin = wrap_margin(options);
+        memory += width as u64 * height as u64 * pixel;
+        (width + 2 * margin, height + 2 * margin)
+    } else {
+        (width, height)
+    };
+    let pixels = width as u64 * height as u64;
+    // the pixelised image
+    memory += pixels * pixel;
+    if options.needs_cell_map() {
+        // the cell of each pixel
+        memory += pixels * size_of::<usize>() as u64;
+    }
+    if options.sampling == Sampling::dominant {
+        // the pixels sorted by cell
+        memory += pixels * pixel;
+    }
+    if options.split_channels.is_some() {
+        // an image for each channel
+        memory += 3 * pixels * pixel;
+    }
+    if !options.regions.is_empty() {
+        // the pixelised image is a copy of the image
+        memory += pixels * pixel;
+    }
+    if options.mask.is_some() {
+        // the weights of the pixels
+        memory += pixels;
+    }
+    if options.faces {
+        // the gray image the faces are found in
+        memory += pixels;
+    }
+    if options.mode == PixelMode::slic {
+        // the color, the superpixel and the distance of each pixel
+        memory += pixels * (size_of::<[f32; 4]>() + size_of::<usize>() + size_of::<f32>()) as u64;
+    }
+    if options.mode == PixelMode::lowpoly {
+        // the gradient, the depth in its triangle and the triangle of each pixel
+        memory += pixels * (size_of::<f32>() + size_of::<f32>() + size_of::<u32>()) as u64;
+    }
+    if options.mode == PixelMode::hilbert || options.mode == PixelMode::crystal {
+        // the cell and the distance to the edge of each pixel
+        memory += pixels * (size_of::<u32>() + size_of::<f32>()) as u64;
+    }
+
+    let cells = match grid(width, height, options) {
+        Some(grid) => grid.cell_count() as u64,
+        // the slic mode has a cell per square of the size, the quadtree at most one per square of its smallest size,
+        // and the lowpoly mode at most 2 triangles per square of the size, but for those along the sides
+        None => {
+            let size = if options.mode == PixelMode::quadtree || options.adaptive { options.min_size } else { options.size };
+            let size = size.max(1);
+            let squares = pixels / (size as u64 * size as u64) + 1;
+            if options.mode == PixelMode::lowpoly { 2 * squares + 4 * (width / size + height / size) as u64 } else { squares }
+        }
+    };
+    let cell = match options.sampling {
+        // a color and a center per cell
+        Sampling::center => pixel + size_of::<(u32, u32, usize)>() as u64,
+        // the weighted sums, the weight and the color per cell, and its center, by which its pixels are weighted
+        Sampling::gaussian => pixel + (size_of::<[f64; 4]>() + size_of::<f64>() + size_of::<(f32, f32)>()) as u64,
+        // the sums, the count and the color per cell
+        _ => pixel + (size_of::<[u64; 4]>() + size_of::<u64>()) as u64,
+    };
+    // the neighbours of each cell, about 6 of them, its center and its error
+    let dithering = if options.dither && options.palette.is_some() {
+        (7 * size_of::<usize>() + size_of::<(f32, f32)>() + size_of::<[f32; 3]>()) as u64
+    } else {
+        0
+    };
+    memory + cells * (cell + dithering)
+}
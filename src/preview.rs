@@ -0,0 +1,245 @@
+use std::path::{Path, PathBuf};
+
+use hexpxl::font::{draw_text, text_width, HEIGHT};
+use hexpxl::{pixelise_with, Options, PixelMode};
+use image::{Rgba, RgbaImage};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+
+
+/// The scale of the text of the controls
+const TEXT_SCALE: u32 = 2;
+/// The height of each row of controls, in pixels
+const ROW: u32 = 28;
+/// The rows of controls: the size, the mode, the border and the save button
+const ROWS: u32 = 4;
+/// The width of the names of the controls, on their left
+const LABEL_WIDTH: u32 = 100;
+/// The width of the values of the sliders, on their right
+const VALUE_WIDTH: u32 = 80;
+/// The largest border width of the slider, in pixels
+const MAX_BORDER: f32 = 16.0;
+/// The largest initial size of the window, without the controls
+const MAX_VIEW: (u32, u32) = (1280, 720);
+
+const BACKGROUND: Rgba<u8> = Rgba { data: [48, 48, 48, 255] };
+const PANEL: Rgba<u8> = Rgba { data: [32, 32, 32, 255] };
+const TEXT: Rgba<u8> = Rgba { data: [224, 224, 224, 255] };
+const DIM: Rgba<u8> = Rgba { data: [128, 128, 128, 255] };
+const ACCENT: Rgba<u8> = Rgba { data: [255, 160, 32, 255] };
+
+///
+/// The parts of the window which react to the mouse
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Control {
+    Size,
+    Mode(PixelMode),
+    Border,
+    Save,
+}
+
+///
+/// Shows the pixelised image in a window, with sliders for its size and its border, the modes and a save button
+///
+/// The image is pixelised again at its full resolution whenever a setting changes,
+/// and scaled to fit the window.
+/// The arrow keys change the size, M the mode, S saves and escape closes the window.
+///
+/// # Arguments
+///
+/// * `img` - The input image
+/// * `options` - The initial settings, and those which have no control
+/// * `src` - The path of the input image, shown in the title
+/// * `destination` - The path where the image is saved, given the current settings
+///
+pub fn preview<D>(img: &RgbaImage, mut options: Options, src: &Path, destination: D) -> Result<(), String>
+    where D: Fn(&Options) -> PathBuf {
+    let (width, height) = img.dimensions();
+    let fit = (MAX_VIEW.0 as f32 / width as f32).min(MAX_VIEW.1 as f32 / height as f32).min(1.0);
+    let view = ((width as f32 * fit).max(1.0) as u32, (height as f32 * fit).max(1.0) as u32);
+    let mut window = Window::new(
+        &format!("hexpxl - {}", src.display()),
+        view.0.max(controls_width()) as usize,
+        (view.1 + ROWS * ROW) as usize,
+        WindowOptions { resize: true, ..WindowOptions::default() },
+    ).map_err(|e| format!("cannot open the preview window: {}", e))?;
+    window.set_target_fps(60);
+
+    let max_size = options.size.max(100);
+    let modes: Vec<PixelMode> = PixelMode::variants().iter().map(|m| m.parse().unwrap()).collect();
+    let mut pixelised = pixelise_with(img, &options);
+    let mut status = String::new();
+    let mut dragging = None;
+    let mut was_down = false;
+    let mut drawn = None;
+    let mut buffer: Vec<u32> = Vec::new();
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let (size, mode, border_width) = (options.size, options.mode, options.border_width);
+        let (window_width, window_height) = window.get_size();
+        let (window_width, window_height) = (window_width as u32, window_height as u32);
+        let panel_top = window_height.saturating_sub(ROWS * ROW);
+
+        let mut save = window.is_key_pressed(Key::S, KeyRepeat::No);
+        if window.is_key_pressed(Key::Right, KeyRepeat::Yes) || window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+            options.size = (options.size + 1).min(max_size);
+        }
+        if window.is_key_pressed(Key::Left, KeyRepeat::Yes) || window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+            options.size = (options.size - 1).max(1);
+        }
+        if window.is_key_pressed(Key::M, KeyRepeat::No) {
+            let next = modes.iter().position(|&m| m == options.mode).map_or(0, |i| (i + 1) % modes.len());
+            options.mode = modes[next];
+        }
+
+        let down = window.get_mouse_down(MouseButton::Left);
+        if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+            let (x, y) = (x.max(0.0) as u32, y.max(0.0) as u32);
+            let control = control_at(x, y.wrapping_sub(panel_top), window_width, &modes);
+            if down && !was_down {
+                dragging = control;
+                match control {
+                    Some(Control::Mode(m)) => options.mode = m,
+                    Some(Control::Save) => save = true,
+                    _ => {}
+                }
+            }
+            if down {
+                let position = slider_position(x, window_width);
+                match dragging {
+                    Some(Control::Size) => options.size = 1 + (position * (max_size - 1) as f32).round() as u32,
+                    Some(Control::Border) => options.border_width = (position * MAX_BORDER * 2.0).round() / 2.0,
+                    _ => {}
+                }
+            }
+        }
+        if !down {
+            dragging = None;
+        }
+        was_down = down;
+
+        let changed = (size, mode, border_width) != (options.size, options.mode, options.border_width);
+        if changed {
+            pixelised = pixelise_with(img, &options);
+            status.clear();
+        }
+        if save {
+            let path = destination(&options);
+            status = match pixelised.save(&path) {
+                Ok(_) => format!("saved {}", path.display()),
+                Err(e) => format!("cannot save {}: {}", path.display(), e),
+            };
+        }
+
+        let state = (window_width, window_height, options.size, options.mode, options.border_width, status.clone());
+        if drawn.as_ref() == Some(&state) {
+            window.update();
+            continue;
+        }
+        let mut canvas = RgbaImage::from_pixel(window_width, window_height, BACKGROUND);
+        draw_view(&mut canvas, &pixelised, panel_top);
+        draw_controls(&mut canvas, panel_top, &options, max_size, &modes, &status);
+        buffer.clear();
+        buffer.extend(canvas.pixels().map(|p| (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32));
+        window.update_with_buffer(&buffer, window_width as usize, window_height as usize)
+            .map_err(|e| format!("cannot draw the preview window: {}", e))?;
+        drawn = Some(state);
+    }
+    Ok(())
+}
+
+/// The smallest width showing all the controls
+fn controls_width() -> u32 {
+    let modes: String = PixelMode::variants().join(" ");
+    LABEL_WIDTH + text_width(&modes, TEXT_SCALE) + 16
+}
+
+/// The left and right ends of the tracks of the sliders
+fn track(window_width: u32) -> (u32, u32) {
+    (LABEL_WIDTH, window_width.saturating_sub(VALUE_WIDTH).max(LABEL_WIDTH + 1))
+}
+
+/// The position of the mouse along the tracks of the sliders, from 0 to 1
+fn slider_position(x: u32, window_width: u32) -> f32 {
+    let (left, right) = track(window_width);
+    ((x as f32 - left as f32) / (right - left) as f32).clamp(0.0, 1.0)
+}
+
+/// The left end of each mode, on the row of the modes
+fn mode_positions(modes: &[PixelMode]) -> Vec<(PixelMode, u32, u32)> {
+    let mut x = LABEL_WIDTH;
+    modes.iter().map(|&m| {
+        let width = text_width(&m.to_string(), TEXT_SCALE);
+        let position = (m, x, width);
+        x += width + text_width(" ", TEXT_SCALE) + TEXT_SCALE;
+        position
+    }).collect()
+}
+
+/// The control at the position, relative to the top left corner of the controls
+fn control_at(x: u32, y: u32, window_width: u32, modes: &[PixelMode]) -> Option<Control> {
+    match y / ROW {
+        0 => Some(Control::Size),
+        1 => mode_positions(modes).into_iter()
+            .find(|&(_, left, width)| x >= left && x < left + width)
+            .map(|(m, _, _)| Control::Mode(m)),
+        2 => Some(Control::Border),
+        3 if x >= LABEL_WIDTH && x < LABEL_WIDTH + text_width("save", TEXT_SCALE) => Some(Control::Save),
+        _ => None,
+    }.filter(|_| y < ROWS * ROW && x < window_width)
+}
+
+/// Draws the image scaled to fit above the controls, with the nearest pixels
+fn draw_view(canvas: &mut RgbaImage, img: &RgbaImage, height: u32) {
+    let (width, (img_width, img_height)) = (canvas.width(), img.dimensions());
+    let scale = (width as f32 / img_width as f32).min(height as f32 / img_height as f32);
+    let (view_width, view_height) = ((img_width as f32 * scale) as u32, (img_height as f32 * scale) as u32);
+    let (left, top) = ((width - view_width) / 2, (height - view_height) / 2);
+    for y in 0..view_height {
+        let src_y = ((y as f32 / scale) as u32).min(img_height - 1);
+        for x in 0..view_width {
+            let src_x = ((x as f32 / scale) as u32).min(img_width - 1);
+            let p = img.get_pixel(src_x, src_y);
+            let alpha = p[3] as u32;
+            // the transparent pixels show the background
+            let mixed = |c: usize| ((p[c] as u32 * alpha + BACKGROUND[c] as u32 * (255 - alpha)) / 255) as u8;
+            canvas.put_pixel(left + x, top + y, Rgba([mixed(0), mixed(1), mixed(2), 255]));
+        }
+    }
+}
+
+fn fill(canvas: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for py in y..(y + height).min(canvas.height()) {
+        for px in x..(x + width).min(canvas.width()) {
+            canvas.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn draw_controls(canvas: &mut RgbaImage, top: u32, options: &Options, max_size: u32, modes: &[PixelMode], status: &str) {
+    let width = canvas.width();
+    fill(canvas, 0, top, width, ROWS * ROW, PANEL);
+    let text_top = |row: u32| top + row * ROW + (ROW - HEIGHT * TEXT_SCALE) / 2;
+    let mut slider = |row: u32, name: &str, position: f32, value: String| {
+        let (left, right) = track(width);
+        let middle = top + row * ROW + ROW / 2;
+        draw_text(canvas, name, 8, text_top(row), TEXT_SCALE, TEXT);
+        fill(canvas, left, middle - 1, right - left, 2, DIM);
+        let knob = left + (position * (right - left) as f32) as u32;
+        fill(canvas, knob.saturating_sub(3), middle - 8, 6, 16, ACCENT);
+        draw_text(canvas, &value, right + 12, text_top(row), TEXT_SCALE, TEXT);
+    };
+
+    slider(0, "size", (options.size - 1) as f32 / (max_size - 1) as f32, options.size.to_string());
+    slider(2, "border", options.border_width / MAX_BORDER, options.border_width.to_string());
+
+    draw_text(canvas, "mode", 8, text_top(1), TEXT_SCALE, TEXT);
+    for (mode, x, _) in mode_positions(modes) {
+        let color = if mode == options.mode { ACCENT } else { DIM };
+        draw_text(canvas, &mode.to_string(), x, text_top(1), TEXT_SCALE, color);
+    }
+
+    draw_text(canvas, "save", LABEL_WIDTH, text_top(3), TEXT_SCALE, ACCENT);
+    let status_left = LABEL_WIDTH + text_width("save ", TEXT_SCALE) + 16;
+    draw_text(canvas, status, status_left, text_top(3), TEXT_SCALE, TEXT);
+}
@@ -0,0 +1,300 @@
+use std::cmp::Reverse;
+use std::marker::PhantomData;
+
+use color::{from_linear, to_linear, Channel};
+use image::{GenericImageView, Rgba};
+use rayon::prelude::*;
+use tessellation::Tessellation;
+
+
+///
+/// Each cell takes the color of the image at its center
+///
+pub fn center_colors<I, C, T>(img: &I, tessellation: &T) -> Vec<Rgba<C>>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel, T: Tessellation + ?Sized {
+    let (width, height) = img.dimensions();
+
+    (0..tessellation.cell_count()).map(|i| {
+        let (x, y) = tessellation.center(i);
+        let (x, y) = center_pixel(x, y, width, height);
+        img.get_pixel(x, y)
+    }).collect()
+}
+
+///
+/// Each cell takes the mean color of all the pixels it contains
+///
+/// # Arguments
+///
+/// * `img` - The input image
+/// * `cells` - The cell index of each pixel of the image, row by row
+/// * `cell_count` - The number of cell indices
+/// * `linear` - Whether the colors are averaged in linear light rather than as sRGB values
+///
+pub fn average_colors<I, C>(img: &I, cells: &[usize], cell_count: usize, linear: bool) -> Vec<Rgba<C>>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    let width = img.width().max(1) as usize;
+    let averaging = Averaging::new(linear);
+    let mut sums = vec![[0u64; 4]; cell_count];
+    let mut counts = vec![0u64; cell_count];
+
+    for (i, &cell) in cells.iter().enumerate() {
+        let pixel = img.get_pixel((i % width) as u32, (i / width) as u32);
+        averaging.add(&mut sums[cell], &pixel.data);
+        counts[cell] += 1;
+    }
+
+    sums.iter().zip(counts).map(|(sum, count)| averaging.mean(sum, count)).collect()
+}
+
+///
+/// Each cell takes the mean color of its pixels, weighted by their closeness to its center
+///
+/// The pixels closer to the center weigh more, so that the noise is smoothed out
+/// without averaging away the details at the center, as described by `Gaussian`.
+///
+/// # Arguments
+///
+/// * `img` - The input image
+/// * `cells` - The cell index of each pixel of the image, row by row
+/// * `tessellation` - The cells, whose centers the pixels are weighted by the distance to
+/// * `sigma` - The spread of the weights, in pixels
+/// * `linear` - Whether the colors are averaged in linear light rather than as sRGB values
+///
+pub fn gaussian_colors<I, C, T>(img: &I, cells: &[usize], tessellation: &T, sigma: f32, linear: bool) -> Vec<Rgba<C>>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel, T: Tessellation + ?Sized {
+    let width = img.width().max(1) as usize;
+    let averaging = Averaging::new(linear);
+    let gaussian = Gaussian::new(tessellation, sigma);
+    let mut sums = vec![[0f64; 4]; tessellation.cell_count()];
+    let mut weights = vec![0f64; tessellation.cell_count()];
+
+    for (i, &cell) in cells.iter().enumerate() {
+        let (x, y) = ((i % width) as u32, (i / width) as u32);
+        let weight = gaussian.weight(x, y, cell);
+        averaging.add_weighted(&mut sums[cell], &img.get_pixel(x, y).data, weight);
+        weights[cell] += weight;
+    }
+
+    sums.iter().zip(weights).map(|(sum, weight)| averaging.weighted_mean(sum, weight)).collect()
+}
+
+///
+/// The weights of the pixels of the gaussian sampling
+///
+/// A pixel at the distance d from the center of its cell weighs exp(-d² / 2σ²), the pixels within σ of the center
+/// making most of the color. The weights are kept from falling to 0, so that the cells whose pixels are all
+/// far from their centers, out of the image, still take their colors.
+///
+pub struct Gaussian {
+    centers: Vec<(f32, f32)>,
+    /// 2σ²
+    spread: f32,
+}
+
+impl Gaussian {
+    pub fn new<T>(tessellation: &T, sigma: f32) -> Gaussian
+        where T: Tessellation + ?Sized {
+        Gaussian {
+            centers: (0..tessellation.cell_count()).map(|i| tessellation.center(i)).collect(),
+            spread: 2.0 * sqr!(sigma.max(0.1)),
+        }
+    }
+
+    pub fn weight(&self, x: u32, y: u32, cell: usize) -> f64 {
+        let (cx, cy) = self.centers[cell];
+        let exponent = (sqr!(x as f32 - cx) + sqr!(y as f32 - cy)) / self.spread;
+        (-exponent.min(600.0) as f64).exp()
+    }
+}
+
+///
+/// Adds up colors and takes their mean, either as sRGB values or in linear light
+///
+/// Averaging sRGB values darkens the mean of contrasted colors, as their values are not proportional to their light.
+/// The colors are weighted by their alpha, so that the colors of the transparent pixels, which are not seen,
+/// do not bleed into the mean. The alpha channel is averaged as it is.
+///
+pub struct Averaging<C> {
+    /// The intensity in linear light of each sRGB value, when averaging in linear light
+    linear: Option<Vec<u16>>,
+    channel: PhantomData<C>,
+}
+
+impl<C: Channel> Averaging<C> {
+    pub fn new(linear: bool) -> Averaging<C> {
+        Averaging {
+            linear: if linear { Some((0..=C::MAX).map(|v| to_linear(C::from_value(v))).collect()) } else { None },
+            channel: PhantomData,
+        }
+    }
+
+    /// Adds the channels of the RGBA pixel to the sum, premultiplied by its alpha
+    pub fn add(&self, sum: &mut [u64; 4], pixel: &[C]) {
+        let alpha = pixel[3].value() as u64;
+        for c in 0..3 {
+            let value = match self.linear {
+                Some(ref linear) => linear[pixel[c].value() as usize] as u64,
+                None => pixel[c].value() as u64,
+            };
+            sum[c] += value * alpha;
+        }
+        sum[3] += alpha;
+    }
+
+    /// Adds the channels of the RGBA pixel to the sum, premultiplied by its alpha and by the weight of the pixel
+    pub fn add_weighted(&self, sum: &mut [f64; 4], pixel: &[C], weight: f64) {
+        let alpha = pixel[3].value() as f64 * weight;
+        for c in 0..3 {
+            let value = match self.linear {
+                Some(ref linear) => linear[pixel[c].value() as usize] as f64,
+                None => pixel[c].value() as f64,
+            };
+            sum[c] += value * alpha;
+        }
+        sum[3] += alpha;
+    }
+
+    /// The mean color of the pixels whose weighted channels add up to `sum`, their weights adding up to `weight`
+    pub fn weighted_mean(&self, sum: &[f64; 4], weight: f64) -> Rgba<C> {
+        let alpha = sum[3];
+        if alpha == 0.0 {
+            return transparent();
+        }
+        let channel = |c: usize| match self.linear {
+            Some(_) => from_linear((sum[c] / alpha) as f32),
+            None => C::from_value((sum[c] / alpha).round() as u32),
+        };
+        Rgba([channel(0), channel(1), channel(2), C::from_value((alpha / weight).round() as u32)])
+    }
+
+    /// The mean color of `count` pixels whose channels add up to `sum`, transparent when there are none
+    pub fn mean(&self, sum: &[u64; 4], count: u64) -> Rgba<C> {
+        let alpha = sum[3];
+        if alpha == 0 {
+            return transparent();
+        }
+        let channel = |c: usize| match self.linear {
+            Some(_) => from_linear(sum[c] as f32 / alpha as f32),
+            None => C::from_value(((sum[c] + alpha / 2) / alpha) as u32),
+        };
+        Rgba([channel(0), channel(1), channel(2), C::from_value(((alpha + count / 2) / count) as u32)])
+    }
+}
+
+///
+/// The mean color of `count` pixels whose channels add up to `sum`, transparent when there are none
+///
+pub fn mean<C: Channel>(sum: &[u64; 4], count: u64) -> Rgba<C> {
+    if count == 0 {
+        return transparent();
+    }
+    let mean = |c: usize| C::from_value(((sum[c] + count / 2) / count) as u32);
+    Rgba([mean(0), mean(1), mean(2), mean(3)])
+}
+
+/// The fully transparent black
+pub fn transparent<C: Channel>() -> Rgba<C> {
+    Rgba([C::from_value(0); 4])
+}
+
+///
+/// The pixel where the color of the cell centered on (x, y) is sampled, in an image of that size
+///
+pub fn center_pixel(x: f32, y: f32, width: u32, height: u32) -> (u32, u32) {
+    ((x.max(0.0).round() as u32).min(width - 1), (y.max(0.0).round() as u32).min(height - 1))
+}
+
+///
+/// Each cell takes the color of the largest cluster of its pixels
+///
+/// The pixels of each cell are split in a few clusters by a k-means,
+/// which starts from pixels as far as possible from each other, so that the contrasted colors are not mixed.
+///
+/// # Arguments
+///
+/// * `img` - The input image
+/// * `cells` - The cell index of each pixel of the image, row by row
+/// * `cell_count` - The number of cell indices
+///
+pub fn dominant_colors<I, C>(img: &I, cells: &[usize], cell_count: usize) -> Vec<Rgba<C>>
+    where I: GenericImageView<Pixel=Rgba<C>>, C: Channel {
+    let width = img.width().max(1) as usize;
+
+    // the pixels sorted by cell, each cell `c` having the pixels [starts[c], starts[c + 1])
+    let mut starts = vec![0; cell_count + 1];
+    for &cell in cells {
+        starts[cell + 1] += 1;
+    }
+    for c in 0..cell_count {
+        starts[c + 1] += starts[c];
+    }
+    let mut next = starts.clone();
+    let mut pixels = vec![[C::from_value(0); 4]; cells.len()];
+    for (i, &cell) in cells.iter().enumerate() {
+        pixels[next[cell]] = img.get_pixel((i % width) as u32, (i / width) as u32).data;
+        next[cell] += 1;
+    }
+
+    (0..cell_count).into_par_iter()
+        .map(|c| dominant_color(&pixels[starts[c]..starts[c + 1]]))
+        .collect()
+}
+
+/// The number of clusters of the dominant sampling
+const CLUSTERS: usize = 3;
+/// The number of iterations after which the k-means stops, even when it has not converged
+const ITERATIONS: usize = 8;
+
+/// The mean color of the largest cluster of the pixels
+fn dominant_color<C: Channel>(pixels: &[[C; 4]]) -> Rgba<C> {
+    if pixels.is_empty() {
+        return transparent();
+    }
+    let distance = |p: &[C; 4], c: &[f32; 4]| -> f32 {
+        (0..4).map(|i| sqr!(p[i].value() as f32 - c[i])).sum()
+    };
+    let closest = |p: &[C; 4], centroids: &[[f32; 4]]| -> usize {
+        (0..centroids.len())
+            .min_by(|&a, &b| distance(p, &centroids[a]).partial_cmp(&distance(p, &centroids[b])).unwrap())
+            .unwrap()
+    };
+
+    // each new centroid is the pixel furthest from the previous ones
+    let mut centroids = vec![pixels[0].map(|v| v.value() as f32)];
+    while centroids.len() < CLUSTERS {
+        let furthest = pixels.iter()
+            .max_by(|a, b| {
+                let da = distance(a, &centroids[closest(a, &centroids)]);
+                let db = distance(b, &centroids[closest(b, &centroids)]);
+                da.partial_cmp(&db).unwrap()
+            })
+            .unwrap();
+        centroids.push(furthest.map(|v| v.value() as f32));
+    }
+
+    let mut sizes = [0u64; CLUSTERS];
+    for _ in 0..ITERATIONS {
+        let mut sums = [[0u64; 4]; CLUSTERS];
+        sizes = [0; CLUSTERS];
+        for p in pixels {
+            let k = closest(p, &centroids);
+            for i in 0..4 {
+                sums[k][i] += p[i].value() as u64;
+            }
+            sizes[k] += 1;
+        }
+
+        let previous = centroids.clone();
+        for k in (0..CLUSTERS).filter(|&k| sizes[k] > 0) {
+            centroids[k] = sums[k].map(|s| s as f32 / sizes[k] as f32);
+        }
+        if centroids == previous {
+            break;
+        }
+    }
+
+    let largest = (0..CLUSTERS).max_by_key(|&k| (sizes[k], Reverse(k))).unwrap();
+    let mean = centroids[largest].map(|v| C::from_value(v.round() as u32));
+    Rgba(mean)
+}
@@ -0,0 +1,226 @@
+use std::io::{self, Read};
+use std::thread;
+use std::time::Instant;
+
+use clap::ArgMatches;
+use form_urlencoded;
+use hexpxl;
+use hexpxl::deep;
+use hexpxl::palette::Palette;
+use hexpxl::plan;
+use image::ImageFormat;
+use rayon;
+use tiny_http;
+use toml::value::{Table, Value};
+use {decode_picture, download, encode, encode_deep, format_memory, image_dimensions, input_format, options_with,
+     parse_output_format, pixelisation_memory, setting_value, stamp, svg_options, text_options, writable,
+     Error, Formats, OutputFormat, Picture, SETTINGS};
+
+
+///
+/// What the server needs to pixelise the requests
+///
+pub struct Server {
+    /// The settings of the config file and of the preset of the command line, which the query strings override
+    pub settings: Table,
+    pub formats: Formats,
+    /// The largest image accepted, in bytes
+    pub max_body: u64,
+    /// The most memory the pixelisation of a request may take, in bytes, roughly
+    pub max_memory: u64,
+    /// The most cells the pixelisation of a request may make, roughly
+    pub max_cells: u64,
+    /// The most times the superpixels of a request may be refined
+    pub max_iterations: u32,
+    /// Whether the images at the url of the query strings are downloaded
+    pub allow_urls: bool,
+    /// The threads pixelising the images of all the requests
+    pub pool: rayon::ThreadPool,
+}
+
+/// The settings which name files, which the query strings cannot read on the server
+const FILE_SETTINGS: &[&str] = &["mask", "gradient-map", "depth-map", "tiles"];
+
+///
+/// Answers the HTTP requests on the address with the pixelised images, until interrupted
+///
+/// Each worker pixelises a request at a time, the others waiting in the queue of the server.
+/// The options of the command line are fixed, those of the query strings override its config file and preset.
+///
+pub fn serve_http(matches: &ArgMatches, server: &Server, address: &str, workers: usize) -> Result<(), Error> {
+    let http = tiny_http::Server::http(address)
+        .map_err(|e| Error::Usage(format!("cannot listen on {}: {}", address, e)))?;
+    let quiet = matches.is_present("quiet");
+    if !quiet {
+        println!("Serving on http://{}, interrupt to stop", address);
+    }
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                for mut request in http.incoming_requests() {
+                    let start = Instant::now();
+                    let response = match (request.method(), request.url().split('?').next()) {
+                        (&tiny_http::Method::Get, Some("/")) | (&tiny_http::Method::Post, Some("/")) =>
+                            server.pool.install(|| pixelise_request(&mut request, matches, server)),
+                        (_, Some("/")) => Err(reply(405, "only GET and POST are allowed")),
+                        _ => Err(reply(404, "the images are pixelised at /")),
+                    }.unwrap_or_else(|response| response);
+                    if !quiet {
+                        println!(
+                            "{} {} {} ({}ms)",
+                            request.method(), request.url(), response.status_code().0, start.elapsed().as_millis()
+                        );
+                    }
+                    // the client may be gone
+                    let _ = request.respond(response);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// A response of the server with the image
+type Reply = tiny_http::Response<io::Cursor<Vec<u8>>>;
+
+/// A response of the server with a message
+fn reply(status: u16, message: &str) -> Reply {
+    tiny_http::Response::from_string(format!("{}\n", message)).with_status_code(status)
+}
+
+///
+/// Pixelises the image of the request with the options of its query string
+///
+/// The image is the body of the POST requests, or is downloaded from the url of the query string.
+/// It is written in the format of the query string, else of --format, else of the image, else as PNG.
+///
+fn pixelise_request(request: &mut tiny_http::Request, matches: &ArgMatches, server: &Server) -> Result<Reply, Reply> {
+    let failed = |e: Error| reply(e.status(), &e.to_string());
+    let query = request.url().split_once('?').map_or("", |(_, query)| query).to_string();
+    let mut url = None;
+    let mut format = None;
+    let mut settings = server.settings.clone();
+    // repeated, as on the command line
+    let mut regions = Vec::new();
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        match &*key {
+            "url" => url = Some(value.into_owned()),
+            "format" => format = Some(parse_output_format(&value).ok_or_else(|| {
+                reply(400, &format!("invalid format '{}'", value))
+            })?),
+            "palette" if Palette::named(&value).is_none() =>
+                return Err(reply(400, &format!("unknown palette '{}', the files of the server cannot be used", value))),
+            key if FILE_SETTINGS.contains(&key) =>
+                return Err(reply(400, &format!("the option {} cannot be used, it reads a file of the server", key))),
+            key if SETTINGS.contains(&key) => {
+                let value = match &*value {
+                    // the flags
+                    "" | "true" => Value::Boolean(true),
+                    "false" => Value::Boolean(false),
+                    value => setting_value(key, Some(value)),
+                };
+                if key == "region" {
+                    regions.push(value);
+                } else {
+                    settings.insert(key.to_string(), value);
+                }
+            }
+            key => return Err(reply(400, &format!("unknown option {}", key))),
+        }
+    }
+    if !regions.is_empty() {
+        settings.insert("region".to_string(), Value::Array(regions));
+    }
+
+    let data = match (request.method(), url) {
+        (&tiny_http::Method::Post, Some(_)) => return Err(reply(400, "either post an image or give its url")),
+        (&tiny_http::Method::Post, None) => {
+            if request.body_length().is_some_and(|length| length as u64 > server.max_body) {
+                return Err(reply(413, &format!("the image is larger than {}", format_memory(server.max_body))));
+            }
+            let mut data = Vec::new();
+            request.as_reader().take(server.max_body + 1).read_to_end(&mut data)
+                .map_err(|e| failed(Error::read("the request", e)))?;
+            if data.len() as u64 > server.max_body {
+                return Err(reply(413, &format!("the image is larger than {}", format_memory(server.max_body))));
+            }
+            data
+        }
+        (_, Some(_)) if !server.allow_urls => return Err(reply(403, "the server does not download the images")),
+        (_, Some(url)) => download(&url, server.max_body).map_err(failed)?,
+        (_, None) => return Err(reply(400, "post an image, or give its url")),
+    };
+
+    let config = Value::Table(settings.clone()).try_into()
+        .map_err(|e| reply(400, &format!("invalid options: {}", e)))?;
+    let options = options_with(matches, config).map_err(failed)?;
+    if options.iterations > server.max_iterations {
+        return Err(reply(400, &format!("the superpixels are refined {} times at most", server.max_iterations)));
+    }
+    let formats = &server.formats;
+    let input_format = input_format("the image", &data, formats).map_err(failed)?;
+    let format = match format.or(formats.output) {
+        Some(format) => writable(format, "the image", formats).map_err(failed)?,
+        None => {
+            let same = if input_format == ImageFormat::WEBP { OutputFormat::Webp } else { OutputFormat::Raster(input_format) };
+            writable(same, "the image", formats).unwrap_or(OutputFormat::Raster(ImageFormat::PNG))
+        }
+    };
+
+    // a small image may decode to a huge one, and the scale enlarges it further
+    let (width, height) = image_dimensions("the image", &data, input_format).map_err(failed)?;
+    // the tiny cells take long to lay out, even to estimate the memory they take
+    if plan::most_cells(width, height, &options) > server.max_cells {
+        return Err(reply(413, &format!(
+            "pixelising the {}x{} image would make more than {} cells", width, height, server.max_cells
+        )));
+    }
+    let channel_bytes = if deep::has_16_bits(&data, input_format) { 2 } else { 1 };
+    let memory = pixelisation_memory(width, height, &options, channel_bytes);
+    if memory > server.max_memory {
+        return Err(reply(413, &format!(
+            "pixelising the {}x{} image would take more than {}", width, height, format_memory(server.max_memory)
+        )));
+    }
+
+    let (img, mut metadata) = decode_picture("the image", &data, input_format, formats).map_err(failed)?;
+    metadata.parameters = Some(stamp(&settings, &options));
+    let (width, height) = img.dimensions();
+    // the cells larger than the image are pixelised still
+    plan::check(width, height, &options).map_err(|e| reply(400, &format!("cannot pixelise the image: {}", e)))?;
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    let encoded = match (format, img) {
+        (OutputFormat::Svg, img) => {
+            svg_options(options).map_err(failed)?;
+            Ok(hexpxl::svg::pixelise_svg(&img.to_rgba(), options).into_bytes())
+        }
+        (OutputFormat::Text, img) => {
+            text_options(options).map_err(failed)?;
+            Ok(hexpxl::to_text(&img.to_rgba(), options).into_bytes())
+        }
+        (_, Picture::Narrow(img)) => encode(hexpxl::pixelise_with(&img, options), "the image", format, formats, &metadata),
+        (_, Picture::Deep(img)) => encode_deep(hexpxl::pixelise_with(&img, options), "the image", format, formats, &metadata),
+    }.map_err(failed)?;
+    let content_type = tiny_http::Header::from_bytes(&b"Content-Type"[..], media_type(format)).unwrap();
+    Ok(tiny_http::Response::from_data(encoded).with_header(content_type))
+}
+
+/// The media type of the images of the format
+fn media_type(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Raster(ImageFormat::PNG) => "image/png",
+        OutputFormat::Raster(ImageFormat::JPEG) => "image/jpeg",
+        OutputFormat::Raster(ImageFormat::GIF) => "image/gif",
+        OutputFormat::Raster(ImageFormat::BMP) => "image/bmp",
+        OutputFormat::Raster(ImageFormat::ICO) => "image/x-icon",
+        OutputFormat::Raster(ImageFormat::TIFF) => "image/tiff",
+        OutputFormat::Raster(ImageFormat::PNM) => "image/x-portable-anymap",
+        OutputFormat::Webp => "image/webp",
+        OutputFormat::Avif => "image/avif",
+        OutputFormat::Svg => "image/svg+xml",
+        OutputFormat::Text => "text/plain; charset=utf-8",
+        OutputFormat::Raster(_) => "application/octet-stream",
+    }
+}
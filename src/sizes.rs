@@ -0,0 +1,172 @@
+use color::luminance;
+use image::{GenericImageView, GrayImage, Rgba};
+
+
+///
+/// How the size of the hexagons changes across the image, rather than being the same everywhere
+///
+#[derive(Debug, Clone)]
+pub enum SizeField {
+    /// The size goes linearly from `start` to `end` across the image, along the direction `angle`,
+    /// in degrees clockwise from the right, so that an angle of 90 goes from the top edge to the bottom one
+    Gradient { start: f32, end: f32, angle: f32 },
+    /// The size goes linearly from `near` at the center, in pixels from the top left corner of the image,
+    /// to `far` at the furthest corner of the image, so that the cells grow away from the center when `near` is the smaller
+    Radial { center: (f32, f32), near: f32, far: f32 },
+    /// The size goes linearly from `near` where the depth map is white to `far` where it is black,
+    /// the map being stretched to the size of the image when they differ
+    Depth { map: GrayImage, near: f32, far: f32 },
+}
+
+impl SizeField {
+    ///
+    /// The size of the hexagons around the point of an image of that size, in pixels
+    ///
+    pub fn size(&self, width: u32, height: u32, (x, y): (f32, f32)) -> f32 {
+        match *self {
+            SizeField::Gradient { start, end, angle } => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                // the corners of the image furthest back and furthest along the direction
+                let along = |x: f32, y: f32| x * cos + y * sin;
+                let (w, h) = (width as f32, height as f32);
+                let corners = [along(0.0, 0.0), along(w, 0.0), along(0.0, h), along(w, h)];
+                let first = corners.iter().cloned().fold(f32::MAX, f32::min);
+                let last = corners.iter().cloned().fold(f32::MIN, f32::max);
+                let t = if last > first { ((along(x, y) - first) / (last - first)).clamp(0.0, 1.0) } else { 0.0 };
+                start + (end - start) * t
+            }
+            SizeField::Radial { center, near, far } => {
+                let distance = |x: f32, y: f32| (sqr!(x - center.0) + sqr!(y - center.1)).sqrt();
+                let (w, h) = (width as f32, height as f32);
+                let furthest = distance(0.0, 0.0).max(distance(w, 0.0)).max(distance(0.0, h)).max(distance(w, h));
+                let t = if furthest > 0.0 { (distance(x, y) / furthest).min(1.0) } else { 0.0 };
+                near + (far - near) * t
+            }
+            SizeField::Depth { ref map, near, far } => {
+                let (w, h) = map.dimensions();
+                // between the centers of the pixels of the map around the point
+                let u = ((x + 0.5) * w as f32 / width.max(1) as f32 - 0.5).clamp(0.0, (w - 1) as f32);
+                let v = ((y + 0.5) * h as f32 / height.max(1) as f32 - 0.5).clamp(0.0, (h - 1) as f32);
+                let (i, j) = (u as u32, v as u32);
+                let (s, t) = (u.fract(), v.fract());
+                let depth = |i: u32, j: u32| map.get_pixel(i.min(w - 1), j.min(h - 1))[0] as f32 / 255.0;
+                let top = depth(i, j) + (depth(i + 1, j) - depth(i, j)) * s;
+                let bottom = depth(i, j + 1) + (depth(i + 1, j + 1) - depth(i, j + 1)) * s;
+                far + (near - far) * (top + (bottom - top) * t)
+            }
+        }
+    }
+
+    /// The smallest size of the hexagons, in pixels
+    pub fn smallest(&self) -> f32 {
+        match *self {
+            SizeField::Gradient { start, end, .. } => start.min(end),
+            SizeField::Radial { near, far, .. } | SizeField::Depth { near, far, .. } => near.min(far),
+        }
+    }
+
+    /// The field with its sizes and positions multiplied by the factor
+    pub fn scaled(&self, factor: f32) -> SizeField {
+        match *self {
+            SizeField::Gradient { start, end, angle } => SizeField::Gradient { start: start * factor, end: end * factor, angle },
+            SizeField::Radial { center, near, far } => SizeField::Radial {
+                center: (center.0 * factor, center.1 * factor),
+                near: near * factor,
+                far: far * factor,
+            },
+            SizeField::Depth { ref map, near, far } =>
+                SizeField::Depth { map: map.clone(), near: near * factor, far: far * factor },
+        }
+    }
+}
+
+///
+/// The sizes of the hexagons following the detail of the image, from the smallest size where it is the busiest
+/// to the largest one where it is flat, for the adaptive hexagons
+///
+/// The detail is the strength of the edges of the image, the change of its luminance from a pixel to the next ones,
+/// averaged over squares of the smallest size and then around each of them over about the largest size, so that
+/// the size changes smoothly. The busiest squares are those above 9 tenths of the others.
+/// The size at each point is interpolated between those of the squares around it.
+///
+pub struct Detail {
+    /// The side of the squares, in pixels
+    block: u32,
+    columns: usize,
+    rows: usize,
+    sizes: Vec<f32>,
+}
+
+impl Detail {
+    pub fn new<I>(img: &I, min_size: u32, max_size: u32) -> Detail
+        where I: GenericImageView<Pixel=Rgba<u8>> {
+        let (width, height) = img.dimensions();
+        let block = min_size.max(2);
+        let (columns, rows) = (width.div_ceil(block).max(1) as usize, height.div_ceil(block).max(1) as usize);
+
+        let luminances: Vec<f32> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| luminance(img.get_pixel(x, y)))
+            .collect();
+        let at = |x: u32, y: u32| luminances[(y * width + x) as usize];
+        let mut sums = vec![0f32; columns * rows];
+        let mut counts = vec![0u32; columns * rows];
+        for y in 0..height {
+            for x in 0..width {
+                let dx = if x + 1 < width { at(x + 1, y) - at(x, y) } else { 0.0 };
+                let dy = if y + 1 < height { at(x, y + 1) - at(x, y) } else { 0.0 };
+                let square = (y / block) as usize * columns + (x / block) as usize;
+                sums[square] += (sqr!(dx) + sqr!(dy)).sqrt();
+                counts[square] += 1;
+            }
+        }
+        let details: Vec<f32> = sums.iter().zip(&counts).map(|(&s, &c)| s / c.max(1) as f32).collect();
+        // the detail around each square, over half of the largest size on each side
+        let radius = (max_size / (2 * block)) as usize;
+        let details = box_blur(&box_blur(&details, columns, rows, radius, (1, 0)), columns, rows, radius, (0, 1));
+
+        let mut sorted = details.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let busiest = sorted[sorted.len() * 9 / 10].max(f32::EPSILON);
+        let (min_size, max_size) = (min_size as f32, max_size.max(min_size) as f32);
+        let sizes = details.iter().map(|&d| max_size - (max_size - min_size) * (d / busiest).min(1.0)).collect();
+        Detail { block, columns, rows, sizes }
+    }
+
+    /// The size of the hexagons around the point, in pixels
+    pub fn size(&self, (x, y): (f32, f32)) -> f32 {
+        // between the centers of the squares around the point
+        let (u, v) = (x / self.block as f32 - 0.5, y / self.block as f32 - 0.5);
+        let cell = |i: f32, j: f32| {
+            let i = (i.max(0.0) as usize).min(self.columns - 1);
+            let j = (j.max(0.0) as usize).min(self.rows - 1);
+            self.sizes[j * self.columns + i]
+        };
+        let (i, j) = (u.floor(), v.floor());
+        let (s, t) = (u - i, v - j);
+        let top = cell(i, j) + (cell(i + 1.0, j) - cell(i, j)) * s;
+        let bottom = cell(i, j + 1.0) + (cell(i + 1.0, j + 1.0) - cell(i, j + 1.0)) * s;
+        top + (bottom - top) * t
+    }
+}
+
+/// The mean of the values over `radius` values on both sides of each of them, along the step
+fn box_blur(values: &[f32], columns: usize, rows: usize, radius: usize, step: (usize, usize)) -> Vec<f32> {
+    let mut blurred = vec![0f32; values.len()];
+    for j in 0..rows {
+        for i in 0..columns {
+            let (mut sum, mut count) = (0.0, 0);
+            for k in 0..(2 * radius + 1) {
+                // the values before the edges are left out
+                let (ni, nj) = ((i + k * step.0).checked_sub(radius * step.0), (j + k * step.1).checked_sub(radius * step.1));
+                if let (Some(ni), Some(nj)) = (ni, nj) {
+                    if ni < columns && nj < rows {
+                        sum += values[nj * columns + ni];
+                        count += 1;
+                    }
+                }
+            }
+            blurred[j * columns + i] = sum / count as f32;
+        }
+    }
+    blurred
+}
@@ -0,0 +1,335 @@
+mod dmc;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use color::{luminance, parse_color};
+use font;
+use image::{GenericImageView, ImageBuffer, Rgba, RgbaImage};
+use tessellation::Square;
+use {cell_colors, cell_map, Options, Steps};
+
+
+///
+/// A thread of a cross-stitch pattern
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thread {
+    /// The DMC code of the thread, e.g. 310
+    pub code: &'static str,
+    /// The DMC name of the thread, e.g. Black
+    pub name: &'static str,
+    pub color: Rgba<u8>,
+    /// The symbol of the thread on the chart
+    pub symbol: char,
+    /// The number of stitches made with the thread
+    pub stitches: u32,
+}
+
+///
+/// A cross-stitch pattern, where each square of the pixelised image is a stitch of a DMC thread
+///
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    columns: u32,
+    rows: u32,
+    /// The thread of each stitch, row by row, none for the transparent squares which are not stitched
+    stitches: Vec<Option<usize>>,
+    /// The threads, the most used first
+    threads: Vec<Thread>,
+}
+
+///
+/// Pixelises an image into a cross-stitch pattern
+///
+/// The image is cut in squares of `size` pixels, each one being a stitch of the DMC thread closest to its color.
+/// The squares which are mostly transparent are not stitched.
+///
+/// When more threads are needed than there are symbols, the least used threads are replaced
+/// by the closest of the others.
+///
+/// # Arguments
+///
+/// * `img` - The input image
+/// * `options` - The pixelisation settings, of which only the size, the sampling and the palette apply
+///
+pub fn stitch_pattern<I>(img: &I, options: &Options) -> Pattern
+    where I: GenericImageView<Pixel=Rgba<u8>> + Sync {
+    let (width, height) = img.dimensions();
+    let grid = Square::new(width, height, options.size);
+    let cells = if options.needs_cell_map() {
+        Some(cell_map(&grid, width, height, options.chunk_size, &Steps::new(0, &|_, _| {})))
+    } else {
+        None
+    };
+    let colors = cell_colors(img, &grid, options, cells.as_deref());
+
+    let all: Vec<Rgba<u8>> = dmc::THREADS.iter().map(|t| parse_color(t.2).unwrap()).collect();
+    let mut nearest = HashMap::new();
+    let mut stitches: Vec<Option<usize>> = colors.iter().map(|&color| {
+        if color[3] < 128 {
+            return None;
+        }
+        Some(*nearest.entry(color.data).or_insert_with(|| closest(&all, color, 0..all.len())))
+    }).collect();
+
+    let counts = |stitches: &[Option<usize>]| {
+        let mut counts = vec![0u32; all.len()];
+        for &t in stitches.iter().flatten() {
+            counts[t] += 1;
+        }
+        counts
+    };
+    let initial = counts(&stitches);
+    let mut used: Vec<usize> = (0..all.len()).filter(|&t| initial[t] > 0).collect();
+    used.sort_by_key(|&t| Reverse(initial[t]));
+    let symbols: Vec<char> = font::SYMBOLS.chars().collect();
+    let kept = &used[..used.len().min(symbols.len())];
+    let merged: HashMap<usize, usize> = used[kept.len()..].iter()
+        .map(|&t| (t, closest(&all, all[t], kept.iter().cloned())))
+        .collect();
+    for stitch in stitches.iter_mut().flatten() {
+        *stitch = *merged.get(stitch).unwrap_or(stitch);
+    }
+
+    let total = counts(&stitches);
+    let mut kept = kept.to_vec();
+    kept.sort_by_key(|&t| Reverse(total[t]));
+    let mut index = vec![0; all.len()];
+    let threads = kept.iter().zip(symbols).enumerate().map(|(i, (&t, symbol))| {
+        index[t] = i;
+        let (code, name, _) = dmc::THREADS[t];
+        Thread { code, name, color: all[t], symbol, stitches: total[t] }
+    }).collect();
+
+    Pattern {
+        columns: width.div_ceil(options.size),
+        rows: height.div_ceil(options.size),
+        stitches: stitches.iter().map(|s| s.map(|t| index[t])).collect(),
+        threads,
+    }
+}
+
+/// The index of the color amongst the `candidates` which is closest to `color`
+fn closest<C>(colors: &[Rgba<u8>], color: Rgba<u8>, candidates: C) -> usize
+    where C: Iterator<Item=usize> {
+    let distance = |c: &Rgba<u8>| -> u32 {
+        (0..3).map(|i| sqr!(c[i] as i32 - color[i] as i32) as u32).sum()
+    };
+    candidates.min_by_key(|&i| distance(&colors[i])).unwrap()
+}
+
+impl Pattern {
+    /// The number of columns and rows of stitches
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.columns, self.rows)
+    }
+
+    /// The threads of the pattern, the most used first
+    pub fn threads(&self) -> &[Thread] {
+        &self.threads
+    }
+
+    /// The thread of the stitch, none if it is not stitched
+    pub fn stitch(&self, column: u32, row: u32) -> Option<&Thread> {
+        self.stitches[(row * self.columns + column) as usize].map(|t| &self.threads[t])
+    }
+
+    ///
+    /// Draws the chart of the pattern, each stitch being a square of `cell` pixels with the symbol of its thread
+    ///
+    /// Every 10th line of the grid is darker and numbered, to help counting the stitches.
+    ///
+    pub fn chart(&self, cell: u32) -> RgbaImage {
+        let cell = cell.max(1);
+        let margin = font::text_width(&self.columns.max(self.rows).to_string(), 1) + 4;
+        // the margin is on all sides, so that the last labels fit
+        let (width, height) = (2 * margin + self.columns * cell + 1, 2 * margin + self.rows * cell + 1);
+        let mut chart: RgbaImage = ImageBuffer::from_pixel(width, height, WHITE);
+
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                if let Some(thread) = self.stitch(column, row) {
+                    let (x, y) = (margin + column * cell + 1, margin + row * cell + 1);
+                    draw_stitch(&mut chart, x, y, cell - 1, thread);
+                }
+            }
+        }
+
+        // the light lines first, so that the dark ones cross over them
+        for dark in &[false, true] {
+            let color = if *dark { BLACK } else { GRAY };
+            let is_dark = |k: u32, last: u32| k.is_multiple_of(10) || k == last;
+            for k in (0..self.columns + 1).filter(|&k| is_dark(k, self.columns) == *dark) {
+                fill(&mut chart, margin + k * cell, margin, 1, self.rows * cell + 1, color);
+            }
+            for k in (0..self.rows + 1).filter(|&k| is_dark(k, self.rows) == *dark) {
+                fill(&mut chart, margin, margin + k * cell, self.columns * cell + 1, 1, color);
+            }
+        }
+
+        for k in (10..self.columns + 1).step_by(10) {
+            let label = k.to_string();
+            let x = (margin + k * cell).saturating_sub(font::text_width(&label, 1) / 2);
+            font::draw_text(&mut chart, &label, x, 1, 1, BLACK);
+        }
+        for k in (10..self.rows + 1).step_by(10) {
+            let label = k.to_string();
+            let x = margin - 2 - font::text_width(&label, 1);
+            font::draw_text(&mut chart, &label, x, (margin + k * cell).saturating_sub(font::HEIGHT / 2), 1, BLACK);
+        }
+
+        chart
+    }
+
+    ///
+    /// Draws the legend of the pattern, listing the symbol, DMC code, number of stitches and name of each thread
+    ///
+    pub fn legend(&self) -> RgbaImage {
+        let scale = 2;
+        let line = (font::HEIGHT + 4) * scale;
+        let chars = |count: u32| count * (font::WIDTH + 1) * scale;
+        let code_x = LEGEND_MARGIN + line + chars(1);
+        let count_x = code_x + chars(6);
+        let name_x = count_x + chars(9);
+
+        let title = self.title();
+        let width = self.threads.iter()
+            .map(|t| name_x + font::text_width(t.name, scale))
+            .chain(Some(LEGEND_MARGIN + font::text_width(&title, scale)))
+            .max().unwrap() + LEGEND_MARGIN;
+        let height = 2 * LEGEND_MARGIN + (self.threads.len() as u32 + 2) * line;
+        let mut legend: RgbaImage = ImageBuffer::from_pixel(width, height, WHITE);
+
+        font::draw_text(&mut legend, &title, LEGEND_MARGIN, LEGEND_MARGIN, scale, BLACK);
+        let y = LEGEND_MARGIN + line;
+        font::draw_text(&mut legend, "DMC", code_x, y, scale, BLACK);
+        font::draw_text(&mut legend, "STITCHES", count_x, y, scale, BLACK);
+        font::draw_text(&mut legend, "NAME", name_x, y, scale, BLACK);
+
+        for (i, thread) in self.threads.iter().enumerate() {
+            let y = LEGEND_MARGIN + (i as u32 + 2) * line;
+            let side = line - scale;
+            fill(&mut legend, LEGEND_MARGIN, y, side + 2, side + 2, BLACK);
+            draw_stitch(&mut legend, LEGEND_MARGIN + 1, y + 1, side, thread);
+
+            let count = thread.stitches.to_string();
+            let text_y = y + (side + 2 - font::HEIGHT * scale) / 2;
+            font::draw_text(&mut legend, thread.code, code_x, text_y, scale, BLACK);
+            font::draw_text(&mut legend, &count, count_x + chars(8) - font::text_width(&count, scale), text_y, scale, BLACK);
+            font::draw_text(&mut legend, thread.name, name_x, text_y, scale, BLACK);
+        }
+
+        legend
+    }
+
+    ///
+    /// Writes the legend of the pattern as a single page PDF document, in A4 format
+    ///
+    pub fn legend_pdf(&self) -> Vec<u8> {
+        let (left, top, line) = (50.0, 792.0, 14.0);
+        let (code_x, count_x, name_x) = (left + 30.0, left + 130.0, left + 150.0);
+        let mut content = String::new();
+        pdf_text(&mut content, left, top, 14.0, &self.title());
+        let header = top - 1.5 * line;
+        pdf_text(&mut content, code_x, header, 10.0, "DMC");
+        // Stitches is 3.557 em wide in Helvetica
+        pdf_text(&mut content, count_x - 35.57, header, 10.0, "Stitches");
+        pdf_text(&mut content, name_x, header, 10.0, "Name");
+
+        for (i, thread) in self.threads.iter().enumerate() {
+            let y = header - (i as f32 + 1.0) * line;
+            // the symbol is drawn dot by dot, as the standard fonts have no such shapes
+            let (side, dot) = (11.0, 1.2);
+            let bottom = y - 2.0;
+            writeln!(content, "{} rg {} {} {} {} re f", pdf_color(thread.color), left, bottom, side, side).unwrap();
+            writeln!(content, "0 G 0.5 w {} {} {} {} re S", left, bottom, side, side).unwrap();
+            writeln!(content, "{} rg", pdf_color(contrast(thread.color))).unwrap();
+            let origin = (left + (side - font::WIDTH as f32 * dot) / 2.0, bottom + (side + font::HEIGHT as f32 * dot) / 2.0);
+            for (row, dots) in font::glyph(thread.symbol).iter().enumerate() {
+                for column in (0..font::WIDTH).filter(|c| dots & (0x10 >> c) != 0) {
+                    let (x, y) = (origin.0 + column as f32 * dot, origin.1 - (row as f32 + 1.0) * dot);
+                    writeln!(content, "{:.2} {:.2} {} {} re f", x, y, dot, dot).unwrap();
+                }
+            }
+
+            let count = thread.stitches.to_string();
+            pdf_text(&mut content, code_x, y, 10.0, thread.code);
+            pdf_text(&mut content, count_x - pdf_width(&count, 10.0), y, 10.0, &count);
+            pdf_text(&mut content, name_x, y, 10.0, thread.name);
+        }
+
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] \
+            /Resources << /Font << /F1 4 0 R >> >> /Contents 5 0 R >>".to_string(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+            format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+        ];
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            writeln!(pdf, "{} 0 obj\n{}\nendobj", i + 1, object).unwrap();
+        }
+        let xref = pdf.len();
+        writeln!(pdf, "xref\n0 {}\n0000000000 65535 f ", objects.len() + 1).unwrap();
+        for offset in offsets {
+            writeln!(pdf, "{:010} 00000 n ", offset).unwrap();
+        }
+        writeln!(pdf, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF", objects.len() + 1, xref).unwrap();
+        pdf.into_bytes()
+    }
+
+    /// The size of the pattern, as the title of the legend
+    fn title(&self) -> String {
+        let total: u32 = self.threads.iter().map(|t| t.stitches).sum();
+        format!("{} x {} stitches, {} stitched, {} threads", self.columns, self.rows, total, self.threads.len())
+    }
+}
+
+const LEGEND_MARGIN: u32 = 10;
+const WHITE: Rgba<u8> = Rgba { data: [255, 255, 255, 255] };
+const GRAY: Rgba<u8> = Rgba { data: [176, 176, 176, 255] };
+const BLACK: Rgba<u8> = Rgba { data: [0, 0, 0, 255] };
+
+/// Fills the rectangle with the color, leaving out the pixels outside of the image
+fn fill(img: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for py in y..(y + height).min(img.height()) {
+        for px in x..(x + width).min(img.width()) {
+            img.put_pixel(px, py, color);
+        }
+    }
+}
+
+/// Draws the square of the thread with its symbol in the middle
+fn draw_stitch(img: &mut RgbaImage, x: u32, y: u32, side: u32, thread: &Thread) {
+    fill(img, x, y, side, side, thread.color);
+    let scale = (side / (font::HEIGHT + 2)).max(1);
+    let symbol_x = x + side.saturating_sub(font::WIDTH * scale) / 2;
+    let symbol_y = y + side.saturating_sub(font::HEIGHT * scale) / 2;
+    font::draw_text(img, &thread.symbol.to_string(), symbol_x, symbol_y, scale, contrast(thread.color));
+}
+
+/// Black on the light colors, white on the dark ones
+fn contrast(color: Rgba<u8>) -> Rgba<u8> {
+    if luminance(color) > 128.0 { BLACK } else { WHITE }
+}
+
+/// The color as the operands of the PDF color operators
+fn pdf_color(color: Rgba<u8>) -> String {
+    format!("{:.3} {:.3} {:.3}", color[0] as f32 / 255.0, color[1] as f32 / 255.0, color[2] as f32 / 255.0)
+}
+
+/// Writes the text in Helvetica, its baseline starting at (x, y)
+fn pdf_text(content: &mut String, x: f32, y: f32, size: f32, text: &str) {
+    let escaped = text.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)");
+    writeln!(content, "0 g BT /F1 {} Tf {:.2} {:.2} Td ({}) Tj ET", size, x, y, escaped).unwrap();
+}
+
+/// The width of a text made of digits in Helvetica, where they are all 0.556 em wide
+fn pdf_width(text: &str, size: f32) -> f32 {
+    text.len() as f32 * 0.556 * size
+}
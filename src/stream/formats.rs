@@ -0,0 +1,296 @@
+use std::io::{self, BufRead, Read, Seek, Write};
+
+use png;
+use tiff;
+use tiff::decoder::{ChunkType, DecodingResult};
+use deep::PngEncoding;
+use stream::{RowReader, RowWriter};
+
+
+fn invalid<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn unsupported(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, message)
+}
+
+///
+/// Copies the pixels of `samples`, made of 1 to 4 channels of 8 bits, into `row` as RGBA
+///
+/// 1 channel is gray, 2 channels are gray and alpha, 3 channels are RGB.
+///
+fn to_rgba(samples: &[u8], channels: usize, row: &mut [u8]) {
+    for (pixel, out) in samples.chunks(channels).zip(row.chunks_mut(4)) {
+        let rgba = match *pixel {
+            [v] => [v, v, v, 255],
+            [v, a] => [v, v, v, a],
+            [r, g, b] => [r, g, b, 255],
+            [r, g, b, a] => [r, g, b, a],
+            _ => unreachable!(),
+        };
+        out.copy_from_slice(&rgba);
+    }
+}
+
+///
+/// The rows of a PNG image, decoded one at a time
+///
+/// The interlaced images cannot be read row by row.
+///
+pub struct PngReader<R: Read> {
+    reader: png::Reader<R>,
+    channels: usize,
+}
+
+impl<R: Read> PngReader<R> {
+    pub fn new(input: R) -> io::Result<PngReader<R>> {
+        let mut decoder = png::Decoder::new(input);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let reader = decoder.read_info().map_err(invalid)?;
+        if reader.info().interlaced {
+            return Err(unsupported("interlaced PNG images cannot be streamed"));
+        }
+        let channels = reader.output_color_type().0.samples();
+        Ok(PngReader { reader, channels })
+    }
+}
+
+impl<R: Read> RowReader for PngReader<R> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.reader.info().size()
+    }
+
+    fn read_row(&mut self, row: &mut [u8]) -> io::Result<()> {
+        let channels = self.channels;
+        let decoded = self.reader.next_row().map_err(invalid)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "the image has fewer rows than expected"))?;
+        to_rgba(decoded.data(), channels, row);
+        Ok(())
+    }
+}
+
+///
+/// The rows of a TIFF image, decoded one strip at a time
+///
+/// The tiled images cannot be read row by row.
+/// The images stored in a single strip are decoded at once.
+///
+pub struct TiffReader<R: Read + Seek> {
+    decoder: tiff::decoder::Decoder<R>,
+    dimensions: (u32, u32),
+    channels: usize,
+    /// The 8 bits samples of the current strip, and the index of the next row in it
+    strip: Vec<u8>,
+    next_row: usize,
+    next_strip: u32,
+}
+
+impl<R: Read + Seek> TiffReader<R> {
+    pub fn new(input: R) -> io::Result<TiffReader<R>> {
+        let mut decoder = tiff::decoder::Decoder::new(input).map_err(invalid)?;
+        if decoder.get_chunk_type() != ChunkType::Strip {
+            return Err(unsupported("tiled TIFF images cannot be streamed"));
+        }
+        let channels = match decoder.colortype().map_err(invalid)? {
+            tiff::ColorType::Gray(8) | tiff::ColorType::Gray(16) => 1,
+            tiff::ColorType::GrayA(8) | tiff::ColorType::GrayA(16) => 2,
+            tiff::ColorType::RGB(8) | tiff::ColorType::RGB(16) => 3,
+            tiff::ColorType::RGBA(8) | tiff::ColorType::RGBA(16) => 4,
+            other => return Err(unsupported(&format!("{:?} TIFF images cannot be streamed", other))),
+        };
+        let dimensions = decoder.dimensions().map_err(invalid)?;
+        Ok(TiffReader { decoder, dimensions, channels, strip: Vec::new(), next_row: 0, next_strip: 0 })
+    }
+}
+
+impl<R: Read + Seek> RowReader for TiffReader<R> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    fn read_row(&mut self, row: &mut [u8]) -> io::Result<()> {
+        let row_size = self.dimensions.0 as usize * self.channels;
+        if self.next_row * row_size >= self.strip.len() {
+            self.strip = match self.decoder.read_chunk(self.next_strip).map_err(invalid)? {
+                DecodingResult::U8(samples) => samples,
+                // keeps the most significant byte, like the 8 bits images
+                DecodingResult::U16(samples) => samples.iter().map(|s| (s >> 8) as u8).collect(),
+                _ => return Err(unsupported("this TIFF sample format cannot be streamed")),
+            };
+            self.next_row = 0;
+            self.next_strip += 1;
+        }
+        let start = self.next_row * row_size;
+        to_rgba(&self.strip[start..start + row_size], self.channels, row);
+        self.next_row += 1;
+        Ok(())
+    }
+}
+
+///
+/// The rows of a binary PNM image: PGM, PPM or PAM
+///
+/// The samples of more than 8 bits are reduced to 8 bits.
+///
+pub struct PnmReader<R: BufRead> {
+    input: R,
+    dimensions: (u32, u32),
+    channels: usize,
+    max_value: u32,
+    samples: Vec<u8>,
+}
+
+impl<R: BufRead> PnmReader<R> {
+    pub fn new(mut input: R) -> io::Result<PnmReader<R>> {
+        let magic = header_token(&mut input)?;
+        let (width, height, channels, max_value) = match &magic[..] {
+            "P5" | "P6" => {
+                let mut number = || header_token(&mut input)?.parse::<u32>().map_err(invalid);
+                let (width, height, max_value) = (number()?, number()?, number()?);
+                (width, height, if magic == "P5" { 1 } else { 3 }, max_value)
+            }
+            "P7" => {
+                let (mut width, mut height, mut depth, mut max_value) = (0, 0, 0, 0);
+                loop {
+                    let key = header_token(&mut input)?;
+                    if key == "ENDHDR" {
+                        break;
+                    }
+                    let value = header_token(&mut input)?;
+                    let number = || value.parse::<u32>().map_err(invalid);
+                    match &key[..] {
+                        "WIDTH" => width = number()?,
+                        "HEIGHT" => height = number()?,
+                        "DEPTH" => depth = number()?,
+                        "MAXVAL" => max_value = number()?,
+                        _ => {}
+                    }
+                }
+                (width, height, depth, max_value)
+            }
+            _ => return Err(unsupported("only the binary PGM, PPM and PAM images can be streamed")),
+        };
+        if !(1..=4).contains(&channels) || max_value == 0 || max_value > 65535 {
+            return Err(invalid("invalid PNM header"));
+        }
+
+        // the header ends with a single whitespace before the samples
+        input.consume(1);
+        Ok(PnmReader { input, dimensions: (width, height), channels: channels as usize, max_value, samples: Vec::new() })
+    }
+}
+
+/// The next word of a PNM header, skipping the comments
+fn header_token<R: BufRead>(input: &mut R) -> io::Result<String> {
+    let mut token = String::new();
+    loop {
+        let byte = match input.fill_buf()?.first() {
+            Some(&b) => b,
+            None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated PNM header")),
+        };
+        if byte == b'#' && token.is_empty() {
+            let mut comment = Vec::new();
+            input.read_until(b'\n', &mut comment)?;
+        } else if byte.is_ascii_whitespace() {
+            if !token.is_empty() {
+                return Ok(token);
+            }
+            input.consume(1);
+        } else {
+            token.push(byte as char);
+            input.consume(1);
+        }
+    }
+}
+
+impl<R: BufRead> RowReader for PnmReader<R> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    fn read_row(&mut self, row: &mut [u8]) -> io::Result<()> {
+        let count = self.dimensions.0 as usize * self.channels;
+        let wide = self.max_value > 255;
+        self.samples.resize(if wide { 2 * count } else { count }, 0);
+        self.input.read_exact(&mut self.samples)?;
+        if wide || self.max_value != 255 {
+            let max_value = self.max_value;
+            let scaled: Vec<u8> = if wide {
+                self.samples.chunks(2).map(|s| ((s[0] as u32 * 256 + s[1] as u32) * 255 / max_value) as u8).collect()
+            } else {
+                self.samples.iter().map(|&s| (s as u32 * 255 / max_value) as u8).collect()
+            };
+            to_rgba(&scaled, self.channels, row);
+        } else {
+            to_rgba(&self.samples, self.channels, row);
+        }
+        Ok(())
+    }
+}
+
+///
+/// Encodes the rows of an RGBA image as a PNG image, as they come
+///
+pub struct PngWriter<W: Write + 'static> {
+    stream: Option<png::StreamWriter<'static, W>>,
+}
+
+impl<W: Write + 'static> PngWriter<W> {
+    pub fn new(output: W, width: u32, height: u32, encoding: &PngEncoding) -> io::Result<PngWriter<W>> {
+        let mut encoder = png::Encoder::new(output, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoding.apply(&mut encoder);
+        let stream = encoder.write_header().and_then(|w| w.into_stream_writer()).map_err(io::Error::other)?;
+        Ok(PngWriter { stream: Some(stream) })
+    }
+}
+
+impl<W: Write + 'static> RowWriter for PngWriter<W> {
+    fn write_row(&mut self, row: &[u8]) -> io::Result<()> {
+        self.stream.as_mut().expect("the image is already finished").write_all(row)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.stream.take() {
+            Some(stream) => stream.finish().map_err(io::Error::other),
+            None => Ok(()),
+        }
+    }
+}
+
+///
+/// Writes the rows of an RGBA image as a PAM image, or as a PPM image without its transparency
+///
+pub struct PnmWriter<W: Write> {
+    output: W,
+    alpha: bool,
+    rgb: Vec<u8>,
+}
+
+impl<W: Write> PnmWriter<W> {
+    pub fn new(mut output: W, width: u32, height: u32, alpha: bool) -> io::Result<PnmWriter<W>> {
+        if alpha {
+            write!(output, "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n", width, height)?;
+        } else {
+            write!(output, "P6\n{} {}\n255\n", width, height)?;
+        }
+        Ok(PnmWriter { output, alpha, rgb: Vec::new() })
+    }
+}
+
+impl<W: Write> RowWriter for PnmWriter<W> {
+    fn write_row(&mut self, row: &[u8]) -> io::Result<()> {
+        if self.alpha {
+            return self.output.write_all(row);
+        }
+        self.rgb.clear();
+        self.rgb.extend(row.chunks(4).flat_map(|p| p[..3].iter().cloned()));
+        self.output.write_all(&self.rgb)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
@@ -0,0 +1,224 @@
+mod formats;
+
+pub use self::formats::{PngReader, PngWriter, PnmReader, PnmWriter, TiffReader};
+
+use std::fmt;
+use std::io;
+use std::mem::size_of;
+
+use image::Rgba;
+use rayon::prelude::*;
+use sampling::{center_pixel, Averaging, Gaussian};
+use {adjust_colors, grid, render_row, Options, PixelMode, Sampling, Steps};
+
+
+///
+/// The rows of an image, read from top to bottom
+///
+pub trait RowReader {
+    /// The width and height of the image
+    fn dimensions(&self) -> (u32, u32);
+
+    /// Reads the next row of pixels into `row`, as RGBA
+    fn read_row(&mut self, row: &mut [u8]) -> io::Result<()>;
+}
+
+///
+/// The destination of the rows of an image, written from top to bottom
+///
+pub trait RowWriter {
+    /// Writes the next row of pixels, given as RGBA
+    fn write_row(&mut self, row: &[u8]) -> io::Result<()>;
+
+    /// Completes the image, once all of its rows are written
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+///
+/// The reasons why an image cannot be streamed
+///
+#[derive(Debug)]
+pub enum StreamError {
+    /// The options cannot be applied to a stream, or need more memory than allowed
+    Options(String),
+    /// The image cannot be read
+    Read(io::Error),
+    /// The pixelised image cannot be written
+    Write(io::Error),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StreamError::Options(ref m) => write!(f, "{}", m),
+            StreamError::Read(ref e) | StreamError::Write(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+///
+/// Pixelises an image row by row, so that it never has to be entirely in memory
+///
+/// The image is read once, keeping only the colors of the cells, or their sums for the average sampling.
+/// The pixelised image is then painted and written in bands of rows.
+/// The heights of the bands are chosen so that the memory used stays under `max_memory` bytes.
+///
+/// The quadtree, slic and lowpoly modes, the adaptive sizes, the dominant sampling, the dithering, the faces, the split channels,
+/// the regions and the mask need the whole image and cannot be streamed, and neither can the scaled images.
+///
+/// # Arguments
+///
+/// * `reader` - The rows of the input image
+/// * `writer` - Receives the rows of the pixelised image
+/// * `options` - The pixelisation settings
+/// * `max_memory` - The memory that the bands, the cells and their colors may use, in bytes
+/// * `progress` - Receives the number of steps done and the total number of steps, after each row.
+///   It is called from several threads.
+///
+pub fn pixelise_stream<R, W, P>(reader: &mut R, writer: &mut W, options: &Options, max_memory: u64, progress: &P)
+                                -> Result<(), StreamError>
+    where R: RowReader + ?Sized, W: RowWriter + ?Sized, P: Fn(u64, u64) + Sync {
+    let (width, height) = reader.dimensions();
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    let band = band_height(width, height, options, max_memory)?;
+    let tessellation = grid(width, height, options).unwrap();
+    let steps = Steps::new(2 * height as u64, progress);
+    let row_size = width as usize * 4;
+
+    let mut row = vec![0; row_size];
+    let mut colors = match options.sampling {
+        Sampling::center => {
+            // the pixels where the colors are sampled, sorted by row
+            let mut centers: Vec<(u32, u32, usize)> = (0..tessellation.cell_count()).map(|i| {
+                let (x, y) = tessellation.center(i);
+                let (x, y) = center_pixel(x, y, width, height);
+                (y, x, i)
+            }).collect();
+            centers.sort_unstable();
+
+            let mut colors = vec![Rgba([0, 0, 0, 0]); tessellation.cell_count()];
+            let mut next = centers.iter().peekable();
+            for y in 0..height {
+                reader.read_row(&mut row).map_err(StreamError::Read)?;
+                while let Some(&&(_, x, i)) = next.peek().filter(|c| c.0 == y) {
+                    let x = x as usize * 4;
+                    colors[i] = Rgba([row[x], row[x + 1], row[x + 2], row[x + 3]]);
+                    next.next();
+                }
+                steps.step();
+            }
+            colors
+        }
+        _ => {
+            let averaging = Averaging::new(options.linear);
+            // the weights of the gaussian sampling, the pixels all weighing 1 for the average one
+            let gaussian = if options.sampling == Sampling::gaussian {
+                Some(Gaussian::new(tessellation.as_ref(), options.gaussian_sigma()))
+            } else {
+                None
+            };
+            let mut sums = vec![[0f64; 4]; tessellation.cell_count()];
+            let mut weights = vec![0f64; tessellation.cell_count()];
+            let mut pixels = vec![0; band as usize * row_size];
+            let mut cells = vec![0; band as usize * width as usize];
+            for top in (0..height).step_by(band as usize) {
+                let rows = band.min(height - top) as usize;
+                for row in pixels[..rows * row_size].chunks_mut(row_size.max(1)) {
+                    reader.read_row(row).map_err(StreamError::Read)?;
+                }
+                let band_cells = cells[..rows * width as usize].par_chunks_mut(width.max(1) as usize);
+                band_cells.with_min_len(options.chunk_size.max(1)).enumerate().for_each(|(y, row)| {
+                    tessellation.row_cells(0, top + y as u32, row);
+                    steps.step();
+                });
+                for (i, (pixel, &cell)) in pixels.chunks(4).zip(&cells[..rows * width as usize]).enumerate() {
+                    let (x, y) = (i as u32 % width, top + i as u32 / width);
+                    let weight = gaussian.as_ref().map_or(1.0, |g| g.weight(x, y, cell));
+                    averaging.add_weighted(&mut sums[cell], pixel, weight);
+                    weights[cell] += weight;
+                }
+            }
+            sums.iter().zip(weights).map(|(sum, weight)| averaging.weighted_mean(sum, weight)).collect()
+        }
+    };
+    adjust_colors(&mut colors, options, None);
+
+    let mut pixelised = vec![0; band as usize * row_size];
+    for top in (0..height).step_by(band as usize) {
+        let rows = band.min(height - top) as usize;
+        let painted = &mut pixelised[..rows * row_size];
+        painted.par_chunks_mut(row_size.max(1)).with_min_len(options.chunk_size.max(1)).enumerate().for_each(|(y, row)| {
+            render_row(tessellation.as_ref(), &colors, options, (top + y as u32, height), row, None, None);
+            steps.step();
+        });
+        for row in painted.chunks(row_size.max(1)) {
+            writer.write_row(row).map_err(StreamError::Write)?;
+        }
+    }
+    writer.finish().map_err(StreamError::Write)
+}
+
+///
+/// The number of rows in the bands of a streamed image, so that the memory used stays under `max_memory` bytes
+///
+/// The memory used is estimated from the number of cells and the size of the rows.
+///
+pub fn band_height(width: u32, height: u32, options: &Options, max_memory: u64) -> Result<u32, StreamError> {
+    let fitted = options.fitted(width, height);
+    let options = &*fitted;
+    if options.mode == PixelMode::quadtree || options.mode == PixelMode::slic || options.mode == PixelMode::lowpoly
+        || options.mode == PixelMode::photomosaic {
+        return Err(StreamError::Options(format!("the {} mode cannot be streamed", options.mode)));
+    }
+    if options.mode == PixelMode::hex && options.adaptive {
+        return Err(StreamError::Options("the detail of a streamed image cannot be found for the adaptive sizes".to_string()));
+    }
+    if options.sampling == Sampling::dominant {
+        return Err(StreamError::Options("the dominant sampling cannot be streamed".to_string()));
+    }
+    if options.dither && options.palette.is_some() {
+        return Err(StreamError::Options("the colors of a streamed image cannot be dithered".to_string()));
+    }
+    if options.faces {
+        return Err(StreamError::Options("the faces cannot be found in a streamed image".to_string()));
+    }
+    if options.split_channels.is_some() {
+        return Err(StreamError::Options("the channels cannot be split in a streamed image".to_string()));
+    }
+    if !options.regions.is_empty() {
+        return Err(StreamError::Options("the regions cannot be pixelised in a streamed image".to_string()));
+    }
+    if options.fit_grid.is_some() {
+        return Err(StreamError::Options("a streamed image cannot be padded nor cropped to its grid".to_string()));
+    }
+    if options.mask.is_some() {
+        return Err(StreamError::Options("the mask cannot be applied to a streamed image".to_string()));
+    }
+    if options.tileable {
+        return Err(StreamError::Options("a streamed image cannot be tileable, its edges are not read together".to_string()));
+    }
+    if options.scale > 1 {
+        return Err(StreamError::Options("a streamed image cannot be scaled up".to_string()));
+    }
+
+    let cell_count = grid(width, height, options).unwrap().cell_count() as u64;
+    let (cell_size, row_size) = match options.sampling {
+        // a color and a center per cell, the input row and a band of output
+        Sampling::center => (size_of::<Rgba<u8>>() + size_of::<(u32, u32, usize)>(), 4),
+        // the sums, the count and the color per cell, a band of input with the cells of its pixels, and of output
+        Sampling::average => (size_of::<[f64; 4]>() + size_of::<f64>() + size_of::<Rgba<u8>>(), 8 + size_of::<usize>()),
+        // and the center of each cell, the pixels being weighted by their distance to it
+        _ => (size_of::<[f64; 4]>() + size_of::<f64>() + size_of::<Rgba<u8>>() + size_of::<(f32, f32)>(), 8 + size_of::<usize>()),
+    };
+    // the voronoi seeds and their buckets
+    let cells = cell_count * (cell_size + 2 * size_of::<usize>()) as u64;
+    let row = width as u64 * row_size as u64;
+
+    if max_memory < cells + row {
+        return Err(StreamError::Options(format!(
+            "streaming this image needs more than {} bytes of memory", cells + row
+        )));
+    }
+    Ok(((max_memory - cells) / row.max(1)).clamp(1, height.max(1) as u64) as u32)
+}
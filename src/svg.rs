@@ -0,0 +1,190 @@
+use std::fmt::Write;
+
+use color::format_color;
+use image::{GenericImageView, Rgba};
+use tessellation::{clip, inset, Tessellation};
+use {cell_colors, cell_map, dot_radius, format_number, plan, tessellation, Canvas, Options, PixelMode, Steps};
+
+
+///
+/// Pixelises an image into an SVG document, each cell being a filled polygon
+///
+/// Only the cells containing at least one pixel of the image are written.
+/// Their polygons are clipped a little beyond the image, so that their borders are hidden outside of it,
+/// like they would be in the rasterised image.
+/// The document is in the pixels of the image, and is shown larger by the scale of the options.
+///
+/// # Arguments
+///
+/// * `img` - The input image to pixelise
+/// * `options` - The pixelisation settings
+///
+pub fn pixelise_svg<I>(img: &I, options: &Options) -> String
+    where I: GenericImageView<Pixel=Rgba<u8>> + Sync {
+    let (width, height) = img.dimensions();
+    let fitted = options.fitted(width, height);
+    match plan::fit_grid(width, height, options) {
+        Some((width, height)) => document(&Canvas { img, width, height, background: options.background }, &fitted),
+        None => document(img, &fitted),
+    }
+}
+
+/// The SVG document of the image, for the options whose columns and rows are already turned into a size
+fn document<I>(img: &I, options: &Options) -> String
+    where I: GenericImageView<Pixel=Rgba<u8>> + Sync {
+    let (width, height) = img.dimensions();
+    let tessellation = tessellation(img, options);
+    let cells = cell_map(tessellation.as_ref(), width, height, options.chunk_size, &Steps::new(0, &|_, _| {}));
+    let colors = cell_colors(img, tessellation.as_ref(), options, Some(&cells));
+
+    let mut used = vec![false; tessellation.cell_count()];
+    for &cell in &cells {
+        used[cell] = true;
+    }
+
+    let margin = options.border_width;
+    let (right, bottom) = (width as f32 + margin, height as f32 + margin);
+    let half_gap = options.gap.max(0.0) / 2.0;
+    let polygons: Vec<(usize, Vec<(f32, f32)>)> = (0..used.len()).filter(|&i| used[i]).map(|i| {
+        let polygon = tessellation.polygon(i);
+        let polygon = if half_gap > 0.0 { inset(&polygon, half_gap) } else { polygon };
+        let polygon = clip(&polygon, |p| p.0 + margin);
+        let polygon = clip(&polygon, |p| right - p.0);
+        let polygon = clip(&polygon, |p| p.1 + margin);
+        (i, clip(&polygon, |p| bottom - p.1))
+    }).collect();
+
+    let mut svg = String::new();
+    writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {w} {h}">"#,
+        width * options.scale.max(1),
+        height * options.scale.max(1),
+        w = width,
+        h = height,
+    ).unwrap();
+
+    let dots = options.mode == PixelMode::dots;
+    if (half_gap > 0.0 || dots) && options.background[3] > 0 {
+        write!(svg, r#"<rect width="{}" height="{}" fill="{}""#, width, height, format_color(options.background)).unwrap();
+        write_opacity(&mut svg, "fill-opacity", options.background);
+        writeln!(svg, "/>").unwrap();
+    }
+    // the shadow is cast by the fills, under the borders
+    let filter = match options.shadow {
+        Some(shadow) => {
+            writeln!(
+                svg,
+                r#"<defs><filter id="shadow" x="-50%" y="-50%" width="200%" height="200%"><feDropShadow dx="{}" dy="{}" stdDeviation="{}" flood-color="{}" flood-opacity="{}"/></filter></defs>"#,
+                format_number(shadow.offset.0),
+                format_number(shadow.offset.1),
+                format_number(shadow.blur.max(0.0) / 4.0),
+                format_color(shadow.color),
+                format_number(shadow.color[3] as f32 / 255.0),
+            ).unwrap();
+            r#" filter="url(#shadow)""#
+        }
+        None => "",
+    };
+    if dots {
+        write_dots(&mut svg, tessellation.as_ref(), &colors, &used, options, filter);
+        writeln!(svg, "</svg>").unwrap();
+        return svg;
+    }
+
+    // the fills go first, so that no cell covers the border of its neighbours
+    writeln!(svg, r#"<g stroke="none"{}>"#, filter).unwrap();
+    for (i, polygon) in &polygons {
+        write!(svg, r#"<polygon points="{}" fill="{}""#, points(polygon), format_color(colors[*i])).unwrap();
+        write_opacity(&mut svg, "fill-opacity", colors[*i]);
+        writeln!(svg, "/>").unwrap();
+    }
+    writeln!(svg, "</g>").unwrap();
+
+    if options.border_width > 0.0 {
+        // the cells separated by a gap have their own borders, inside of them
+        let stroke_width = if half_gap > 0.0 { options.border_width / 2.0 } else { options.border_width };
+        write!(
+            svg,
+            r#"<g fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round""#,
+            format_color(options.border_color),
+            format_number(stroke_width),
+        ).unwrap();
+        write_opacity(&mut svg, "stroke-opacity", options.border_color);
+        writeln!(svg, ">").unwrap();
+        for (_, polygon) in &polygons {
+            let polygon = if half_gap > 0.0 { inset(polygon, stroke_width / 2.0) } else { polygon.clone() };
+            writeln!(svg, r#"<polygon points="{}"/>"#, points(&polygon)).unwrap();
+        }
+        writeln!(svg, "</g>").unwrap();
+    }
+
+    writeln!(svg, "</svg>").unwrap();
+    svg
+}
+
+///
+/// Writes a circle at the center of each cell, for the dots mode
+///
+/// The circles are not clipped by their cells, unlike the rasterised dots, which only differ for the largest ones.
+/// The `filter` attribute casts their shadow, when there is one.
+///
+fn write_dots(svg: &mut String, tessellation: &dyn Tessellation, colors: &[Rgba<u8>], used: &[bool], options: &Options,
+              filter: &str) {
+    let border = options.border_width > 0.0;
+    writeln!(svg, "<g{}>", filter).unwrap();
+    if border {
+        // the border is inside the dots, as in the rasterised image
+        write!(
+            svg,
+            r#"<g stroke="{}" stroke-width="{}""#,
+            format_color(options.border_color),
+            format_number(options.border_width),
+        ).unwrap();
+        write_opacity(svg, "stroke-opacity", options.border_color);
+        writeln!(svg, ">").unwrap();
+    }
+    for i in (0..used.len()).filter(|&i| used[i]) {
+        let radius = dot_radius(colors[i], options);
+        let radius = if border { radius - options.border_width / 2.0 } else { radius };
+        if radius <= 0.0 {
+            continue;
+        }
+        let (x, y) = tessellation.center(i);
+        write!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}""#,
+            format_number(x),
+            format_number(y),
+            format_number(radius),
+            format_color(colors[i]),
+        ).unwrap();
+        write_opacity(svg, "fill-opacity", colors[i]);
+        writeln!(svg, "/>").unwrap();
+    }
+    if border {
+        writeln!(svg, "</g>").unwrap();
+    }
+    writeln!(svg, "</g>").unwrap();
+}
+
+/// The vertices of the polygon, as the value of the `points` attribute
+fn points(polygon: &[(f32, f32)]) -> String {
+    let mut points: Vec<String> = polygon.iter()
+        .map(|&(x, y)| format!("{},{}", format_number(x), format_number(y)))
+        .collect();
+    // the clipping leaves duplicated vertices where a cell touches a limit
+    points.dedup();
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    points.join(" ")
+}
+
+/// The opacity attribute for colors which are not opaque
+fn write_opacity(svg: &mut String, attribute: &str, color: Rgba<u8>) {
+    if color[3] < 255 {
+        write!(svg, r#" {}="{}""#, attribute, format_number(color[3] as f32 / 255.0)).unwrap();
+    }
+}
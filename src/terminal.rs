@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt::Write;
+
+use hexpxl::color::luminance;
+use image::{imageops, FilterType, Rgba, RgbaImage};
+
+
+/// The width of the terminals which do not tell theirs in COLUMNS, in characters
+const DEFAULT_COLUMNS: u32 = 80;
+/// The width of the characters, in pixels, assumed to fit the kitty and sixel images to the terminal
+const CHARACTER_WIDTH: u32 = 10;
+/// The largest payload of each escape sequence of the kitty images, in base64 characters
+const KITTY_CHUNK: usize = 4096;
+/// The levels of each channel of the colors of the sixel images, which have 6 x 6 x 6 colors
+const SIXEL_LEVELS: u32 = 6;
+
+arg_enum! {
+    ///
+    /// How the images are drawn in the terminal
+    ///
+    /// The half blocks draw two pixels in each character, with the 24 bits colors which most terminals have,
+    /// the braille characters 2 x 4 dots of one color, and the kitty and sixel graphics draw the pixels themselves,
+    /// in the terminals which have them.
+    ///
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Graphics {
+        auto,
+        blocks,
+        kitty,
+        sixel,
+        braille,
+    }
+}
+
+impl Graphics {
+    ///
+    /// The graphics of the terminal, those asked for or, for auto, those guessed from its environment:
+    /// kitty for kitty, WezTerm and Ghostty, sixel for foot, mlterm and the terminals whose TERM tells
+    /// they have them, else the half blocks
+    ///
+    pub fn resolved(self) -> Graphics {
+        if self != Graphics::auto {
+            return self;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        let program = env::var("TERM_PROGRAM").unwrap_or_default();
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || ["xterm-kitty", "xterm-ghostty"].contains(&term.as_str())
+            || ["WezTerm", "ghostty"].contains(&program.as_str()) {
+            Graphics::kitty
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            Graphics::sixel
+        } else {
+            Graphics::blocks
+        }
+    }
+}
+
+///
+/// The image as the characters and the escape sequences drawing it in the terminal,
+/// shrunk to the width of the terminal, as told by COLUMNS, when it is wider
+///
+/// The pixels which are more than half transparent are left out, showing the background of the terminal.
+/// The braille characters are left uncolored when NO_COLOR is set.
+///
+pub fn draw(img: &RgbaImage, graphics: Graphics) -> String {
+    let columns = env::var("COLUMNS").ok()
+        .and_then(|columns| columns.trim().parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(DEFAULT_COLUMNS);
+    match graphics.resolved() {
+        Graphics::kitty => kitty(&fitted(img, columns * CHARACTER_WIDTH)),
+        Graphics::sixel => sixel(&fitted(img, columns * CHARACTER_WIDTH)),
+        Graphics::braille => braille(&fitted(img, columns * 2), env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())),
+        _ => blocks(&fitted(img, columns)),
+    }
+}
+
+/// The image shrunk to that width when it is wider, keeping the edges of the cells sharp
+fn fitted(img: &RgbaImage, width: u32) -> RgbaImage {
+    if img.width() <= width {
+        return img.clone();
+    }
+    let height = ((img.height() as u64 * width as u64 + img.width() as u64 / 2) / img.width() as u64).max(1);
+    imageops::resize(img, width, height as u32, FilterType::Nearest)
+}
+
+fn opaque(pixel: &Rgba<u8>) -> bool {
+    pixel[3] >= 128
+}
+
+///
+/// Each character draws the pixels of two rows, the upper half block having the color of the upper one
+/// and the background that of the lower one
+///
+fn blocks(img: &RgbaImage) -> String {
+    let (width, height) = img.dimensions();
+    let mut text = String::new();
+    for y in (0..height).step_by(2) {
+        for x in 0..width {
+            let top = Some(*img.get_pixel(x, y)).filter(opaque);
+            let bottom = Some(y + 1).filter(|&y| y < height).map(|y| *img.get_pixel(x, y)).filter(opaque);
+            match (top, bottom) {
+                (Some(top), Some(bottom)) => write!(
+                    text, "\x1b[38;2;{};{};{};48;2;{};{};{}m\u{2580}",
+                    top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                ),
+                (Some(top), None) => write!(text, "\x1b[0;38;2;{};{};{}m\u{2580}", top[0], top[1], top[2]),
+                (None, Some(bottom)) => write!(text, "\x1b[0;38;2;{};{};{}m\u{2584}", bottom[0], bottom[1], bottom[2]),
+                (None, None) => write!(text, "\x1b[0m "),
+            }.unwrap();
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    text
+}
+
+///
+/// Each braille character draws 2 x 4 pixels, the dots of the pixels which are lit being raised
+///
+/// In color, the lit pixels are those at least as bright as the mean of their character, which has their mean color,
+/// so that the characters of one color are full and those across the edges of the cells draw the brighter side.
+/// Without color, the lit pixels are those brighter than a middle gray.
+///
+fn braille(img: &RgbaImage, colored: bool) -> String {
+    // the bits of the dots of the braille characters, by column and row
+    const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+    let (width, height) = img.dimensions();
+    let mut text = String::new();
+    for top in (0..height).step_by(4) {
+        for left in (0..width).step_by(2) {
+            let pixels: Vec<(u32, Rgba<u8>)> = (0..2).flat_map(|dx| (0..4).map(move |dy| (dx, dy)))
+                .filter(|&(dx, dy)| left + dx < width && top + dy < height)
+                .map(|(dx, dy)| (DOTS[dx as usize][dy as usize], *img.get_pixel(left + dx, top + dy)))
+                .filter(|(_, pixel)| opaque(pixel))
+                .collect();
+            let threshold = if colored {
+                pixels.iter().map(|(_, pixel)| luminance(*pixel)).sum::<f32>() / pixels.len().max(1) as f32
+            } else {
+                128.0
+            };
+            let lit: Vec<&(u32, Rgba<u8>)> = pixels.iter().filter(|(_, pixel)| luminance(*pixel) >= threshold).collect();
+            let dots = lit.iter().fold(0, |dots, (dot, _)| dots | dot);
+            if colored && !lit.is_empty() {
+                let mean = |c: usize| lit.iter().map(|(_, pixel)| pixel[c] as u32).sum::<u32>() / lit.len() as u32;
+                write!(text, "\x1b[38;2;{};{};{}m", mean(0), mean(1), mean(2)).unwrap();
+            }
+            text.push(std::char::from_u32(0x2800 + dots).unwrap());
+        }
+        text.push_str(if colored { "\x1b[0m\n" } else { "\n" });
+    }
+    text
+}
+
+///
+/// The image sent with the graphics protocol of kitty, as base64 RGBA pixels split in chunks
+///
+fn kitty(img: &RgbaImage) -> String {
+    let (width, height) = img.dimensions();
+    let encoded = base64(img);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut text = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        if i == 0 {
+            write!(text, "\x1b_Ga=T,f=32,s={},v={},m={};", width, height, more).unwrap();
+        } else {
+            write!(text, "\x1b_Gm={};", more).unwrap();
+        }
+        // base64 is ascii
+        text.push_str(std::str::from_utf8(chunk).unwrap());
+        text.push_str("\x1b\\");
+    }
+    text.push('\n');
+    text
+}
+
+///
+/// The image as sixels, its colors snapped to 6 levels of each channel
+///
+/// Each sixel draws a column of 6 pixels of one color. The bands of 6 rows are drawn one color after the other,
+/// and the runs of the same sixel are compressed.
+///
+fn sixel(img: &RgbaImage) -> String {
+    let (width, height) = img.dimensions();
+    let level = |value: u8| (value as u32 * (SIXEL_LEVELS - 1) + 127) / 255;
+    let index = |pixel: &Rgba<u8>| (level(pixel[0]) * SIXEL_LEVELS + level(pixel[1])) * SIXEL_LEVELS + level(pixel[2]);
+
+    // the pixels which are left out keep the background
+    let mut text = format!("\x1bP0;1q\"1;1;{};{}", width, height);
+    let used: BTreeSet<u32> = img.pixels().filter(|p| opaque(p)).map(index).collect();
+    for &color in &used {
+        let percent = |level: u32| level * 100 / (SIXEL_LEVELS - 1);
+        let (r, g, b) = (color / SIXEL_LEVELS / SIXEL_LEVELS, color / SIXEL_LEVELS % SIXEL_LEVELS, color % SIXEL_LEVELS);
+        write!(text, "#{};2;{};{};{}", color, percent(r), percent(g), percent(b)).unwrap();
+    }
+    for top in (0..height).step_by(6) {
+        let mut band: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+        for y in top..(top + 6).min(height) {
+            for x in 0..width {
+                let pixel = img.get_pixel(x, y);
+                if opaque(pixel) {
+                    band.entry(index(pixel)).or_insert_with(|| vec![0; width as usize])[x as usize] |= 1 << (y - top);
+                }
+            }
+        }
+        for (i, (color, bits)) in band.iter().enumerate() {
+            if i > 0 {
+                // back to the start of the band for the next color
+                text.push('$');
+            }
+            write!(text, "#{}", color).unwrap();
+            let end = bits.iter().rposition(|&b| b != 0).map_or(0, |end| end + 1);
+            let mut x = 0;
+            while x < end {
+                let run = bits[x..end].iter().take_while(|&&b| b == bits[x]).count();
+                let sixel = (63 + bits[x]) as char;
+                if run > 3 {
+                    write!(text, "!{}{}", run, sixel).unwrap();
+                } else {
+                    (0..run).for_each(|_| text.push(sixel));
+                }
+                x += run;
+            }
+        }
+        text.push('-');
+    }
+    text.push_str("\x1b\\\n");
+    text
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
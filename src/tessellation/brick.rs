@@ -0,0 +1,68 @@
+use tessellation::Tessellation;
+
+
+///
+/// Rows of rectangles, every other row being shifted by half a rectangle, like the bricks of a wall
+///
+pub struct Brick {
+    width: u32,
+    height: u32,
+    columns: usize,
+    rows: usize,
+}
+
+impl Brick {
+    pub fn new(width: u32, height: u32, cell_width: u32, cell_height: u32) -> Brick {
+        Brick {
+            width: cell_width,
+            height: cell_height,
+            // the shifted rows start with half a brick
+            columns: width.div_ceil(cell_width) as usize + 1,
+            rows: height.div_ceil(cell_height) as usize,
+        }
+    }
+
+    /// The shift of the odd rows
+    fn offset(&self, row: usize) -> u32 {
+        if row % 2 == 1 { self.width / 2 } else { 0 }
+    }
+
+    /// The top left corner of the brick, which is left of the image for the first bricks of the shifted rows
+    fn corner(&self, index: usize) -> (i64, i64) {
+        let (column, row) = (index % self.columns, index / self.columns);
+        (
+            column as i64 * self.width as i64 - self.offset(row) as i64,
+            row as i64 * self.height as i64,
+        )
+    }
+}
+
+impl Tessellation for Brick {
+    fn cell_count(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let row = (y / self.height) as usize;
+        row * self.columns + ((x + self.offset(row)) / self.width) as usize
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        let (x, y) = self.corner(index);
+        (x as f32 + (self.width - 1) as f32 / 2.0, y as f32 + (self.height - 1) as f32 / 2.0)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let (left, top) = self.corner(index);
+        let (dx, dy) = ((x as i64 - left) as u32, (y as i64 - top) as u32);
+        // the pixel spans [d, d+1) in the cell
+        dx.min(self.width - 1 - dx).min(dy).min(self.height - 1 - dy) as f32
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        let (x, y) = self.corner(index);
+        let (x, y) = (x as f32, y as f32);
+        let (w, h) = (self.width as f32, self.height as f32);
+        vec![(x, y), (x + w, y), (x + w, y + h), (x, y + h)]
+    }
+}
@@ -0,0 +1,82 @@
+use random::Random;
+use tessellation::{Labels, Tessellation};
+
+
+///
+/// Crystals grown from random seeds until they fill the image
+///
+/// Seeds are scattered randomly on the image, with on average 1 seed per `size` x `size` square, like those of the voronoi mode.
+/// The crystals then grow by a randomized breadth first search: at each step, a random pixel of the edges of the crystals
+/// gives its free neighbours to its crystal, so that the crystals grow unevenly, with jagged boundaries.
+///
+pub struct Crystal {
+    cells: Labels,
+}
+
+/// No crystal yet
+const FREE: u32 = u32::MAX;
+
+impl Crystal {
+    pub fn new(width: u32, height: u32, size: u32, seed: u64) -> Crystal {
+        let size = size.max(1);
+        let count = ((width as u64 * height as u64) / sqr!(size as u64)).max(1);
+
+        let mut random = Random::new(seed);
+        let mut labels = vec![FREE; width as usize * height as usize];
+        let mut edges = Vec::new();
+        let mut crystals = 0;
+        for _ in 0..count {
+            let x = (random.next_f32() * width as f32) as u32;
+            let y = (random.next_f32() * height as f32) as u32;
+            let i = (y * width + x) as usize;
+            // the seeds falling on the same pixel make a single crystal
+            if labels[i] == FREE {
+                labels[i] = crystals;
+                crystals += 1;
+                edges.push(i);
+            }
+        }
+
+        while !edges.is_empty() {
+            let i = edges.swap_remove((random.next_u64() % edges.len() as u64) as usize);
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let row = width as usize;
+            let neighbours = [
+                (x > 0, i.wrapping_sub(1)),
+                (x + 1 < width, i + 1),
+                (y > 0, i.wrapping_sub(row)),
+                (y + 1 < height, i + row),
+            ];
+            for &(inside, n) in &neighbours {
+                if inside && labels[n] == FREE {
+                    labels[n] = labels[i];
+                    edges.push(n);
+                }
+            }
+        }
+
+        Crystal { cells: Labels::new(labels, width, height, crystals as usize) }
+    }
+}
+
+impl Tessellation for Crystal {
+    fn cell_count(&self) -> usize {
+        self.cells.cell_count()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        self.cells.cell_index(x, y)
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.cells.center(index)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        self.cells.edge_distance(x, y, index)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.cells.polygon(index)
+    }
+}
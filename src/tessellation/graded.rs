@@ -0,0 +1,110 @@
+use tessellation::{Tessellation, Voronoi};
+
+
+///
+/// Hexagons whose size changes smoothly across the image
+///
+/// The centers of the hexagons are laid in rows, as in a grid of pointy hexagons, but the rows are as far apart
+/// and their centers as far apart along them as the size at each point asks for. Each row follows the one above it
+/// at 3/2 of the local size, so that the rows bend where the size changes along them, and each pixel belongs
+/// to the cell of the closest center. Where the size does not change, the cells are the regular hexagons.
+///
+pub struct Graded {
+    cells: Voronoi,
+}
+
+impl Graded {
+    ///
+    /// # Arguments
+    ///
+    /// * `width`, `height` - The size of the image
+    /// * `size` - The outer radius of the hexagons around each point, in pixels
+    /// * `flat` - Whether the hexagons have an edge on top, their rows being columns
+    ///
+    pub fn new<F>(width: u32, height: u32, size: F, flat: bool) -> Graded
+        where F: Fn(f32, f32) -> f32 {
+        let size = |x: f32, y: f32| size(x, y).max(1.0);
+        let seeds = if flat {
+            rows(height, width, |x, y| size(y, x)).into_iter().map(|(x, y)| (y, x)).collect()
+        } else {
+            rows(width, height, size)
+        };
+        // the buckets hold a seed each on average
+        let bucket = ((width as f32 * height as f32 / seeds.len().max(1) as f32).sqrt() as u32).max(1);
+        Graded { cells: Voronoi::with_seeds(width, height, bucket, seeds) }
+    }
+}
+
+///
+/// The centers of the rows of pointy hexagons covering an image of that size, the size varying over it
+///
+/// Each row is followed at every pixel of its length, and its centers are placed every `sqrt(3)` times
+/// the size along it, those of every other row being shifted by half of that.
+///
+fn rows<F>(width: u32, height: u32, size: F) -> Vec<(f32, f32)>
+    where F: Fn(f32, f32) -> f32 {
+    let step = 3f32.sqrt();
+    let mut seeds = Vec::new();
+    // the height of the row at each pixel of its length, the first one along the top edge
+    let mut row = vec![0f32; width as usize + 1];
+    for parity in [0.0, 0.5].iter().cycle() {
+        // the number of steps taken along the row, and the next one at which a center is placed
+        let mut steps = 0.0;
+        let mut next = *parity;
+        for x in 0..row.len() {
+            let y = row[x];
+            if x == 0 {
+                if next == 0.0 {
+                    seeds.push((0.0, y));
+                    next += 1.0;
+                }
+                continue;
+            }
+            let (before, previous) = (steps, row[x - 1]);
+            steps += (1.0 + sqr!(y - previous)).sqrt() / (step * size(x as f32, y));
+            while steps >= next {
+                // where the step was crossed, between the pixels
+                let t = (next - before) / (steps - before);
+                seeds.push((x as f32 - 1.0 + t, previous + (y - previous) * t));
+                next += 1.0;
+            }
+        }
+        // the center past the right edge
+        let last = row[width as usize];
+        seeds.push((width as f32 + (next - steps) * step * size(width as f32, last), last));
+
+        if row.iter().all(|&y| y > height as f32) {
+            break;
+        }
+        for (x, y) in row.iter_mut().enumerate() {
+            let here = size(x as f32, *y);
+            // the size halfway to the next row
+            *y += 1.5 * size(x as f32, *y + 0.75 * here);
+        }
+    }
+    // the centers far below the image have no pixels
+    seeds.retain(|&(x, y)| y <= height as f32 + 2.0 * size(x, y));
+    seeds
+}
+
+impl Tessellation for Graded {
+    fn cell_count(&self) -> usize {
+        self.cells.cell_count()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        self.cells.cell_index(x, y)
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.cells.center(index)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        self.cells.edge_distance(x, y, index)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.cells.polygon(index)
+    }
+}
@@ -0,0 +1,110 @@
+use tessellation::Tessellation;
+
+
+///
+/// Rectangles twice as long as they are wide, laid in the zig-zag of a herringbone parquet
+///
+/// With rectangles of sides s and 2s, the pattern repeats along the vectors u = (s, s) and v = (2s, -2s),
+/// each of its unit cells holding a horizontal rectangle [0, 2s) x [0, s)
+/// and a vertical one [2s, 3s) x [-s, s), translated by a u + b v for the integers a and b.
+/// The horizontal rectangles translated along u make the steps of a staircase going down to the right,
+/// the vertical ones filling the steps of the staircase.
+///
+/// Given a point P = a u + b v, its rectangle is one of those of the unit cells
+/// (floor(a) - 1 or floor(a), floor(b) - 1 to floor(b) + 1), found by checking whether P lies in each of them.
+///
+pub struct Herringbone {
+    side: i64,
+    /// The lowest a and b of the unit cells covering the image
+    first: (i64, i64),
+    /// The numbers of unit cells along u and v
+    counts: (usize, usize),
+}
+
+impl Herringbone {
+    ///
+    /// # Arguments
+    ///
+    /// * `side` - The width of the rectangles, which are twice as long
+    ///
+    pub fn new(width: u32, height: u32, side: u32) -> Herringbone {
+        let side = side.max(1) as i64;
+        let (width, height) = (width as i64, height as i64);
+        // the corners of the image give the extreme a = (x + y) / 2s and b = (x - y) / 4s
+        let first = (-2, -height.div_euclid(4 * side) - 2);
+        let last = ((width + height).div_euclid(2 * side) + 2, width.div_euclid(4 * side) + 2);
+
+        Herringbone {
+            side,
+            first,
+            counts: ((last.0 - first.0 + 1) as usize, (last.1 - first.1 + 1) as usize),
+        }
+    }
+
+    /// The top left corner of the unit cell (a, b)
+    fn origin(&self, a: i64, b: i64) -> (i64, i64) {
+        (self.side * (a + 2 * b), self.side * (a - 2 * b))
+    }
+
+    /// The left, top, right and bottom of the rectangle of the index, its right and bottom excluded
+    fn rectangle(&self, index: usize) -> (i64, i64, i64, i64) {
+        let cell = index / 2;
+        let (a, b) = ((cell / self.counts.1) as i64 + self.first.0, (cell % self.counts.1) as i64 + self.first.1);
+        let (x, y) = self.origin(a, b);
+        let s = self.side;
+        if index % 2 == 1 {
+            (x + 2 * s, y - s, x + 3 * s, y + s)
+        } else {
+            (x, y, x + 2 * s, y + s)
+        }
+    }
+
+    fn index(&self, a: i64, b: i64, vertical: bool) -> usize {
+        let cell = (a - self.first.0) as usize * self.counts.1 + (b - self.first.1) as usize;
+        2 * cell + vertical as usize
+    }
+}
+
+impl Tessellation for Herringbone {
+    fn cell_count(&self) -> usize {
+        2 * self.counts.0 * self.counts.1
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let (x, y) = (x as i64, y as i64);
+        let (a, b) = ((x + y).div_euclid(2 * self.side), (x - y).div_euclid(4 * self.side));
+        for da in -1..=0 {
+            for db in -1..=1 {
+                let (a, b) = (a + da, b + db);
+                let (ox, oy) = self.origin(a, b);
+                let (dx, dy) = (x - ox, y - oy);
+                let s = self.side;
+                if (0..2 * s).contains(&dx) && (0..s).contains(&dy) {
+                    return self.index(a, b, false);
+                }
+                if (2 * s..3 * s).contains(&dx) && (-s..s).contains(&dy) {
+                    return self.index(a, b, true);
+                }
+            }
+        }
+        unreachable!("the rectangles around a point cover it")
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        let (left, top, right, bottom) = self.rectangle(index);
+        ((left + right - 1) as f32 / 2.0, (top + bottom - 1) as f32 / 2.0)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let (left, top, right, bottom) = self.rectangle(index);
+        let (x, y) = (x as i64, y as i64);
+        // the pixel spans [d, d+1) in the cell
+        (x - left).min(right - 1 - x).min(y - top).min(bottom - 1 - y) as f32
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        let (left, top, right, bottom) = self.rectangle(index);
+        let (left, top, right, bottom) = (left as f32, top as f32, right as f32, bottom as f32);
+        vec![(left, top), (right, top), (right, bottom), (left, bottom)]
+    }
+}
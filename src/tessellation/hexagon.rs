@@ -0,0 +1,296 @@
+use std::f32::consts::PI;
+use rayon::prelude::*;
+use tessellation::{bisector_distance, closest_region, is_closer, square_around, Tessellation};
+use Orientation;
+
+
+/// The most pixels whose cells are kept in the table of a period of rows, 16 MB of indices
+const MAX_TABLE: usize = 1 << 22;
+
+
+///
+/// A grid of hexagons
+///
+/// Illustration in doc/schema.xcf (gimp file)
+///
+/// On an regular hexagonal grid,
+/// with an hexagon centered on the origin,
+/// with 2 of its edges parallel to the Y axis,
+/// with an outer circle radius R,
+/// with an inner circle radius r = R cos(PI/6),
+///
+/// the hexagons to the left and to the right (on the X axis) of the centered hexagon
+/// have their centers at x = 0, x = 2r, x = 4r etc.
+/// These positions are referred to as x_0, x_2, x_4 etc.
+///
+/// The y coordinate is y_0 = 0
+///
+/// Above and below the line of the hexagons on the X axis, hexagons are shifted by 1r.
+/// Their centers are at 1r, 3r, 5r etc.
+/// Those positions are referred to as x_1, x_3, x_5 etc.
+///
+/// Considering the line above the row on the X axis, the y coordinate is y_1 = 3R/2
+/// Let the gap g = 3R/2
+///
+/// Given a point P on the plane. That point's closest hex center will be located on (Hx,Hy)
+///
+///
+/// How to find Hx and Hy?
+///
+/// On the X axis, the point will be located between 2 x coordinates, x_low and x_high, with abs(high-low) = 1
+/// On the Y axis, the point will be located between 2 y coordinates, y_low and y_high, with abs(high-low) = 1
+///
+/// The closest hex center will be at either of (x_low,y_low), (x_low,y_high), (x_high,y_low) or (x_high,y_high)
+///
+/// We can notice that given the coordinate system we use, there will never be any hex center on indices with different parities.
+/// The closest center is therefore either on coordinates which indices have the same parity.
+///
+/// This reduces the number of points to check to only 2.
+///
+/// This describes pointy top hexagons.
+/// Flat top hexagons use the same grid, with the X and Y axes swapped.
+///
+/// The rows of hexagons repeat every 2 gaps, 3R pixels, the centers of the third row being 2 rows below those
+/// of the first one. The cells of the pixels of the first 3R rows are therefore found once, and those of the next rows
+/// taken from them. The centers of a row of hexagons being 2r = R sqrt(3) pixels apart, which is not a whole number,
+/// the pixels do not repeat along the rows, and the table spans the whole width of the image.
+///
+pub struct Hexagon {
+    inner_radius: f32,
+    gap: f32,
+    columns: usize,
+    rows: usize,
+    flat: bool,
+    /// The rows of pixels after which the cells repeat, 2 rows of hexagons further
+    period: u32,
+    /// The width of the rows of the table, in the pointy top coordinates
+    width: u32,
+    /// The cells of the pixels of the first period of rows, in the pointy top coordinates,
+    /// when the image has enough rows for the table to be worth it and it is not too large
+    table: Option<Vec<u32>>,
+}
+
+impl Hexagon {
+    pub fn new(width: u32, height: u32, outer_radius: u32, orientation: Orientation) -> Hexagon {
+        let inner_radius = outer_radius as f32 * (PI / 6.0).cos();
+        let gap = 3.0 * outer_radius as f32 / 2.0;
+        let flat = orientation == Orientation::flat;
+        let (width, height) = if flat { (height, width) } else { (width, height) };
+
+        let mut hexagon = Hexagon {
+            inner_radius,
+            gap,
+            columns: (width as f32 / inner_radius) as usize + 2,
+            rows: (height as f32 / gap) as usize + 2,
+            flat,
+            period: 3 * outer_radius,
+            width,
+            table: None,
+        };
+        let period = hexagon.period as usize;
+        if period > 0 && height as usize > 2 * period && period * width as usize <= MAX_TABLE {
+            let mut table = vec![0; period * width as usize];
+            table.par_chunks_mut(width.max(1) as usize).enumerate().for_each(|(y, row)| {
+                let mut cells = vec![0; row.len()];
+                hexagon.nearest_line((0, y as u32), false, &mut cells);
+                for (cell, &nearest) in row.iter_mut().zip(&cells) {
+                    *cell = nearest as u32;
+                }
+            });
+            hexagon.table = Some(table);
+        }
+        hexagon
+    }
+
+    /// The index of the cell whose center is the closest to the point (x, y), in the pointy top coordinates
+    fn nearest(&self, x: u32, y: u32) -> usize {
+        let x_low_idx = (x as f32 / self.inner_radius) as u32;
+        let x_high_idx = x_low_idx + 1;
+
+        let y_low_idx = (y as f32 / self.gap) as u32;
+        let y_high_idx = y_low_idx + 1;
+
+        let (corner_a_idx, corner_b_idx) =
+            // do they have the same parity?
+            if x_low_idx.is_multiple_of(2) == y_low_idx.is_multiple_of(2) {
+                ((x_low_idx, y_low_idx), (x_high_idx, y_high_idx))
+            } else {
+                ((x_low_idx, y_high_idx), (x_high_idx, y_low_idx))
+            };
+
+        // first Hx / Hy
+        let corner_a = self.position(corner_a_idx.0, corner_a_idx.1);
+        // second Hx / Hy
+        let corner_b = self.position(corner_b_idx.0, corner_b_idx.1);
+
+        let (x_idx, y_idx) = if is_closer((x as f32, y as f32), corner_a, corner_b) {
+            corner_a_idx
+        } else {
+            corner_b_idx
+        };
+
+        y_idx as usize * self.columns + x_idx as usize
+    }
+
+    ///
+    /// The cells of the points from (x, y) on, along the X axis or along the Y axis, in the pointy top coordinates,
+    /// as found by `nearest`
+    ///
+    /// With the simd feature, the cells of 8 points are found at once on the processors having AVX2.
+    ///
+    fn nearest_line(&self, (x, y): (u32, u32), along_y: bool, cells: &mut [usize]) {
+        let point = |i: usize| if along_y { (x, y + i as u32) } else { (x + i as u32, y) };
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        let done = if is_x86_feature_detected!("avx2") {
+            for (k, lanes) in cells.chunks_exact_mut(8).enumerate() {
+                // AVX2 was just detected
+                unsafe { avx2::nearest(self, std::array::from_fn(|i| point(k * 8 + i)), lanes) };
+            }
+            cells.len() / 8 * 8
+        } else {
+            0
+        };
+        #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+        let done = 0;
+        for (i, cell) in cells.iter_mut().enumerate().skip(done) {
+            let (x, y) = point(i);
+            *cell = self.nearest(x, y);
+        }
+    }
+
+    /// The cell of the point (x, y) of the pointy top coordinates, taken from the table of the first rows
+    fn tabled(&self, table: &[u32], x: u32, y: u32) -> usize {
+        let periods = (y / self.period) as usize;
+        table[(y % self.period) as usize * self.width as usize + x as usize] as usize + periods * 2 * self.columns
+    }
+
+    fn position(&self, x_idx: u32, y_idx: u32) -> (f32, f32) {
+        self.signed_position(x_idx as i64, y_idx as i64)
+    }
+
+    /// The center of the cell and the centers of its 6 neighbours,
+    /// on the same row and on the rows above and below, in the pointy top coordinates
+    fn neighbourhood(&self, index: usize) -> ((f32, f32), [(f32, f32); 6]) {
+        let (i, j) = ((index % self.columns) as i64, (index / self.columns) as i64);
+        let n = |di: i64, dj: i64| self.signed_position(i + di, j + dj);
+
+        (n(0, 0), [n(2, 0), n(1, 1), n(-1, 1), n(-2, 0), n(-1, -1), n(1, -1)])
+    }
+
+    /// Same as position, also valid outside of the image
+    fn signed_position(&self, x_idx: i64, y_idx: i64) -> (f32, f32) {
+        // each center is computed from its indices, so that the rounding errors do not add up along the rows
+        (x_idx as f32 * self.inner_radius, y_idx as f32 * self.gap)
+    }
+}
+
+impl Tessellation for Hexagon {
+    fn cell_count(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let (x, y) = if self.flat { (y, x) } else { (x, y) };
+        match self.table {
+            Some(ref table) if x < self.width => self.tabled(table, x, y),
+            _ => self.nearest(x, y),
+        }
+    }
+
+    fn row_cells(&self, x: u32, y: u32, cells: &mut [usize]) {
+        match self.table {
+            // the row of the image is a column of the pointy top grid
+            Some(ref table) if self.flat && y < self.width => {
+                for (i, cell) in cells.iter_mut().enumerate() {
+                    *cell = self.tabled(table, y, x + i as u32);
+                }
+            }
+            Some(ref table) if !self.flat && x as usize + cells.len() <= self.width as usize => {
+                let start = (y % self.period) as usize * self.width as usize + x as usize;
+                let periods = (y / self.period) as usize;
+                for (cell, &tabled) in cells.iter_mut().zip(&table[start..]) {
+                    *cell = tabled as usize + periods * 2 * self.columns;
+                }
+            }
+            _ if self.flat => self.nearest_line((y, x), true, cells),
+            _ => self.nearest_line((x, y), false, cells),
+        }
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        let (x, y) = self.position((index % self.columns) as u32, (index / self.columns) as u32);
+        if self.flat { (y, x) } else { (x, y) }
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let (x, y) = if self.flat { (y, x) } else { (x, y) };
+        let (c, neighbours) = self.neighbourhood(index);
+
+        neighbours.iter()
+            .map(|&n| bisector_distance((x as f32, y as f32), c, n))
+            .fold(f32::MAX, f32::min)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        let (c, neighbours) = self.neighbourhood(index);
+        let bounds = square_around(c, 2.0 * self.inner_radius.max(self.gap));
+
+        closest_region(c, neighbours.iter().cloned(), bounds).into_iter()
+            .map(|(x, y)| if self.flat { (y, x) } else { (x, y) })
+            .collect()
+    }
+
+    fn axial(&self, index: usize) -> Option<(i32, i32)> {
+        let (i, j) = ((index % self.columns) as i32, (index / self.columns) as i32);
+        // the indices of a center have the same parity, the q axis is along the rows of hexagons
+        Some(if self.flat { (j, (i - j) / 2) } else { ((i - j) / 2, j) })
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod avx2 {
+    use std::arch::x86_64::*;
+    use super::Hexagon;
+
+    ///
+    /// The cells of 8 points of the pointy top coordinates, as found by `Hexagon::nearest`
+    ///
+    /// Each lane does the operations of `nearest`, in the same order, so that the cells are the same.
+    ///
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn nearest(hexagon: &Hexagon, points: [(u32, u32); 8], cells: &mut [usize]) {
+        let xs: [i32; 8] = std::array::from_fn(|i| points[i].0 as i32);
+        let ys: [i32; 8] = std::array::from_fn(|i| points[i].1 as i32);
+        let x = _mm256_cvtepi32_ps(_mm256_loadu_si256(xs.as_ptr() as *const __m256i));
+        let y = _mm256_cvtepi32_ps(_mm256_loadu_si256(ys.as_ptr() as *const __m256i));
+        let radius = _mm256_set1_ps(hexagon.inner_radius);
+        let gap = _mm256_set1_ps(hexagon.gap);
+        let one = _mm256_set1_epi32(1);
+
+        let x_low = _mm256_cvttps_epi32(_mm256_div_ps(x, radius));
+        let y_low = _mm256_cvttps_epi32(_mm256_div_ps(y, gap));
+        let x_high = _mm256_add_epi32(x_low, one);
+        let y_high = _mm256_add_epi32(y_low, one);
+        // all ones in the lanes whose low indices have the same parity
+        let same = _mm256_cmpeq_epi32(_mm256_and_si256(_mm256_xor_si256(x_low, y_low), one), _mm256_setzero_si256());
+        // the first corner is (x_low, y_low) or (x_low, y_high), the second one (x_high, y_high) or (x_high, y_low)
+        let a_y = _mm256_blendv_epi8(y_high, y_low, same);
+        let b_y = _mm256_blendv_epi8(y_low, y_high, same);
+
+        let a_dx = _mm256_sub_ps(_mm256_mul_ps(_mm256_cvtepi32_ps(x_low), radius), x);
+        let a_dy = _mm256_sub_ps(_mm256_mul_ps(_mm256_cvtepi32_ps(a_y), gap), y);
+        let b_dx = _mm256_sub_ps(_mm256_mul_ps(_mm256_cvtepi32_ps(x_high), radius), x);
+        let b_dy = _mm256_sub_ps(_mm256_mul_ps(_mm256_cvtepi32_ps(b_y), gap), y);
+        let a_distance = _mm256_add_ps(_mm256_mul_ps(a_dx, a_dx), _mm256_mul_ps(a_dy, a_dy));
+        let b_distance = _mm256_add_ps(_mm256_mul_ps(b_dx, b_dx), _mm256_mul_ps(b_dy, b_dy));
+        let closer = _mm256_castps_si256(_mm256_cmp_ps::<_CMP_LT_OQ>(a_distance, b_distance));
+
+        let mut x_idx = [0i32; 8];
+        let mut y_idx = [0i32; 8];
+        _mm256_storeu_si256(x_idx.as_mut_ptr() as *mut __m256i, _mm256_blendv_epi8(x_high, x_low, closer));
+        _mm256_storeu_si256(y_idx.as_mut_ptr() as *mut __m256i, _mm256_blendv_epi8(b_y, a_y, closer));
+        for (i, cell) in cells.iter_mut().enumerate() {
+            *cell = y_idx[i] as usize * hexagon.columns + x_idx[i] as usize;
+        }
+    }
+}
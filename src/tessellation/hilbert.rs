@@ -0,0 +1,106 @@
+use tessellation::{Labels, Tessellation};
+
+
+///
+/// Runs of pixels along a Hilbert curve filling the image
+///
+/// The curve is the generalized Hilbert curve of Jakub Červený, which fills rectangles of any size:
+/// the rectangle is split in 2 along its longer side when it is much longer than wide, in 3 otherwise,
+/// the parts being rotated so that the curve goes from a corner of the rectangle to the next one.
+/// Each step of the curve goes to a pixel beside the last one, but for a diagonal step in some odd sizes,
+/// so that the runs of `size` pixels along it are blocks of pixels next to each other.
+///
+pub struct Hilbert {
+    cells: Labels,
+}
+
+impl Hilbert {
+    ///
+    /// # Arguments
+    ///
+    /// * `run` - The number of pixels of each cell
+    ///
+    pub fn new(width: u32, height: u32, run: u32) -> Hilbert {
+        let run = run.max(1);
+        let mut labels = vec![0; width as usize * height as usize];
+        let mut step = 0u32;
+        let mut visit = |x: i64, y: i64| {
+            labels[(y * width as i64 + x) as usize] = step / run;
+            step += 1;
+        };
+        if width >= height {
+            curve((0, 0), (width as i64, 0), (0, height as i64), &mut visit);
+        } else {
+            curve((0, 0), (0, height as i64), (width as i64, 0), &mut visit);
+        }
+        let count = (width * height).div_ceil(run) as usize;
+
+        Hilbert { cells: Labels::new(labels, width, height, count) }
+    }
+}
+
+///
+/// Visits the pixels of the rectangle at `p` along the curve, given its major axis `a`, along which it starts,
+/// and its minor axis `b`
+///
+fn curve<F>(p: (i64, i64), a: (i64, i64), b: (i64, i64), visit: &mut F) where F: FnMut(i64, i64) {
+    let (w, h) = ((a.0 + a.1).abs(), (b.0 + b.1).abs());
+    let da = (a.0.signum(), a.1.signum());
+    let db = (b.0.signum(), b.1.signum());
+    if w == 0 || h == 0 {
+        return;
+    }
+    if h == 1 || w == 1 {
+        let (count, d) = if h == 1 { (w, da) } else { (h, db) };
+        for i in 0..count {
+            visit(p.0 + i * d.0, p.1 + i * d.1);
+        }
+        return;
+    }
+
+    let mut a2 = (a.0.div_euclid(2), a.1.div_euclid(2));
+    let mut b2 = (b.0.div_euclid(2), b.1.div_euclid(2));
+    if 2 * w > 3 * h {
+        // the halves along the major axis, with an even width first
+        if (a2.0 + a2.1).abs() % 2 == 1 && w > 2 {
+            a2 = (a2.0 + da.0, a2.1 + da.1);
+        }
+        curve(p, a2, b, visit);
+        curve((p.0 + a2.0, p.1 + a2.1), (a.0 - a2.0, a.1 - a2.1), b, visit);
+    } else {
+        // up along the minor axis, across along the major one, and back down, with an even height first
+        if (b2.0 + b2.1).abs() % 2 == 1 && h > 2 {
+            b2 = (b2.0 + db.0, b2.1 + db.1);
+        }
+        curve(p, b2, a2, visit);
+        curve((p.0 + b2.0, p.1 + b2.1), a, (b.0 - b2.0, b.1 - b2.1), visit);
+        curve(
+            (p.0 + (a.0 - da.0) + (b2.0 - db.0), p.1 + (a.1 - da.1) + (b2.1 - db.1)),
+            (-b2.0, -b2.1),
+            (-(a.0 - a2.0), -(a.1 - a2.1)),
+            visit,
+        );
+    }
+}
+
+impl Tessellation for Hilbert {
+    fn cell_count(&self) -> usize {
+        self.cells.cell_count()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        self.cells.cell_index(x, y)
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.cells.center(index)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        self.cells.edge_distance(x, y, index)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.cells.polygon(index)
+    }
+}
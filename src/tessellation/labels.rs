@@ -0,0 +1,190 @@
+use tessellation::Tessellation;
+
+
+///
+/// Cells given by the cell of each pixel, for the cells which are not shapes
+///
+/// The cell of each pixel is stored, as well as its distance to the closest edge,
+/// found by a chamfer distance transform.
+///
+pub struct Labels {
+    width: u32,
+    height: u32,
+    /// The cell of each pixel, row by row
+    labels: Vec<u32>,
+    /// The distance from each pixel to the closest pixel of another cell
+    edges: Vec<f32>,
+    centers: Vec<(f32, f32)>,
+    /// The first pixel of each cell, in reading order
+    firsts: Vec<u32>,
+}
+
+impl Labels {
+    ///
+    /// # Arguments
+    ///
+    /// * `labels` - The cell of each pixel, row by row, each of the `count` cells having at least one pixel
+    ///
+    pub fn new(labels: Vec<u32>, width: u32, height: u32, count: usize) -> Labels {
+        let mut firsts = vec![u32::MAX; count];
+        for (i, &label) in labels.iter().enumerate() {
+            if firsts[label as usize] == u32::MAX {
+                firsts[label as usize] = i as u32;
+            }
+        }
+        let centers = centers(&labels, width, count);
+        let edges = edge_distances(&labels, width, height);
+
+        Labels {
+            width,
+            height,
+            labels,
+            edges,
+            centers,
+            firsts,
+        }
+    }
+
+    /// Whether the pixel (x, y), which may be outside of the image, is in the cell
+    fn contains(&self, x: i64, y: i64, index: usize) -> bool {
+        x >= 0 && y >= 0 && x < self.width as i64 && y < self.height as i64
+            && self.labels[(y * self.width as i64 + x) as usize] as usize == index
+    }
+}
+
+///
+/// The center of each cell, the mean of its pixels
+///
+/// A cell whose mean is in another cell is centered on its pixel closest to the mean instead.
+///
+fn centers(labels: &[u32], width: u32, count: usize) -> Vec<(f32, f32)> {
+    let position = |i: usize| ((i as u32 % width) as f32, (i as u32 / width) as f32);
+    let mut sums = vec![(0.0f64, 0.0f64, 0u64); count];
+    for (i, &label) in labels.iter().enumerate() {
+        let (x, y) = position(i);
+        let sum = &mut sums[label as usize];
+        *sum = (sum.0 + x as f64, sum.1 + y as f64, sum.2 + 1);
+    }
+    let mut centers: Vec<(f32, f32)> = sums.iter().map(|s| ((s.0 / s.2 as f64) as f32, (s.1 / s.2 as f64) as f32)).collect();
+
+    let outside: Vec<bool> = centers.iter().enumerate().map(|(cell, &(x, y))| {
+        labels[(y.round() as u32 * width + x.round() as u32) as usize] as usize != cell
+    }).collect();
+    if outside.contains(&true) {
+        let means = centers.clone();
+        let mut closest = vec![f32::MAX; count];
+        for (i, &label) in labels.iter().enumerate() {
+            let cell = label as usize;
+            if outside[cell] {
+                let (x, y) = position(i);
+                let distance = sqr!(x - means[cell].0) + sqr!(y - means[cell].1);
+                if distance < closest[cell] {
+                    closest[cell] = distance;
+                    centers[cell] = (x, y);
+                }
+            }
+        }
+    }
+    centers
+}
+
+///
+/// The distance from each pixel to the closest pixel of another cell
+///
+/// The pixels next to another cell are at 0, the distances of the other ones being propagated
+/// forwards and then backwards over the image, 1 to the side and √2 diagonally.
+///
+fn edge_distances(labels: &[u32], width: u32, height: u32) -> Vec<f32> {
+    let (w, h) = (width as i64, height as i64);
+    let label = |x: i64, y: i64| labels[(y * w + x) as usize];
+    let mut edges: Vec<f32> = (0..labels.len()).map(|i| {
+        let (x, y) = (i as i64 % w, i as i64 / w);
+        let here = labels[i];
+        let next_to_other = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)].iter()
+            .any(|&(nx, ny)| nx >= 0 && ny >= 0 && nx < w && ny < h && label(nx, ny) != here);
+        if next_to_other { 0.0 } else { f32::MAX }
+    }).collect();
+
+    let diagonal = 2.0f32.sqrt();
+    let mut propagate = |x: i64, y: i64, neighbours: &[(i64, i64, f32)]| {
+        let i = (y * w + x) as usize;
+        for &(dx, dy, step) in neighbours {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx >= 0 && ny >= 0 && nx < w && ny < h {
+                let through = edges[(ny * w + nx) as usize] + step;
+                if through < edges[i] {
+                    edges[i] = through;
+                }
+            }
+        }
+    };
+    let before = [(-1, 0, 1.0), (0, -1, 1.0), (-1, -1, diagonal), (1, -1, diagonal)];
+    for y in 0..h {
+        for x in 0..w {
+            propagate(x, y, &before);
+        }
+    }
+    let after = [(1, 0, 1.0), (0, 1, 1.0), (1, 1, diagonal), (-1, 1, diagonal)];
+    for y in (0..h).rev() {
+        for x in (0..w).rev() {
+            propagate(x, y, &after);
+        }
+    }
+    edges
+}
+
+impl Tessellation for Labels {
+    fn cell_count(&self) -> usize {
+        self.centers.len()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        self.labels[(y * self.width + x) as usize] as usize
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.centers[index]
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, _index: usize) -> f32 {
+        self.edges[(y * self.width + x) as usize]
+    }
+
+    ///
+    /// The outline of the cell, following the sides of its pixels
+    ///
+    /// The outline goes clockwise around the cell, from the top left corner of its first pixel.
+    /// The cells enclosed by the cell are not cut out of it.
+    ///
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        let first = self.firsts[index];
+        let start = ((first % self.width) as i64, (first / self.width) as i64);
+        // the pixels above and to the left of the first one are in other cells, so its top left corner is a vertex
+        let mut outline = vec![(start.0 as f32, start.1 as f32)];
+        let (mut vertex, mut direction) = (start, (1i64, 0i64));
+        loop {
+            vertex = (vertex.0 + direction.0, vertex.1 + direction.1);
+            // the cell is on the right of the direction, the Y axis pointing down
+            let right = (-direction.1, direction.0);
+            let ahead = |side: i64| (
+                (2 * vertex.0 + direction.0 + side * right.0).div_euclid(2),
+                (2 * vertex.1 + direction.1 + side * right.1).div_euclid(2),
+            );
+            let (ahead_right, ahead_left) = (ahead(1), ahead(-1));
+            let turn = if !self.contains(ahead_right.0, ahead_right.1, index) {
+                right
+            } else if self.contains(ahead_left.0, ahead_left.1, index) {
+                (direction.1, -direction.0)
+            } else {
+                direction
+            };
+            if vertex == start && turn == (1, 0) {
+                return outline;
+            }
+            if turn != direction {
+                outline.push((vertex.0 as f32, vertex.1 as f32));
+                direction = turn;
+            }
+        }
+    }
+}
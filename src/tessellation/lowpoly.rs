@@ -0,0 +1,308 @@
+use image::{GenericImageView, Rgba};
+use tessellation::Tessellation;
+
+
+///
+/// Triangles between feature points of the image, like a low poly model
+///
+/// The gradient of each pixel is found by a Sobel filter of its luma and alpha.
+/// Each square of `size` x `size` pixels whose strongest gradient is above the mean gradient of the image
+/// gives a feature point at its strongest pixel, on the edges and the corners of the image,
+/// and each square of 4 `size` x 4 `size` pixels without any gives its center,
+/// so that the smooth areas are cut into larger triangles. The corners of the image and points every `size` pixels
+/// along its sides make the points span the whole image.
+///
+/// The points are joined by their Delaunay triangulation, built by inserting them one by one (Bowyer-Watson):
+/// the triangles whose circumcircles contain the new point are removed, and the hole is filled with triangles
+/// joining its sides to the point. The points are on the pixels, so that the tests of the triangulation are exact.
+///
+pub struct Lowpoly {
+    width: u32,
+    triangles: Vec<[(f32, f32); 3]>,
+    /// The triangle of each pixel, row by row
+    labels: Vec<u32>,
+}
+
+/// No triangle, beyond the sides of the image
+const NONE: usize = usize::MAX;
+
+///
+/// A Delaunay triangulation being built
+///
+/// The vertices of each triangle turn the same way, and its neighbour i is across its side opposite to its vertex i.
+///
+struct Triangulation {
+    points: Vec<(i64, i64)>,
+    vertices: Vec<[usize; 3]>,
+    neighbours: Vec<[usize; 3]>,
+    removed: Vec<bool>,
+}
+
+/// Twice the signed area of the triangle abc, positive when its vertices turn the same way as those of the triangulation
+fn orient(a: (i64, i64), b: (i64, i64), c: (i64, i64)) -> i64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+impl Triangulation {
+    /// The rectangle from (0, 0) to `corner`, cut along its diagonal
+    fn new(corner: (i64, i64)) -> Triangulation {
+        Triangulation {
+            points: vec![(0, 0), (corner.0, 0), corner, (0, corner.1)],
+            vertices: vec![[0, 1, 2], [0, 2, 3]],
+            neighbours: vec![[NONE, 1, NONE], [NONE, NONE, 0]],
+            removed: vec![false; 2],
+        }
+    }
+
+    fn corner(&self, triangle: usize, i: usize) -> (i64, i64) {
+        self.points[self.vertices[triangle][i % 3]]
+    }
+
+    /// Whether the point is strictly inside the circumcircle of the triangle, the coordinates being taken from the point
+    fn in_circle(&self, triangle: usize, p: (i64, i64)) -> bool {
+        let [a, b, c] = [0, 1, 2].map(|i| {
+            let v = self.corner(triangle, i);
+            ((v.0 - p.0) as i128, (v.1 - p.1) as i128)
+        });
+        let lift = |v: (i128, i128)| v.0 * v.0 + v.1 * v.1;
+        let determinant = lift(a) * (b.0 * c.1 - b.1 * c.0)
+            - lift(b) * (a.0 * c.1 - a.1 * c.0)
+            + lift(c) * (a.0 * b.1 - a.1 * b.0);
+        determinant > 0
+    }
+
+    /// The triangle containing the point, found by walking towards it from the triangle `start`
+    fn locate(&self, start: usize, p: (i64, i64)) -> usize {
+        let mut triangle = start;
+        'walk: loop {
+            for i in 0..3 {
+                let next = self.neighbours[triangle][i];
+                if next != NONE && orient(self.corner(triangle, i + 1), self.corner(triangle, i + 2), p) < 0 {
+                    triangle = next;
+                    continue 'walk;
+                }
+            }
+            return triangle;
+        }
+    }
+
+    /// Adds the point, returning one of its triangles
+    fn insert(&mut self, p: (i64, i64), start: usize) -> usize {
+        let index = self.points.len();
+        self.points.push(p);
+
+        // the triangles whose circumcircles contain the point, and the sides around them
+        let first = self.locate(start, p);
+        let mut cavity = vec![first];
+        self.removed[first] = true;
+        let mut sides = Vec::new();
+        let mut k = 0;
+        while k < cavity.len() {
+            let triangle = cavity[k];
+            k += 1;
+            for i in 0..3 {
+                let next = self.neighbours[triangle][i];
+                if next != NONE && self.removed[next] {
+                    continue;
+                }
+                if next != NONE && self.in_circle(next, p) {
+                    self.removed[next] = true;
+                    cavity.push(next);
+                } else {
+                    let vertices = self.vertices[triangle];
+                    sides.push((vertices[(i + 1) % 3], vertices[(i + 2) % 3], next, triangle));
+                }
+            }
+        }
+
+        // the new triangles join the sides to the point, but for the sides of the image the point lies on
+        let created = self.vertices.len();
+        sides.retain(|&(a, b, _, _)| orient(self.points[a], self.points[b], p) != 0);
+        for &(a, b, outside, removed) in &sides {
+            let triangle = self.vertices.len();
+            self.vertices.push([a, b, index]);
+            self.neighbours.push([NONE, NONE, outside]);
+            self.removed.push(false);
+            if outside != NONE {
+                for n in self.neighbours[outside].iter_mut() {
+                    if *n == removed {
+                        *n = triangle;
+                    }
+                }
+            }
+        }
+        for triangle in created..self.vertices.len() {
+            let [a, b, _] = self.vertices[triangle];
+            for other in created..self.vertices.len() {
+                let [c, d, _] = self.vertices[other];
+                if c == b {
+                    self.neighbours[triangle][0] = other;
+                }
+                if d == a {
+                    self.neighbours[triangle][1] = other;
+                }
+            }
+        }
+        created
+    }
+}
+
+impl Lowpoly {
+    pub fn new<I>(img: &I, size: u32) -> Lowpoly
+        where I: GenericImageView<Pixel=Rgba<u8>> {
+        let (width, height) = img.dimensions();
+        let size = size.max(1);
+        let gradients = gradients(img);
+
+        let mut points = features(&gradients, width, height, size);
+        for k in 1..width.div_ceil(size) {
+            points.push((k * size, 0));
+            points.push((k * size, height));
+        }
+        for k in 1..height.div_ceil(size) {
+            points.push((0, k * size));
+            points.push((width, k * size));
+        }
+        // along the rows of squares, one way and back, so that each point is close to the last one
+        points.sort_by_key(|&(x, y)| {
+            let row = y / size;
+            (row, if row % 2 == 1 { u32::MAX - x } else { x })
+        });
+
+        let mut triangulation = Triangulation::new((width as i64, height as i64));
+        let mut last = 0;
+        for &(x, y) in &points {
+            last = triangulation.insert((x as i64, y as i64), last);
+        }
+
+        let triangles: Vec<[(f32, f32); 3]> = (0..triangulation.vertices.len())
+            .filter(|&t| !triangulation.removed[t])
+            .map(|t| [0, 1, 2].map(|i| {
+                let (x, y) = triangulation.corner(t, i);
+                (x as f32, y as f32)
+            }))
+            .collect();
+
+        // each pixel goes to the triangle it is the deepest in, those on the sides to either of their triangles
+        let mut labels = vec![0; width as usize * height as usize];
+        let mut depths = vec![f32::MIN; labels.len()];
+        for (index, triangle) in triangles.iter().enumerate() {
+            let left = triangle.iter().map(|v| v.0).fold(f32::MAX, f32::min).max(0.0) as u32;
+            let top = triangle.iter().map(|v| v.1).fold(f32::MAX, f32::min).max(0.0) as u32;
+            let right = (triangle.iter().map(|v| v.0).fold(f32::MIN, f32::max) as u32).min(width.saturating_sub(1));
+            let bottom = (triangle.iter().map(|v| v.1).fold(f32::MIN, f32::max) as u32).min(height.saturating_sub(1));
+            for y in top..=bottom {
+                for x in left..=right {
+                    let i = (y * width + x) as usize;
+                    let depth = inner_distance(triangle, (x as f32, y as f32));
+                    if depth > depths[i] {
+                        depths[i] = depth;
+                        labels[i] = index as u32;
+                    }
+                }
+            }
+        }
+
+        Lowpoly { width, triangles, labels }
+    }
+}
+
+/// The strength of the edges at each pixel, the Sobel gradient of its luma and alpha, row by row
+fn gradients<I>(img: &I) -> Vec<f32>
+    where I: GenericImageView<Pixel=Rgba<u8>> {
+    let (width, height) = img.dimensions();
+    let mut values = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let p = img.get_pixel(x, y);
+            values.push([0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32, p[3] as f32]);
+        }
+    }
+    let value = |x: i64, y: i64| {
+        let (x, y) = (x.clamp(0, width as i64 - 1), y.clamp(0, height as i64 - 1));
+        values[(y * width as i64 + x) as usize]
+    };
+    let mut gradients = Vec::with_capacity(values.len());
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let strength: f32 = (0..2).map(|c| {
+                let v = |dx: i64, dy: i64| value(x + dx, y + dy)[c];
+                let gx = v(1, -1) + 2.0 * v(1, 0) + v(1, 1) - v(-1, -1) - 2.0 * v(-1, 0) - v(-1, 1);
+                let gy = v(-1, 1) + 2.0 * v(0, 1) + v(1, 1) - v(-1, -1) - 2.0 * v(0, -1) - v(1, -1);
+                (sqr!(gx) + sqr!(gy)).sqrt()
+            }).sum();
+            gradients.push(strength);
+        }
+    }
+    gradients
+}
+
+/// The feature points inside the image: the strongest pixels of the squares, and the centers of the empty areas
+fn features(gradients: &[f32], width: u32, height: u32, size: u32) -> Vec<(u32, u32)> {
+    let mean = gradients.iter().sum::<f32>() / gradients.len().max(1) as f32;
+    let (columns, rows) = (width.div_ceil(size), height.div_ceil(size));
+    let mut points = Vec::new();
+    let mut found = vec![false; (columns * rows) as usize];
+    for row in 0..rows {
+        for column in 0..columns {
+            let mut best = (mean, None);
+            for y in row * size..((row + 1) * size).min(height) {
+                for x in column * size..((column + 1) * size).min(width) {
+                    let gradient = gradients[(y * width + x) as usize];
+                    if gradient > best.0 {
+                        best = (gradient, Some((x, y)));
+                    }
+                }
+            }
+            // the points on the top and left sides of the image would fall on those along them
+            if let Some((x, y)) = best.1.filter(|&(x, y)| x > 0 && y > 0) {
+                points.push((x, y));
+                found[(row * columns + column) as usize] = true;
+            }
+        }
+    }
+
+    for row in (0..rows).step_by(4) {
+        for column in (0..columns).step_by(4) {
+            let (bottom, right) = ((row + 4).min(rows), (column + 4).min(columns));
+            let empty = (row..bottom).all(|r| (column..right).all(|c| !found[(r * columns + c) as usize]));
+            let center = ((column * size + (right * size).min(width)) / 2, (row * size + (bottom * size).min(height)) / 2);
+            if empty && center.0 > 0 && center.1 > 0 {
+                points.push(center);
+            }
+        }
+    }
+    points
+}
+
+/// The distance from the point to the closest side of the triangle, negative outside of it
+fn inner_distance(triangle: &[(f32, f32); 3], p: (f32, f32)) -> f32 {
+    (0..3).map(|i| {
+        let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+        let length = (sqr!(b.0 - a.0) + sqr!(b.1 - a.1)).sqrt();
+        ((b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)) / length
+    }).fold(f32::MAX, f32::min)
+}
+
+impl Tessellation for Lowpoly {
+    fn cell_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        self.labels[(y * self.width + x) as usize] as usize
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        let triangle = &self.triangles[index];
+        ((triangle[0].0 + triangle[1].0 + triangle[2].0) / 3.0, (triangle[0].1 + triangle[1].1 + triangle[2].1) / 3.0)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        inner_distance(&self.triangles[index], (x as f32, y as f32)).max(0.0)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.triangles[index].to_vec()
+    }
+}
@@ -0,0 +1,253 @@
+mod square;
+mod hexagon;
+mod triangle;
+mod voronoi;
+mod brick;
+mod rhombille;
+mod quadtree;
+mod slic;
+mod labels;
+mod scales;
+mod herringbone;
+mod penrose;
+mod trihex;
+mod rings;
+mod spiral;
+mod hilbert;
+mod lowpoly;
+mod crystal;
+mod graded;
+mod rotated;
+mod stretched;
+mod shifted;
+
+pub use self::square::Square;
+pub use self::hexagon::Hexagon;
+pub use self::triangle::Triangle;
+pub use self::voronoi::Voronoi;
+pub use self::brick::Brick;
+pub use self::rhombille::Rhombille;
+pub use self::quadtree::Quadtree;
+pub use self::slic::Slic;
+pub use self::labels::Labels;
+pub use self::scales::Scales;
+pub use self::herringbone::Herringbone;
+pub use self::penrose::Penrose;
+pub use self::trihex::Trihex;
+pub use self::rings::Rings;
+pub use self::spiral::Spiral;
+pub use self::hilbert::Hilbert;
+pub use self::lowpoly::Lowpoly;
+pub use self::crystal::Crystal;
+pub use self::graded::Graded;
+pub use self::rotated::Rotated;
+pub use self::stretched::Stretched;
+pub use self::shifted::Shifted;
+
+
+///
+/// A partition of the image into cells
+///
+/// The cells are identified by an index in [0, cell_count).
+/// Some indices may not match any pixel of the image.
+///
+pub trait Tessellation: Sync {
+    /// The number of cell indices
+    fn cell_count(&self) -> usize;
+
+    /// The index of the cell containing the pixel (x, y)
+    fn cell_index(&self, x: u32, y: u32) -> usize;
+
+    /// The indices of the cells of the pixels of the row `y`, from the column `x` on, as many as `cells` holds
+    fn row_cells(&self, x: u32, y: u32, cells: &mut [usize]) {
+        for (i, cell) in cells.iter_mut().enumerate() {
+            *cell = self.cell_index(x + i as u32, y);
+        }
+    }
+
+    /// The center of the cell, where its color is sampled in center sampling
+    fn center(&self, index: usize) -> (f32, f32);
+
+    /// The distance from the pixel (x, y) to the closest edge of its cell `index`
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32;
+
+    /// The vertices of the cell, in order
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)>;
+
+    /// The axial coordinates q and r of the cell, for the grids of hexagons
+    fn axial(&self, _index: usize) -> Option<(i32, i32)> {
+        None
+    }
+}
+
+///
+/// Whether the candidate center `a` is closer to the point `p` than the candidate center `b`
+///
+/// The coordinates are floats, as the point is as often left of the centers as right of them,
+/// and their differences would wrap around as unsigned integers.
+///
+pub fn is_closer(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> bool {
+    let d1 = sqr!(a.0 - p.0) + sqr!(a.1 - p.1);
+    let d2 = sqr!(b.0 - p.0) + sqr!(b.1 - p.1);
+
+    d1 < d2
+}
+
+///
+/// The distance from the point `p` to the bisector of the centers `c` and `n`
+///
+/// When the cells are the points closest to their centers, the edge between the cells of `c` and `n`
+/// lies on this bisector, and the distance from `p` to the edge of its cell `c`
+/// is the smallest of these distances amongst all the neighbours `n`.
+///
+pub fn bisector_distance(p: (f32, f32), c: (f32, f32), n: (f32, f32)) -> f32 {
+    let dc = sqr!(c.0 - p.0) + sqr!(c.1 - p.1);
+    let dn = sqr!(n.0 - p.0) + sqr!(n.1 - p.1);
+    let between = (sqr!(n.0 - c.0) + sqr!(n.1 - c.1)).sqrt();
+
+    (dn - dc) / (2.0 * between)
+}
+
+///
+/// Clips the convex polygon to the points where `f` is positive
+///
+/// `f` must be linear, so that the intersections of the edges of the polygon
+/// with the limit of the area are found by interpolating `f`.
+///
+pub fn clip<F>(polygon: &[(f32, f32)], f: F) -> Vec<(f32, f32)>
+    where F: Fn((f32, f32)) -> f32 {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let (fa, fb) = (f(a), f(b));
+        if fa >= 0.0 {
+            clipped.push(a);
+        }
+        if (fa >= 0.0) != (fb >= 0.0) {
+            let t = fa / (fa - fb);
+            clipped.push((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)));
+        }
+    }
+    clipped
+}
+
+///
+/// The convex polygon shrunk by moving each of its edges by `distance` towards its inside
+///
+pub fn inset(polygon: &[(f32, f32)], distance: f32) -> Vec<(f32, f32)> {
+    let count = polygon.len() as f32;
+    let inside = polygon.iter().fold((0.0, 0.0), |c, p| (c.0 + p.0 / count, c.1 + p.1 / count));
+    (0..polygon.len()).fold(polygon.to_vec(), |shrunk, i| {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        let length = (sqr!(b.0 - a.0) + sqr!(b.1 - a.1)).sqrt();
+        if length == 0.0 {
+            return shrunk;
+        }
+        // the distance to the line of the edge, positive on the inside
+        let side = |p: (f32, f32)| ((b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)) / length;
+        let sign = if side(inside) < 0.0 { -1.0 } else { 1.0 };
+        clip(&shrunk, |p| sign * side(p) - distance)
+    })
+}
+
+///
+/// The points of `bounds` which are closer to the center `c` than to any of the `neighbours`
+///
+pub fn closest_region<N>(c: (f32, f32), neighbours: N, bounds: Vec<(f32, f32)>) -> Vec<(f32, f32)>
+    where N: IntoIterator<Item=(f32, f32)> {
+    neighbours.into_iter().fold(bounds, |polygon, n| {
+        clip(&polygon, |p| bisector_distance(p, c, n))
+    })
+}
+
+///
+/// The square of side 2 `half_side` centered on `c`
+///
+pub fn square_around(c: (f32, f32), half_side: f32) -> Vec<(f32, f32)> {
+    vec![
+        (c.0 - half_side, c.1 - half_side),
+        (c.0 + half_side, c.1 - half_side),
+        (c.0 + half_side, c.1 + half_side),
+        (c.0 - half_side, c.1 + half_side),
+    ]
+}
+
+///
+/// Whether the point is inside the polygon, by the number of its edges crossed by a ray from the point to the right
+///
+pub fn contains(polygon: &[(f32, f32)], p: (f32, f32)) -> bool {
+    let mut inside = false;
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) * (b.0 - a.0) / (b.1 - a.1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{contains, inset};
+    use std::f32::consts::PI;
+
+    const CENTER: (f32, f32) = (50.0, 50.0);
+
+    /// The pointy top hexagon of outer radius 10 around the center, its corners turning clockwise on the image
+    fn hexagon() -> Vec<(f32, f32)> {
+        (0..6).map(|i| at(10.0, -90.0 + 60.0 * i as f32)).collect()
+    }
+
+    /// The point at the distance from the center, in the direction of the angle in degrees
+    fn at(distance: f32, angle: f32) -> (f32, f32) {
+        let (sin, cos) = (angle * PI / 180.0).sin_cos();
+        (CENTER.0 + distance * cos, CENTER.1 + distance * sin)
+    }
+
+    #[test]
+    fn hexagon_contains() {
+        let hexagon = hexagon();
+        assert!(contains(&hexagon, CENTER));
+        // the inner radius is 10 cos 30, 8.66
+        for i in 0..6 {
+            let (edge, corner) = (-60.0 + 60.0 * i as f32, -90.0 + 60.0 * i as f32);
+            assert!(contains(&hexagon, at(8.4, edge)));
+            assert!(!contains(&hexagon, at(8.9, edge)));
+            assert!(contains(&hexagon, at(9.7, corner)));
+            assert!(!contains(&hexagon, at(10.3, corner)));
+        }
+        assert!(!contains(&hexagon, (0.0, 0.0)));
+        assert!(!contains(&hexagon, (100.0, 50.0)));
+    }
+
+    #[test]
+    fn hexagon_inset() {
+        for hexagon in [hexagon(), hexagon().into_iter().rev().collect()] {
+            let shrunk = inset(&hexagon, 2.0);
+            assert_eq!(shrunk.len(), 6);
+            // the inner radius shrinks from 8.66 to 6.66, and the outer one from 10 to 6.66 / cos 30, 7.6906
+            for &corner in &shrunk {
+                let distance = ((corner.0 - CENTER.0).powi(2) + (corner.1 - CENTER.1).powi(2)).sqrt();
+                assert!((distance - 7.6906).abs() < 1e-3, "{:?} is {} from the center", corner, distance);
+            }
+            assert!(contains(&shrunk, CENTER));
+            for i in 0..6 {
+                let (edge, corner) = (-60.0 + 60.0 * i as f32, -90.0 + 60.0 * i as f32);
+                // inside the inset hexagon, near its edges and its corners
+                assert!(contains(&shrunk, at(6.4, edge)));
+                assert!(contains(&shrunk, at(7.4, corner)));
+                // in the band between the inset hexagon and the hexagon
+                assert!(!contains(&shrunk, at(6.9, edge)) && contains(&hexagon, at(6.9, edge)));
+                assert!(!contains(&shrunk, at(8.0, corner)) && contains(&hexagon, at(8.0, corner)));
+                // outside both
+                assert!(!contains(&shrunk, at(9.0, edge)));
+                assert!(!contains(&shrunk, at(10.5, corner)));
+            }
+        }
+    }
+
+    #[test]
+    fn inset_beyond_the_inner_radius() {
+        assert!(inset(&hexagon(), 9.0).is_empty());
+    }
+}
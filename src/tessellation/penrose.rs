@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+use std::f64::consts::PI;
+use tessellation::Tessellation;
+
+
+///
+/// A Penrose tiling of thick and thin rhombi, which never repeats
+///
+/// The tiling is built by deflation from a wheel of 10 Robinson triangles around the center of the image,
+/// each triangle being split into 2 or 3 smaller ones, φ times smaller, until their sides are `size` long.
+/// The smaller triangles lie in the one they were split from,
+/// so that the triangles away from the image are dropped along the way.
+///
+/// Each triangle is half of a rhombus, cut along its base BC, the other half being its mirror image across BC.
+/// The 2 halves of a rhombus give the same rhombus, which is kept once, found from the middle of its base.
+///
+/// To find the rhombus of a point, the rhombi are sorted in buckets of `size` x `size` pixels
+/// by the bounding boxes of their vertices, and those of the point's bucket are checked.
+///
+pub struct Penrose {
+    size: u32,
+    columns: u32,
+    rows: u32,
+    rhombi: Vec<[(f32, f32); 4]>,
+    buckets: Vec<Vec<usize>>,
+}
+
+/// A Robinson triangle, its apex A, the thin ones having an angle of 36° at A and the thick ones of 108°
+struct Half {
+    thick: bool,
+    a: (f64, f64),
+    b: (f64, f64),
+    c: (f64, f64),
+}
+
+/// The golden ratio, by which the triangles shrink at each deflation
+const PHI: f64 = 1.618_034;
+
+/// The point of the segment from a to b at the fraction t of its length
+fn towards(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))
+}
+
+impl Half {
+    fn split(&self) -> Vec<Half> {
+        let (a, b, c) = (self.a, self.b, self.c);
+        if self.thick {
+            let q = towards(b, a, 1.0 / PHI);
+            let r = towards(b, c, 1.0 / PHI);
+            vec![
+                Half { thick: true, a: r, b: c, c: a },
+                Half { thick: true, a: q, b: r, c: b },
+                Half { thick: false, a: r, b: q, c: a },
+            ]
+        } else {
+            let p = towards(a, b, 1.0 / PHI);
+            vec![Half { thick: false, a: c, b: p, c: b }, Half { thick: true, a: p, b: c, c: a }]
+        }
+    }
+
+    /// Whether the bounding box of the triangle overlaps the rectangle
+    fn overlaps(&self, area: (f64, f64, f64, f64)) -> bool {
+        overlap(bounds(&[self.a, self.b, self.c]), area)
+    }
+}
+
+/// The left, top, right and bottom of the points
+fn bounds(points: &[(f64, f64)]) -> (f64, f64, f64, f64) {
+    points.iter().fold((f64::MAX, f64::MAX, f64::MIN, f64::MIN), |(left, top, right, bottom), p| {
+        (left.min(p.0), top.min(p.1), right.max(p.0), bottom.max(p.1))
+    })
+}
+
+fn overlap(a: (f64, f64, f64, f64), b: (f64, f64, f64, f64)) -> bool {
+    a.0 <= b.2 && a.2 >= b.0 && a.1 <= b.3 && a.3 >= b.1
+}
+
+impl Penrose {
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The length of the sides of the rhombi
+    ///
+    pub fn new(width: u32, height: u32, size: u32) -> Penrose {
+        let size = size.max(1);
+        let center = (width as f64 / 2.0, height as f64 / 2.0);
+        // the wheel is a decagon, whose inner circle must contain the image,
+        // and whose triangles have sides of `size` once deflated
+        let reach = center.0.hypot(center.1) / (PI / 10.0).cos();
+        let deflations = ((reach / size as f64).ln() / PHI.ln()).ceil().max(0.0) as i32;
+        let side = size as f64;
+        let radius = side * PHI.powi(deflations);
+
+        let mut halves: Vec<Half> = (0..10).map(|i| {
+            let corner = |k: i32| {
+                let angle = (2 * k - 1) as f64 * PI / 10.0;
+                (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+            };
+            let (b, c) = if i % 2 == 0 { (corner(i + 1), corner(i)) } else { (corner(i), corner(i + 1)) };
+            Half { thick: false, a: center, b, c }
+        }).collect();
+        // the rhombi of the triangles reach up to a side beyond them
+        let margin = 2.0 * side;
+        for _ in 0..deflations {
+            halves = halves.iter()
+                .flat_map(|half| half.split())
+                .filter(|half| half.overlaps((-margin, -margin, width as f64 + margin, height as f64 + margin)))
+                .collect();
+        }
+
+        let image = (0.0, 0.0, width as f64, height as f64);
+        let columns = width.div_ceil(size).max(1);
+        let rows = height.div_ceil(size).max(1);
+        let bucket = |v: f64, count: u32| ((v / size as f64).floor().max(0.0) as u32).min(count - 1);
+        let mut bases = HashSet::new();
+        let mut rhombi = Vec::new();
+        let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); (columns * rows) as usize];
+        for half in &halves {
+            let (a, b, c) = (half.a, half.b, half.c);
+            let key = (((b.0 + c.0) * 64.0).round() as i64, ((b.1 + c.1) * 64.0).round() as i64);
+            let mirror = (b.0 + c.0 - a.0, b.1 + c.1 - a.1);
+            // the vertices turn the same way around all the rhombi, for the signs of the distances to their sides
+            let turn = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+            let rhombus = if turn > 0.0 { [a, b, mirror, c] } else { [a, c, mirror, b] };
+            let (left, top, right, bottom) = bounds(&rhombus);
+            if !overlap((left, top, right, bottom), image) || !bases.insert(key) {
+                continue;
+            }
+            for row in bucket(top, rows)..=bucket(bottom, rows) {
+                for column in bucket(left, columns)..=bucket(right, columns) {
+                    buckets[(row * columns + column) as usize].push(rhombi.len());
+                }
+            }
+            rhombi.push([
+                (rhombus[0].0 as f32, rhombus[0].1 as f32),
+                (rhombus[1].0 as f32, rhombus[1].1 as f32),
+                (rhombus[2].0 as f32, rhombus[2].1 as f32),
+                (rhombus[3].0 as f32, rhombus[3].1 as f32),
+            ]);
+        }
+
+        Penrose {
+            size,
+            columns,
+            rows,
+            rhombi,
+            buckets,
+        }
+    }
+
+    /// The rhombi whose bounding boxes overlap the bucket of the point
+    fn candidates(&self, x: u32, y: u32) -> &[usize] {
+        let column = (x / self.size).min(self.columns - 1);
+        let row = (y / self.size).min(self.rows - 1);
+        &self.buckets[(row * self.columns + column) as usize]
+    }
+
+    /// The distance from the point to the closest side of the rhombus, negative outside of it
+    fn inner_distance(&self, p: (f32, f32), index: usize) -> f32 {
+        let rhombus = &self.rhombi[index];
+        (0..4).map(|i| {
+            let (a, b) = (rhombus[i], rhombus[(i + 1) % 4]);
+            let length = (sqr!(b.0 - a.0) + sqr!(b.1 - a.1)).sqrt();
+            ((b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)) / length
+        }).fold(f32::MAX, f32::min)
+    }
+}
+
+impl Tessellation for Penrose {
+    fn cell_count(&self) -> usize {
+        self.rhombi.len()
+    }
+
+    ///
+    /// The rhombus containing the pixel,
+    /// or the one it is the least outside of when it lies on the sides of the rhombi, rounded
+    ///
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let p = (x as f32, y as f32);
+        let mut best = (f32::MIN, 0);
+        for &index in self.candidates(x, y) {
+            let distance = self.inner_distance(p, index);
+            if distance >= 0.0 {
+                return index;
+            }
+            if distance > best.0 {
+                best = (distance, index);
+            }
+        }
+        best.1
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        let rhombus = &self.rhombi[index];
+        ((rhombus[0].0 + rhombus[2].0) / 2.0, (rhombus[0].1 + rhombus[2].1) / 2.0)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        self.inner_distance((x as f32, y as f32), index).max(0.0)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.rhombi[index].to_vec()
+    }
+}
@@ -0,0 +1,161 @@
+use image::{GenericImageView, Rgba};
+use tessellation::Tessellation;
+
+
+///
+/// Squares of varying sizes, smaller where the image has more details
+///
+/// The image is first covered by a grid of squares of side `max_size`.
+/// Each square whose color variance exceeds the threshold is split into 4 quarters,
+/// which are split again in the same way, until the quarters would be smaller than `min_size`.
+///
+/// The squares on the right and bottom edges of the image are cut by the edges,
+/// so the cells are rectangles in general.
+///
+/// The variance of a square is the mean of the variances of its 4 color channels.
+///
+pub struct Quadtree {
+    max_size: u32,
+    columns: u32,
+    /// All the squares, the roots of the grid first
+    nodes: Vec<Node>,
+    /// The node of each cell
+    cells: Vec<usize>,
+}
+
+struct Node {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    content: Content,
+}
+
+enum Content {
+    /// An undivided square, with its cell index
+    Cell(usize),
+    /// The index of the first of the 4 quarters, in reading order
+    Quarters(usize),
+}
+
+impl Quadtree {
+    pub fn new<I>(img: &I, min_size: u32, max_size: u32, variance_threshold: f32) -> Quadtree
+        where I: GenericImageView<Pixel=Rgba<u8>> {
+        let (width, height) = img.dimensions();
+        let min_size = min_size.max(1);
+        let max_size = max_size.max(min_size);
+        let columns = width.div_ceil(max_size);
+        let rows = height.div_ceil(max_size);
+
+        let mut tree = Quadtree {
+            max_size,
+            columns,
+            nodes: Vec::new(),
+            cells: Vec::new(),
+        };
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = (column * max_size, row * max_size);
+                tree.push(x, y, max_size.min(width - x), max_size.min(height - y));
+            }
+        }
+
+        // the quarters are appended behind their parent, so that all of them are visited
+        let mut i = 0;
+        while i < tree.nodes.len() {
+            let (x, y, w, h) = {
+                let n = &tree.nodes[i];
+                (n.x, n.y, n.width, n.height)
+            };
+            if w >= 2 * min_size && h >= 2 * min_size && variance(img, x, y, w, h) > variance_threshold {
+                let (left, top) = (w / 2, h / 2);
+                let first = tree.nodes.len();
+                tree.push(x, y, left, top);
+                tree.push(x + left, y, w - left, top);
+                tree.push(x, y + top, left, h - top);
+                tree.push(x + left, y + top, w - left, h - top);
+                tree.nodes[i].content = Content::Quarters(first);
+            }
+            i += 1;
+        }
+
+        // the splits left behind the cell indices of the split squares
+        tree.cells.clear();
+        for i in 0..tree.nodes.len() {
+            if let Content::Cell(_) = tree.nodes[i].content {
+                tree.nodes[i].content = Content::Cell(tree.cells.len());
+                tree.cells.push(i);
+            }
+        }
+
+        tree
+    }
+
+    fn push(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.nodes.push(Node { x, y, width, height, content: Content::Cell(0) });
+    }
+
+    fn node(&self, index: usize) -> &Node {
+        &self.nodes[self.cells[index]]
+    }
+}
+
+/// The mean of the variances of the color channels over the rectangle
+fn variance<I>(img: &I, x: u32, y: u32, width: u32, height: u32) -> f32
+    where I: GenericImageView<Pixel=Rgba<u8>> {
+    let mut sums = [0u64; 4];
+    let mut squares = [0u64; 4];
+    for j in y..(y + height) {
+        for i in x..(x + width) {
+            let pixel = img.get_pixel(i, j);
+            for c in 0..4 {
+                sums[c] += pixel[c] as u64;
+                squares[c] += sqr!(pixel[c] as u64);
+            }
+        }
+    }
+
+    let count = width as f64 * height as f64;
+    let total: f64 = (0..4).map(|c| squares[c] as f64 / count - sqr!(sums[c] as f64 / count)).sum();
+    (total / 4.0) as f32
+}
+
+impl Tessellation for Quadtree {
+    fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let mut node = &self.nodes[((y / self.max_size) * self.columns + x / self.max_size) as usize];
+        loop {
+            match node.content {
+                Content::Cell(index) => return index,
+                Content::Quarters(first) => {
+                    let right = x >= node.x + node.width / 2;
+                    let bottom = y >= node.y + node.height / 2;
+                    node = &self.nodes[first + right as usize + 2 * bottom as usize];
+                }
+            }
+        }
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        let n = self.node(index);
+        (n.x as f32 + (n.width - 1) as f32 / 2.0, n.y as f32 + (n.height - 1) as f32 / 2.0)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let n = self.node(index);
+        let (dx, dy) = (x - n.x, y - n.y);
+        // the pixel spans [d, d+1) in the cell
+        dx.min(n.width - 1 - dx).min(dy).min(n.height - 1 - dy) as f32
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        let n = self.node(index);
+        let (x, y) = (n.x as f32, n.y as f32);
+        let (w, h) = (n.width as f32, n.height as f32);
+        vec![(x, y), (x + w, y), (x + w, y + h), (x, y + h)]
+    }
+}
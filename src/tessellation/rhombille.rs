@@ -0,0 +1,78 @@
+use std::f32::consts::PI;
+use tessellation::{Hexagon, Tessellation};
+use Orientation;
+
+
+///
+/// A grid of hexagons, each of them split into 3 rhombi looking like the faces of an isometric cube
+///
+/// Each rhombus goes from the center of its hexagon to 3 consecutive vertices of it.
+/// The rhombi of the hexagon h are the cells 3h, 3h + 1 and 3h + 2,
+/// which are the top, left and right faces of the cube with pointy top hexagons,
+/// and its upper right, left and lower right faces with flat top hexagons.
+///
+/// Given a point P, its rhombus is found from the angle between the center of its hexagon and P.
+///
+pub struct Rhombille {
+    hexagons: Hexagon,
+    outer_radius: f32,
+    /// The angle of the vertex starting the first rhombus, counterclockwise from the X axis
+    first_vertex: f32,
+}
+
+impl Rhombille {
+    pub fn new(width: u32, height: u32, outer_radius: u32, orientation: Orientation) -> Rhombille {
+        Rhombille {
+            hexagons: Hexagon::new(width, height, outer_radius, orientation),
+            outer_radius: outer_radius as f32,
+            first_vertex: if orientation == Orientation::flat { 0.0 } else { PI / 6.0 },
+        }
+    }
+
+    /// The point at `distance` from the given one, in the direction `angle`, counterclockwise from the X axis
+    fn towards(&self, (x, y): (f32, f32), angle: f32, distance: f32) -> (f32, f32) {
+        // the Y axis of the image points down
+        (x + distance * angle.cos(), y - distance * angle.sin())
+    }
+
+    /// The center of the hexagon and the 3 vertices of the rhombus, counterclockwise
+    fn rhombus(&self, index: usize) -> [(f32, f32); 4] {
+        let center = self.hexagons.center(index / 3);
+        let first = self.first_vertex + (index % 3) as f32 * 2.0 * PI / 3.0;
+        let vertex = |i: f32| self.towards(center, first + i * PI / 3.0, self.outer_radius);
+        [center, vertex(0.0), vertex(1.0), vertex(2.0)]
+    }
+}
+
+impl Tessellation for Rhombille {
+    fn cell_count(&self) -> usize {
+        self.hexagons.cell_count() * 3
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let hexagon = self.hexagons.cell_index(x, y);
+        let (cx, cy) = self.hexagons.center(hexagon);
+        let angle = ((cy - y as f32).atan2(x as f32 - cx) - self.first_vertex).rem_euclid(2.0 * PI);
+        hexagon * 3 + ((angle / (2.0 * PI / 3.0)) as usize).min(2)
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        // the middle of the diagonal from the center of the hexagon
+        let first = self.first_vertex + (index % 3) as f32 * 2.0 * PI / 3.0;
+        self.towards(self.hexagons.center(index / 3), first + PI / 3.0, self.outer_radius / 2.0)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let (x, y) = (x as f32, y as f32);
+        let rhombus = self.rhombus(index);
+        // the rhombus is convex, the closest edge is the one whose line is the closest
+        (0..4).map(|i| {
+            let ((ax, ay), (bx, by)) = (rhombus[i], rhombus[(i + 1) % 4]);
+            ((bx - ax) * (y - ay) - (by - ay) * (x - ax)).abs() / (sqr!(bx - ax) + sqr!(by - ay)).sqrt()
+        }).fold(f32::MAX, f32::min)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.rhombus(index).to_vec()
+    }
+}
@@ -0,0 +1,131 @@
+use std::f32::consts::PI;
+use tessellation::Tessellation;
+
+
+///
+/// Concentric rings around a center, cut into arcs
+///
+/// The ring k spans the radii [k s, (k+1) s), for the rings of width s.
+/// The first ring is a disc of a single cell, and the ring k > 0 is cut into round(2π (k + 1/2)) arcs,
+/// their lengths along the middle of the ring being about s,
+/// the first arc starting at the angle 0, to the right of the center, and the arcs following clockwise.
+///
+/// Given a point P at the radius r and the angle θ from the center,
+/// its ring is floor(r / s) and its arc floor(n θ / 2π) for the n arcs of the ring.
+///
+pub struct Rings {
+    width: f32,
+    center: (f32, f32),
+    /// The index of the first arc of each ring
+    starts: Vec<usize>,
+}
+
+impl Rings {
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the rings, and the length of their arcs, roughly
+    /// * `center` - The center of the rings, in pixels, which may lie out of the image
+    ///
+    pub fn new(image_width: u32, image_height: u32, width: u32, center: (f32, f32)) -> Rings {
+        let width = width.max(1) as f32;
+        let to_corner = |x: f32, y: f32| (sqr!(x - center.0) + sqr!(y - center.1)).sqrt();
+        let (w, h) = (image_width as f32, image_height as f32);
+        let reach = to_corner(0.0, 0.0).max(to_corner(w, 0.0)).max(to_corner(0.0, h)).max(to_corner(w, h));
+        let rings = (reach / width) as usize + 1;
+
+        let mut starts = Vec::with_capacity(rings + 1);
+        starts.push(0);
+        for ring in 0..rings {
+            let start = starts[ring];
+            starts.push(start + Rings::arcs(ring));
+        }
+
+        Rings {
+            width,
+            center,
+            starts,
+        }
+    }
+
+    /// The number of arcs of the ring
+    fn arcs(ring: usize) -> usize {
+        if ring == 0 { 1 } else { (2.0 * PI * (ring as f32 + 0.5)).round() as usize }
+    }
+
+    /// The radius and the angle of the point, in [0, 2π)
+    fn polar(&self, p: (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = (p.0 - self.center.0, p.1 - self.center.1);
+        let angle = dy.atan2(dx);
+        ((sqr!(dx) + sqr!(dy)).sqrt(), if angle < 0.0 { angle + 2.0 * PI } else { angle })
+    }
+
+    /// The ring and the arc of the cell
+    fn ring_arc(&self, index: usize) -> (usize, usize) {
+        let ring = self.starts.partition_point(|&start| start <= index) - 1;
+        (ring, index - self.starts[ring])
+    }
+
+    fn point(&self, radius: f32, angle: f32) -> (f32, f32) {
+        (self.center.0 + radius * angle.cos(), self.center.1 + radius * angle.sin())
+    }
+}
+
+impl Tessellation for Rings {
+    fn cell_count(&self) -> usize {
+        self.starts[self.starts.len() - 1]
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let (radius, angle) = self.polar((x as f32, y as f32));
+        let ring = ((radius / self.width) as usize).min(self.starts.len() - 2);
+        let arcs = Rings::arcs(ring);
+        let arc = ((angle / (2.0 * PI) * arcs as f32) as usize).min(arcs - 1);
+        self.starts[ring] + arc
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        match self.ring_arc(index) {
+            (0, _) => self.center,
+            (ring, arc) => {
+                self.point((ring as f32 + 0.5) * self.width, (arc as f32 + 0.5) * 2.0 * PI / Rings::arcs(ring) as f32)
+            }
+        }
+    }
+
+    ///
+    /// The distance to the circles around the ring, or to the radii between the arcs
+    ///
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let (ring, arc) = self.ring_arc(index);
+        let (radius, angle) = self.polar((x as f32, y as f32));
+        let inner = ring as f32 * self.width;
+        let across = (radius - inner).min(inner + self.width - radius);
+        if ring == 0 {
+            return across.max(0.0);
+        }
+        let step = 2.0 * PI / Rings::arcs(ring) as f32;
+        let from_start = (angle - arc as f32 * step).rem_euclid(2.0 * PI);
+        let turn = from_start.min(step - from_start);
+        let along = if turn < PI / 2.0 { radius * turn.sin() } else { radius };
+        across.min(along).max(0.0)
+    }
+
+    ///
+    /// The arcs outlined by 8 segments along each of their circles, the disc of the center by 48
+    ///
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        const SEGMENTS: usize = 8;
+        match self.ring_arc(index) {
+            (0, _) => (0..48).map(|k| self.point(self.width, k as f32 * 2.0 * PI / 48.0)).collect(),
+            (ring, arc) => {
+                let step = 2.0 * PI / Rings::arcs(ring) as f32;
+                let angle = |k: usize| (arc as f32 + k as f32 / SEGMENTS as f32) * step;
+                let (inner, outer) = (ring as f32 * self.width, (ring + 1) as f32 * self.width);
+                (0..=SEGMENTS).map(|k| self.point(outer, angle(k)))
+                    .chain((0..=SEGMENTS).rev().map(|k| self.point(inner, angle(k))))
+                    .collect()
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use tessellation::Tessellation;
+
+
+///
+/// A grid rotated around the center of the image
+///
+/// The pixels are rotated into the space of the grid before looking up their cell,
+/// and the centers and vertices of the cells are rotated back into the space of the image.
+///
+/// The grid is built for the bounding box of the rotated image,
+/// so that it covers the whole image whatever the angle.
+///
+pub struct Rotated<T> {
+    grid: T,
+    cos: f32,
+    sin: f32,
+    /// The center of the image, in the image's space
+    center: (f32, f32),
+    /// The center of the image, in the grid's space
+    grid_center: (f32, f32),
+    grid_size: (u32, u32),
+}
+
+impl<T: Tessellation> Rotated<T> {
+    ///
+    /// # Arguments
+    ///
+    /// * `angle` - The rotation of the grid, in degrees, clockwise
+    /// * `grid` - Builds the grid for the given width and height
+    ///
+    pub fn new<F>(width: u32, height: u32, angle: f32, grid: F) -> Rotated<T>
+        where F: FnOnce(u32, u32) -> T {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (w, h) = (width as f32, height as f32);
+        // 1 more pixel on each side for the rounding of the rotated coordinates
+        let grid_width = (w * cos.abs() + h * sin.abs()).ceil() as u32 + 2;
+        let grid_height = (w * sin.abs() + h * cos.abs()).ceil() as u32 + 2;
+
+        Rotated {
+            grid: grid(grid_width, grid_height),
+            cos,
+            sin,
+            center: (w / 2.0, h / 2.0),
+            grid_center: (grid_width as f32 / 2.0, grid_height as f32 / 2.0),
+            grid_size: (grid_width, grid_height),
+        }
+    }
+
+    fn to_grid(&self, x: u32, y: u32) -> (u32, u32) {
+        let (dx, dy) = (x as f32 - self.center.0, y as f32 - self.center.1);
+        let gx = self.cos * dx + self.sin * dy + self.grid_center.0;
+        let gy = -self.sin * dx + self.cos * dy + self.grid_center.1;
+
+        (
+            (gx.max(0.0).round() as u32).min(self.grid_size.0 - 1),
+            (gy.max(0.0).round() as u32).min(self.grid_size.1 - 1),
+        )
+    }
+
+    fn to_image(&self, p: (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = (p.0 - self.grid_center.0, p.1 - self.grid_center.1);
+        (
+            self.cos * dx - self.sin * dy + self.center.0,
+            self.sin * dx + self.cos * dy + self.center.1,
+        )
+    }
+}
+
+impl<T: Tessellation> Tessellation for Rotated<T> {
+    fn cell_count(&self) -> usize {
+        self.grid.cell_count()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let (gx, gy) = self.to_grid(x, y);
+        self.grid.cell_index(gx, gy)
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.to_image(self.grid.center(index))
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let (gx, gy) = self.to_grid(x, y);
+        self.grid.edge_distance(gx, gy, index)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.grid.polygon(index).into_iter().map(|p| self.to_image(p)).collect()
+    }
+
+    fn axial(&self, index: usize) -> Option<(i32, i32)> {
+        self.grid.axial(index)
+    }
+}
@@ -0,0 +1,160 @@
+use std::f32::consts::PI;
+use tessellation::Tessellation;
+
+
+///
+/// Overlapping rows of round scales, like the scales of a fish
+///
+/// The scales are discs of radius R, laid in rows 2R apart along the rows,
+/// every other row being shifted by R. The rows are v = R (1 - overlap) apart,
+/// so that each scale is covered by the scales of the rows above it,
+/// and only its lower part is seen, a semicircle when the overlap is 0.
+/// As v <= R, each point is covered by at least one scale.
+///
+/// Given a point P, its scale is the topmost of the scales covering it,
+/// found by looking at the rows whose centers are less than R away from P, from the top one down.
+/// On each row, the only scale which can cover P is the one whose center is the closest to P along the row.
+///
+pub struct Scales {
+    radius: f32,
+    row_spacing: f32,
+    /// The number of rows above the image, whose scales still cover its top
+    rows_above: usize,
+    columns: usize,
+    rows: usize,
+}
+
+impl Scales {
+    ///
+    /// # Arguments
+    ///
+    /// * `radius` - The radius of the scales
+    /// * `overlap` - How much of the scales are covered by the rows above them, from 0 to 0.9
+    ///
+    pub fn new(width: u32, height: u32, radius: u32, overlap: f32) -> Scales {
+        let radius = radius.max(1) as f32;
+        let row_spacing = Scales::row_spacing(radius, overlap);
+        let rows_above = (radius / row_spacing).ceil() as usize;
+
+        Scales {
+            radius,
+            row_spacing,
+            rows_above,
+            // the first scales of the rows are left of the image
+            columns: (width as f32 / (2.0 * radius)) as usize + 3,
+            rows: rows_above + ((height as f32 + radius) / row_spacing) as usize + 1,
+        }
+    }
+
+    /// The distance between the rows of scales of that radius and overlap
+    pub fn row_spacing(radius: f32, overlap: f32) -> f32 {
+        radius * (1.0 - overlap.clamp(0.0, 0.9))
+    }
+
+    /// The center of the scale of the column i and the row j, the first columns and rows being outside of the image
+    fn position(&self, i: usize, j: usize) -> (f32, f32) {
+        let shift = if j % 2 == 1 { self.radius } else { 0.0 };
+        (
+            (i as f32 - 1.0) * 2.0 * self.radius + shift,
+            (j as f32 - self.rows_above as f32) * self.row_spacing,
+        )
+    }
+
+    /// The column of the scale of the row j whose center is the closest to x
+    fn column(&self, x: f32, j: usize) -> usize {
+        let shift = if j % 2 == 1 { self.radius } else { 0.0 };
+        let column = ((x - shift) / (2.0 * self.radius)).round() + 1.0;
+        (column.max(0.0) as usize).min(self.columns - 1)
+    }
+
+    /// The rows whose centers are less than R away from y, from the top one down
+    fn rows_around(&self, y: f32) -> impl Iterator<Item=usize> {
+        let row = |y: f32| y / self.row_spacing + self.rows_above as f32;
+        let first = row(y - self.radius).ceil().max(0.0) as usize;
+        let last = (row(y + self.radius).floor().max(0.0) as usize).min(self.rows - 1);
+        first..=last
+    }
+
+    fn distance(p: (f32, f32), c: (f32, f32)) -> f32 {
+        (sqr!(p.0 - c.0) + sqr!(p.1 - c.1)).sqrt()
+    }
+
+    /// The index of the topmost scale covering the point, or of the closest one when rounding leaves it uncovered
+    fn scale_at(&self, p: (f32, f32)) -> usize {
+        let mut closest = (f32::MAX, 0);
+        for j in self.rows_around(p.1) {
+            let i = self.column(p.0, j);
+            let distance = Scales::distance(p, self.position(i, j));
+            let index = j * self.columns + i;
+            if distance <= self.radius {
+                return index;
+            }
+            if distance < closest.0 {
+                closest = (distance, index);
+            }
+        }
+        closest.1
+    }
+}
+
+impl Tessellation for Scales {
+    fn cell_count(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        self.scale_at((x as f32, y as f32))
+    }
+
+    ///
+    /// The middle of the part of the scale seen along its vertical axis,
+    /// between its bottom and the bottom of the scale 2 rows above, which is the one covering that axis
+    ///
+    fn center(&self, index: usize) -> (f32, f32) {
+        let (x, y) = self.position(index % self.columns, index / self.columns);
+        let top = (y - self.radius).max(y - 2.0 * self.row_spacing + self.radius);
+        (x, (top + y + self.radius) / 2.0)
+    }
+
+    ///
+    /// The distance to the edge of the scale, or to the edges of the scales above it covering it
+    ///
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let p = (x as f32, y as f32);
+        let (i, j) = (index % self.columns, index / self.columns);
+        let mut distance = self.radius - Scales::distance(p, self.position(i, j));
+        for row in self.rows_around(p.1).take_while(|&row| row < j) {
+            let column = self.column(p.0, row);
+            for column in column.saturating_sub(1)..=(column + 1).min(self.columns - 1) {
+                let above = Scales::distance(p, self.position(column, row)) - self.radius;
+                if above >= 0.0 {
+                    distance = distance.min(above);
+                }
+            }
+        }
+        distance
+    }
+
+    ///
+    /// The outline of the part of the scale which is seen, found along rays from its center
+    ///
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        const RAYS: usize = 48;
+        let c = self.center(index);
+        (0..RAYS).map(|k| {
+            let angle = 2.0 * PI * k as f32 / RAYS as f32;
+            let along = |distance: f32| (c.0 + distance * angle.cos(), c.1 + distance * angle.sin());
+            // the seen part lies in the scale, less than 2R from its center
+            let (mut inside, mut outside) = (0.0, 2.0 * self.radius);
+            for _ in 0..16 {
+                let middle = (inside + outside) / 2.0;
+                if self.scale_at(along(middle)) == index {
+                    inside = middle;
+                } else {
+                    outside = middle;
+                }
+            }
+            along(inside)
+        }).collect()
+    }
+}
@@ -0,0 +1,69 @@
+use tessellation::Tessellation;
+
+
+///
+/// A periodic grid whose cells are shifted by an offset
+///
+/// Shifting a grid by its period gives the same grid, so the offset is replaced by its opposite modulo the period,
+/// which is then added to the coordinates of the pixels.
+/// The grid is built larger than the image by that much, so that it still covers the whole image.
+/// The offset is rounded to the pixel.
+///
+pub struct Shifted<T> {
+    grid: T,
+    shift: (u32, u32),
+}
+
+impl<T: Tessellation> Shifted<T> {
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The shift of the cells, in pixels, to the right and to the bottom
+    /// * `period` - The distances along the X and Y axes by which the grid can be shifted without changing
+    /// * `grid` - Builds the grid for the given width and height
+    ///
+    pub fn new<F>(width: u32, height: u32, offset: (f32, f32), period: (f32, f32), grid: F) -> Shifted<T>
+        where F: FnOnce(u32, u32) -> T {
+        let shift = |offset: f32, period: f32| (period - offset.rem_euclid(period)).round() as u32;
+        let shift = (shift(offset.0, period.0), shift(offset.1, period.1));
+
+        Shifted {
+            grid: grid(width + shift.0, height + shift.1),
+            shift,
+        }
+    }
+
+    fn to_image(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (x - self.shift.0 as f32, y - self.shift.1 as f32)
+    }
+}
+
+impl<T: Tessellation> Tessellation for Shifted<T> {
+    fn cell_count(&self) -> usize {
+        self.grid.cell_count()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        self.grid.cell_index(x + self.shift.0, y + self.shift.1)
+    }
+
+    fn row_cells(&self, x: u32, y: u32, cells: &mut [usize]) {
+        self.grid.row_cells(x + self.shift.0, y + self.shift.1, cells)
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.to_image(self.grid.center(index))
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        self.grid.edge_distance(x + self.shift.0, y + self.shift.1, index)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.grid.polygon(index).into_iter().map(|p| self.to_image(p)).collect()
+    }
+
+    fn axial(&self, index: usize) -> Option<(i32, i32)> {
+        self.grid.axial(index)
+    }
+}
@@ -0,0 +1,232 @@
+use color::to_linear;
+use image::{GenericImageView, Rgba};
+use rayon::prelude::*;
+use tessellation::{Labels, Tessellation};
+
+
+///
+/// Superpixels following the edges of the image, found by simple linear iterative clustering (SLIC)
+///
+/// The centers of the superpixels start on a grid of squares of side `size`,
+/// each of them moved to the pixel of least gradient around it, so that it does not start on an edge.
+/// Each iteration then gives each pixel to the closest center within `size` of it along both axes,
+/// and moves the centers to the means of their pixels.
+/// The distance of a pixel to a center mixes the difference of their colors, in CIELAB,
+/// with the distance between them, weighted by the `compactness`:
+/// the higher the compactness, the more regular the superpixels, and the less they follow the edges.
+///
+/// The iterations may split a superpixel into several pieces. The pieces smaller than a quarter of a square
+/// are merged into a neighbour, and the other ones become superpixels of their own,
+/// so that every cell is in one piece.
+///
+pub struct Slic {
+    cells: Labels,
+}
+
+/// A superpixel being clustered: its mean color and position
+#[derive(Clone, Copy)]
+struct Cluster {
+    color: [f32; 4],
+    x: f32,
+    y: f32,
+}
+
+impl Slic {
+    pub fn new<I>(img: &I, size: u32, compactness: f32, iterations: u32) -> Slic
+        where I: GenericImageView<Pixel=Rgba<u8>> {
+        let (width, height) = img.dimensions();
+        let size = size.max(1);
+        let mut colors = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                colors.push(lab(img.get_pixel(x, y)));
+            }
+        }
+
+        let labels = cluster(&colors, width, height, size, compactness, iterations);
+        let (labels, firsts) = connect(&labels, width, height, (sqr!(size) / 4).max(1) as usize);
+
+        Slic { cells: Labels::new(labels, width, height, firsts.len()) }
+    }
+}
+
+///
+/// The color in CIELAB, with its alpha from 0 to 100 like the lightness, so that the transparent pixels
+/// are told apart from the opaque ones
+///
+fn lab(pixel: Rgba<u8>) -> [f32; 4] {
+    let [r, g, b] = [0, 1, 2].map(|c| to_linear(pixel[c]) as f32 / 65535.0);
+    // the D65 white point
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.9505;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.089;
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz), pixel[3] as f32 * 100.0 / 255.0]
+}
+
+fn color_distance(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    (0..4).map(|c| sqr!(a[c] - b[c])).sum()
+}
+
+/// The cluster of each pixel after the iterations
+fn cluster(colors: &[[f32; 4]], width: u32, height: u32, size: u32, compactness: f32, iterations: u32) -> Vec<u32> {
+    let (columns, rows) = (width.div_ceil(size), height.div_ceil(size));
+    let color = |x: u32, y: u32| &colors[(y * width + x) as usize];
+    let gradient = |x: u32, y: u32| {
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+        let (top, bottom) = (y.saturating_sub(1), (y + 1).min(height - 1));
+        color_distance(color(right, y), color(left, y)) + color_distance(color(x, bottom), color(x, top))
+    };
+
+    let mut clusters = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            let x = (column * size + size / 2).min(width - 1);
+            let y = (row * size + size / 2).min(height - 1);
+            let (mut best, mut least) = ((x, y), gradient(x, y));
+            for j in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for i in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    let g = gradient(i, j);
+                    if g < least {
+                        best = (i, j);
+                        least = g;
+                    }
+                }
+            }
+            clusters.push(Cluster { color: *color(best.0, best.1), x: best.0 as f32, y: best.1 as f32 });
+        }
+    }
+
+    // each pixel starts in the cluster of its square
+    let mut labels: Vec<u32> = (0..height).flat_map(|y| (0..width).map(move |x| (y / size) * columns + x / size)).collect();
+    let weight = sqr!(compactness / size as f32);
+    for _ in 0..iterations {
+        // the clusters within a square of a pixel are in the 3 x 3 buckets around it
+        let mut buckets: Vec<Vec<u32>> = vec![Vec::new(); (columns * rows) as usize];
+        for (i, c) in clusters.iter().enumerate() {
+            let (column, row) = ((c.x as u32 / size).min(columns - 1), (c.y as u32 / size).min(rows - 1));
+            buckets[(row * columns + column) as usize].push(i as u32);
+        }
+
+        labels.par_chunks_mut(width.max(1) as usize).enumerate().for_each(|(y, row_labels)| {
+            let row = y as u32 / size;
+            for (x, label) in row_labels.iter_mut().enumerate() {
+                let column = x as u32 / size;
+                let pixel = color(x as u32, y as u32);
+                let mut closest = f32::MAX;
+                for j in row.saturating_sub(1)..=(row + 1).min(rows - 1) {
+                    for i in column.saturating_sub(1)..=(column + 1).min(columns - 1) {
+                        for &k in &buckets[(j * columns + i) as usize] {
+                            let c = &clusters[k as usize];
+                            let (dx, dy) = (c.x - x as f32, c.y - y as f32);
+                            if dx.abs() > size as f32 || dy.abs() > size as f32 {
+                                continue;
+                            }
+                            let distance = color_distance(pixel, &c.color) + (sqr!(dx) + sqr!(dy)) * weight;
+                            if distance < closest {
+                                closest = distance;
+                                *label = k;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        let mut sums = vec![[0.0f64; 7]; clusters.len()];
+        for (i, &label) in labels.iter().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let sum = &mut sums[label as usize];
+            for c in 0..4 {
+                sum[c] += colors[i][c] as f64;
+            }
+            sum[4] += x as f64;
+            sum[5] += y as f64;
+            sum[6] += 1.0;
+        }
+        for (c, sum) in clusters.iter_mut().zip(&sums) {
+            // a cluster losing all its pixels stays where it is
+            if sum[6] > 0.0 {
+                let n = sum[6];
+                *c = Cluster {
+                    color: [0, 1, 2, 3].map(|i| (sum[i] / n) as f32),
+                    x: (sum[4] / n) as f32,
+                    y: (sum[5] / n) as f32,
+                };
+            }
+        }
+    }
+    labels
+}
+
+///
+/// The cells of the connected pieces of the clusters, and the first pixel of each cell
+///
+/// The pieces smaller than `min_size` pixels join the cell of the pixel before their first one,
+/// to the left of it or, on the left edge, above it.
+///
+fn connect(labels: &[u32], width: u32, height: u32, min_size: usize) -> (Vec<u32>, Vec<u32>) {
+    let (w, h) = (width as i64, height as i64);
+    let mut cells = vec![u32::MAX; labels.len()];
+    let mut firsts = Vec::new();
+    let mut piece = Vec::new();
+    for start in 0..labels.len() {
+        if cells[start] != u32::MAX {
+            continue;
+        }
+        // the pieces are flooded through the sides of the pixels
+        let cell = firsts.len() as u32;
+        piece.clear();
+        piece.push(start);
+        cells[start] = cell;
+        let mut i = 0;
+        while i < piece.len() {
+            let p = piece[i];
+            let (x, y) = ((p as i64) % w, (p as i64) / w);
+            for &(nx, ny) in &[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if nx >= 0 && ny >= 0 && nx < w && ny < h {
+                    let n = (ny * w + nx) as usize;
+                    if cells[n] == u32::MAX && labels[n] == labels[start] {
+                        cells[n] = cell;
+                        piece.push(n);
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        let before = if start as i64 % w > 0 { Some(start - 1) } else { start.checked_sub(width as usize) };
+        match before {
+            Some(before) if piece.len() < min_size => {
+                for &p in &piece {
+                    cells[p] = cells[before];
+                }
+            }
+            _ => firsts.push(start as u32),
+        }
+    }
+    (cells, firsts)
+}
+
+impl Tessellation for Slic {
+    fn cell_count(&self) -> usize {
+        self.cells.cell_count()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        self.cells.cell_index(x, y)
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.cells.center(index)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        self.cells.edge_distance(x, y, index)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.cells.polygon(index)
+    }
+}
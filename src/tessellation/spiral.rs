@@ -0,0 +1,129 @@
+use std::f32::consts::PI;
+use tessellation::Tessellation;
+
+
+///
+/// A band winding around a center along an Archimedean spiral, cut into cells along its length
+///
+/// The spiral r = a φ, with a = s / 2π, turns clockwise from the center, its arms being s apart.
+/// A point P at the radius r and the angle θ from the center lies between 2 arms,
+/// at the angle ψ = θ + 2π n of the inner one, n = floor((r / a - θ) / 2π) being the number of turns below P.
+/// The band between the arms is cut every s along the length of the inner arm,
+/// L(ψ) = a / 2 (ψ sqrt(1 + ψ²) + asinh ψ) from the center, so that the cell of P is floor(L(ψ) / s).
+/// The points below the first turn of the spiral, for which ψ < 0, are in the first cell.
+///
+pub struct Spiral {
+    width: f32,
+    center: (f32, f32),
+    count: usize,
+}
+
+impl Spiral {
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the band, and the length of its cells, roughly
+    /// * `center` - The center of the spiral, in pixels, which may lie out of the image
+    ///
+    pub fn new(image_width: u32, image_height: u32, width: u32, center: (f32, f32)) -> Spiral {
+        let width = width.max(1) as f32;
+        let to_corner = |x: f32, y: f32| (sqr!(x - center.0) + sqr!(y - center.1)).sqrt();
+        let (w, h) = (image_width as f32, image_height as f32);
+        let reach = to_corner(0.0, 0.0).max(to_corner(w, 0.0)).max(to_corner(0.0, h)).max(to_corner(w, h));
+        let mut spiral = Spiral { width, center, count: 0 };
+        spiral.count = (spiral.length(reach / spiral.a()) / width) as usize + 1;
+        spiral
+    }
+
+    /// The growth of the radius along the spiral, per radian
+    fn a(&self) -> f32 {
+        self.width / (2.0 * PI)
+    }
+
+    /// The length of the spiral from the center to the angle ψ
+    fn length(&self, psi: f32) -> f32 {
+        let psi = psi.max(0.0);
+        self.a() / 2.0 * (psi * (1.0 + sqr!(psi)).sqrt() + psi.asinh())
+    }
+
+    /// The angle ψ at which the spiral is that long, found by bisection as the length grows with the angle
+    fn angle_at(&self, length: f32) -> f32 {
+        let (mut low, mut high) = (0.0, 1.0);
+        while self.length(high) < length {
+            high *= 2.0;
+        }
+        for _ in 0..32 {
+            let middle = (low + high) / 2.0;
+            if self.length(middle) < length {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        (low + high) / 2.0
+    }
+
+    /// The radius of the point, and the angle ψ of the inner arm below it
+    fn polar(&self, p: (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = (p.0 - self.center.0, p.1 - self.center.1);
+        let radius = (sqr!(dx) + sqr!(dy)).sqrt();
+        let theta = dy.atan2(dx).rem_euclid(2.0 * PI);
+        let turns = ((radius / self.a() - theta) / (2.0 * PI)).floor();
+        (radius, theta + 2.0 * PI * turns)
+    }
+
+    /// The angles ψ at which the cell starts and ends, the first one starting a turn below the center
+    fn span(&self, index: usize) -> (f32, f32) {
+        let start = if index == 0 { -2.0 * PI } else { self.angle_at(index as f32 * self.width) };
+        (start, self.angle_at((index + 1) as f32 * self.width))
+    }
+
+    fn point(&self, radius: f32, angle: f32) -> (f32, f32) {
+        (self.center.0 + radius * angle.cos(), self.center.1 + radius * angle.sin())
+    }
+}
+
+impl Tessellation for Spiral {
+    fn cell_count(&self) -> usize {
+        self.count
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let (_, psi) = self.polar((x as f32, y as f32));
+        ((self.length(psi) / self.width) as usize).min(self.count - 1)
+    }
+
+    /// The middle of the band, halfway along the cell
+    fn center(&self, index: usize) -> (f32, f32) {
+        let psi = self.angle_at((index as f32 + 0.5) * self.width);
+        self.point(self.a() * (psi + PI), psi)
+    }
+
+    ///
+    /// The distance to the arms around the band, measured along the radius,
+    /// or to the ends of the cell, measured along the circle through the point,
+    /// the length of the arm growing by a sqrt(1 + ψ²) per radian
+    ///
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let (radius, psi) = self.polar((x as f32, y as f32));
+        let inner = self.a() * psi.max(0.0);
+        let across = (radius - inner).min(self.a() * (psi + 2.0 * PI) - radius);
+        let length = self.length(psi);
+        let start = index as f32 * self.width;
+        let to_ends = if index == 0 { start + self.width - length } else { (length - start).min(start + self.width - length) };
+        let along = to_ends / (self.a() * (1.0 + sqr!(psi.max(0.0))).sqrt()) * radius;
+        across.min(along).max(0.0)
+    }
+
+    ///
+    /// The cell outlined by 12 segments along each of its arms
+    ///
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        const SEGMENTS: usize = 12;
+        let (start, end) = self.span(index);
+        let angle = |k: usize| start + (end - start) * k as f32 / SEGMENTS as f32;
+        (0..=SEGMENTS).map(|k| self.point(self.a() * (angle(k) + 2.0 * PI), angle(k)))
+            .chain((0..=SEGMENTS).rev().map(|k| self.point(self.a() * angle(k).max(0.0), angle(k))))
+            .collect()
+    }
+}
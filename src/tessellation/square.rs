@@ -0,0 +1,51 @@
+use tessellation::Tessellation;
+
+
+///
+/// A grid of squares, aligned on the image's axes
+///
+/// The squares take their color from their top left corner.
+///
+pub struct Square {
+    side: u32,
+    columns: usize,
+    rows: usize,
+}
+
+impl Square {
+    pub fn new(width: u32, height: u32, side: u32) -> Square {
+        Square {
+            side,
+            columns: width.div_ceil(side) as usize,
+            rows: height.div_ceil(side) as usize,
+        }
+    }
+}
+
+impl Tessellation for Square {
+    fn cell_count(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        (y / self.side) as usize * self.columns + (x / self.side) as usize
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        let x = (index % self.columns) as u32 * self.side;
+        let y = (index / self.columns) as u32 * self.side;
+        (x as f32, y as f32)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, _index: usize) -> f32 {
+        let (dx, dy) = (x % self.side, y % self.side);
+        // the pixel spans [d, d+1) in the cell
+        dx.min(self.side - 1 - dx).min(dy).min(self.side - 1 - dy) as f32
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        let (x, y) = self.center(index);
+        let side = self.side as f32;
+        vec![(x, y), (x + side, y), (x + side, y + side), (x, y + side)]
+    }
+}
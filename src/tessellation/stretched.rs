@@ -0,0 +1,76 @@
+use tessellation::Tessellation;
+
+
+///
+/// A grid squeezed along one of its axes, for the cells which are wider than they are tall, or taller than wide
+///
+/// The grid is built larger than the image along that axis, by the `factors` of each axis, which are 1 for the other one.
+/// The pixels are scaled into the space of the grid before looking up their cell,
+/// and the centers and vertices of the cells are scaled back into the space of the image.
+/// As the grid is larger than the image, its cells keep their edges to the pixel.
+///
+pub struct Stretched<T> {
+    grid: T,
+    factors: (f32, f32),
+    grid_size: (u32, u32),
+}
+
+impl<T: Tessellation> Stretched<T> {
+    ///
+    /// # Arguments
+    ///
+    /// * `factors` - How many times larger than the image the grid is, along the X and Y axes, from 1
+    /// * `grid` - Builds the grid for the given width and height
+    ///
+    pub fn new<F>(width: u32, height: u32, factors: (f32, f32), grid: F) -> Stretched<T>
+        where F: FnOnce(u32, u32) -> T {
+        // 1 more pixel for the rounding of the scaled coordinates
+        let grid_size = ((width as f32 * factors.0).ceil() as u32 + 1, (height as f32 * factors.1).ceil() as u32 + 1);
+
+        Stretched {
+            grid: grid(grid_size.0, grid_size.1),
+            factors,
+            grid_size,
+        }
+    }
+
+    fn to_grid(&self, x: u32, y: u32) -> (u32, u32) {
+        (
+            ((x as f32 * self.factors.0).round() as u32).min(self.grid_size.0 - 1),
+            ((y as f32 * self.factors.1).round() as u32).min(self.grid_size.1 - 1),
+        )
+    }
+
+    fn to_image(&self, p: (f32, f32)) -> (f32, f32) {
+        (p.0 / self.factors.0, p.1 / self.factors.1)
+    }
+}
+
+impl<T: Tessellation> Tessellation for Stretched<T> {
+    fn cell_count(&self) -> usize {
+        self.grid.cell_count()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let (gx, gy) = self.to_grid(x, y);
+        self.grid.cell_index(gx, gy)
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.to_image(self.grid.center(index))
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        // the distances shrink by the factor of the axis they are along, at most by the largest one
+        let (gx, gy) = self.to_grid(x, y);
+        self.grid.edge_distance(gx, gy, index) / self.factors.0.max(self.factors.1)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        self.grid.polygon(index).into_iter().map(|p| self.to_image(p)).collect()
+    }
+
+    fn axial(&self, index: usize) -> Option<(i32, i32)> {
+        self.grid.axial(index)
+    }
+}
@@ -0,0 +1,108 @@
+use std::f32::consts::PI;
+use tessellation::{bisector_distance, closest_region, is_closer, square_around, Tessellation};
+
+
+///
+/// A grid of equilateral triangles
+///
+/// The triangles are laid in rows of height h = s sqrt(3)/2, where s is the length of their sides.
+/// On each row, the triangles alternate between pointing up and pointing down.
+///
+/// The triangles of the row k have their centers at x = 0, x = s/2, x = s etc.
+/// These positions are referred to as x_0, x_1, x_2 etc.
+/// The triangle at x_i points up when i and k have the same parity, down otherwise.
+/// Its center is at y = kh + 2h/3 when pointing up, y = kh + h/3 when pointing down.
+///
+/// Given a point P on the plane, located between x_low and x_high, with abs(high-low) = 1,
+/// P belongs to either of the triangles centered on x_low or x_high.
+/// As these 2 triangles are symmetrical along their common side,
+/// the closest of these 2 centers is the center of the triangle containing P.
+///
+pub struct Triangle {
+    half_side: f32,
+    row_height: f32,
+    columns: usize,
+    rows: usize,
+}
+
+impl Triangle {
+    pub fn new(width: u32, height: u32, side: u32) -> Triangle {
+        let half_side = side as f32 / 2.0;
+        let row_height = side as f32 * (PI / 3.0).sin();
+
+        Triangle {
+            half_side,
+            row_height,
+            columns: (width as f32 / half_side) as usize + 2,
+            rows: (height as f32 / row_height) as usize + 1,
+        }
+    }
+
+    fn position(&self, x_idx: u32, y_idx: u32) -> (f32, f32) {
+        self.signed_position(x_idx as i64, y_idx as i64)
+    }
+
+    /// The center of the cell and the centers of the 3 cells sharing a side with it
+    fn neighbourhood(&self, index: usize) -> ((f32, f32), [(f32, f32); 3]) {
+        let (i, j) = ((index % self.columns) as i64, (index / self.columns) as i64);
+        // the neighbour sharing the horizontal side is below when pointing up, above otherwise
+        let dj = if Triangle::points_up(i, j) { 1 } else { -1 };
+        let n = |di: i64, dj: i64| self.signed_position(i + di, j + dj);
+
+        (n(0, 0), [n(-1, 0), n(1, 0), n(0, dj)])
+    }
+
+    /// Same as position, also valid outside of the image
+    fn signed_position(&self, x_idx: i64, y_idx: i64) -> (f32, f32) {
+        let x = x_idx as f32 * self.half_side;
+        let y = if Triangle::points_up(x_idx, y_idx) {
+            (y_idx as f32 + 2.0 / 3.0) * self.row_height
+        } else {
+            (y_idx as f32 + 1.0 / 3.0) * self.row_height
+        };
+        (x, y)
+    }
+
+    fn points_up(x_idx: i64, y_idx: i64) -> bool {
+        (x_idx - y_idx) % 2 == 0
+    }
+}
+
+impl Tessellation for Triangle {
+    fn cell_count(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let x_low_idx = (x as f32 / self.half_side) as u32;
+        let y_idx = (y as f32 / self.row_height) as u32;
+
+        let low = self.position(x_low_idx, y_idx);
+        let high = self.position(x_low_idx + 1, y_idx);
+
+        let x_idx = if is_closer((x as f32, y as f32), low, high) {
+            x_low_idx
+        } else {
+            x_low_idx + 1
+        };
+
+        y_idx as usize * self.columns + x_idx as usize
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.position((index % self.columns) as u32, (index / self.columns) as u32)
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let (c, neighbours) = self.neighbourhood(index);
+
+        neighbours.iter()
+            .map(|&n| bisector_distance((x as f32, y as f32), c, n))
+            .fold(f32::MAX, f32::min)
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        let (c, neighbours) = self.neighbourhood(index);
+        closest_region(c, neighbours.iter().cloned(), square_around(c, 2.0 * self.half_side))
+    }
+}
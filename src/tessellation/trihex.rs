@@ -0,0 +1,142 @@
+use std::f32::consts::PI;
+use tessellation::Tessellation;
+use Orientation;
+
+
+///
+/// A trihexagonal tiling, 3.6.3.6, of hexagons surrounded by triangles, each triangle between 3 hexagons
+///
+/// The sides of the cells lie on 3 families of parallel lines, turned by 120° from each other,
+/// the lines of each family being d = √3 s apart for the cells of side s.
+/// Along the unit normals u_0, u_1 and u_2 of the families, a point P lies in the strips
+/// n_k = floor(t_k) between 2 lines, where t_k = P.u_k / d + 1/6.
+///
+/// As u_0 + u_1 + u_2 = 0, the t_k of any point add up to 1/2, so that their fractional parts f_k
+/// add up to 1/2, 3/2 or 5/2, and n_0 + n_1 + n_2 is 0, -1 or -2.
+/// The points whose f_k add up to 3/2 make up a hexagon, and the others the triangles
+/// pointing one way and the other, so that the cell of a point is found from n_0, n_1 and that sum.
+/// Conversely, as u_0 u_0^T + u_1 u_1^T + u_2 u_2^T = 3/2 I, P = 2/3 d sum((t_k - 1/6) u_k).
+///
+/// The pointy top cells have sides parallel to the Y axis, the flat top ones to the X axis.
+///
+pub struct Trihex {
+    spacing: f32,
+    normals: [(f32, f32); 3],
+    /// The lowest strips n_0 and n_1 of the image
+    first: (i64, i64),
+    /// The numbers of strips n_0 and n_1 across the image
+    counts: (usize, usize),
+}
+
+/// The fractional parts f_k of the vertices of the hexagons, and of the 2 kinds of triangles, in order
+const HEXAGON: [[f32; 3]; 6] = [
+    [1.0, 0.5, 0.0], [1.0, 0.0, 0.5], [0.5, 0.0, 1.0], [0.0, 0.5, 1.0], [0.0, 1.0, 0.5], [0.5, 1.0, 0.0],
+];
+const TRIANGLES: [[[f32; 3]; 3]; 2] = [
+    [[0.5, 0.0, 0.0], [0.0, 0.5, 0.0], [0.0, 0.0, 0.5]],
+    [[0.5, 1.0, 1.0], [1.0, 0.5, 1.0], [1.0, 1.0, 0.5]],
+];
+
+impl Trihex {
+    ///
+    /// # Arguments
+    ///
+    /// * `side` - The length of the sides of the hexagons and of the triangles
+    ///
+    pub fn new(width: u32, height: u32, side: u32, orientation: Orientation) -> Trihex {
+        let spacing = side.max(1) as f32 * 3f32.sqrt();
+        let turn = if orientation == Orientation::flat { PI / 2.0 } else { 0.0 };
+        let normal = |k: usize| {
+            let angle = turn + k as f32 * 2.0 * PI / 3.0;
+            (angle.cos(), angle.sin())
+        };
+        let normals = [normal(0), normal(1), normal(2)];
+
+        let corners = [(0.0, 0.0), (width as f32, 0.0), (0.0, height as f32), (width as f32, height as f32)];
+        let strips = |u: (f32, f32)| {
+            let t = corners.iter().map(|p| (p.0 * u.0 + p.1 * u.1) / spacing + 1.0 / 6.0);
+            let (low, high) = t.fold((f32::MAX, f32::MIN), |(low, high), t| (low.min(t), high.max(t)));
+            (low.floor() as i64 - 1, high.floor() as i64 + 1)
+        };
+        let (strips_0, strips_1) = (strips(normals[0]), strips(normals[1]));
+
+        Trihex {
+            spacing,
+            normals,
+            first: (strips_0.0, strips_1.0),
+            counts: ((strips_0.1 - strips_0.0 + 1) as usize, (strips_1.1 - strips_1.0 + 1) as usize),
+        }
+    }
+
+    /// The t_k of the point
+    fn coordinates(&self, p: (f32, f32)) -> [f32; 3] {
+        let t = |u: (f32, f32)| (p.0 * u.0 + p.1 * u.1) / self.spacing + 1.0 / 6.0;
+        [t(self.normals[0]), t(self.normals[1]), t(self.normals[2])]
+    }
+
+    /// The point of the t_k
+    fn point(&self, t: [f32; 3]) -> (f32, f32) {
+        let scale = 2.0 / 3.0 * self.spacing;
+        (0..3).fold((0.0, 0.0), |p, k| {
+            let u = self.normals[k];
+            (p.0 + scale * (t[k] - 1.0 / 6.0) * u.0, p.1 + scale * (t[k] - 1.0 / 6.0) * u.1)
+        })
+    }
+
+    /// The strips n_k of the cell, and its kind: 0 and 2 for the triangles, 1 for the hexagons
+    fn strips(&self, index: usize) -> ([i64; 3], usize) {
+        let kind = index % 3;
+        let cell = index / 3;
+        let n_0 = (cell % self.counts.0) as i64 + self.first.0;
+        let n_1 = (cell / self.counts.0) as i64 + self.first.1;
+        ([n_0, n_1, -(kind as i64) - n_0 - n_1], kind)
+    }
+
+    /// The point of the cell of the fractional parts f_k
+    fn inside(&self, index: usize, f: [f32; 3]) -> (f32, f32) {
+        let (n, _) = self.strips(index);
+        self.point([n[0] as f32 + f[0], n[1] as f32 + f[1], n[2] as f32 + f[2]])
+    }
+}
+
+impl Tessellation for Trihex {
+    fn cell_count(&self) -> usize {
+        3 * self.counts.0 * self.counts.1
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let t = self.coordinates((x as f32, y as f32));
+        let n = [t[0].floor() as i64, t[1].floor() as i64, t[2].floor() as i64];
+        // rounding may put the points on the sides of the cells out of the 3 possible sums
+        let kind = (-(n[0] + n[1] + n[2])).clamp(0, 2) as usize;
+        let column = (n[0] - self.first.0).clamp(0, self.counts.0 as i64 - 1) as usize;
+        let row = (n[1] - self.first.1).clamp(0, self.counts.1 as i64 - 1) as usize;
+        3 * (row * self.counts.0 + column) + kind
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        let (_, kind) = self.strips(index);
+        let f = (1.0 + 2.0 * kind as f32) / 6.0;
+        self.inside(index, [f, f, f])
+    }
+
+    ///
+    /// The distance to the closest of the lines around the cell, all of them farther than its sides but those of its sides
+    ///
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let t = self.coordinates((x as f32, y as f32));
+        let (n, _) = self.strips(index);
+        (0..3).map(|k| {
+            let f = t[k] - n[k] as f32;
+            f.min(1.0 - f)
+        }).fold(f32::MAX, f32::min).max(0.0) * self.spacing
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        let vertices: &[[f32; 3]] = match self.strips(index).1 {
+            1 => &HEXAGON,
+            kind => &TRIANGLES[kind / 2],
+        };
+        vertices.iter().map(|&f| self.inside(index, f)).collect()
+    }
+}
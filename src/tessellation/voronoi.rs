@@ -0,0 +1,173 @@
+use random::Random;
+use tessellation::{bisector_distance, clip, square_around, Tessellation};
+
+
+///
+/// A Voronoi diagram
+///
+/// Seeds are scattered randomly on the image, with on average 1 seed per `size` x `size` square.
+/// Each pixel belongs to the cell of its closest seed.
+///
+/// To find the closest seed, the seeds are sorted in buckets of `size` x `size` pixels.
+/// The buckets are searched in rings of increasing radius around the point's bucket.
+/// A bucket of the ring n is at least (n-1) * size away from the point,
+/// so the search stops once the closest seed found so far is closer than that.
+///
+/// The distance to the edge of a cell is bounded by half of the difference of the distances to
+/// the cell's seed and to the other seed, which gives another stop condition for the search of the edges.
+///
+/// Similarly, the seeds further than twice the distance from the cell's seed to any vertex of its polygon
+/// cannot cut the polygon.
+///
+pub struct Voronoi {
+    size: u32,
+    columns: u32,
+    rows: u32,
+    seeds: Vec<(f32, f32)>,
+    buckets: Vec<Vec<usize>>,
+}
+
+impl Voronoi {
+    pub fn new(width: u32, height: u32, size: u32, seed: u64) -> Voronoi {
+        let size = size.max(1);
+        let count = ((width as u64 * height as u64) / sqr!(size as u64)).max(1);
+
+        let mut random = Random::new(seed);
+        let seeds = (0..count).map(|_| {
+            let x = random.next_f32() * width as f32;
+            (x, random.next_f32() * height as f32)
+        }).collect();
+        Voronoi::with_seeds(width, height, size, seeds)
+    }
+
+    ///
+    /// The Voronoi diagram of the given seeds, sorted in buckets of `size` x `size` pixels
+    ///
+    /// The seeds outside of the image go to the buckets along its edges.
+    ///
+    pub fn with_seeds(width: u32, height: u32, size: u32, seeds: Vec<(f32, f32)>) -> Voronoi {
+        let size = size.max(1);
+        let columns = width.div_ceil(size).max(1);
+        let rows = height.div_ceil(size).max(1);
+
+        let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); (columns * rows) as usize];
+        for (i, &(x, y)) in seeds.iter().enumerate() {
+            let column = (x.max(0.0) as u32 / size).min(columns - 1);
+            let row = (y.max(0.0) as u32 / size).min(rows - 1);
+            buckets[(row * columns + column) as usize].push(i);
+        }
+
+        Voronoi {
+            size,
+            columns,
+            rows,
+            seeds,
+            buckets,
+        }
+    }
+}
+
+impl Voronoi {
+    /// The seeds in the buckets at the distance `ring` from the bucket (column, row)
+    fn ring_seeds<'a>(&'a self, column: i64, row: i64, ring: i64) -> impl Iterator<Item=usize> + 'a {
+        let columns = self.columns as i64;
+        let rows = self.rows as i64;
+
+        ((row - ring)..(row + ring + 1))
+            .flat_map(move |j| ((column - ring)..(column + ring + 1)).map(move |i| (i, j)))
+            .filter(move |&(i, j)| {
+                let on_ring = (j - row).abs() == ring || (i - column).abs() == ring;
+                on_ring && i >= 0 && j >= 0 && i < columns && j < rows
+            })
+            .flat_map(move |(i, j)| self.buckets[(j * columns + i) as usize].iter().cloned())
+    }
+
+    fn last_ring(&self) -> i64 {
+        self.columns.max(self.rows) as i64
+    }
+
+    /// A bucket of the ring n is at least that far from any point of the central bucket
+    fn reach(&self, ring: i64) -> f32 {
+        (ring - 1).max(0) as f32 * self.size as f32
+    }
+}
+
+impl Tessellation for Voronoi {
+    fn cell_count(&self) -> usize {
+        self.seeds.len()
+    }
+
+    fn cell_index(&self, x: u32, y: u32) -> usize {
+        let column = (x / self.size) as i64;
+        let row = (y / self.size) as i64;
+
+        let mut closest = 0;
+        let mut best = f32::MAX;
+        for ring in 0..(self.last_ring() + 1) {
+            if best <= sqr!(self.reach(ring)) {
+                break;
+            }
+            for s in self.ring_seeds(column, row, ring) {
+                let seed = self.seeds[s];
+                let d = sqr!(seed.0 - x as f32) + sqr!(seed.1 - y as f32);
+                if d < best {
+                    best = d;
+                    closest = s;
+                }
+            }
+        }
+
+        closest
+    }
+
+    fn center(&self, index: usize) -> (f32, f32) {
+        self.seeds[index]
+    }
+
+    fn edge_distance(&self, x: u32, y: u32, index: usize) -> f32 {
+        let p = (x as f32, y as f32);
+        let c = self.seeds[index];
+        let to_seed = (sqr!(c.0 - p.0) + sqr!(c.1 - p.1)).sqrt();
+        let column = (x / self.size) as i64;
+        let row = (y / self.size) as i64;
+
+        let mut best = f32::MAX;
+        for ring in 0..(self.last_ring() + 1) {
+            if (self.reach(ring) - to_seed) / 2.0 >= best {
+                break;
+            }
+            for s in self.ring_seeds(column, row, ring) {
+                if s != index {
+                    best = best.min(bisector_distance(p, c, self.seeds[s]));
+                }
+            }
+        }
+
+        best
+    }
+
+    fn polygon(&self, index: usize) -> Vec<(f32, f32)> {
+        let c = self.seeds[index];
+        let column = (c.0 as u32 / self.size) as i64;
+        let row = (c.1 as u32 / self.size) as i64;
+        let extent = self.last_ring() as f32 * self.size as f32;
+
+        let mut polygon = square_around(c, extent);
+        for ring in 0..(self.last_ring() + 1) {
+            let radius = polygon.iter()
+                .map(|v| (sqr!(v.0 - c.0) + sqr!(v.1 - c.1)).sqrt())
+                .fold(0.0, f32::max);
+            if self.reach(ring) > 2.0 * radius {
+                break;
+            }
+            for s in self.ring_seeds(column, row, ring) {
+                if s != index {
+                    let n = self.seeds[s];
+                    polygon = clip(&polygon, |p| bisector_distance(p, c, n));
+                }
+            }
+        }
+
+        polygon
+    }
+}
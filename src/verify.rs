@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bench::test_image;
+use hexpxl::{pixelise_with, Alpha, Options, Orientation, PixelMode, Sampling, Shadow};
+use hexpxl::mosaic::Tiles;
+use hexpxl::palette::Palette;
+use image::{Rgba, RgbaImage};
+use twox_hash::XxHash3_128;
+
+
+/// The width and the height of the reference images
+const SIDES: (u32, u32) = (64, 48);
+/// The size of the pixels of the reference pixelisations, small enough for many cells to fit in the images
+const SIZE: u32 = 8;
+
+///
+/// A reference pixelisation, whose golden image is named after it
+///
+pub struct Case {
+    pub name: String,
+    pub options: Options,
+    /// Whether the reference image has transparent parts, rather than being opaque
+    pub transparent: bool,
+}
+
+impl Case {
+    fn new(name: &str, options: Options) -> Case {
+        Case { name: name.to_string(), options: Options { size: SIZE, ..options }, transparent: false }
+    }
+
+    /// The pixelisation of its reference image
+    pub fn render(&self) -> RgbaImage {
+        let img = if self.transparent { transparent_image(SIDES.0, SIDES.1) } else { test_image(SIDES.0, SIDES.1) };
+        pixelise_with(&img, &self.options)
+    }
+}
+
+///
+/// The reference pixelisations: each mode with its default options, then the options which change
+/// the geometry or the colors of the cells, on the hexagons
+///
+pub fn cases() -> Vec<Case> {
+    let mut cases: Vec<Case> = PixelMode::variants().iter()
+        .filter_map(|m| m.parse::<PixelMode>().ok())
+        .map(|mode| {
+            let tiles = if mode == PixelMode::photomosaic { tiles() } else { None };
+            Case::new(&mode.to_string(), Options { mode, tiles, ..Options::default() })
+        })
+        .collect();
+
+    let hex = || Options { mode: PixelMode::hex, ..Options::default() };
+    cases.extend(vec![
+        Case::new("hex-flat", Options { orientation: Orientation::flat, ..hex() }),
+        Case::new("hex-angle", Options { angle: 30.0, ..hex() }),
+        Case::new("hex-offset", Options { offset: (3.0, -5.0), ..hex() }),
+        Case::new("hex-average", Options { sampling: Sampling::average, ..hex() }),
+        Case::new("hex-dominant", Options { sampling: Sampling::dominant, ..hex() }),
+        Case::new("hex-gaussian", Options { sampling: Sampling::gaussian, ..hex() }),
+        Case::new("hex-linear", Options { linear: true, sampling: Sampling::average, ..hex() }),
+        Case::new("hex-border", Options { border_width: 1.5, border_color: Rgba([20, 20, 20, 255]), ..hex() }),
+        Case::new("hex-gap", Options { gap: 2.0, background: Rgba([255, 255, 255, 255]), ..hex() }),
+        Case::new("hex-antialias", Options { gap: 2.0, antialias: true, ..hex() }),
+        Case::new("hex-bevel", Options { bevel: 2.0, ..hex() }),
+        Case::new("hex-shadow", Options {
+            gap: 2.0,
+            background: Rgba([255, 255, 255, 255]),
+            shadow: Some(Shadow { offset: (2.0, 2.0), blur: 2.0, color: Rgba([0, 0, 0, 160]) }),
+            ..hex()
+        }),
+        Case::new("hex-palette", Options { palette: Palette::named("pico8"), ..hex() }),
+        Case::new("hex-dither", Options { palette: Palette::named("pico8"), dither: true, ..hex() }),
+        Case::new("hex-posterize", Options { posterize: Some(3), ..hex() }),
+        Case::new("hex-scale", Options { scale: 2, ..hex() }),
+        Case::new("hex-tileable", Options { tileable: true, ..hex() }),
+        Case::new("sqr-rectangles", Options {
+            mode: PixelMode::sqr,
+            cell_width: Some(12),
+            cell_height: Some(6),
+            ..Options::default()
+        }),
+        Case::new("dots-radius", Options { mode: PixelMode::dots, dot_radius: Some(3.0), ..Options::default() }),
+    ]);
+    for &alpha in &[Alpha::preserve, Alpha::flatten, Alpha::binarize] {
+        let mut case = Case::new(&format!("hex-alpha-{}", alpha), Options { alpha, ..hex() });
+        case.transparent = true;
+        cases.push(case);
+    }
+    cases
+}
+
+///
+/// A transparent reference image: the synthetic image fading out from its left edge to its right edge,
+/// with a hole in its middle
+///
+fn transparent_image(width: u32, height: u32) -> RgbaImage {
+    let mut img = test_image(width, height);
+    let (cx, cy, radius) = (width as f32 / 2.0, height as f32 / 2.0, height as f32 / 5.0);
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+        let hole = dx * dx + dy * dy < radius * radius;
+        pixel[3] = if hole { 0 } else { 255 - (x * 255 / width) as u8 };
+    }
+    img
+}
+
+/// The tiles of the photomosaic mode: plain tiles of a few colors, the same on every machine
+fn tiles() -> Option<Tiles> {
+    let colors = [[230, 60, 50], [60, 180, 90], [50, 90, 220], [240, 220, 120], [30, 30, 40]];
+    Tiles::new(colors.iter().map(|&[r, g, b]| RgbaImage::from_pixel(8, 8, Rgba([r, g, b, 255]))).collect())
+}
+
+///
+/// How a pixelisation differs from its golden image: the number of its pixels of which a channel differs
+/// by more than the tolerance, or None when their sizes differ
+///
+pub fn differences(image: &RgbaImage, golden: &RgbaImage, tolerance: u8) -> Option<usize> {
+    if image.dimensions() != golden.dimensions() {
+        return None;
+    }
+    Some(image.pixels().zip(golden.pixels())
+        .filter(|(a, b)| (0..4).any(|c| (a[c] as i16 - b[c] as i16).unsigned_abs() > tolerance as u16))
+        .count())
+}
+
+/// The golden image of the case in the directory
+pub fn golden_path(directory: &Path, case: &Case) -> PathBuf {
+    directory.join(format!("{}.png", case.name))
+}
+
+/// The golden images of the directory which no case has, left behind by the cases which were removed
+pub fn stale(directory: &Path, cases: &[Case]) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(directory).into_iter().flatten().flatten()
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| name.ends_with(".png"))
+        .filter(|name| !cases.iter().any(|case| format!("{}.png", case.name) == *name))
+        .collect();
+    names.sort();
+    names
+}
+
+///
+/// The hash of the pixels of an image, as 32 hexadecimal digits: the 128 bits XXH3 of its width, its height,
+/// the bits of its channels and its channels, in that order and big-endian
+///
+/// The hash only depends on the pixels, not on how the image was encoded nor on its metadata.
+///
+pub fn hash(width: u32, height: u32, depth: u8, channels: &[u8]) -> String {
+    let mut hasher = XxHash3_128::new();
+    hasher.write(&width.to_be_bytes());
+    hasher.write(&height.to_be_bytes());
+    hasher.write(&[depth]);
+    hasher.write(channels);
+    format!("{:032x}", hasher.finish_128())
+}
+
+/// Whether the text is a hash as written by `hash`, ignoring the case of its digits
+pub fn is_hash(text: &str) -> bool {
+    text.len() == 32 && text.chars().all(|c| c.is_ascii_hexdigit())
+}
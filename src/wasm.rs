@@ -0,0 +1,25 @@
+use image::{self, DynamicImage, ImageFormat};
+use wasm_bindgen::prelude::*;
+use PixelMode;
+
+
+///
+/// Pixelises an encoded image into a PNG image, for JavaScript
+///
+/// # Arguments
+///
+/// * `bytes` - The input image, in any of the formats decoded by the image crate
+/// * `mode` - The shape of the pixels: sqr, hex, tri, voronoi or quadtree
+/// * `size` - The size of the pixels, in pixels
+///
+#[wasm_bindgen(js_name = pixelise)]
+pub fn pixelise_png(bytes: &[u8], mode: &str, size: u32) -> Result<Vec<u8>, JsValue> {
+    let mode: PixelMode = mode.parse().map_err(|e: String| JsValue::from_str(&e))?;
+    let img = image::load_from_memory(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(::pixelise(&img, mode, size))
+        .write_to(&mut png, ImageFormat::PNG)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(png)
+}
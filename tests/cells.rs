@@ -0,0 +1,59 @@
+extern crate hexpxl;
+extern crate image;
+
+use hexpxl::{cell_count, pixelise_with, plan, Options, PixelMode};
+use image::{Rgba, RgbaImage};
+
+/// An image whose pixels all differ, so that the pixels of a cell only match when they were averaged together
+fn gradient(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| Rgba([(x * 5) as u8, (y * 7) as u8, 0, 255]))
+}
+
+///
+/// The cells larger than the image pixelise it as a single cell, whatever the mode and wherever the grid falls
+///
+#[test]
+fn cells_larger_than_the_image() {
+    let img = gradient(40, 30);
+    for &mode in &[PixelMode::sqr, PixelMode::hex, PixelMode::tri, PixelMode::brick, PixelMode::voronoi, PixelMode::penrose] {
+        for &size in &[40, 100] {
+            let options = Options { mode, size, ..Options::default() };
+            let pixelised: RgbaImage = pixelise_with(&img, &options);
+            let first = *pixelised.get_pixel(0, 0);
+            assert!(pixelised.pixels().all(|&p| p == first), "the {} cells of size {} are not a single cell", mode, size);
+            assert_eq!(cell_count(&img, &options), 1);
+            assert_eq!(plan::plan(40, 30, &options, 1).cells, Some(1));
+            assert!(plan::check(40, 30, &options).unwrap().unwrap().contains("single cell"));
+        }
+    }
+
+    // the cells only larger than the shorter side still cover the image with a few of them
+    let options = Options { mode: PixelMode::sqr, size: 35, ..Options::default() };
+    assert_eq!(cell_count(&img, &options), 2);
+    assert!(plan::check(40, 30, &options).unwrap().unwrap().contains("a few cells"));
+}
+
+#[test]
+fn smallest_size_larger_than_the_largest() {
+    let options = Options { mode: PixelMode::quadtree, min_size: 32, max_size: 16, ..Options::default() };
+    assert!(plan::check(40, 30, &options).is_err());
+    let options = Options { mode: PixelMode::quadtree, min_size: 16, max_size: 16, ..Options::default() };
+    assert_eq!(plan::check(40, 30, &options), Ok(None));
+}
+
+///
+/// The most cells estimated without laying them out are at least the cells of the pixelisation
+///
+#[test]
+fn most_cells() {
+    let img = gradient(90, 70);
+    for &mode in &[PixelMode::sqr, PixelMode::hex, PixelMode::tri, PixelMode::brick, PixelMode::voronoi,
+        PixelMode::trihex, PixelMode::quadtree, PixelMode::slic, PixelMode::hilbert, PixelMode::lowpoly] {
+        for &size in &[3, 7, 16] {
+            let options = Options { mode, size, min_size: size, ..Options::default() };
+            let most = plan::most_cells(90, 70, &options);
+            let cells = cell_count(&img, &options) as u64;
+            assert!(cells <= most, "the {} cells of size {} are {}, more than the {} estimated", mode, size, cells, most);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::env;
+use std::fs;
+use std::process::{self, Command};
+
+///
+/// The pixelisations of the reference images match their golden images, written by `hexpxl verify-golden --update`
+///
+#[test]
+fn golden_images() {
+    let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+    hexpxl(&["verify-golden", "--golden", golden]);
+}
+
+/// Runs hexpxl with the arguments, failing with its output unless it succeeds, which it returns
+fn hexpxl(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_hexpxl")).args(args).output().expect("cannot run hexpxl");
+    assert!(output.status.success(), "{}{}",
+            String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+///
+/// The hashes printed by --print-hash only depend on the pixels, and `hexpxl verify` checks them
+///
+#[test]
+fn pixel_hashes() {
+    let src = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/hex.png");
+    let directory = env::temp_dir();
+    let png = directory.join(format!("hexpxl-hash-{}.png", process::id())).display().to_string();
+    let bmp = directory.join(format!("hexpxl-hash-{}.bmp", process::id())).display().to_string();
+    let hash = |dst: &str| {
+        let line = hexpxl(&["--quiet", "--print-hash", src, dst]);
+        assert_eq!(line.trim_end().split("  ").nth(1), Some(dst));
+        line.split(' ').next().unwrap().to_string()
+    };
+    let (png_hash, bmp_hash) = (hash(&png), hash(&bmp));
+    assert_eq!(png_hash, bmp_hash);
+
+    hexpxl(&["verify", &bmp, &png_hash]);
+    let wrong = Command::new(env!("CARGO_BIN_EXE_hexpxl"))
+        .args(["verify", &png, "00000000000000000000000000000000"])
+        .status()
+        .expect("cannot run hexpxl verify");
+    let _ = fs::remove_file(&png);
+    let _ = fs::remove_file(&bmp);
+    assert_eq!(wrong.code(), Some(5));
+}
+
+///
+/// The parameters written into an image pixelise it again the same way, with the seeds which TOML cannot hold as they are
+///
+#[test]
+fn parameters_round_trip() {
+    let src = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/hex.png");
+    let directory = env::temp_dir();
+    let first = directory.join(format!("hexpxl-params-{}.png", process::id())).display().to_string();
+    let again = directory.join(format!("hexpxl-params-{}-again.png", process::id())).display().to_string();
+    let config = directory.join(format!("hexpxl-params-{}.toml", process::id())).display().to_string();
+    let hash = |line: String| line.split(' ').next().unwrap().to_string();
+
+    let seed = u64::MAX.to_string();
+    let first_hash = hash(hexpxl(&["--quiet", "--print-hash", "--mode", "voronoi", "--seed", &seed, src, &first, "6"]));
+    let parameters = hexpxl(&["--show-params", &first]);
+    fs::write(&config, &parameters).unwrap();
+    let again_hash = hash(hexpxl(&["--quiet", "--print-hash", "--config", &config, src, &again]));
+    let _ = fs::remove_file(&first);
+    let _ = fs::remove_file(&again);
+    let _ = fs::remove_file(&config);
+
+    assert!(parameters.lines().any(|line| line == "seed = -1"), "{}", parameters);
+    assert_eq!(first_hash, again_hash);
+}
@@ -0,0 +1,77 @@
+extern crate hexpxl;
+extern crate image;
+
+use hexpxl::{pixelise_with, Options, Orientation, PixelMode};
+use image::{Rgba, RgbaImage};
+
+///
+/// An image whose pixels hold their own coordinates, red being x and green y,
+/// so that each pixel of its pixelisation tells which pixel its cell was sampled at
+///
+fn coordinates(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| Rgba([x as u8, y as u8, 0, 255]))
+}
+
+///
+/// Checks that every pixel takes the color of the hexagon whose center is the closest to it,
+/// the centers being found by trying all of them
+///
+/// The centers of the pointy top hexagons are at (i r, j 3R/2) for the i and j of the same parity, r being
+/// the inner radius and R the outer radius. A pixel halfway between two centers may take either of them.
+///
+fn check(width: u32, height: u32, outer_radius: u32, orientation: Orientation) {
+    let img = coordinates(width, height);
+    let options = Options { mode: PixelMode::hex, size: outer_radius, orientation, ..Options::default() };
+    let pixelised: RgbaImage = pixelise_with(&img, &options);
+
+    let flat = orientation == Orientation::flat;
+    let inner_radius = outer_radius as f32 * (std::f32::consts::PI / 6.0).cos();
+    let gap = 3.0 * outer_radius as f32 / 2.0;
+    let (along, across) = if flat { (height, width) } else { (width, height) };
+    let mut centers = Vec::new();
+    for j in 0..(across as f32 / gap) as i64 + 2 {
+        for i in (j % 2..(along as f32 / inner_radius) as i64 + 2).step_by(2) {
+            let (x, y) = (i as f32 * inner_radius, j as f32 * gap);
+            centers.push(if flat { (y, x) } else { (x, y) });
+        }
+    }
+    // the pixel of the image which a cell centered on c is sampled at
+    let sampled = |c: (f32, f32)| {
+        ((c.0.max(0.0).round() as u32).min(width - 1), (c.1.max(0.0).round() as u32).min(height - 1))
+    };
+
+    for (x, y, pixel) in pixelised.enumerate_pixels() {
+        let distance = |c: &(f32, f32)| (c.0 - x as f32).powi(2) + (c.1 - y as f32).powi(2);
+        let closest = centers.iter().map(distance).fold(f32::MAX, f32::min);
+        let found = (pixel[0] as u32, pixel[1] as u32);
+        assert!(
+            centers.iter().any(|c| distance(c) <= closest + 1e-3 && sampled(*c) == found),
+            "the pixel ({}, {}) of a {}x{} image with hexagons of radius {} ({}) was sampled at {:?}",
+            x, y, width, height, outer_radius, orientation, found
+        );
+    }
+}
+
+#[test]
+fn pixels_right_of_their_centers() {
+    // the pixels right of the center of their cell, whose distance once wrapped around as an unsigned difference
+    for &radius in &[1, 2, 3, 5, 8] {
+        check(61, 17, radius, Orientation::pointy);
+    }
+}
+
+#[test]
+fn pixels_at_the_boundaries_of_the_rows() {
+    // tall enough for the cells to be taken from the table of the first rows
+    for &radius in &[2, 3, 7, 12] {
+        check(50, 200, radius, Orientation::pointy);
+    }
+}
+
+#[test]
+fn flat_hexagons() {
+    for &radius in &[2, 5, 9] {
+        check(120, 70, radius, Orientation::flat);
+        check(30, 200, radius, Orientation::flat);
+    }
+}